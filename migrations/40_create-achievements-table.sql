@@ -0,0 +1,69 @@
+DO $$ BEGIN
+    CREATE TYPE achievement AS ENUM (
+        'hundred_cm',
+        'win_streak',
+        'loan_repaid',
+        'dod_thrice'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE IF NOT EXISTS Achievements (
+    chat_id bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    uid bigint NOT NULL REFERENCES Users(uid),
+    achievement achievement NOT NULL,
+    unlocked_at timestamptz NOT NULL DEFAULT current_timestamp,
+
+    PRIMARY KEY (chat_id, uid, achievement)
+);
+
+COMMENT ON TABLE  Achievements             IS 'What every player has unlocked in every chat; an achievement whose condition stops holding keeps its row';
+COMMENT ON COLUMN Achievements.achievement IS 'What the conditions are is decided by the bot, so a threshold may change without touching the rows unlocked before';
+
+-- The same function as in migration 35, with one table more. The list is written out by hand, so
+-- every new table with a user id has to come back here.
+CREATE OR REPLACE FUNCTION erase_user(p_uid bigint, p_ban_days int DEFAULT 90)
+    RETURNS void
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    deleted int := 0;
+    affected int;
+BEGIN
+    IF p_ban_days < 0 THEN
+        RAISE EXCEPTION 'the ban length must not be negative, got %', p_ban_days;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM Users WHERE uid = p_uid) THEN
+        RAISE EXCEPTION 'there is no user with uid = %', p_uid;
+    END IF;
+
+    -- Every table that keeps rows owned by a user. A new one must be added here as well;
+    -- the test `erase_user_covers_every_table_with_a_uid` fails when it isn't.
+    DELETE FROM Dicks                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Stats           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Loans                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Promo_Code_Activations WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Stale_Dick_Shrinks     WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Imports                WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Dick_of_Day            WHERE winner_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Achievements           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+
+    UPDATE Users
+       SET name         = '',
+           created_at   = current_timestamp,
+           banned_until = current_timestamp + make_interval(days => p_ban_days)
+     WHERE uid = p_uid;
+
+    RAISE NOTICE 'erased the user %: % rows deleted, banned for % days', p_uid, deleted, p_ban_days;
+END
+$$;
@@ -0,0 +1,3 @@
+ALTER TABLE Battle_Stats ADD COLUMN IF NOT EXISTS mercies_shown int NOT NULL DEFAULT 0 CHECK ( mercies_shown >= 0 );
+
+COMMENT ON COLUMN Battle_Stats.mercies_shown IS 'How many times the user has won a battle and given the award back; the battle itself still counts as won';
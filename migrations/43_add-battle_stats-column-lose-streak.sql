@@ -0,0 +1,3 @@
+ALTER TABLE Battle_Stats ADD COLUMN IF NOT EXISTS lose_streak_current smallint NOT NULL DEFAULT 0 CHECK ( lose_streak_current >= 0 );
+
+COMMENT ON COLUMN Battle_Stats.lose_streak_current IS 'Battles lost in a row, reset by the first win; a shielded loss is not recorded at all and so does not count';
@@ -0,0 +1,56 @@
+ALTER TABLE Users ADD COLUMN IF NOT EXISTS username varchar(32);
+
+-- Telegram doesn't tell usernames apart by case.
+CREATE INDEX IF NOT EXISTS idx_users_username ON Users (lower(username)) WHERE username IS NOT NULL;
+
+COMMENT ON COLUMN Users.username IS 'The @username without the at sign, as of the last growth, to find the opponent of a /pvp challenge by a mention; NULL if the user has none';
+
+-- The same function as in migration 44, which forgets the username together with the name.
+CREATE OR REPLACE FUNCTION erase_user(p_uid bigint, p_ban_days int DEFAULT 90)
+    RETURNS void
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    deleted int := 0;
+    affected int;
+BEGIN
+    IF p_ban_days < 0 THEN
+        RAISE EXCEPTION 'the ban length must not be negative, got %', p_ban_days;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM Users WHERE uid = p_uid) THEN
+        RAISE EXCEPTION 'there is no user with uid = %', p_uid;
+    END IF;
+
+    -- Every table that keeps rows owned by a user. A new one must be added here as well;
+    -- the test `erase_user_covers_every_table_with_a_uid` fails when it isn't.
+    DELETE FROM Dicks                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Stats           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Loans                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Promo_Code_Activations WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Stale_Dick_Shrinks     WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Imports                WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Dick_of_Day            WHERE winner_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Achievements           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Inventory              WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    UPDATE Promo_Codes SET inviter_uid = NULL, capacity = 0 WHERE inviter_uid = p_uid;
+
+    UPDATE Users
+       SET name         = '',
+           username     = NULL,
+           created_at   = current_timestamp,
+           banned_until = current_timestamp + make_interval(days => p_ban_days)
+     WHERE uid = p_uid;
+
+    RAISE NOTICE 'erased the user %: % rows deleted, banned for % days', p_uid, deleted, p_ban_days;
+END
+$$;
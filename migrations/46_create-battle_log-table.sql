@@ -0,0 +1,69 @@
+CREATE TABLE IF NOT EXISTS Battle_Log (
+    id          bigserial PRIMARY KEY,
+    chat_id     bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    winner_uid  bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    loser_uid   bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    bet         bigint NOT NULL CHECK ( bet > 0 ),
+    mercy_shown boolean NOT NULL DEFAULT false,
+    fought_at   timestamptz NOT NULL DEFAULT current_timestamp
+);
+
+-- A rivalry is looked up by the pair, whichever of the two has won.
+CREATE INDEX IF NOT EXISTS idx_battle_log_pair
+    ON Battle_Log (chat_id, LEAST(winner_uid, loser_uid), GREATEST(winner_uid, loser_uid), fought_at DESC);
+
+COMMENT ON TABLE Battle_Log IS 'Every battle fought, for the head-to-head records of /rivalry; Battle_Stats keeps the totals per player';
+COMMENT ON COLUMN Battle_Log.bet IS 'The stake that has changed hands, as multiplied by an event';
+COMMENT ON COLUMN Battle_Log.mercy_shown IS 'The winner has given the award back, so no length has changed hands after all';
+
+-- The same function as in migration 45, which deletes the battles of the user as well.
+CREATE OR REPLACE FUNCTION erase_user(p_uid bigint, p_ban_days int DEFAULT 90)
+    RETURNS void
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    deleted int := 0;
+    affected int;
+BEGIN
+    IF p_ban_days < 0 THEN
+        RAISE EXCEPTION 'the ban length must not be negative, got %', p_ban_days;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM Users WHERE uid = p_uid) THEN
+        RAISE EXCEPTION 'there is no user with uid = %', p_uid;
+    END IF;
+
+    -- Every table that keeps rows owned by a user. A new one must be added here as well;
+    -- the test `erase_user_covers_every_table_with_a_uid` fails when it isn't.
+    DELETE FROM Dicks                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Stats           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Log             WHERE winner_uid = p_uid OR loser_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Loans                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Promo_Code_Activations WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Stale_Dick_Shrinks     WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Imports                WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Dick_of_Day            WHERE winner_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Achievements           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Inventory              WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    UPDATE Promo_Codes SET inviter_uid = NULL, capacity = 0 WHERE inviter_uid = p_uid;
+
+    UPDATE Users
+       SET name         = '',
+           username     = NULL,
+           created_at   = current_timestamp,
+           banned_until = current_timestamp + make_interval(days => p_ban_days)
+     WHERE uid = p_uid;
+
+    RAISE NOTICE 'erased the user %: % rows deleted, banned for % days', p_uid, deleted, p_ban_days;
+END
+$$;
@@ -0,0 +1,87 @@
+DO $$ BEGIN
+    CREATE TYPE length_change_reason AS ENUM (
+        'grow',
+        'dod',
+        'pvp',
+        'tournament',
+        'royale',
+        'loan',
+        'loan_payout',
+        'shrink',
+        'promo',
+        'import',
+        'shop'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE IF NOT EXISTS Length_Events (
+    id         bigserial PRIMARY KEY,
+    chat_id    bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    uid        bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    change     bigint NOT NULL,
+    length     bigint NOT NULL,
+    reason     length_change_reason NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_length_events_dick ON Length_Events (chat_id, uid, created_at);
+
+COMMENT ON TABLE  Length_Events        IS 'Every change of the length of every dick, for /history; Dicks keeps only the current length';
+COMMENT ON COLUMN Length_Events.change IS 'Signed: a loss is negative';
+COMMENT ON COLUMN Length_Events.length IS 'The length right after the change, so a day is drawn by its last event alone';
+
+-- The same function as in migration 46, which deletes the length history of the user as well.
+CREATE OR REPLACE FUNCTION erase_user(p_uid bigint, p_ban_days int DEFAULT 90)
+    RETURNS void
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    deleted int := 0;
+    affected int;
+BEGIN
+    IF p_ban_days < 0 THEN
+        RAISE EXCEPTION 'the ban length must not be negative, got %', p_ban_days;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM Users WHERE uid = p_uid) THEN
+        RAISE EXCEPTION 'there is no user with uid = %', p_uid;
+    END IF;
+
+    -- Every table that keeps rows owned by a user. A new one must be added here as well;
+    -- the test `erase_user_covers_every_table_with_a_uid` fails when it isn't.
+    DELETE FROM Dicks                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Stats           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Log             WHERE winner_uid = p_uid OR loser_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Loans                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Promo_Code_Activations WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Stale_Dick_Shrinks     WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Imports                WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Dick_of_Day            WHERE winner_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Achievements           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Inventory              WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Length_Events          WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    UPDATE Promo_Codes SET inviter_uid = NULL, capacity = 0 WHERE inviter_uid = p_uid;
+
+    UPDATE Users
+       SET name         = '',
+           username     = NULL,
+           created_at   = current_timestamp,
+           banned_until = current_timestamp + make_interval(days => p_ban_days)
+     WHERE uid = p_uid;
+
+    RAISE NOTICE 'erased the user %: % rows deleted, banned for % days', p_uid, deleted, p_ban_days;
+END
+$$;
@@ -0,0 +1,19 @@
+ALTER TABLE Loans ADD COLUMN IF NOT EXISTS interest_rate double precision NOT NULL DEFAULT 0
+    CHECK ( interest_rate >= 0.0 AND interest_rate <= 1.0 );
+ALTER TABLE Loans ADD COLUMN IF NOT EXISTS due_date date;
+ALTER TABLE Loans ADD COLUMN IF NOT EXISTS accrued_at date NOT NULL DEFAULT current_date;
+
+COMMENT ON COLUMN Loans.debt          IS 'The balance as of accrued_at; the interest of the days since then is added by loan_balance()';
+COMMENT ON COLUMN Loans.interest_rate IS 'Daily, compound, fixed when the loan is taken or refinanced; the loans of old have none';
+COMMENT ON COLUMN Loans.due_date      IS 'NULL if the loans have no term; after it, the overdue penalty perk applies';
+COMMENT ON COLUMN Loans.accrued_at    IS 'When the interest was added to the debt the last time';
+
+-- The interest is added lazily: whenever the debt is read, and for good whenever it is paid, so no
+-- job has to walk all the loans every night.
+CREATE OR REPLACE FUNCTION loan_balance(p_debt bigint, p_interest_rate double precision, p_accrued_at date)
+    RETURNS bigint
+    LANGUAGE SQL
+    STABLE
+AS $$
+    SELECT LEAST(ROUND(p_debt * POWER(1.0 + p_interest_rate::numeric, GREATEST(current_date - p_accrued_at, 0))), 9223372036854775807)::bigint
+$$;
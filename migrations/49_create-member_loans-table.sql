@@ -0,0 +1,77 @@
+-- The values can't be used in the transaction they are added in, and nothing here uses them.
+ALTER TYPE length_change_reason ADD VALUE IF NOT EXISTS 'gift';
+ALTER TYPE length_change_reason ADD VALUE IF NOT EXISTS 'lend';
+ALTER TYPE length_change_reason ADD VALUE IF NOT EXISTS 'lend_repayment';
+
+CREATE TABLE IF NOT EXISTS Member_Loans (
+    id           bigserial PRIMARY KEY,
+    chat_id      bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    lender_uid   bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    borrower_uid bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    debt         int NOT NULL CHECK ( debt >= 0 ),
+    payout_ratio real NOT NULL CHECK ( payout_ratio > 0.0 AND payout_ratio < 1.0 ),
+    created_at   timestamptz NOT NULL DEFAULT current_timestamp,
+    repaid_at    timestamptz,
+    CHECK ( lender_uid <> borrower_uid )
+);
+
+CREATE INDEX IF NOT EXISTS idx_member_loans_borrower ON Member_Loans (chat_id, borrower_uid) WHERE repaid_at IS NULL;
+
+COMMENT ON TABLE  Member_Loans      IS 'The length lent by one member to another with /gift; unlike Loans, owed to the lender rather than to the bank';
+COMMENT ON COLUMN Member_Loans.debt IS 'What is left to pay back to the lender, out of the growths of the borrower';
+
+-- The same function as in migration 47, which deletes the loans between members as well, on both sides.
+CREATE OR REPLACE FUNCTION erase_user(p_uid bigint, p_ban_days int DEFAULT 90)
+    RETURNS void
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    deleted int := 0;
+    affected int;
+BEGIN
+    IF p_ban_days < 0 THEN
+        RAISE EXCEPTION 'the ban length must not be negative, got %', p_ban_days;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM Users WHERE uid = p_uid) THEN
+        RAISE EXCEPTION 'there is no user with uid = %', p_uid;
+    END IF;
+
+    -- Every table that keeps rows owned by a user. A new one must be added here as well;
+    -- the test `erase_user_covers_every_table_with_a_uid` fails when it isn't.
+    DELETE FROM Dicks                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Stats           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Log             WHERE winner_uid = p_uid OR loser_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Loans                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Promo_Code_Activations WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Stale_Dick_Shrinks     WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Imports                WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Dick_of_Day            WHERE winner_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Achievements           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Inventory              WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Length_Events          WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Member_Loans           WHERE lender_uid = p_uid OR borrower_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    UPDATE Promo_Codes SET inviter_uid = NULL, capacity = 0 WHERE inviter_uid = p_uid;
+
+    UPDATE Users
+       SET name         = '',
+           username     = NULL,
+           created_at   = current_timestamp,
+           banned_until = current_timestamp + make_interval(days => p_ban_days)
+     WHERE uid = p_uid;
+
+    RAISE NOTICE 'erased the user %: % rows deleted, banned for % days', p_uid, deleted, p_ban_days;
+END
+$$;
@@ -0,0 +1,8 @@
+ALTER TABLE Dicks ADD COLUMN IF NOT EXISTS grow_streak int NOT NULL DEFAULT 0 CHECK ( grow_streak >= 0 );
+
+COMMENT ON COLUMN Dicks.grow_streak IS 'Days in a row the dick has been grown on, up to the day of updated_at; broken if that day is before yesterday';
+
+-- The days before are not known, so whoever has grown since yesterday starts with a single one.
+-- The trigger on Dicks spends a bonus attempt per write and refuses a dick grown today without
+-- one, so an attempt is added only for it to take away.
+UPDATE Dicks SET grow_streak = 1, bonus_attempts = bonus_attempts + 1 WHERE updated_at >= current_date - 1;
@@ -0,0 +1,19 @@
+DO $$ BEGIN
+    CREATE TYPE broadcast_kind AS ENUM (
+        'shrink',
+        'week_podium',
+        'month_podium'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+ALTER TABLE Scheduled_Shrink_Broadcasts ADD COLUMN IF NOT EXISTS kind broadcast_kind NOT NULL DEFAULT 'shrink';
+
+-- A chat may be owed a shrink summary and a podium for the same day, so the kind joins the key.
+DROP INDEX IF EXISTS Scheduled_Shrink_Broadcasts_chat_date_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS Scheduled_Shrink_Broadcasts_chat_kind_date_idx
+    ON Scheduled_Shrink_Broadcasts (chat_id, kind, shrink_date);
+
+COMMENT ON COLUMN Scheduled_Shrink_Broadcasts.kind        IS 'What the message is about: the shrinks of a day or the podium of a period that is over';
+COMMENT ON COLUMN Scheduled_Shrink_Broadcasts.shrink_date IS 'The day whose shrinks the summary is about, or the first day of the period of a podium';
@@ -0,0 +1,3 @@
+ALTER TABLE Users ADD COLUMN IF NOT EXISTS hidden_from_global_top bool NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN Users.hidden_from_global_top IS 'Whether the user has opted out of the /globaltop, which shows their name to everybody outside of their chats';
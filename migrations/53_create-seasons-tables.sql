@@ -0,0 +1,82 @@
+-- The value can't be used in the transaction it is added in, and nothing here uses it.
+ALTER TYPE length_change_reason ADD VALUE IF NOT EXISTS 'season';
+
+CREATE TABLE IF NOT EXISTS Seasons (
+    id       bigserial PRIMARY KEY,
+    chat_id  bigint NOT NULL REFERENCES Chats(id) ON DELETE CASCADE,
+    number   int NOT NULL CHECK ( number > 0 ),
+    ended_on date NOT NULL,
+    UNIQUE (chat_id, number)
+);
+
+CREATE TABLE IF NOT EXISTS Season_Results (
+    season_id bigint NOT NULL REFERENCES Seasons(id) ON DELETE CASCADE,
+    uid       bigint NOT NULL REFERENCES Users(uid) ON DELETE CASCADE,
+    length    bigint NOT NULL,
+    position  int NOT NULL CHECK ( position > 0 ),
+    PRIMARY KEY (season_id, uid)
+);
+
+COMMENT ON TABLE  Seasons                 IS 'The finished seasons of a chat: at the end of each the lengths of Dicks were archived into Season_Results and reset to zero';
+COMMENT ON COLUMN Seasons.number          IS 'The seasons of a chat are numbered from 1, in the order they ended';
+COMMENT ON COLUMN Seasons.ended_on        IS 'The last day of the season';
+COMMENT ON TABLE  Season_Results          IS 'The lengths the members had when their season ended; only the dicks that weren''t zero';
+COMMENT ON COLUMN Season_Results.position IS 'The place in the final top of the season; the one at 1 is the champion';
+
+-- The same function as in migration 49, which deletes the archived results of the seasons as well.
+CREATE OR REPLACE FUNCTION erase_user(p_uid bigint, p_ban_days int DEFAULT 90)
+    RETURNS void
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    deleted int := 0;
+    affected int;
+BEGIN
+    IF p_ban_days < 0 THEN
+        RAISE EXCEPTION 'the ban length must not be negative, got %', p_ban_days;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM Users WHERE uid = p_uid) THEN
+        RAISE EXCEPTION 'there is no user with uid = %', p_uid;
+    END IF;
+
+    -- Every table that keeps rows owned by a user. A new one must be added here as well;
+    -- the test `erase_user_covers_every_table_with_a_uid` fails when it isn't.
+    DELETE FROM Dicks                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Stats           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Battle_Log             WHERE winner_uid = p_uid OR loser_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Loans                  WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Promo_Code_Activations WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Stale_Dick_Shrinks     WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Imports                WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Dick_of_Day            WHERE winner_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Achievements           WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Inventory              WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Length_Events          WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Member_Loans           WHERE lender_uid = p_uid OR borrower_uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    DELETE FROM Season_Results         WHERE uid = p_uid;
+    GET DIAGNOSTICS affected = ROW_COUNT; deleted := deleted + affected;
+    UPDATE Promo_Codes SET inviter_uid = NULL, capacity = 0 WHERE inviter_uid = p_uid;
+
+    UPDATE Users
+       SET name         = '',
+           username     = NULL,
+           created_at   = current_timestamp,
+           banned_until = current_timestamp + make_interval(days => p_ban_days)
+     WHERE uid = p_uid;
+
+    RAISE NOTICE 'erased the user %: % rows deleted, banned for % days', p_uid, deleted, p_ban_days;
+END
+$$;
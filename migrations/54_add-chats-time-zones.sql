@@ -0,0 +1,62 @@
+-- The date of a moment as it's seen in the time zone, UTC if there is none.
+CREATE OR REPLACE FUNCTION local_date(p_at timestamptz, p_time_zone text)
+    RETURNS date
+    LANGUAGE SQL
+    STABLE
+AS $$
+    SELECT (p_at AT TIME ZONE coalesce(p_time_zone, 'UTC'))::date;
+$$;
+
+-- The date of a moment in the time zone the admins of the chat have set in its settings.
+CREATE OR REPLACE FUNCTION chat_date(p_chat_id bigint, p_at timestamptz)
+    RETURNS date
+    LANGUAGE SQL
+    STABLE
+AS $$
+    SELECT local_date(p_at, (SELECT settings->>'timezone' FROM Chats WHERE id = p_chat_id));
+$$;
+
+COMMENT ON FUNCTION local_date IS 'The date of the moment in an IANA time zone like ''Europe/Moscow'', or in UTC for NULL';
+COMMENT ON FUNCTION chat_date  IS 'The date of the moment in the time zone of the chat (Chats.settings->>''timezone''), or in UTC if it has none';
+
+-- The same function as in migration 8, which starts a new day at the midnight of the chat.
+-- The attempts are checked first, so a bonus one doesn't have to look up the chat.
+CREATE OR REPLACE FUNCTION check_and_update_dicks_timestamp()
+    RETURNS TRIGGER
+    LANGUAGE PLPGSQL
+AS $$
+BEGIN
+    IF NEW.bonus_attempts = 0 AND chat_date(NEW.chat_id, current_timestamp) = chat_date(NEW.chat_id, OLD.updated_at) THEN
+        RAISE EXCEPTION 'Your dick has been already grown today!'
+            USING ERRCODE = 'GD0E1';
+    END IF;
+
+    IF NEW.bonus_attempts > 0 THEN
+        NEW.bonus_attempts := NEW.bonus_attempts - 1;
+    END IF;
+
+    RETURN NEW;
+END
+$$;
+
+-- The same function as in migration 7, which elects the Dick of the Day once a day of the chat.
+CREATE OR REPLACE FUNCTION check_dod_timestamp()
+    RETURNS TRIGGER
+    LANGUAGE PLPGSQL
+AS $$
+DECLARE
+    dod_name varchar;
+    today date := chat_date(NEW.chat_id, current_timestamp);
+BEGIN
+    SELECT name INTO dod_name FROM Dick_of_Day dod
+        JOIN Users u ON dod.winner_uid = u.uid
+        WHERE dod.created_at = today AND chat_id = NEW.chat_id;
+    IF dod_name IS NOT NULL THEN
+        RAISE EXCEPTION '%', dod_name
+            USING ERRCODE = 'GD0E2';
+    END IF;
+
+    NEW.created_at := today;
+    RETURN NEW;
+END
+$$;
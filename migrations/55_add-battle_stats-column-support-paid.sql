@@ -0,0 +1,3 @@
+ALTER TABLE Battle_Stats ADD COLUMN IF NOT EXISTS support_paid bigint NOT NULL DEFAULT 0 CHECK ( support_paid >= 0 );
+
+COMMENT ON COLUMN Battle_Stats.support_paid IS 'How much of the net loss the loser-support perk has already refunded; only the rest is paid out, so a streak that is never broken can''t be farmed';
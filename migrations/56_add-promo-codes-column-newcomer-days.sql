@@ -0,0 +1,6 @@
+ALTER TABLE Promo_Codes ADD COLUMN IF NOT EXISTS newcomer_days integer CHECK ( newcomer_days >= 0 );
+
+-- the default of REFERRAL_NEWCOMER_DAYS; every code takes the configured value on the next /invite of its owner
+UPDATE Promo_Codes SET newcomer_days = 3 WHERE inviter_uid IS NOT NULL AND newcomer_days IS NULL;
+
+COMMENT ON COLUMN Promo_Codes.newcomer_days IS 'For how many days after the registration a user may still activate the /invite code as a newcomer';
@@ -0,0 +1,2 @@
+COMMENT ON TABLE  Seasons        IS 'The finished seasons of a chat: at the end of each the lengths of Dicks above zero were archived into Season_Results and reset to zero; the lengths below zero, Loans and Member_Loans are carried into the next season';
+COMMENT ON TABLE  Season_Results IS 'The lengths the members had when their season ended; only the dicks that were above zero';
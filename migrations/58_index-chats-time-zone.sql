@@ -0,0 +1,9 @@
+-- no-transaction
+-- Backs the shrink scheduler's lookup of the chats at their midnight: the zones whose midnight has
+-- come are found first, and only their chats are read, in the order of the ids the run walks them
+-- in. The chats without a zone are in UTC, so they are indexed under it, the way they are looked up.
+--
+-- Kept alone in its migration for the same reason as migration 29: CREATE INDEX CONCURRENTLY can't
+-- run in the implicit transaction of a multi-statement string.
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_chats_time_zone
+    ON Chats ((coalesce(settings->>'timezone', 'UTC')), id);
@@ -0,0 +1,6 @@
+-- Added without a default first, so the rows logged before are left NULL rather than all stamped
+-- with the moment of this migration: nobody knows when they were made, and max() skips them.
+ALTER TABLE Stale_Dick_Shrinks ADD COLUMN IF NOT EXISTS shrunk_at timestamptz;
+ALTER TABLE Stale_Dick_Shrinks ALTER COLUMN shrunk_at SET DEFAULT current_timestamp;
+
+COMMENT ON COLUMN Stale_Dick_Shrinks.shrunk_at IS 'The moment the run made the shrink; created_at is the day of the chat it belongs to, which may be many hours off. NULL for the shrinks made before it was kept';
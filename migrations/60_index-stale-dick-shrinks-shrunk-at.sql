@@ -0,0 +1,8 @@
+-- no-transaction
+-- Backs the gauge of the last daily shrink, which reads max(shrunk_at) at every tick of the
+-- broadcast worker; without it, that is a walk over every shrink ever logged.
+--
+-- Kept alone in its migration for the same reason as migration 29: CREATE INDEX CONCURRENTLY can't
+-- run in the implicit transaction of a multi-statement string.
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_stale_dick_shrinks_shrunk_at
+    ON Stale_Dick_Shrinks(shrunk_at);
@@ -7,7 +7,7 @@ use teloxide::requests::Requester;
 use teloxide::types::{BotCommand, BotCommandScope};
 use teloxide::utils::command::BotCommands;
 use crate::config::CachedEnvToggles;
-use crate::handlers::{CleanupCommands, DickCommands, DickOfDayCommands, HelpCommands, ImportCommands, LanguageCommands, LoanCommands, PrivacyCommands, PromoCommands, StartCommands, SupportCommands, TopicsCommands};
+use crate::handlers::{AchievementsCommands, CleanupCommands, DickCommands, DickOfDayCommands, GiftCommands, HelpCommands, HistoryCommands, GlobalTopCommands, ImportCommands, InviteCommands, LanguageCommands, LeagueCommands, LoanCommands, PrivacyCommands, PromoAdminCommands, PromoCommands, PvpTopCommands, RepayCommands, RivalryCommands, RoyaleCommands, SeasonsCommands, SettingsCommands, ShopCommands, StartCommands, SupportCommands, TimeZoneCommands, TopicsCommands, TournamentCommands};
 use crate::handlers::pvp::{BattleCommands, BattleCommandsNoArgs};
 use crate::handlers::stats::StatsCommands;
 
@@ -23,16 +23,32 @@ pub static COMMAND_NAMES: Lazy<HashSet<String>> = Lazy::new(|| {
         HelpCommands::bot_commands(),
         PrivacyCommands::bot_commands(),
         PromoCommands::bot_commands(),
+        InviteCommands::bot_commands(),
+        PromoAdminCommands::bot_commands(),
         SupportCommands::bot_commands(),
         StatsCommands::bot_commands(),
+        PvpTopCommands::bot_commands(),
+        GlobalTopCommands::bot_commands(),
+        LeagueCommands::bot_commands(),
+        SeasonsCommands::bot_commands(),
+        RivalryCommands::bot_commands(),
+        HistoryCommands::bot_commands(),
+        AchievementsCommands::bot_commands(),
         LanguageCommands::bot_commands(),
         TopicsCommands::bot_commands(),
         CleanupCommands::bot_commands(),
+        SettingsCommands::bot_commands(),
+        TimeZoneCommands::bot_commands(),
         DickCommands::bot_commands(),
         DickOfDayCommands::bot_commands(),
         BattleCommands::bot_commands(),
         BattleCommandsNoArgs::bot_commands(),
+        TournamentCommands::bot_commands(),
+        RoyaleCommands::bot_commands(),
         LoanCommands::bot_commands(),
+        RepayCommands::bot_commands(),
+        GiftCommands::bot_commands(),
+        ShopCommands::bot_commands(),
         ImportCommands::bot_commands(),
     ].concat()
         .into_iter()
@@ -55,6 +71,10 @@ pub struct CommandToggles {
     /// Whether `/cleanup` is advertised — there is nothing for a chat to choose while the
     /// self-destruction is switched off altogether.
     pub cleanup_enabled: bool,
+    /// Whether `/invite` is advertised — with no invites configured it has no code to hand out.
+    pub invite_enabled: bool,
+    /// Whether `/seasons` is advertised — without seasons there are no champions to list.
+    pub seasons_enabled: bool,
 }
 
 pub async fn set_my_commands(
@@ -73,7 +93,9 @@ pub async fn set_my_commands(
         HelpCommands::bot_commands(),
         PrivacyCommands::bot_commands(),
         PromoCommands::bot_commands(),
+        if toggles.invite_enabled { InviteCommands::bot_commands() } else { Vec::new() },
         StatsCommands::bot_commands(),
+        GlobalTopCommands::bot_commands(),
         if toggles.personal_language_enabled { LanguageCommands::bot_commands() } else { Vec::new() },
         if toggles.support_enabled { SupportCommands::bot_commands() } else { Vec::new() },
     ];
@@ -82,16 +104,29 @@ pub async fn set_my_commands(
         DickCommands::bot_commands(),
         DickOfDayCommands::bot_commands(),
         BattleCommands::bot_commands(),
+        TournamentCommands::bot_commands(),
+        RoyaleCommands::bot_commands(),
         LoanCommands::bot_commands(),
+        RepayCommands::bot_commands(),
+        GiftCommands::bot_commands(),
+        ShopCommands::bot_commands(),
         StatsCommands::bot_commands(),
+        PvpTopCommands::bot_commands(),
+        LeagueCommands::bot_commands(),
+        if toggles.seasons_enabled { SeasonsCommands::bot_commands() } else { Vec::new() },
+        RivalryCommands::bot_commands(),
+        HistoryCommands::bot_commands(),
+        AchievementsCommands::bot_commands(),
     ];
-    // The chat-wide /language, /topics and /cleanup are admin-only, so they live in the admin
-    // scope, not the group one.
+    // The chat-wide /language, /topics, /cleanup, /settings and /timezone are admin-only, so they live in
+    // the admin scope, not the group one.
     let admin_commands = [group_commands.clone(), vec![
         ImportCommands::bot_commands(),
         LanguageCommands::bot_commands(),
         TopicsCommands::bot_commands(),
         if toggles.cleanup_enabled { CleanupCommands::bot_commands() } else { Vec::new() },
+        SettingsCommands::bot_commands(),
+        TimeZoneCommands::bot_commands(),
     ]].concat();
 
     let requests = vec![
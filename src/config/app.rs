@@ -1,12 +1,21 @@
+use std::time::Duration;
 use reqwest::Url;
 use crate::config::caches::CachesConfig;
 use crate::config::env::*;
 use crate::config::toggles::*;
 use crate::config::announcements::*;
+use crate::config::events::Events;
 use crate::config::self_destruction::*;
 use crate::config::shrink::{BroadcastConfig, DailyShrinkConfig};
 use crate::config::incrementor::IncrementorConfig;
-use crate::domain::primitives::{AttemptsCount, Bet, DaysCount, Limit, PayoutRatio, Ratio};
+use crate::config::shop::ShopConfig;
+use crate::config::referral::ReferralConfig;
+use crate::config::loan::LoanConfig;
+use crate::config::gift::GiftConfig;
+use crate::config::podium::PodiumConfig;
+use crate::config::seasons::SeasonsConfig;
+use crate::domain::objects::ChatGameSettings;
+use crate::domain::primitives::{AttemptsCount, Bet, DaysCount, Limit, PayoutRatio, Ratio, UserId};
 use crate::domain::primitives::chat::TelegramChatId;
 
 #[derive(Clone)]
@@ -16,14 +25,27 @@ pub struct AppConfig {
     pub top_limit: Limit,
     pub inactivity_days: DaysCount,
     pub loan_payout_ratio: PayoutRatio,
+    pub loan: LoanConfig,
+    pub gift: GiftConfig,
     pub dod_rich_exclusion_ratio: Option<Ratio>,
     pub pvp_default_bet: Bet,
+    pub pvp_mercy_window: Duration,
+    pub pvp_challenge_expiry: Duration,
+    pub pvp_rematch_window: Duration,
+    pub tournament_registration: Duration,
+    pub royale_registration: Duration,
     pub incrementor: IncrementorConfig,
     pub daily_shrink: DailyShrinkConfig,
+    pub podium: PodiumConfig,
+    pub seasons: SeasonsConfig,
+    pub shop: ShopConfig,
+    pub referral: ReferralConfig,
     pub announcements: AnnouncementsConfig,
+    pub events: Events,
     pub self_destruction: SelfDestructionConfig,
     pub command_toggles: CachedEnvToggles,
     pub support_chat_id: Option<TelegramChatId>,
+    pub owner_id: Option<UserId>,
     pub caches: CachesConfig,
 }
 
@@ -45,6 +67,13 @@ impl AppConfig {
         let top_unlimited = get_env_value_or_default("TOP_UNLIMITED_ENABLED", false);
         let multiple_loans = get_env_value_or_default("MULTIPLE_LOANS_ENABLED", false);
         let pvp_default_bet = env_value!("PVP_DEFAULT_BET": Bet, or = 1);
+        let pvp_mercy_window = EnvDuration::minutes("PVP_MERCY_WINDOW_MINUTES").or(10).read();
+        let pvp_challenge_expiry = EnvDuration::minutes("PVP_CHALLENGE_EXPIRY_MINUTES").or(5).at_least(1).read();
+        let pvp_rematch_window = EnvDuration::minutes("PVP_REMATCH_WINDOW_MINUTES").or(2).read();
+        let tournament_registration = EnvDuration::seconds("TOURNAMENT_REGISTRATION_SECONDS").or(120).at_least(10).read();
+        let royale_registration = EnvDuration::seconds("ROYALE_REGISTRATION_SECONDS").or(60).at_least(10).read();
+        let royale_weighted_by_length = get_env_value_or_default("ROYALE_WEIGHTED_BY_LENGTH", false);
+        let outcome_mode = get_optional_env_value("PVP_OUTCOME_MODE");
         let check_acceptor_length = get_env_value_or_default("PVP_CHECK_ACCEPTOR_LENGTH", false);
         let callback_locks = get_env_value_or_default("PVP_CALLBACK_LOCKS_ENABLED", true);
         let show_stats = get_env_value_or_default("PVP_STATS_SHOW", true);
@@ -69,6 +98,7 @@ impl AppConfig {
             },
         };
         let announcements_file = get_env_value_or_default("ANNOUNCEMENTS_FILE", "announcements.yml".to_string());
+        let events_file = get_env_value_or_default("EVENTS_FILE", "events.yml".to_string());
         let self_destruction = SelfDestructionConfig {
             notice: EnvDuration::minutes("MSG_SELFDESTRUCT_DELAY_NOTICE_MINUTES").read(),
             report: EnvDuration::minutes("MSG_SELFDESTRUCT_DELAY_REPORT_MINUTES").read(),
@@ -89,6 +119,7 @@ impl AppConfig {
             retention: EnvDuration::days("MSG_SELFDESTRUCT_TABLE_CLEANING_DELAY_DAYS").or(1).read(),
         };
         let support_chat_id = get_optional_chat_id("SUPPORT_CHAT_ID");
+        let owner_id = get_optional_user_id("OWNER_ID");
         Self {
             features: FeatureToggles {
                 chats_merging,
@@ -100,6 +131,8 @@ impl AppConfig {
                     callback_locks,
                     show_stats,
                     show_stats_notice,
+                    royale_weighted_by_length,
+                    outcome_mode,
                 },
                 most_popular_language_enabled,
                 hide_inactive_zero_length_from_top,
@@ -107,19 +140,58 @@ impl AppConfig {
             top_limit,
             inactivity_days,
             loan_payout_ratio,
+            loan: LoanConfig::from_env(),
+            gift: GiftConfig::from_env(),
             dod_rich_exclusion_ratio,
             pvp_default_bet,
+            pvp_mercy_window,
+            pvp_challenge_expiry,
+            pvp_rematch_window,
+            tournament_registration,
+            royale_registration,
             incrementor: IncrementorConfig::from_env(),
             daily_shrink,
+            podium: PodiumConfig::from_env(),
+            seasons: SeasonsConfig::from_env(),
+            shop: ShopConfig::from_env(),
+            referral: ReferralConfig::from_env(),
             announcements: AnnouncementsConfig::load(&announcements_file),
+            events: Events::load(&events_file),
             self_destruction,
             command_toggles: Default::default(),
             support_chat_id,
+            owner_id,
             caches: CachesConfig::from_env(),
         }
     }
 }
 
+impl AppConfig {
+    /// The configuration with the rules the chat changed for itself put in. Everything else stays
+    /// the bot's own, so a handler can take this for the global one and not know the difference.
+    pub fn for_chat(&self, settings: &ChatGameSettings) -> Self {
+        let mut config = self.clone();
+        if let Some(top_limit) = settings.top_limit {
+            config.top_limit = top_limit;
+        }
+        // a chat may tune the loans, not turn them on when the bot has them off
+        if let Some(ratio) = settings.loan_payout_ratio && !self.loan_payout_ratio.is_zero() {
+            config.loan_payout_ratio = ratio;
+        }
+        if let Some(mode) = settings.dod_selection_mode {
+            config.features.dod_selection_mode = mode;
+        }
+        if let Some(check) = settings.pvp_check_acceptor_length {
+            config.features.pvp.check_acceptor_length = check;
+        }
+        if let Some(mode) = settings.pvp_outcome_mode {
+            config.features.pvp.outcome_mode = mode;
+        }
+        config.incrementor = self.incrementor.for_chat(settings);
+        config
+    }
+}
+
 impl DatabaseConfig {
     pub fn from_env() -> anyhow::Result<Self> {
         Ok(Self {
@@ -19,6 +19,8 @@ pub struct CachesConfig {
     pub chat_topics: Duration,
     /// Which of the bot's messages a chat has it clean up, on the same terms.
     pub chat_cleanup: Duration,
+    /// The rules of the game a chat changed for itself, on the same terms.
+    pub chat_settings: Duration,
     /// How often the ban list is re-read. Never zero: it is an interval as much as a lifetime,
     /// and a zero one is a busy loop.
     pub ban_list_refresh: Duration,
@@ -34,6 +36,7 @@ impl CachesConfig {
             chat_language: EnvDuration::seconds("CHAT_LANGUAGE_CACHE_TIME_SECONDS").or(3600).read(),
             chat_topics: EnvDuration::seconds("CHAT_TOPICS_CACHE_TIME_SECONDS").or(3600).read(),
             chat_cleanup: EnvDuration::seconds("CHAT_CLEANUP_CACHE_TIME_SECONDS").or(3600).read(),
+            chat_settings: EnvDuration::seconds("CHAT_SETTINGS_CACHE_TIME_SECONDS").or(3600).read(),
             ban_list_refresh: EnvDuration::seconds("BAN_LIST_REFRESH_SECONDS").or(900).at_least(1).read(),
             bot_admin: EnvDuration::seconds("BOT_ADMIN_CACHE_TIME_SECONDS").or(3600).at_least(1).read(),
         }
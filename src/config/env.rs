@@ -4,7 +4,7 @@ use std::str::FromStr;
 use std::time::Duration;
 use anyhow::anyhow;
 use crate::domain::primitives::chat::TelegramChatId;
-use crate::domain::primitives::Ratio;
+use crate::domain::primitives::{Ratio, UserId};
 
 pub(super) fn get_env_mandatory_value<T, E>(key: &str) -> anyhow::Result<T>
 where
@@ -197,6 +197,14 @@ pub(super) fn get_optional_chat_id(key: &str) -> Option<TelegramChatId> {
         .map(TelegramChatId::new)
 }
 
+pub(super) fn get_optional_user_id(key: &str) -> Option<UserId> {
+    get_optional_env_string(key)?
+        .parse::<u64>()
+        .inspect_err(|e| tracing::warn!(key = %key, error = %e, "user id is not a number"))
+        .ok()
+        .map(UserId::new)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
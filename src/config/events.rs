@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use crate::domain::primitives::{Bet, LanguageCode, SupportedLanguage};
+
+/// The global events, shared by every clone so that one [`Events::reload`] reaches the incrementor
+/// and the handlers at once.
+#[derive(Clone, Default)]
+pub struct Events {
+    events: Arc<RwLock<Vec<Event>>>,
+}
+
+/// A period of time when the rules of the game are a bit different for everybody.
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Event {
+    pub id: String,
+    pub since: NaiveDate,
+    /// The last day of the event, inclusive.
+    pub until: NaiveDate,
+    names: HashMap<SupportedLanguage, String>,
+    pub modifiers: EventModifiers,
+}
+
+/// What an event changes. Every modifier is optional: an unset one leaves the usual rule alone.
+#[derive(Clone, Default, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct EventModifiers {
+    pub growth_min: Option<i16>,
+    pub growth_max: Option<i16>,
+    pub dod_bonus_multiplier: Option<u8>,
+    pub pvp_bet_multiplier: Option<u32>,
+}
+
+impl Events {
+    /// Loads the events from a YAML file (see [`EventsFile`]). As with the announcements, a missing
+    /// file means no events and a broken one is logged and means no events too.
+    pub fn load(path: &str) -> Self {
+        Self { events: Arc::new(RwLock::new(Self::read(path))) }
+    }
+
+    /// Reads `path` again and replaces the events.
+    // Called only from the SIGHUP handler, which Windows doesn't have.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    #[tracing::instrument(skip_all, fields(path = %path))]
+    pub fn reload(&self, path: &str) {
+        let fresh = Self::read(path);
+        let mut storage = match self.events.write() {
+            Ok(storage) => storage,
+            Err(_) => {
+                tracing::error!("the events lock is poisoned, skipping the reload");
+                return;
+            }
+        };
+        let count = fresh.len();
+        *storage = fresh;
+        tracing::info!(path = %path, events = count, "reloaded the events");
+    }
+
+    /// The event going on today (UTC, the same day the growth is counted by).
+    pub fn active(&self) -> Option<Event> {
+        self.active_on(Utc::now().date_naive())
+    }
+
+    /// When events overlap, the one written first in the file wins.
+    pub fn active_on(&self, date: NaiveDate) -> Option<Event> {
+        self.events.read().ok()?
+            .iter()
+            .find(|event| (event.since..=event.until).contains(&date))
+            .cloned()
+    }
+
+    fn read(path: &str) -> Vec<Event> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!(path = %path, "no events file, the events are disabled");
+                return Vec::default()
+            }
+            Err(e) => {
+                tracing::warn!(path = %path, error = %e, "couldn't read the events file");
+                return Vec::default()
+            }
+        };
+        let file: EventsFile = serde_saphyr::from_str(&content)
+            .inspect_err(|e| tracing::warn!(path = %path, error = %e, "couldn't parse the events file"))
+            .unwrap_or_default();
+        file.events.into_iter()
+            .filter_map(Event::try_from_entry)
+            .collect()
+    }
+}
+
+impl Event {
+    /// The name in the language of the chat, or in English, or the id if the file has neither.
+    pub fn name(&self, lang_code: &LanguageCode) -> &str {
+        self.names.get(&lang_code.to_supported_language())
+            .or_else(|| self.names.get(&SupportedLanguage::EN))
+            .unwrap_or(&self.id)
+    }
+
+    fn try_from_entry(entry: EventEntry) -> Option<Self> {
+        let modifiers = entry.modifiers;
+        let invalid_reason = if entry.since > entry.until {
+            Some("it ends before it starts")
+        } else if matches!((modifiers.growth_min, modifiers.growth_max), (Some(min), Some(max)) if min > max) {
+            Some("the growth range is empty")
+        } else if modifiers.dod_bonus_multiplier == Some(0) || modifiers.pvp_bet_multiplier == Some(0) {
+            Some("a multiplier is zero")
+        } else {
+            None
+        };
+        if let Some(reason) = invalid_reason {
+            tracing::warn!(id = %entry.id, reason, "skipping an invalid event of the events file");
+            return None
+        }
+        let names = entry.names.into_iter()
+            .filter_map(|(code, name)| SupportedLanguage::from_str(&code)
+                .inspect_err(|_| tracing::warn!(code = %code, "skipping an unknown language code of the events file"))
+                .ok().map(|lang| (lang, name)))
+            .collect();
+        Some(Self { id: entry.id, since: entry.since, until: entry.until, names, modifiers })
+    }
+}
+
+impl EventModifiers {
+    /// The usual range with the event's bounds put in. A bound that would turn the range inside
+    /// out is ignored together with the other one, so a growth is always possible.
+    pub fn growth_range(&self, usual: RangeInclusive<i16>) -> RangeInclusive<i16> {
+        let start = self.growth_min.unwrap_or(*usual.start());
+        let end = self.growth_max.unwrap_or(*usual.end());
+        if start <= end { start..=end } else { usual }
+    }
+
+    pub fn dod_bonus_range(&self, usual: RangeInclusive<u8>) -> RangeInclusive<u8> {
+        match self.dod_bonus_multiplier {
+            Some(multiplier) => usual.start().saturating_mul(multiplier)..=usual.end().saturating_mul(multiplier),
+            None => usual,
+        }
+    }
+
+    pub fn pvp_bet(&self, bet: Bet) -> Bet {
+        match self.pvp_bet_multiplier {
+            Some(multiplier) => Bet::new(bet.value().saturating_mul(multiplier)),
+            None => bet,
+        }
+    }
+}
+
+/// The on-disk shape of the events file, deserialized from YAML:
+///
+/// ```yaml
+/// events:
+///   - id: big-october
+///     since: 2026-10-01
+///     until: 2026-10-31
+///     names:
+///       en: Big October
+///       ru: Большой октябрь
+///     modifiers:
+///       growth_max: 20
+///       dod_bonus_multiplier: 2
+///       pvp_bet_multiplier: 2
+/// ```
+#[derive(Deserialize, Default)]
+struct EventsFile {
+    #[serde(default)]
+    events: Vec<EventEntry>,
+}
+
+#[derive(Deserialize)]
+struct EventEntry {
+    id: String,
+    since: NaiveDate,
+    until: NaiveDate,
+    #[serde(default)]
+    names: HashMap<String, String>,
+    #[serde(default)]
+    modifiers: EventModifiers,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use chrono::NaiveDate;
+    use crate::domain::primitives::{Bet, LanguageCode};
+    use super::{EventModifiers, Events};
+
+    /// Writes `content` to a uniquely-named temp file and returns the loaded events.
+    fn load_from(content: &str) -> Events {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir()
+            .join(format!("dgb-events-{}-{seq}.yml", std::process::id()));
+        let mut file = std::fs::File::create(&path).expect("couldn't create a temp file");
+        file.write_all(content.as_bytes()).expect("couldn't write the temp file");
+        let events = Events::load(path.to_str().expect("non-UTF-8 temp path"));
+        let _ = std::fs::remove_file(&path);
+        events
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").expect("invalid date in the test")
+    }
+
+    const FILE: &str = r#"
+events:
+  - id: big-october
+    since: 2026-10-01
+    until: 2026-10-31
+    names:
+      en: Big October
+      ru: Большой октябрь
+    modifiers:
+      growth_max: 20
+      pvp_bet_multiplier: 2
+  - id: overlapping
+    since: 2026-10-31
+    until: 2026-11-02
+"#;
+
+    #[test]
+    fn the_active_event_is_found_by_date() {
+        let events = load_from(FILE);
+        assert!(events.active_on(date("2026-09-30")).is_none());
+        let event = events.active_on(date("2026-10-01")).expect("no event on the first day");
+        assert_eq!(event.id, "big-october");
+        assert_eq!(event.modifiers.growth_max, Some(20));
+        assert_eq!(events.active_on(date("2026-10-31")).map(|e| e.id), Some("big-october".to_owned()),
+            "the first event of the file must win, and the last day must be included");
+        assert_eq!(events.active_on(date("2026-11-02")).map(|e| e.id), Some("overlapping".to_owned()));
+        assert!(events.active_on(date("2026-11-03")).is_none());
+    }
+
+    #[test]
+    fn the_name_falls_back_to_english_and_then_to_the_id() {
+        let events = load_from(FILE);
+        let event = events.active_on(date("2026-10-10")).expect("no event");
+        assert_eq!(event.name(&LanguageCode::new("ru".to_owned())), "Большой октябрь");
+        assert_eq!(event.name(&LanguageCode::new("it".to_owned())), "Big October");
+        let nameless = events.active_on(date("2026-11-01")).expect("no event");
+        assert_eq!(nameless.name(&LanguageCode::new("en".to_owned())), "overlapping");
+    }
+
+    #[test]
+    fn invalid_events_are_skipped() {
+        let events = load_from(r#"
+events:
+  - id: backwards
+    since: 2026-10-31
+    until: 2026-10-01
+  - id: empty-range
+    since: 2026-10-01
+    until: 2026-10-31
+    modifiers:
+      growth_min: 10
+      growth_max: 5
+  - id: zero
+    since: 2026-10-01
+    until: 2026-10-31
+    modifiers:
+      pvp_bet_multiplier: 0
+"#);
+        assert!(events.active_on(date("2026-10-10")).is_none());
+    }
+
+    #[test]
+    fn missing_or_malformed_file_yields_no_events() {
+        assert!(Events::load("definitely/does/not/exist.yml").active_on(date("2026-10-10")).is_none());
+        assert!(load_from("events: [this is not a list").active_on(date("2026-10-10")).is_none());
+    }
+
+    #[test]
+    fn modifiers() {
+        let modifiers = EventModifiers {
+            growth_min: None,
+            growth_max: Some(20),
+            dod_bonus_multiplier: Some(2),
+            pvp_bet_multiplier: Some(3),
+        };
+        assert_eq!(modifiers.growth_range(-5..=10), -5..=20);
+        assert_eq!(modifiers.dod_bonus_range(1..=5), 2..=10);
+        assert_eq!(modifiers.pvp_bet(Bet::new(10)), Bet::new(30));
+
+        let inside_out = EventModifiers { growth_min: Some(15), ..Default::default() };
+        assert_eq!(inside_out.growth_range(-5..=10), -5..=10);
+        assert_eq!(EventModifiers::default().pvp_bet(Bet::new(10)), Bet::new(10));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::config::env::env_value;
+use crate::domain::primitives::GiftAmount;
+
+/// The caps of `/gift`, per member of a chat a day: how much one may give away and how much one
+/// may get. The latter keeps a horde of fresh accounts from pumping their length into one dick.
+///
+/// A zero cap of either kind turns the command off.
+#[derive(Clone, Copy, Default)]
+pub struct GiftConfig {
+    pub daily_given_cap: GiftAmount,
+    pub daily_received_cap: GiftAmount,
+}
+
+impl GiftConfig {
+    pub(super) fn from_env() -> Self {
+        Self {
+            daily_given_cap: env_value!("GIFT_DAILY_GIVEN_CAP": GiftAmount, or = 20),
+            daily_received_cap: env_value!("GIFT_DAILY_RECEIVED_CAP": GiftAmount, or = 20),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.daily_given_cap.is_zero() && !self.daily_received_cap.is_zero()
+    }
+}
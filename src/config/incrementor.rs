@@ -1,6 +1,8 @@
 use std::ops::RangeInclusive;
 use crate::config::env::{env_value, get_env_value_or_default};
-use crate::domain::primitives::{DaysCount, Ratio};
+use crate::domain::objects::ChatGameSettings;
+use domain_types::traits::SaturatingInto;
+use crate::domain::primitives::{DaysCount, GrowStreak, LengthIncrement, LoseStreak, Ratio};
 use domain_types::literal;
 
 /// Tuning of the length changes produced by the incrementor and its perks.
@@ -16,6 +18,33 @@ pub struct IncrementorConfig {
 #[derive(Clone, Default)]
 pub struct PerksConfig {
     pub help_pussies_ratio: Ratio,
+    pub loser_support: LoserSupportConfig,
+    /// The share of every growth taken away from those who haven't repaid a loan by its due date.
+    pub loan_overdue_penalty_ratio: Ratio,
+    pub grow_streak: GrowStreakConfig,
+}
+
+/// Who counts as a chronic loser and how much of what they have lost in battles comes back with
+/// every growth.
+#[derive(Copy, Clone, Default)]
+pub struct LoserSupportConfig {
+    pub ratio: Ratio,
+    pub min_lose_streak: LoseStreak,
+}
+
+/// The bonus of a growth for every day in a row the dick has been grown on before, up to a limit.
+#[derive(Copy, Clone, Default)]
+pub struct GrowStreakConfig {
+    pub bonus_per_day: LengthIncrement,
+    pub max_days: DaysCount,
+}
+
+impl GrowStreakConfig {
+    /// The first day of a streak earns nothing: it isn't a streak yet.
+    pub fn bonus(&self, streak: GrowStreak) -> i64 {
+        let days = streak.value().saturating_sub(1).min(self.max_days.value());
+        i64::from(days).saturating_mul(self.bonus_per_day.value().saturating_into())
+    }
 }
 
 impl Default for IncrementorConfig {
@@ -44,10 +73,32 @@ impl IncrementorConfig {
             dod_bonus_range: *defaults.dod_bonus_range.start()..=dod_max_bonus,
             perks: PerksConfig {
                 help_pussies_ratio: env_value!("HELP_PUSSIES_COEF": Ratio),
+                loser_support: LoserSupportConfig {
+                    ratio: env_value!("LOSER_SUPPORT_COEF": Ratio),
+                    min_lose_streak: env_value!("LOSER_SUPPORT_MIN_LOSE_STREAK": LoseStreak, or = 3, at_least = 1),
+                },
+                loan_overdue_penalty_ratio: env_value!("LOAN_OVERDUE_PENALTY_COEF": Ratio),
+                grow_streak: GrowStreakConfig {
+                    bonus_per_day: env_value!("GROW_STREAK_BONUS_PER_DAY": LengthIncrement),
+                    max_days: env_value!("GROW_STREAK_MAX_DAYS": DaysCount, or = 7),
+                },
             },
         }
     }
 
+    /// The growth range and the ratio the chat chose, if any. As with the events, a bound that would
+    /// turn the range inside out is ignored together with the other one.
+    pub fn for_chat(&self, settings: &ChatGameSettings) -> Self {
+        let start = settings.growth_min.unwrap_or(*self.growth_range.start());
+        let end = settings.growth_max.unwrap_or(*self.growth_range.end());
+        let growth_range = if start <= end { start..=end } else { self.growth_range.clone() };
+        Self {
+            growth_range,
+            grow_shrink_ratio: settings.grow_shrink_ratio.unwrap_or(self.grow_shrink_ratio),
+            ..self.clone()
+        }
+    }
+
     pub fn growth_range_min(&self) -> i16 {
         self.growth_range.clone()
             .min()
@@ -0,0 +1,23 @@
+use crate::config::env::env_value;
+use crate::domain::primitives::{DaysCount, Ratio};
+
+/// The terms of the new loans, fixed in every loan when it's taken or refinanced, so a change of
+/// them doesn't reach the debts already owed.
+///
+/// Zeros give the loans of old: no interest and no due date.
+#[derive(Clone, Copy, Default)]
+pub struct LoanConfig {
+    /// Daily and compound.
+    pub interest_rate: Ratio,
+    /// How many days a loan is given for; the overdue penalty perk applies after that.
+    pub term: DaysCount,
+}
+
+impl LoanConfig {
+    pub(super) fn from_env() -> Self {
+        Self {
+            interest_rate: env_value!("LOAN_INTEREST_DAILY_RATE": Ratio),
+            term: env_value!("LOAN_TERM_DAYS": DaysCount),
+        }
+    }
+}
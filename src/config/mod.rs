@@ -2,8 +2,15 @@ mod app;
 mod bot;
 mod toggles;
 mod announcements;
+mod events;
 mod self_destruction;
 mod shrink;
+mod shop;
+mod referral;
+mod loan;
+mod gift;
+mod podium;
+mod seasons;
 mod throttle;
 mod incrementor;
 mod env;
@@ -16,9 +23,16 @@ pub use app::*;
 pub use bot::*;
 pub use toggles::*;
 pub use announcements::*;
+pub use events::*;
 pub use self_destruction::*;
 pub use throttle::*;
 pub use incrementor::*;
+pub use shop::*;
+pub use referral::*;
+pub use loan::*;
+pub use gift::*;
+pub use podium::*;
+pub use seasons::*;
 pub use help::*;
 pub use integrations::*;
 pub use redis::*;
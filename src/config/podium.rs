@@ -0,0 +1,30 @@
+use crate::config::env::get_env_value_or_default;
+use crate::domain::enums::TopPeriod;
+
+/// Which podiums are announced to the chats when their period is over. The tops of the periods
+/// are there for `/top` either way.
+#[derive(Clone, Copy, Default)]
+pub struct PodiumConfig {
+    pub week: bool,
+    pub month: bool,
+}
+
+impl PodiumConfig {
+    pub(super) fn from_env() -> Self {
+        Self {
+            week: get_env_value_or_default("PODIUM_WEEK_ENABLED", false),
+            month: get_env_value_or_default("PODIUM_MONTH_ENABLED", false),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.week || self.month
+    }
+
+    pub fn is_enabled_for(&self, period: TopPeriod) -> bool {
+        match period {
+            TopPeriod::Week => self.week,
+            TopPeriod::Month => self.month,
+        }
+    }
+}
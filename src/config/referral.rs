@@ -0,0 +1,41 @@
+use crate::config::env::env_value;
+use crate::domain::primitives::{DaysCount, PromoBonus, PromoCapacity};
+use crate::repo::ReferralCodeParams;
+
+/// What the personal codes of `/invite` pay, in centimeters added in every chat of the user.
+///
+/// Zero invites turn `/invite` off; the codes handed out before keep the invites they had left.
+#[derive(Clone, Copy, Default)]
+pub struct ReferralConfig {
+    pub invitee_bonus: PromoBonus,
+    pub inviter_bonus: PromoBonus,
+    pub max_invites: PromoCapacity,
+    /// For how many days after the registration a user may still come by an invite.
+    pub newcomer_days: DaysCount,
+}
+
+impl ReferralConfig {
+    pub(super) fn from_env() -> Self {
+        Self {
+            invitee_bonus: env_value!("REFERRAL_BONUS_INVITEE": PromoBonus, or = 5),
+            inviter_bonus: env_value!("REFERRAL_BONUS_INVITER": PromoBonus, or = 5),
+            max_invites: env_value!("REFERRAL_MAX_INVITES": PromoCapacity, or = 10),
+            newcomer_days: env_value!("REFERRAL_NEWCOMER_DAYS": DaysCount, or = 3),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.max_invites.value() > 0
+    }
+}
+
+impl From<ReferralConfig> for ReferralCodeParams {
+    fn from(value: ReferralConfig) -> Self {
+        Self {
+            invitee_bonus: value.invitee_bonus,
+            inviter_bonus: value.inviter_bonus,
+            max_invites: value.max_invites,
+            newcomer_days: value.newcomer_days,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{Datelike, Months, NaiveDate};
+use crate::config::env::get_env_value_or_default;
+
+/// When the seasons end, if ever. At the end of a season the lengths of a chat are archived and
+/// reset to zero, so the feature is off until it's asked for.
+#[derive(Clone, Copy, Default)]
+pub struct SeasonsConfig {
+    /// How many months a season lasts for every chat: they end together at the UTC midnight a month
+    /// whose number since the year 0 divides by it starts on — `3` ends them with the quarters,
+    /// `12` with the years. Zero leaves the ends to the admins of each chat.
+    pub length_months: u32,
+    /// Whether the admins may set the end of the current season of their chat themselves, which
+    /// takes the place of the global end until the season is over.
+    pub chat_scheduling: bool,
+}
+
+impl SeasonsConfig {
+    pub(super) fn from_env() -> Self {
+        Self {
+            length_months: get_env_value_or_default("SEASONS_LENGTH_MONTHS", 0),
+            chat_scheduling: get_env_value_or_default("SEASONS_CHAT_SCHEDULING_ENABLED", false),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.length_months > 0 || self.chat_scheduling
+    }
+
+    /// Whether the global season ended yesterday, which is what a run at the midnight of `today`
+    /// has to know.
+    pub fn global_season_ends_before(&self, today: NaiveDate) -> bool {
+        self.length_months > 0 && today.day() == 1 && Self::month_number(today) % self.length_months == 0
+    }
+
+    /// The last day of the global season `today` belongs to, or `None` if there are no global
+    /// seasons.
+    pub fn global_season_end(&self, today: NaiveDate) -> Option<NaiveDate> {
+        if self.length_months == 0 {
+            return None
+        }
+        let first_of_month = today.with_day(1)?;
+        let months_left = self.length_months - Self::month_number(today) % self.length_months;
+        first_of_month.checked_add_months(Months::new(months_left))?.pred_opt()
+    }
+
+    fn month_number(date: NaiveDate) -> u32 {
+        date.year().unsigned_abs() * 12 + date.month0()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use super::SeasonsConfig;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).expect("invalid date in the test")
+    }
+
+    const QUARTERLY: SeasonsConfig = SeasonsConfig { length_months: 3, chat_scheduling: false };
+
+    #[test]
+    fn a_quarter_ends_before_its_next_one_starts() {
+        assert!(QUARTERLY.global_season_ends_before(date(2026, 10, 1)));
+        assert!(QUARTERLY.global_season_ends_before(date(2027, 1, 1)));
+        assert!(!QUARTERLY.global_season_ends_before(date(2026, 11, 1)));
+        assert!(!QUARTERLY.global_season_ends_before(date(2026, 10, 2)));
+    }
+
+    #[test]
+    fn the_end_of_the_current_season() {
+        assert_eq!(QUARTERLY.global_season_end(date(2026, 10, 18)), Some(date(2026, 12, 31)));
+        assert_eq!(QUARTERLY.global_season_end(date(2026, 10, 1)), Some(date(2026, 12, 31)));
+        assert_eq!(QUARTERLY.global_season_end(date(2026, 12, 31)), Some(date(2026, 12, 31)));
+        let monthly = SeasonsConfig { length_months: 1, ..QUARTERLY };
+        assert_eq!(monthly.global_season_end(date(2028, 2, 10)), Some(date(2028, 2, 29)));
+    }
+
+    #[test]
+    fn no_global_seasons_without_a_length() {
+        let per_chat = SeasonsConfig { length_months: 0, chat_scheduling: true };
+        assert!(per_chat.enabled());
+        assert!(!per_chat.global_season_ends_before(date(2027, 1, 1)));
+        assert_eq!(per_chat.global_season_end(date(2026, 10, 18)), None);
+        assert!(!SeasonsConfig::default().enabled());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::config::env::env_value;
+use crate::domain::enums::ShopItem;
+use crate::domain::primitives::Price;
+
+/// Prices of the `/shop`, in centimeters taken from the buyer's length.
+///
+/// A zero price takes the item off the shelf rather than giving it away: a free shield or ticket
+/// would be bought every day by everybody, and then it wouldn't be worth anything.
+#[derive(Clone, Default)]
+pub struct ShopConfig {
+    pub reroll: Price,
+    pub shield: Price,
+    pub dod_ticket: Price,
+}
+
+impl ShopConfig {
+    pub(super) fn from_env() -> Self {
+        Self {
+            reroll: env_value!("SHOP_PRICE_REROLL": Price, or = 5),
+            shield: env_value!("SHOP_PRICE_SHIELD": Price, or = 10),
+            dod_ticket: env_value!("SHOP_PRICE_DOD_TICKET": Price, or = 30),
+        }
+    }
+
+    pub fn price(&self, item: ShopItem) -> Price {
+        match item {
+            ShopItem::Reroll => self.reroll,
+            ShopItem::Shield => self.shield,
+            ShopItem::DodTicket => self.dod_ticket,
+        }
+    }
+
+    /// Whether the item is sold at all.
+    pub fn sold(&self, item: ShopItem) -> bool {
+        self.price(item).value() > 0
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::{Arc, RwLock};
 
 const CACHED_ENV_TOGGLES_POISONED_MSG: &str = "CachedEnvToggles map was poisoned";
 
-#[derive(Copy, Clone, Default, derive_more::FromStr, derive_more::Display)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, derive_more::FromStr, derive_more::Display)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum DickOfDaySelectionMode {
     WEIGHTS,
@@ -12,6 +12,21 @@ pub enum DickOfDaySelectionMode {
     RANDOM
 }
 
+/// How the winner of a battle is chosen. Whatever the mode, the stake of the loser goes to the winner.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, derive_more::FromStr, derive_more::Display)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum PvpOutcomeMode {
+    /// The longer dick has the better odds.
+    WEIGHTS,
+    /// The shorter dick has the better odds, so that a battle is a way to catch up.
+    UNDERDOG,
+    /// No odds at all: both players pick a move of rock-paper-scissors, and the moves decide.
+    RPS,
+    /// A coin flip.
+    #[default]
+    RANDOM
+}
+
 #[derive(Clone, Copy)]
 pub struct FeatureToggles {
     pub chats_merging: bool,
@@ -44,6 +59,9 @@ pub struct BattlesFeatureToggles {
     pub callback_locks: bool,
     pub show_stats: bool,
     pub show_stats_notice: bool,
+    /// The longer the dick, the better the odds to take the pot of a `/royale`, instead of equal ones.
+    pub royale_weighted_by_length: bool,
+    pub outcome_mode: PvpOutcomeMode,
 }
 
 #[derive(Clone, Default)]
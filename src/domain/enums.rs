@@ -1,6 +1,9 @@
 //! Closed sets of domain values: neither primitives wrapping something else, nor objects made of
 //! several fields, but names the whole bot agrees on.
 
+use std::ops::Range;
+use chrono::{Datelike, Days, Months, NaiveDate};
+
 /// Lifetime category of a bot message, used to decide when (if ever) it self-destructs.
 ///
 /// * `Notice` = canned, always-the-same messages (help, privacy, errors, statuses);
@@ -20,3 +23,170 @@ pub enum MessageGroup {
     Event,
     Application,
 }
+
+/// Something a player has done in a chat that stays with them there for good.
+///
+/// The snake_case spelling is shared by the `achievement` enum of the database and the i18n keys
+/// under `commands.achievements.names`, so the order here is also the order they are listed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+#[sqlx(type_name = "achievement", rename_all = "snake_case")]
+pub enum Achievement {
+    HundredCm,
+    WinStreak,
+    LoanRepaid,
+    DodThrice,
+}
+
+/// What the `/shop` sells. Every item is bought ahead and used up by the handler it belongs to:
+/// a reroll by `/grow`, a shield by a battle, a ticket by the election of the Dick of the Day.
+///
+/// The snake_case spelling is shared by the `shop_item` enum of the database, the i18n keys under
+/// `commands.shop.items` and the callback data of the buy buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+#[sqlx(type_name = "shop_item", rename_all = "snake_case")]
+pub enum ShopItem {
+    Reroll,
+    Shield,
+    DodTicket,
+}
+
+/// Why the length of a dick has changed, as written into `Length_Events` for `/history`.
+///
+/// The snake_case spelling is shared by the `length_change_reason` enum of the database and the
+/// i18n keys under `commands.history.reasons`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+#[sqlx(type_name = "length_change_reason", rename_all = "snake_case")]
+pub enum LengthChangeReason {
+    Grow,
+    Dod,
+    Pvp,
+    Tournament,
+    Royale,
+    Loan,
+    LoanPayout,
+    Shrink,
+    Promo,
+    Import,
+    Shop,
+    Gift,
+    Lend,
+    LendRepayment,
+    Season,
+}
+
+/// How `/gift` gives the length away: for good, or as a loan the recipient pays back to the giver
+/// out of their growths.
+///
+/// The snake_case spelling is shared by the callback data of the confirmation buttons and the
+/// i18n keys under `commands.gift.callback.given`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum GiftKind {
+    Gift,
+    Lend,
+}
+
+impl From<GiftKind> for LengthChangeReason {
+    fn from(kind: GiftKind) -> Self {
+        match kind {
+            GiftKind::Gift => Self::Gift,
+            GiftKind::Lend => Self::Lend,
+        }
+    }
+}
+
+/// A rule of the game that the administrators of a chat may change for their chat with
+/// `/settings`. The rest of the rules are the same everywhere.
+///
+/// The snake_case spelling is shared by the keys of the `game` object of `Chats.settings`, the i18n
+/// keys under `commands.settings.names` and the callback data of the picker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum GameSetting {
+    GrowthMin,
+    GrowthMax,
+    GrowShrinkRatio,
+    DodSelectionMode,
+    TopLimit,
+    PvpCheckAcceptorLength,
+    PvpOutcomeMode,
+    LoanPayoutRatio,
+}
+
+/// The periods `/top` can rank the growth over instead of the length, and that a podium is
+/// announced at the end of. Weeks start on Monday, as in ISO 8601; both are in UTC.
+///
+/// The snake_case spelling is shared by the argument of the command, the i18n keys under
+/// `commands.top.periods` and the `broadcast_kind` enum of the database (as a prefix).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum TopPeriod {
+    Week,
+    Month,
+}
+
+impl TopPeriod {
+    /// The days of the period `day` falls into, the end excluded.
+    pub fn range_of(self, day: NaiveDate) -> Range<NaiveDate> {
+        let start = match self {
+            Self::Week => day - Days::new(day.weekday().num_days_from_monday().into()),
+            Self::Month => day.with_day(1).unwrap_or(day),
+        };
+        let end = match self {
+            Self::Week => start + Days::new(7),
+            Self::Month => start + Months::new(1),
+        };
+        start..end
+    }
+}
+
+/// What the `/pvptop` ranks the fighters of a chat by. The first one is what the bare command shows.
+///
+/// The snake_case spelling is shared by the argument of the command, the i18n keys under
+/// `commands.pvptop.rankings` and the callback data of the buttons switching between them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum BattleRanking {
+    #[default]
+    WinRate,
+    Wins,
+    WinStreak,
+    NetLength,
+}
+
+/// What the `/globaltop` ranks the users by: the longest of their dicks, or all of them together.
+/// The first one is what the bare command shows.
+///
+/// The snake_case spelling is shared by the argument of the command, the i18n keys under
+/// `commands.globaltop.rankings` and the callback data of the buttons switching between them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum GlobalRanking {
+    #[default]
+    Max,
+    Total,
+}
+
+/// What the `/league` ranks the chats by: the average length of their members, so that a big chat
+/// doesn't win by its size alone, or the sum of them. The first one is what the bare command shows.
+///
+/// The snake_case spelling is shared by the argument of the command and the i18n keys under
+/// `commands.league.rankings`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum LeagueRanking {
+    #[default]
+    Average,
+    Sum,
+}
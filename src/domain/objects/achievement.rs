@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use crate::domain::enums::Achievement;
+use crate::domain::primitives::{ElectionsCount, Length, WinStreak};
+
+const HUNDRED_CM: Length = Length::new(100);
+const LONG_WIN_STREAK: WinStreak = WinStreak::new(10);
+const DOD_ELECTIONS: ElectionsCount = ElectionsCount::new(3);
+
+/// Everything the conditions of [`Achievement`] are judged by, for one player in one chat. Read in
+/// one go after each event, so that an achievement reached by some other way than the event at
+/// hand (an import, a promo code) is noticed the next time the player does anything at all.
+#[derive(Debug, Default)]
+pub struct AchievementProgress {
+    pub length: Length,
+    pub win_streak_max: WinStreak,
+    pub loan_repaid: bool,
+    pub dod_elections: ElectionsCount,
+}
+
+#[derive(Debug)]
+pub struct UnlockedAchievement {
+    pub achievement: Achievement,
+    pub unlocked_at: DateTime<Utc>,
+}
+
+impl Achievement {
+    pub fn is_earned(self, progress: &AchievementProgress) -> bool {
+        match self {
+            Achievement::HundredCm => progress.length >= HUNDRED_CM,
+            Achievement::WinStreak => progress.win_streak_max >= LONG_WIN_STREAK,
+            Achievement::LoanRepaid => progress.loan_repaid,
+            Achievement::DodThrice => progress.dod_elections >= DOD_ELECTIONS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::enums::Achievement;
+    use crate::domain::primitives::{ElectionsCount, Length, WinStreak};
+    use super::AchievementProgress;
+
+    #[test]
+    fn nothing_is_earned_by_a_newcomer() {
+        let progress = AchievementProgress::default();
+        assert!(!Achievement::HundredCm.is_earned(&progress));
+        assert!(!Achievement::WinStreak.is_earned(&progress));
+        assert!(!Achievement::LoanRepaid.is_earned(&progress));
+        assert!(!Achievement::DodThrice.is_earned(&progress));
+    }
+
+    #[test]
+    fn the_thresholds_are_inclusive() {
+        let progress = AchievementProgress {
+            length: Length::new(100),
+            win_streak_max: WinStreak::new(10),
+            loan_repaid: true,
+            dod_elections: ElectionsCount::new(3),
+        };
+        assert!(Achievement::HundredCm.is_earned(&progress));
+        assert!(Achievement::WinStreak.is_earned(&progress));
+        assert!(Achievement::LoanRepaid.is_earned(&progress));
+        assert!(Achievement::DodThrice.is_earned(&progress));
+
+        let one_short = AchievementProgress {
+            length: Length::new(99),
+            win_streak_max: WinStreak::new(9),
+            loan_repaid: false,
+            dod_elections: ElectionsCount::new(2),
+        };
+        assert!(!Achievement::HundredCm.is_earned(&one_short));
+        assert!(!Achievement::WinStreak.is_earned(&one_short));
+        assert!(!Achievement::DodThrice.is_earned(&one_short));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::domain::primitives::{Length, Position, UserId};
+use chrono::NaiveDate;
+use crate::domain::enums::{LengthChangeReason, ShopItem};
+use crate::domain::primitives::{GrowStreak, Length, LoanPayout, Position, UserId};
 
 #[derive(Debug)]
 pub struct Dick {
@@ -13,3 +15,67 @@ pub struct GrowthResult {
     pub new_length: Length,
     pub pos_in_top: Option<Position>,
 }
+
+/// What a growth brings about besides the length of the grower. It's written in the same
+/// transaction as the growth, so a growth rejected by the database leaves none of it behind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrowthSettlement {
+    /// The share of the net loss in battles refunded by the loser-support perk.
+    SupportPaid(Length),
+    /// The share of the growth paid to a member who has lent length to the grower with `/gift`.
+    MemberLoanPayout {
+        loan_id: i64,
+        lender: UserId,
+        payout: LoanPayout,
+    },
+    /// An item the growth uses up, like the reroll of a shrink.
+    ItemUsed(ShopItem),
+}
+
+/// A place in the top of a period: what the dick has gained over it rather than how long it is.
+pub struct PeriodGrowth {
+    pub owner_uid: UserId,
+    pub owner_name: String,
+    /// The net change over the period, positive by definition: those who haven't gained anything
+    /// aren't ranked at all.
+    pub gained: Length,
+    pub length: Length,
+}
+
+/// The days in a row a dick has been grown on. A missed day breaks the streak only once it's
+/// over: whoever grew yesterday has the whole of today to keep it going.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Streak {
+    pub days: GrowStreak,
+    pub grown_today: bool,
+}
+
+impl Streak {
+    /// What the streak becomes once the dick is grown today.
+    pub fn with_today(self) -> GrowStreak {
+        if self.grown_today {
+            self.days
+        } else {
+            GrowStreak::new(self.days.value().saturating_add(1))
+        }
+    }
+}
+
+/// The trajectory of a dick over the last days, drawn by `/history`.
+pub struct LengthHistory {
+    /// One per day, the oldest first: the length at the end of the day.
+    pub days: Vec<DailyLength>,
+    /// What the length has been won and lost by over the same days, one total per reason.
+    pub changes: Vec<ReasonedChange>,
+}
+
+pub struct DailyLength {
+    pub day: NaiveDate,
+    pub length: Length,
+}
+
+pub struct ReasonedChange {
+    pub reason: LengthChangeReason,
+    /// Signed: a loss is negative.
+    pub change: Length,
+}
@@ -0,0 +1,121 @@
+use std::str::FromStr;
+use crate::config::{DickOfDaySelectionMode, PvpOutcomeMode};
+use crate::domain::enums::GameSetting;
+use crate::domain::primitives::{Limit, PayoutRatio, Ratio};
+
+/// What a chat changed of the rules of the game. A field that is `None` follows the bot's own
+/// configuration, and all of them being `None` is the default: a chat that never touched
+/// `/settings` has no `game` key at all.
+///
+/// Every value travels as text — through the callback data of the picker and into the `game`
+/// object — so [`Self::set`] and [`Self::get`] are the one place that knows how each is spelled.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChatGameSettings {
+    pub growth_min: Option<i16>,
+    pub growth_max: Option<i16>,
+    pub grow_shrink_ratio: Option<Ratio>,
+    pub dod_selection_mode: Option<DickOfDaySelectionMode>,
+    pub top_limit: Option<Limit>,
+    pub pvp_check_acceptor_length: Option<bool>,
+    pub pvp_outcome_mode: Option<PvpOutcomeMode>,
+    pub loan_payout_ratio: Option<PayoutRatio>,
+}
+
+impl ChatGameSettings {
+    /// Reads the value of one setting, spelled as [`Self::get`] spells it. Returns `false` and
+    /// changes nothing if the value doesn't fit: a top of zero rows, say, or a ratio above one.
+    pub fn set(&mut self, setting: GameSetting, value: &str) -> bool {
+        match setting {
+            GameSetting::GrowthMin => set_parsed(&mut self.growth_min, value, Some),
+            GameSetting::GrowthMax => set_parsed(&mut self.growth_max, value, Some),
+            GameSetting::GrowShrinkRatio => set_parsed(&mut self.grow_shrink_ratio, value,
+                |ratio: f64| Ratio::new(ratio).ok()),
+            GameSetting::DodSelectionMode => set_parsed(&mut self.dod_selection_mode, value, Some),
+            GameSetting::TopLimit => set_parsed(&mut self.top_limit, value,
+                |limit: u16| (limit > 0).then(|| Limit::new(limit))),
+            GameSetting::PvpCheckAcceptorLength => set_parsed(&mut self.pvp_check_acceptor_length, value, Some),
+            GameSetting::PvpOutcomeMode => set_parsed(&mut self.pvp_outcome_mode, value, Some),
+            GameSetting::LoanPayoutRatio => set_parsed(&mut self.loan_payout_ratio, value,
+                |ratio: f32| PayoutRatio::new(ratio).ok()),
+        }
+    }
+
+    /// The value the chat chose for a setting, or `None` when it left the choice to the bot.
+    pub fn get(&self, setting: GameSetting) -> Option<String> {
+        match setting {
+            GameSetting::GrowthMin => self.growth_min.map(|v| v.to_string()),
+            GameSetting::GrowthMax => self.growth_max.map(|v| v.to_string()),
+            GameSetting::GrowShrinkRatio => self.grow_shrink_ratio.map(|v| v.value().to_string()),
+            GameSetting::DodSelectionMode => self.dod_selection_mode.map(|v| v.to_string()),
+            GameSetting::TopLimit => self.top_limit.map(|v| v.to_string()),
+            GameSetting::PvpCheckAcceptorLength => self.pvp_check_acceptor_length.map(|v| v.to_string()),
+            GameSetting::PvpOutcomeMode => self.pvp_outcome_mode.map(|v| v.to_string()),
+            GameSetting::LoanPayoutRatio => self.loan_payout_ratio.map(|v| v.value().to_string()),
+        }
+    }
+
+    /// Whether the chat follows the bot's configuration in everything.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn set_parsed<T, P: FromStr>(field: &mut Option<T>, value: &str, validate: impl FnOnce(P) -> Option<T>) -> bool {
+    match value.parse().ok().and_then(validate) {
+        Some(value) => {
+            field.replace(value);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strum::IntoEnumIterator;
+    use super::*;
+
+    #[test]
+    fn test_an_untouched_chat_chose_nothing() {
+        let settings = ChatGameSettings::default();
+        assert!(settings.is_default());
+        assert!(GameSetting::iter().all(|setting| settings.get(setting).is_none()));
+    }
+
+    /// What goes in by `set` comes out of `get` spelled the same way, or the picker couldn't tell
+    /// which of its buttons is the current one.
+    #[test]
+    fn test_a_value_is_spelled_back_the_way_it_was_set() {
+        let values = [
+            (GameSetting::GrowthMin, "-5"),
+            (GameSetting::GrowthMax, "20"),
+            (GameSetting::GrowShrinkRatio, "0.7"),
+            (GameSetting::DodSelectionMode, "WEIGHTS"),
+            (GameSetting::TopLimit, "15"),
+            (GameSetting::PvpCheckAcceptorLength, "true"),
+            (GameSetting::PvpOutcomeMode, "RPS"),
+            (GameSetting::LoanPayoutRatio, "0.05"),
+        ];
+        let mut settings = ChatGameSettings::default();
+        for (setting, value) in values {
+            assert!(settings.set(setting, value), "{setting} refused {value}");
+            assert_eq!(settings.get(setting).as_deref(), Some(value));
+        }
+        assert!(!settings.is_default());
+        assert_eq!(settings.dod_selection_mode, Some(DickOfDaySelectionMode::WEIGHTS));
+    }
+
+    #[test]
+    fn test_a_value_that_does_not_fit_changes_nothing() {
+        let mut settings = ChatGameSettings::default();
+        assert!(settings.set(GameSetting::TopLimit, "10"));
+        assert!(!settings.set(GameSetting::TopLimit, "0"));
+        assert!(!settings.set(GameSetting::TopLimit, "many"));
+        assert!(!settings.set(GameSetting::GrowShrinkRatio, "1.5"));
+        assert!(!settings.set(GameSetting::GrowthMin, "-100000"));
+        assert!(!settings.set(GameSetting::DodSelectionMode, "LOTTERY"));
+        assert!(!settings.set(GameSetting::PvpOutcomeMode, "EXCLUSION"));
+        assert_eq!(settings.get(GameSetting::TopLimit).as_deref(), Some("10"));
+        assert_eq!(settings.grow_shrink_ratio, None);
+    }
+}
@@ -0,0 +1,9 @@
+use crate::domain::enums::ShopItem;
+use crate::domain::primitives::ItemsCount;
+
+/// Items of one kind a player has bought in a chat and not used yet.
+#[derive(Debug, PartialEq)]
+pub struct InventoryItem {
+    pub item: ShopItem,
+    pub quantity: ItemsCount,
+}
@@ -1,7 +1,55 @@
-use crate::domain::primitives::{Debt, PayoutRatio};
+use chrono::NaiveDate;
+use num_traits::ToPrimitive;
+use crate::domain::primitives::{DaysCount, Debt, PayoutRatio, Ratio};
+
+/// A projection further than this is no projection at all.
+const MAX_PROJECTION_DAYS: u32 = 3650;
 
 #[derive(Debug)]
 pub struct Loan {
+    /// The balance: what is left of the principal with the interest accrued until today.
     pub debt: Debt,
     pub payout_ratio: PayoutRatio,
+    pub interest_rate: Ratio,
+    pub due_date: Option<NaiveDate>,
+    pub overdue: bool,
+}
+
+impl Loan {
+    /// How many days the balance takes to be repaid at `daily_payout` centimeters a day with the
+    /// interest growing it meanwhile. `None` if the interest keeps up with the payouts.
+    pub fn days_to_repay(&self, daily_payout: f64) -> Option<DaysCount> {
+        let mut balance = self.debt.value().to_f64()?;
+        let growth = 1.0 + self.interest_rate.value();
+        (1..=MAX_PROJECTION_DAYS)
+            .find(|_| {
+                balance = balance * growth - daily_payout;
+                balance <= 0.0
+            })
+            .map(DaysCount::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use domain_types::literal;
+    use crate::domain::primitives::{DaysCount, Debt, PayoutRatio, Ratio};
+    use super::Loan;
+
+    fn loan(debt: u64, interest_rate: Ratio) -> Loan {
+        Loan { debt: Debt::new(debt), payout_ratio: literal!(PayoutRatio = 0.1), interest_rate, due_date: None, overdue: false }
+    }
+
+    #[test]
+    fn test_days_to_repay() {
+        let no_interest = loan(10, literal!(Ratio = 0.0));
+        assert_eq!(no_interest.days_to_repay(1.0), Some(DaysCount::new(10)));
+        assert_eq!(no_interest.days_to_repay(3.0), Some(DaysCount::new(4)));
+        assert_eq!(no_interest.days_to_repay(0.0), None);
+
+        let with_interest = loan(100, literal!(Ratio = 0.1));
+        assert_eq!(with_interest.days_to_repay(10.0), None, "the interest eats the whole payout");
+        let days = with_interest.days_to_repay(20.0).expect("a payout twice the interest must repay the loan");
+        assert!(days > DaysCount::new(5), "the interest must make it longer than without it: {days}");
+    }
 }
@@ -6,6 +6,10 @@ mod tghack;
 mod stats;
 mod topics;
 mod cleanup;
+mod game_settings;
+mod achievement;
+mod inventory;
+mod time_zone;
 
 pub use announcement::*;
 pub use user::*;
@@ -15,3 +19,7 @@ pub use tghack::*;
 pub use stats::*;
 pub use topics::*;
 pub use cleanup::*;
+pub use game_settings::*;
+pub use achievement::*;
+pub use inventory::*;
+pub use time_zone::*;
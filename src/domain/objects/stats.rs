@@ -1,4 +1,7 @@
-use crate::domain::primitives::{BattlesCount, Length, Percentage, WinStreak};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::domain::objects::Dick;
+use crate::domain::primitives::{BattlesCount, Bet, Count, Length, LoseStreak, Percentage, Position, UserId, WinStreak};
+use crate::domain::primitives::chat::InternalChatId;
 use domain_types::literal;
 
 pub struct UserStats {
@@ -6,8 +9,10 @@ pub struct UserStats {
     pub battles_won: BattlesCount,
     pub win_streak_max: WinStreak,
     pub win_streak_current: WinStreak,
+    pub lose_streak_current: LoseStreak,
     pub acquired_length: Length,
     pub lost_length: Length,
+    pub mercies_shown: BattlesCount,
 }
 
 pub type WinnerStats = UserStats;
@@ -22,6 +27,70 @@ pub struct BattleStats {
     pub loser: LoserStats,
 }
 
+/// A line of the `/pvptop`: the stats of a member of the chat and their place in the ranking.
+pub struct RankedFighter {
+    pub uid: UserId,
+    pub name: String,
+    pub position: Position,
+    pub stats: UserStats,
+}
+
+/// A line of the `/globaltop`: a user, the length they are ranked by across all their chats, and
+/// their place in the ranking.
+pub struct RankedUser {
+    pub uid: UserId,
+    pub name: String,
+    pub length: Length,
+    pub position: Position,
+}
+
+/// A line of the `/league`: a chat that has joined it and the length of its members it's ranked by.
+pub struct LeagueStanding {
+    pub chat_id: InternalChatId,
+    /// The title of the chat as of its joining: the bot is told the title only with a message.
+    pub title: String,
+    pub members: Count<Dick>,
+    pub length: Length,
+    pub position: Position,
+    /// Whether it's the chat the standings were asked from.
+    pub is_current: bool,
+}
+
+/// A line of `/seasons`: who finished a past season of the chat at the top, and with how much.
+pub struct SeasonChampion {
+    pub number: u32,
+    pub ended_on: NaiveDate,
+    pub uid: UserId,
+    pub name: String,
+    pub length: Length,
+}
+
+/// The battles of two members of a chat against each other, as seen by the first of them.
+pub struct Rivalry {
+    pub battles: BattlesCount,
+    pub wins: BattlesCount,
+    pub losses: BattlesCount,
+    /// The length won from the rival minus the length lost to them. The awards given back don't
+    /// count: no length has changed hands in those battles after all.
+    pub net_length: Length,
+    /// The most recent first.
+    pub last_encounters: Vec<Encounter>,
+}
+
+pub struct Encounter {
+    pub winner: UserId,
+    pub bet: Bet,
+    pub mercy_shown: bool,
+    pub fought_at: DateTime<Utc>,
+}
+
+impl UserStats {
+    /// The length won in battles minus the length lost in them.
+    pub fn net_length(&self) -> Length {
+        Length::new(self.acquired_length.value().saturating_sub(self.lost_length.value()))
+    }
+}
+
 pub trait WinRateAware {
     fn win_rate_percentage(&self) -> Percentage;
 }
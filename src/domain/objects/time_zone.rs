@@ -0,0 +1,30 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Offset, Utc};
+
+/// The time zone a chat starts its days in, which is when a dick may grow again, a new Dick of the
+/// Day may be elected and the inactive dicks shrink.
+///
+/// The offset is the one the zone has right now: it changes with the daylight saving time, so it's
+/// read from the database along with the name every time rather than kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatTimeZone {
+    /// The IANA name the admins have set, like `Europe/Moscow`; `None` for UTC, the default.
+    pub name: Option<String>,
+    pub utc_offset: FixedOffset,
+}
+
+impl ChatTimeZone {
+    /// The date of the moment in the chat. An old moment is seen with the offset of today, which is
+    /// an hour off at worst across a change of the daylight saving time.
+    pub fn date_of(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.utc_offset).date_naive()
+    }
+}
+
+impl Default for ChatTimeZone {
+    fn default() -> Self {
+        Self {
+            name: None,
+            utc_offset: Utc.fix(),
+        }
+    }
+}
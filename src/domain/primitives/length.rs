@@ -9,6 +9,8 @@ number!(LengthIncrement, u64);
 
 number!(Bet, u32);
 number!(LoanPayout, u32);
+number!(Price, u32);
+number!(GiftAmount, u32);
 
 #[domain_type(number, features(no_auto_display))]
 struct SignedLengthChange(i64);
@@ -82,3 +84,11 @@ impl Bet {
         LengthChange::signed(-value)
     }
 }
+
+impl Price {
+    /// What paying the price does to the buyer's length.
+    pub fn as_length_change(&self) -> LengthChange {
+        let value: i64 = self.0.into();
+        LengthChange::signed(-value)
+    }
+}
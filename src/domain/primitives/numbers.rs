@@ -16,9 +16,25 @@ struct BattlesCount(u32);
 #[domain_type(number)]
 struct WinStreak(u16);
 
+/// Battles lost in a row: the opposite of [`WinStreak`], kept for the perk that supports losers.
+#[domain_type(number)]
+struct LoseStreak(u16);
+
+/// Days in a row a dick has been grown on.
+#[domain_type(number)]
+struct GrowStreak(u32);
+
 #[domain_type(number)]
 struct Position(u64);
 
+/// How many times a player has been elected the Dick of the Day in one chat.
+#[domain_type(number)]
+struct ElectionsCount(u32);
+
+/// How many items of one kind a player keeps in their inventory.
+#[domain_type(number)]
+struct ItemsCount(u32);
+
 #[domain_type(number)]
 struct AffectedRows(u64);
 
@@ -0,0 +1,108 @@
+use autometrics::autometrics;
+use std::collections::HashMap;
+use anyhow::anyhow;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use crate::config::MessageGroup;
+use crate::domain::enums::Achievement;
+use crate::domain::primitives::{LanguageCode, UserId};
+use crate::domain::primitives::chat::ChatIdKind;
+use crate::handlers::{FromRefs, HandlerDeps, HandlerResult, reply_html};
+use crate::{metrics, reply_html_ephemeral};
+use crate::repo::Repositories;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum AchievementsCommands {
+    #[command(description = "achievements")]
+    Achievements,
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn achievements_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, self_destruction, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_ACHIEVEMENTS.chat.inc();
+
+    let from = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    let chat_id = msg.chat.id.into();
+    let answer = achievements_impl(&repos, FromRefs(from, &chat_id), &lang_code).await?;
+    reply_html_ephemeral!(bot, msg, answer, self_destruction, MessageGroup::Report, lang_code);
+    Ok(())
+}
+
+/// Every achievement there is, the unlocked ones with the date and the rest with a lock, so that
+/// the list also tells what is still ahead.
+pub(crate) async fn achievements_impl(
+    repos: &Repositories,
+    from_refs: FromRefs<'_>,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<String> {
+    let unlocked: HashMap<Achievement, String> = repos.achievements
+        .get_unlocked(&from_refs.1.kind(), UserId::from(from_refs.0)).await?
+        .into_iter()
+        .map(|a| (a.achievement, a.unlocked_at.format(DATE_FORMAT).to_string()))
+        .collect();
+    let lines = Achievement::iter()
+        .map(|achievement| {
+            let name = t!(&format!("commands.achievements.names.{achievement}"), locale = lang_code);
+            let condition = t!(&format!("commands.achievements.conditions.{achievement}"), locale = lang_code);
+            match unlocked.get(&achievement) {
+                Some(date) => t!("commands.achievements.line.unlocked", locale = lang_code,
+                    name = name, condition = condition, date = date),
+                None => t!("commands.achievements.line.locked", locale = lang_code,
+                    name = name, condition = condition),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let title = t!("commands.achievements.title", locale = lang_code,
+        unlocked = unlocked.len(), total = Achievement::iter().count());
+    Ok(format!("{title}\n\n{lines}"))
+}
+
+/// Unlocks whatever the player has reached by now and returns the part of the reply that announces
+/// it, or an empty string. Called after every event that can move a condition: a growth (which is
+/// also where a loan gets paid off), an election and a battle (the winner's award pays loans too).
+///
+/// Best-effort: the event has already happened by the time this runs, so a failure here is logged
+/// and the announcement is simply left out — the next event unlocks it instead.
+pub(crate) async fn unlock_achievements(
+    repos: &Repositories,
+    chat_id: &ChatIdKind,
+    uid: UserId,
+    lang_code: &LanguageCode,
+) -> String {
+    let unlocked: anyhow::Result<Vec<Achievement>> = async {
+        let progress = repos.achievements.get_progress(chat_id, uid).await?;
+        let earned: Vec<Achievement> = Achievement::iter()
+            .filter(|a| a.is_earned(&progress))
+            .collect();
+        repos.achievements.unlock(chat_id, uid, &earned).await
+    }.await;
+    let unlocked = match unlocked {
+        Ok(unlocked) if unlocked.is_empty() => return String::default(),
+        Ok(unlocked) => unlocked,
+        Err(e) => {
+            tracing::error!(error = format!("{e:#}"), "couldn't unlock the achievements");
+            return String::default()
+        }
+    };
+    let names = unlocked.into_iter()
+        .inspect(|a| metrics::ACHIEVEMENT_UNLOCKED.record(*a))
+        .map(|a| t!(&format!("commands.achievements.names.{a}"), locale = lang_code).to_string())
+        .map(|name| format!("<b>{name}</b>"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("\n\n{}", t!("commands.achievements.unlocked", locale = lang_code, names = names))
+}
@@ -50,7 +50,7 @@ pub async fn cleanup_cmd_handler(
     }
     let from_id = msg.from.as_ref().map(|user| user.id)
         .ok_or(anyhow!("unexpected absence of a FROM field"))?;
-    if !is_chat_admin(&bot, &msg, from_id).await? {
+    if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
         reply_html_ephemeral!(bot, msg, t!("commands.cleanup.errors.admins_only", locale = &lang_code),
             self_destruction, MessageGroup::Notice, lang_code);
         return Ok(());
@@ -1,24 +1,30 @@
 use autometrics::autometrics;
 
 use anyhow::anyhow;
-use chrono::{Datelike, Utc};
+use std::collections::HashMap;
+use std::str::FromStr;
+use chrono::{NaiveDate, Utc};
 use domain_types::traits::SaturatingInto;
 use num_traits::ToPrimitive;
 use rust_i18n::t;
+use strum::IntoEnumIterator;
 use teloxide::Bot;
 use teloxide::macros::BotCommands;
 use teloxide::types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ReplyMarkup};
 use teloxide::types::{User as TeloxideUser};
 use crate::config::{AppConfig, MessageGroup};
 use crate::{metrics, reply_html_ephemeral, repo};
-use crate::domain::objects::GrowthResult;
-use crate::domain::primitives::chat::ChatIdPartiality;
-use crate::domain::primitives::{LanguageCode, Username, Offset, Page, UserId, DaysCount, InvalidPage};
-use crate::handlers::{answer_callback_feature_disabled, banned_until_of, HandlerDeps, HandlerResult, TaggedReply, reply_html, utils};
-use crate::handlers::utils::{callbacks, Incrementor};
+use crate::domain::enums::{ShopItem, TopPeriod};
+use crate::domain::objects::{GrowthResult, GrowthSettlement};
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
+use crate::domain::primitives::{Count, LanguageCode, Limit, Username, Offset, Page, UserId, DaysCount, InvalidPage, GrowStreak};
+use crate::handlers::{achievements, answer_callback_feature_disabled, banned_until_of, HandlerDeps, HandlerResult, TaggedReply, reply_html, utils};
+use crate::handlers::utils::{callbacks, Increment, Incrementor};
+use crate::settings::GameSettingsPolicy;
 
 const TOMORROW_SQL_CODE: &str = "GD0E1";
 const CALLBACK_PREFIX_TOP_PAGE: &str = "top:page:";
+const DATE_FORMAT: &str = "%d.%m.%Y";
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -26,7 +32,7 @@ pub enum DickCommands {
     #[command(description = "grow")]
     Grow,
     #[command(description = "top")]
-    Top,
+    Top(String),
 }
 
 #[autometrics]
@@ -36,6 +42,7 @@ pub async fn dick_cmd_handler(
     msg: Message,
     cmd: DickCommands,
     incr: Incrementor,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
@@ -51,8 +58,23 @@ pub async fn dick_cmd_handler(
             let reply = grow_impl(&repos, incr, from_refs, &lang_code).await?;
             reply_html_ephemeral!(bot, msg, reply.text, self_destruction, reply.group, lang_code);
         },
-        DickCommands::Top => {
+        DickCommands::Top(args) => {
             metrics::CMD_TOP_COUNTER.chat.inc();
+            let config = settings.config_for(&chat_id.kind(), &config).await;
+            let period = match parse_period(&args) {
+                Ok(period) => period,
+                Err(()) => {
+                    let periods = TopPeriod::iter().map(|p| format!("<code>/top {p}</code>")).collect::<Vec<_>>().join(", ");
+                    let text = t!("commands.top.errors.unknown_period", locale = &lang_code, periods = periods);
+                    reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+                    return Ok(())
+                }
+            };
+            if let Some(period) = period {
+                let top = period_top_impl(&repos, &config, from_refs, &lang_code, period).await?;
+                reply_html_ephemeral!(bot, msg, top.lines, self_destruction, MessageGroup::Report, lang_code);
+                return Ok(())
+            }
             let top = top_impl(&repos, &config, from_refs, &lang_code, Page::first()).await?;
             let keyboard = (top.has_more_pages && config.features.top_unlimited)
                 .then(|| ReplyMarkup::InlineKeyboard(
@@ -75,7 +97,7 @@ pub(crate) async fn grow_impl(
     let (from, chat_id) = (from_refs.0, from_refs.1);
     let uid = UserId::from(from);
     let name = utils::get_full_name(from);
-    let user = match repos.users.create_or_update(uid, &name).await {
+    let user = match repos.users.create_or_update_with_username(uid, &name, from.username.as_deref()).await {
         Ok(user) => user,
         Err(e) if let Some(date) = banned_until_of(&e) => {
             let text = t!("errors.banned", locale = lang_code, date = date).to_string();
@@ -87,8 +109,21 @@ pub(crate) async fn grow_impl(
     let days_since_registration = days_since_registration.num_days().to_u32()
         .map(DaysCount::new)
         .ok_or_else(|| anyhow!("days since registration are too much: {days_since_registration}"))?;
-    let increment = incr.growth_increment(uid, chat_id.kind(), days_since_registration).await;
-    let grow_result = repos.dicks.create_or_grow(uid, chat_id, increment.total).await;
+    let mut increment = incr.growth_increment(uid, chat_id.kind(), days_since_registration).await;
+
+    let mut reroll_part = String::default();
+    let mut rerolled = false;
+    if increment.total.value().is_negative()
+        && let Some(rerolled_increment) = reroll_shrink(repos, &incr, uid, chat_id, days_since_registration).await
+    {
+        reroll_part = format!("\n{}", t!("commands.grow.rerolled", locale = lang_code, shrink = increment.total.value().abs()));
+        increment = rerolled_increment;
+        rerolled = true;
+    }
+    let grow_result = repos.dicks.create_or_grow_with_settlements(uid, chat_id, increment.total, &increment.settlements).await;
+    if rerolled && grow_result.is_ok() {
+        metrics::SHOP_ITEM_USED.record(ShopItem::Reroll);
+    }
 
     let (main_part, group) = match grow_result {
         Ok(GrowthResult { new_length, pos_in_top }) => {
@@ -98,11 +133,20 @@ pub(crate) async fn grow_impl(
             let answer = t!("commands.grow.result", locale = lang_code,
                 event = event, incr = increment.total.value().abs(), length = new_length);
             let perks_part = increment.perks_part_of_answer(lang_code);
+            let event_part = increment.event_part_of_answer(lang_code);
+            let achievements_part = achievements::unlock_achievements(repos, &chat_id.kind(), uid, lang_code).await;
+            let streak = repos.dicks.fetch_grow_streak(uid, &chat_id.kind()).await?.days;
+            // a streak of one day is just a growth
+            let streak_part = if streak > GrowStreak::new(1) {
+                format!("\n{}", t!("commands.grow.streak", locale = lang_code, days = streak))
+            } else {
+                String::default()
+            };
             let text = if let Some(pos) = pos_in_top {
                 let position = t!("commands.grow.position", locale = lang_code, pos = pos);
-                format!("{answer}\n{position}{perks_part}")
+                format!("{answer}{reroll_part}\n{position}{streak_part}{perks_part}{event_part}{achievements_part}")
             } else {
-                format!("{answer}{perks_part}")
+                format!("{answer}{reroll_part}{streak_part}{perks_part}{event_part}{achievements_part}")
             };
             (text, MessageGroup::Event)
         },
@@ -119,24 +163,52 @@ pub(crate) async fn grow_impl(
             }
         }
     };
-    let time_left_part = utils::date::get_time_till_next_day_string(lang_code);
+    let time_zone = repos.chats.get_time_zone(&chat_id.kind()).await?;
+    let time_left_part = utils::date::get_time_till_next_day_string(lang_code, time_zone.utc_offset);
     Ok(TaggedReply { text: format!("{main_part}{time_left_part}"), group })
 }
 
+/// Rolls a shrink once again if the user has bought a reroll in the `/shop`. The roll is replaced
+/// before anything is written, so the perks of the shrink are never settled and those of the new
+/// roll are settled just once, along with the growth. So is the reroll itself: it's used up only
+/// by a growth that does take place.
+///
+/// Best-effort like the rest of the extras of a growth: a failure leaves the shrink as it was.
+async fn reroll_shrink(
+    repos: &repo::Repositories,
+    incr: &Incrementor,
+    uid: UserId,
+    chat_id: &ChatIdPartiality,
+    days_since_registration: DaysCount,
+) -> Option<Increment> {
+    let chat_id_kind = chat_id.kind();
+    let has_reroll = repos.inventory.get_items(&chat_id_kind, uid).await
+        .inspect_err(|e| tracing::error!(error = format!("{e:#}"), "couldn't check for a reroll"))
+        .ok()?
+        .iter()
+        .any(|item| item.item == ShopItem::Reroll);
+    if !has_reroll {
+        return None
+    }
+    let mut rerolled = incr.growth_increment(uid, chat_id_kind, days_since_registration).await;
+    rerolled.settlements.push(GrowthSettlement::ItemUsed(ShopItem::Reroll));
+    Some(rerolled)
+}
+
 pub(crate) struct Top {
     pub lines: String,
     pub(crate) has_more_pages: bool,
 }
 
 impl Top {
-    fn from(s: impl ToString) -> Self {
+    pub(crate) fn from(s: impl ToString) -> Self {
         Self {
             lines: s.to_string(),
             has_more_pages: false,
         }
     }
 
-    fn with_more_pages(s: impl ToString) -> Self {
+    pub(crate) fn with_more_pages(s: impl ToString) -> Self {
         Self {
             lines: s.to_string(),
             has_more_pages: true,
@@ -156,20 +228,28 @@ pub(crate) async fn top_impl(
     let query_limit = config.top_limit + 1; // fetch +1 row to know whether more rows exist or not
     let dicks = repos.dicks.get_top(&chat_id, offset, query_limit, config.inactivity_days).await?;
     let has_more_pages = dicks.len() > usize::from(config.top_limit);
+    // Without seasons there's nobody to have won one, so the query isn't worth making.
+    let season_wins = if config.seasons.enabled() {
+        repos.seasons.get_season_wins(&chat_id).await?
+    } else {
+        HashMap::new()
+    };
+    let time_zone = repos.chats.get_time_zone(&chat_id).await?;
     let mut any_inactive = false;
     let lines = dicks.into_iter()
         .take(usize::from(config.top_limit))
         .enumerate()
         .map(|(i, d)| {
             let escaped_name = Username::new(d.owner_name).escaped();
-            let name = if d.owner_uid == from.id {
+            let mut name = if d.owner_uid == from.id {
                 format!("<u>{escaped_name}</u>")
             } else {
                 escaped_name
             };
+            name.push_str(&season_badge(season_wins.get(&d.owner_uid).map_or(0, Count::value)));
             let now = Utc::now();
             let inactive = (now - d.grown_at).num_days() > i64::from(config.inactivity_days);
-            let can_grow = now.num_days_from_ce() > d.grown_at.num_days_from_ce();
+            let can_grow = time_zone.date_of(now) > time_zone.date_of(d.grown_at);
             let pos: i64 = d.position.map(|p| p.saturating_into())
                 .unwrap_or_else(|| (i + 1).saturating_into());
             let mut line = t!("commands.top.line", locale = &lang_code,
@@ -204,6 +284,86 @@ pub(crate) async fn top_impl(
     Ok(res)
 }
 
+/// The cup of a champion of the past seasons, with the number of them if there are several.
+fn season_badge(wins: u64) -> String {
+    match wins {
+        0 => String::new(),
+        1 => " 🏆".to_owned(),
+        n => format!(" 🏆×{n}"),
+    }
+}
+
+/// A bare command shows the top of all time; an unknown period is an `Err`.
+fn parse_period(args: &str) -> Result<Option<TopPeriod>, ()> {
+    let args = args.trim();
+    if args.is_empty() {
+        Ok(None)
+    } else {
+        TopPeriod::from_str(&args.to_lowercase()).map(Some).map_err(|_| ())
+    }
+}
+
+/// The top of the period that is going on, by what has been gained over it. Never cut into pages:
+/// it's meant to be a race for the podium rather than a census.
+pub(crate) async fn period_top_impl(
+    repos: &repo::Repositories,
+    config: &AppConfig,
+    from_refs: FromRefs<'_>,
+    lang_code: &LanguageCode,
+    period: TopPeriod,
+) -> anyhow::Result<Top> {
+    let (from, chat_id) = (from_refs.0, from_refs.1.kind());
+    let days = period.range_of(Utc::now().date_naive());
+    let growths = repos.dicks.get_period_top(&chat_id, days, config.top_limit).await?;
+    let template_prefix = format!("commands.top.periods.{period}");
+    if growths.is_empty() {
+        return Ok(Top::from(t!(&format!("{template_prefix}.empty"), locale = lang_code)))
+    }
+    let lines = growths.into_iter()
+        .enumerate()
+        .map(|(i, growth)| {
+            let escaped_name = Username::new(growth.owner_name).escaped();
+            let name = if growth.owner_uid == from.id {
+                format!("<u>{escaped_name}</u>")
+            } else {
+                escaped_name
+            };
+            t!("commands.top.period_line", locale = lang_code,
+                n = i + 1, name = name, gained = growth.gained, length = growth.length).to_string()
+        })
+        .collect::<Vec<String>>();
+    let title = t!(&format!("{template_prefix}.title"), locale = lang_code);
+    Ok(Top::from(format!("{}\n\n{}", title, lines.join("\n"))))
+}
+
+/// The first three of a period that is over, announced to the chat by the broadcast worker.
+/// `since` is the first day of the period, which is what the queue keeps.
+pub(crate) async fn podium_impl(
+    repos: &repo::Repositories,
+    chat_id: &ChatIdKind,
+    lang_code: &LanguageCode,
+    period: TopPeriod,
+    since: NaiveDate,
+) -> anyhow::Result<String> {
+    const MEDALS: [&str; 3] = ["🥇", "🥈", "🥉"];
+
+    let days = period.range_of(since);
+    let last_day = days.end.pred_opt().unwrap_or(days.end);
+    let growths = repos.dicks.get_period_top(chat_id, days.clone(), Limit::new(3)).await?;
+    if growths.is_empty() {
+        // whoever gained has left the game between the queueing and now
+        return Ok(t!("commands.top.podium_empty", locale = lang_code).to_string())
+    }
+    let lines = growths.into_iter()
+        .zip(MEDALS)
+        .map(|(growth, medal)| t!("commands.top.podium_line", locale = lang_code, medal = medal,
+            name = Username::new(growth.owner_name).escaped(), gained = growth.gained).to_string())
+        .collect::<Vec<String>>();
+    let title = t!(&format!("commands.top.periods.{period}.podium"), locale = lang_code,
+        since = days.start.format(DATE_FORMAT), until = last_day.format(DATE_FORMAT));
+    Ok(format!("{}\n\n{}", title, lines.join("\n")))
+}
+
 pub fn page_callback_filter(query: CallbackQuery) -> bool {
     query.data
         .filter(|d| d.starts_with(CALLBACK_PREFIX_TOP_PAGE))
@@ -215,6 +375,7 @@ pub fn page_callback_filter(query: CallbackQuery) -> bool {
 pub async fn page_callback_handler(
     bot: Bot,
     q: CallbackQuery,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, lang_resolver, .. } = deps;
@@ -233,7 +394,9 @@ pub async fn page_callback_handler(
             .map(Page::new)
             .map_err(|e| InvalidPage::for_value(&r, e)))
         .map_err(|e| anyhow!(e))?;
-    let chat_id_kind = edit_msg_req_params.clone().into();
+    let chat_id_kind: ChatIdKind = edit_msg_req_params.clone().into();
+    // The limit must be the one the first page was cut by, or the pages would overlap or skip rows.
+    let config = settings.config_for(&chat_id_kind, &config).await;
     let chat_id_partiality = ChatIdPartiality::Specific(chat_id_kind);
     let from_refs = FromRefs(&q.from, &chat_id_partiality);
     let top = top_impl(&repos, &config, from_refs, &lang_code, page).await?;
@@ -244,14 +407,18 @@ pub async fn page_callback_handler(
 }
 
 pub(crate) fn build_pagination_keyboard(page: Page, has_more_pages: bool) -> InlineKeyboardMarkup {
+    let buttons = pagination_buttons(page, has_more_pages, |page| format!("{CALLBACK_PREFIX_TOP_PAGE}{page}"));
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
+/// The "⬅️ ➡️" row of any paged top; `callback` makes the data of the button leading to a page.
+pub(crate) fn pagination_buttons(page: Page, has_more_pages: bool, callback: impl Fn(Page) -> String) -> Vec<InlineKeyboardButton> {
     let mut buttons = Vec::new();
     if page > 0 {
-        let prev_page = page - 1;
-        buttons.push(InlineKeyboardButton::callback("⬅️", format!("{CALLBACK_PREFIX_TOP_PAGE}{prev_page}")))
+        buttons.push(InlineKeyboardButton::callback("⬅️", callback(page - 1)))
     }
     if has_more_pages {
-        let next_page = page + 1;
-        buttons.push(InlineKeyboardButton::callback("➡️", format!("{CALLBACK_PREFIX_TOP_PAGE}{next_page}")))
+        buttons.push(InlineKeyboardButton::callback("➡️", callback(page + 1)))
     }
-    InlineKeyboardMarkup::new(vec![buttons])
+    buttons
 }
@@ -7,10 +7,13 @@ use teloxide::macros::BotCommands;
 use teloxide::types::{LinkPreviewOptions, Message};
 use crate::{metrics, reply_html_ephemeral, repo};
 use crate::config::{AppConfig, DickOfDaySelectionMode, MessageGroup};
+use crate::domain::enums::ShopItem;
 use crate::domain::objects::GrowthResult;
-use crate::domain::primitives::{LanguageCode, Username};
-use crate::handlers::{FromRefs, HandlerDeps, HandlerResult, TaggedReply, reply_html, utils};
+use crate::domain::primitives::{LanguageCode, UserId, Username};
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::handlers::{achievements, FromRefs, HandlerDeps, HandlerResult, TaggedReply, reply_html, utils};
 use crate::handlers::utils::Incrementor;
+use crate::settings::GameSettingsPolicy;
 
 const DOD_ALREADY_CHOSEN_SQL_CODE: &str = "GD0E2";
 
@@ -28,6 +31,7 @@ pub async fn dod_cmd_handler(
     bot: Bot,
     msg: Message,
     incr: Incrementor,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config: cfg, self_destruction, lang_resolver } = deps;
@@ -36,6 +40,7 @@ pub async fn dod_cmd_handler(
     let from = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?;
     let chat_id = msg.chat.id.into();
     let from_refs = FromRefs(from, &chat_id);
+    let cfg = settings.config_for(&chat_id.kind(), &cfg).await;
     // A real election is a permanent event; the "already chosen"/"no candidates" statuses
     // are scheduled (as a Notice). `dick_of_day_impl` tells them apart via the reply group.
     let reply = dick_of_day_impl(cfg, &repos, incr, from_refs, &lang_code).await?;
@@ -52,7 +57,12 @@ pub(crate) async fn dick_of_day_impl(
     lang_code: &LanguageCode,
 ) -> anyhow::Result<TaggedReply> {
     let chat_id = from_refs.1;
+    // A ticket from the /shop outweighs any selection mode. It's spent only once the election has
+    // actually taken place, so a ticket holder drawn on a day that already has its winner keeps it.
+    let ticket_holder = repos.inventory.get_random_active_holder(&chat_id.kind(), ShopItem::DodTicket, cfg.inactivity_days).await?;
+    let by_ticket = ticket_holder.is_some();
     let winner = match cfg.features.dod_selection_mode {
+        _ if by_ticket => ticket_holder,
         DickOfDaySelectionMode::WEIGHTS => {
             repos.users.get_random_active_member_with_poor_in_priority(&chat_id.kind(), cfg.inactivity_days).await?
         },
@@ -65,17 +75,24 @@ pub(crate) async fn dick_of_day_impl(
     let (answer, group) = match winner {
         Some(winner) => {
             let increment = incr.dod_increment(winner.uid, chat_id.kind()).await;
-            let dod_result = repos.dicks.set_dod_winner(chat_id, winner.uid, increment.total).await;
+            let dod_result = repos.dicks.set_dod_winner(chat_id, winner.uid, increment.total, &increment.settlements).await;
             let (main_part, group) = match dod_result {
                 Ok(Some(GrowthResult { new_length, pos_in_top })) => {
                     let answer = t!("commands.dod.result", locale = lang_code,
                         uid = winner.uid, name = winner.name.escaped(), growth = increment.total, length = new_length);
                     let perks_part = increment.perks_part_of_answer(lang_code);
+                    let event_part = increment.event_part_of_answer(lang_code);
+                    let ticket_part = if by_ticket {
+                        spend_ticket(repos, chat_id, winner.uid, lang_code).await
+                    } else {
+                        String::default()
+                    };
+                    let achievements_part = achievements::unlock_achievements(repos, &chat_id.kind(), winner.uid, lang_code).await;
                     let text = if let Some(pos) = pos_in_top {
                         let position = t!("commands.dod.position", locale = lang_code, pos = pos);
-                        format!("{answer}\n{position}{perks_part}")
+                        format!("{answer}{ticket_part}\n{position}{perks_part}{event_part}{achievements_part}")
                     } else {
-                        format!("{answer}{perks_part}")
+                        format!("{answer}{ticket_part}{perks_part}{event_part}{achievements_part}")
                     };
                     (text, MessageGroup::Event)
                 },
@@ -97,7 +114,8 @@ pub(crate) async fn dick_of_day_impl(
                     }
                 }
             };
-            let time_left_part = utils::date::get_time_till_next_day_string(lang_code);
+            let time_zone = repos.chats.get_time_zone(&chat_id.kind()).await?;
+            let time_left_part = utils::date::get_time_till_next_day_string(lang_code, time_zone.utc_offset);
             (format!("{main_part}{time_left_part}"), group)
         },
         None => (t!("commands.dod.no_candidates", locale = lang_code).to_string(), MessageGroup::Notice)
@@ -109,6 +127,23 @@ pub(crate) async fn dick_of_day_impl(
     Ok(TaggedReply { text: format!("{answer}{announcement}"), group })
 }
 
+/// Takes the ticket the winner was elected with and returns the line that says so. The election
+/// has already been written by then, so a failure is only logged; the ticket is then left to win
+/// once more, which is the lesser evil than an election that never happened.
+async fn spend_ticket(
+    repos: &repo::Repositories,
+    chat_id: &ChatIdPartiality,
+    uid: UserId,
+    lang_code: &LanguageCode,
+) -> String {
+    match repos.inventory.consume(&chat_id.kind(), uid, ShopItem::DodTicket).await {
+        Ok(true) => metrics::SHOP_ITEM_USED.record(ShopItem::DodTicket),
+        Ok(false) => tracing::warn!(uid = %uid, chat_id = %chat_id, "the ticket of the elected holder has disappeared"),
+        Err(e) => tracing::error!(uid = %uid, chat_id = %chat_id, error = %e, "couldn't spend the ticket of the elected holder"),
+    }
+    format!("\n{}", t!("commands.dod.by_ticket", locale = lang_code))
+}
+
 fn disabled_link_preview() -> LinkPreviewOptions {
     LinkPreviewOptions {
         is_disabled: true,
@@ -0,0 +1,322 @@
+//! Length given or lent by one member to another. The length only moves, so the chat as a whole
+//! grows no longer from it; the daily caps keep the gifts from turning into a way to pump up a
+//! single dick with the accounts of friends.
+
+use anyhow::anyhow;
+use autometrics::autometrics;
+use derive_more::Display;
+use domain_types::literal;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::prelude::{CallbackQuery, Message, UserId};
+use teloxide::types::ReplyMarkup;
+use callbacks::{EditMessageReqParamsKind, InvalidCallbackData};
+
+use crate::{check_invoked_by_owner_and_get_answer_params, metrics, reply_html_ephemeral, repo};
+use crate::config::MessageGroup;
+use crate::domain::enums::GiftKind;
+use crate::domain::primitives::{FloatPercentage, GiftAmount, PayoutRatio};
+use crate::domain::primitives::chat::{ChatIdPartiality, InlineMessageId};
+use crate::handlers::{CallbackButton, HandlerDeps, HandlerImplResult, HandlerResult, reply_html};
+use crate::handlers::pvp::UserInfo;
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackDataBuilder};
+use crate::settings::GameSettingsPolicy;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum GiftCommands {
+    #[command(description = "gift")]
+    Gift(String),
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn gift_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: GiftCommands,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_GIFT.invoked();
+    if !config.gift.enabled() {
+        let text = t!("errors.feature_disabled", locale = &lang_code);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    }
+
+    let GiftCommands::Gift(args) = cmd;
+    let Some(amount) = parse_amount(&args) else {
+        let text = t!("commands.gift.usage", locale = &lang_code);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    };
+    let me: UserInfo = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?.into();
+    // in a forum, a message without a reply is still a reply to the first one of the topic
+    let recipient: Option<UserInfo> = msg.reply_to_message()
+        .filter(|reply| reply.forum_topic_created().is_none())
+        .and_then(|reply| reply.from.as_ref())
+        .filter(|user| !user.is_bot && me.uid != user.id)
+        .map(UserInfo::from);
+    let Some(recipient) = recipient else {
+        let text = t!("commands.gift.errors.no_reply", locale = &lang_code);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    };
+
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let chat_id_kind = chat_id.kind();
+    // checked once more on the click, but there's no point to ask for a confirmation of the impossible
+    let length = repos.dicks.fetch_length(me.uid, &chat_id_kind).await?;
+    if length < i64::from(amount.value()) {
+        let text = t!("commands.gift.errors.not_enough_length", locale = &lang_code, length = length);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    }
+
+    let config = settings.config_for(&chat_id_kind, &config).await;
+    let data = |action| GiftCallbackData { uid: me.uid.into(), recipient: recipient.uid.into(), amount, action };
+    let mut buttons = vec![CallbackButton::new(
+        t!("commands.gift.confirmation.buttons.gift", locale = &lang_code).to_string(),
+        data(GiftCallbackAction::Give),
+    )];
+    // a member loan is paid back the way the loans of the bank are, so it shares their ratio
+    let payout_ratio = config.loan_payout_ratio;
+    let lending_enabled = payout_ratio > 0.0 && payout_ratio < 1.0;
+    if lending_enabled {
+        buttons.push(CallbackButton::new(
+            t!("commands.gift.confirmation.buttons.lend", locale = &lang_code,
+                payout_percentage = FloatPercentage::from(payout_ratio)).to_string(),
+            data(GiftCallbackAction::Lend { payout_ratio }),
+        ));
+    }
+    buttons.push(CallbackButton::new(
+        t!("commands.gift.confirmation.buttons.cancel", locale = &lang_code).to_string(),
+        data(GiftCallbackAction::Cancel),
+    ));
+    let text = t!("commands.gift.confirmation.text", locale = &lang_code,
+        amount = amount, name = recipient.name.escaped()).to_string();
+    let result = HandlerImplResult::WithKeyboard { text, buttons };
+    let markup = result.keyboard().map(ReplyMarkup::InlineKeyboard);
+    reply_html_ephemeral!(bot, msg, result.text(), self_destruction, MessageGroup::Application, lang_code,
+        reply_markup = markup);
+    Ok(())
+}
+
+fn parse_amount(args: &str) -> Option<GiftAmount> {
+    args.trim().parse::<u32>().ok()
+        .filter(|value| *value > 0)
+        .map(GiftAmount::new)
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    GiftCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn gift_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let data = GiftCallbackData::parse(&query)?;
+    // a loan puts the recipient into debt, so it's up to the recipient to take it
+    let owner = match data.action {
+        GiftCallbackAction::Accept { .. } | GiftCallbackAction::Decline => data.recipient,
+        GiftCallbackAction::Give | GiftCallbackAction::Lend { .. } | GiftCallbackAction::Cancel => data.uid,
+    };
+    let answer = check_invoked_by_owner_and_get_answer_params!(bot, query, owner);
+
+    let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+    // the outcome of the offer is kept like any other event
+    match &edit_msg_params {
+        EditMessageReqParamsKind::Chat(chat_id, message_id) =>
+            self_destruction.cancel_message(*chat_id, *message_id).await,
+        EditMessageReqParamsKind::Inline { inline_message_id, .. } =>
+            self_destruction.cancel_inline(InlineMessageId::new(inline_message_id.clone())).await,
+    };
+    let (kind, payout_ratio) = match data.action {
+        GiftCallbackAction::Give => (GiftKind::Gift, literal!(PayoutRatio = 0.0)),
+        GiftCallbackAction::Lend { payout_ratio } => {
+            // nothing is moved yet: the length is lent only once the recipient accepts the debt
+            let giver = UserInfo::from(&query.from);
+            let recipient = get_recipient(&repos, data.recipient).await?;
+            let text = t!("commands.gift.offer.text", locale = &lang_code,
+                name = giver.name.escaped(), recipient = recipient.name.escaped(), amount = data.amount,
+                payout_percentage = FloatPercentage::from(payout_ratio)).to_string();
+            let offer = |action| GiftCallbackData { action, ..data };
+            let result = HandlerImplResult::WithKeyboard { text, buttons: vec![
+                CallbackButton::new(t!("commands.gift.offer.buttons.accept", locale = &lang_code).to_string(),
+                    offer(GiftCallbackAction::Accept { payout_ratio })),
+                CallbackButton::new(t!("commands.gift.offer.buttons.decline", locale = &lang_code).to_string(),
+                    offer(GiftCallbackAction::Decline)),
+            ]};
+            callbacks::edit_message_text_with_keyboard(&bot, edit_msg_params, result.text(), result.keyboard()).await?;
+            answer.await?;
+            return Ok(())
+        }
+        GiftCallbackAction::Accept { payout_ratio } => (GiftKind::Lend, payout_ratio),
+        GiftCallbackAction::Cancel => {
+            let text = t!("commands.gift.callback.cancelled", locale = &lang_code);
+            callbacks::edit_message_text(&bot, edit_msg_params, text).await?;
+            answer.await?;
+            return Ok(())
+        }
+        GiftCallbackAction::Decline => {
+            let recipient = UserInfo::from(&query.from);
+            let text = t!("commands.gift.callback.declined", locale = &lang_code, recipient = recipient.name.escaped());
+            callbacks::edit_message_text(&bot, edit_msg_params, text).await?;
+            answer.await?;
+            return Ok(())
+        }
+    };
+
+    let chat_id = callbacks::resolve_chat_id(&repos, config.features.chats_merging, &edit_msg_params).await?;
+    if kind == GiftKind::Lend && payout_ratio != settings.config_for(&chat_id, &config).await.loan_payout_ratio {
+        // an admin may have changed the ratio of the chat since the offer was made
+        let text = t!("commands.loan.callback.payout_ratio_changed", locale = &lang_code);
+        callbacks::edit_message_text(&bot, edit_msg_params, text).await?;
+        answer.await?;
+        return Ok(())
+    }
+    let result = repos.gifts.give(&chat_id, data.uid.into(), data.recipient.into(), data.amount, kind, payout_ratio).await?;
+    let text = match result {
+        repo::GiftResult::Given { giver_length, recipient_length } => {
+            metrics::CMD_GIFT.finished();
+            // whoever has clicked, it's the giver who gives
+            let giver: UserInfo = repos.users.get_user(data.uid.into()).await?
+                .ok_or(anyhow!("the giver of a gift must be present in the database"))?
+                .into();
+            let recipient = get_recipient(&repos, data.recipient).await?;
+            t!(&format!("commands.gift.callback.given.{kind}"), locale = &lang_code,
+                name = giver.name.escaped(), recipient = recipient.name.escaped(), amount = data.amount,
+                length = giver_length, recipient_length = recipient_length,
+                payout_percentage = FloatPercentage::from(payout_ratio))
+        }
+        repo::GiftResult::NotEnoughLength(length) =>
+            t!("commands.gift.errors.not_enough_length", locale = &lang_code, length = length),
+        repo::GiftResult::NoRecipient => t!("commands.gift.errors.no_recipient", locale = &lang_code),
+        repo::GiftResult::GivenCapReached(left) =>
+            t!("commands.gift.errors.given_cap", locale = &lang_code, cap = config.gift.daily_given_cap, left = left),
+        repo::GiftResult::ReceivedCapReached(left) =>
+            t!("commands.gift.errors.received_cap", locale = &lang_code, cap = config.gift.daily_received_cap, left = left),
+    };
+    callbacks::edit_message_text_with_keyboard(&bot, edit_msg_params, text, None).await?;
+    answer.await?;
+    Ok(())
+}
+
+async fn get_recipient(repos: &repo::Repositories, recipient: UserId) -> anyhow::Result<UserInfo> {
+    repos.users.get_user(recipient.into()).await?
+        .ok_or(anyhow!("the recipient of a gift must be present in the database"))
+        .map(UserInfo::from)
+}
+
+#[derive(Display)]
+#[display("{uid}:{recipient}:{amount}:{action}")]
+pub(crate) struct GiftCallbackData {
+    uid: UserId,
+    recipient: UserId,
+    amount: GiftAmount,
+    action: GiftCallbackAction,
+}
+
+#[derive(Display)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) enum GiftCallbackAction {
+    #[display("gift")]
+    Give,
+    /// Offers the loan to the recipient.
+    #[display("lend:{payout_ratio}")]
+    Lend { payout_ratio: PayoutRatio },
+    #[display("cancel")]
+    Cancel,
+    /// The recipient takes the offered loan.
+    #[display("accept:{payout_ratio}")]
+    Accept { payout_ratio: PayoutRatio },
+    #[display("decline")]
+    Decline,
+}
+
+impl CallbackDataWithPrefix for GiftCallbackData {
+    fn prefix() -> &'static str {
+        "gift"
+    }
+}
+
+impl TryFrom<String> for GiftCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
+        let recipient = callbacks::parse_part(&mut parts, &err, "recipient").map(UserId)?;
+        let amount = callbacks::parse_part(&mut parts, &err, "amount")?;
+        let action = parts.next()
+            .ok_or_else(|| err.missing_part("action"))?;
+        let action = match action {
+            "gift" => GiftCallbackAction::Give,
+            "lend" => GiftCallbackAction::Lend {
+                payout_ratio: callbacks::parse_part(&mut parts, &err, "payout_ratio")?
+            },
+            "cancel" => GiftCallbackAction::Cancel,
+            "accept" => GiftCallbackAction::Accept {
+                payout_ratio: callbacks::parse_part(&mut parts, &err, "payout_ratio")?
+            },
+            "decline" => GiftCallbackAction::Decline,
+            _ => return Err(err.split_err())
+        };
+        Ok(Self { uid, recipient, amount, action })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use teloxide::types::UserId;
+    use domain_types::literal;
+    use crate::domain::primitives::{GiftAmount, PayoutRatio};
+    use crate::handlers::gift::{parse_amount, GiftCallbackAction, GiftCallbackData};
+    use crate::handlers::utils::callbacks::{build_callback_query, CallbackDataWithPrefix};
+
+    #[test]
+    fn test_parse() {
+        let (uid, recipient) = (UserId(123456), UserId(654321));
+        for (action, expected) in [
+            ("gift", GiftCallbackAction::Give),
+            ("lend:0.1", GiftCallbackAction::Lend { payout_ratio: literal!(PayoutRatio = 0.1) }),
+            ("cancel", GiftCallbackAction::Cancel),
+            ("accept:0.1", GiftCallbackAction::Accept { payout_ratio: literal!(PayoutRatio = 0.1) }),
+            ("decline", GiftCallbackAction::Decline),
+        ] {
+            let data_string = format!("gift:{uid}:{recipient}:5:{action}");
+            let data = GiftCallbackData::parse(&build_callback_query(data_string.clone()))
+                .unwrap_or_else(|e| panic!("callback data for '{action}' must be parsed successfully: {e}"));
+            assert_eq!(data.uid, uid);
+            assert_eq!(data.recipient, recipient);
+            assert_eq!(data.amount, GiftAmount::new(5));
+            assert_eq!(data.action, expected);
+            assert_eq!(data.to_data_string(), data_string);
+        }
+        assert!(GiftCallbackData::parse(&build_callback_query(format!("gift:{uid}:{recipient}:5:steal"))).is_err());
+        assert!(GiftCallbackData::parse(&build_callback_query(format!("gift:{uid}:{recipient}:5:lend"))).is_err());
+        assert!(GiftCallbackData::parse(&build_callback_query(format!("gift:{uid}:{recipient}:5:accept"))).is_err());
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount(" 5 "), Some(GiftAmount::new(5)));
+        assert_eq!(parse_amount(""), None);
+        assert_eq!(parse_amount("0"), None);
+        assert_eq!(parse_amount("-5"), None);
+        assert_eq!(parse_amount("all"), None);
+    }
+}
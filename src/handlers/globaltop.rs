@@ -0,0 +1,241 @@
+//! The leaderboard of the users of every chat at once, by the longest of their dicks or by all of
+//! them together. It's asked for in a private chat or in the inline mode, since no chat of its own
+//! stands behind it.
+//!
+//! The names of the players are shown here to strangers, unlike in any other top, so a player may
+//! take themselves out of it with `/globaltop hide` and come back with `/globaltop show`.
+
+use std::str::FromStr;
+use anyhow::anyhow;
+use autometrics::autometrics;
+use derive_more::Display;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ReplyMarkup};
+use crate::{metrics, reply_html};
+use crate::config::AppConfig;
+use crate::domain::enums::GlobalRanking;
+use crate::domain::objects::RankedUser;
+use crate::domain::primitives::{LanguageCode, Offset, Page, UserId, Username};
+use crate::handlers::{answer_callback_feature_disabled, dick, reply_html, HandlerDeps, HandlerResult};
+use crate::handlers::dick::Top;
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackData, InvalidCallbackDataBuilder};
+use crate::repo::Repositories;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum GlobalTopCommands {
+    #[command(description = "globaltop")]
+    Globaltop(String),
+}
+
+/// What the argument of the command asks for: a ranking to look at or the visibility of the caller.
+#[derive(Debug, PartialEq)]
+enum GlobalTopAction {
+    Show(GlobalRanking),
+    SetHidden(bool),
+}
+
+/// Callback payload of the page and ranking buttons. Wire format: `globaltop:<ranking>:<page>`.
+#[derive(Display, Clone, Copy)]
+#[display("{ranking}:{page}")]
+pub(crate) struct GlobalTopCallbackData {
+    pub ranking: GlobalRanking,
+    pub page: Page,
+}
+
+impl CallbackDataWithPrefix for GlobalTopCallbackData {
+    fn prefix() -> &'static str {
+        "globaltop"
+    }
+}
+
+impl TryFrom<String> for GlobalTopCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let ranking = callbacks::parse_part(&mut parts, &err, "ranking")?;
+        let page = callbacks::parse_part(&mut parts, &err, "page")?;
+        Ok(Self { ranking, page })
+    }
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn globaltop_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: GlobalTopCommands,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_GLOBALTOP.chat.inc();
+
+    let from = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    let GlobalTopCommands::Globaltop(args) = cmd;
+    match parse_args(&args) {
+        Some(GlobalTopAction::Show(ranking)) => {
+            let (top, keyboard) = global_top_impl(&repos, &config, UserId::from(from), &lang_code, ranking, Page::first()).await?;
+            reply_html!(bot, msg, top.lines, reply_markup = Some(ReplyMarkup::InlineKeyboard(keyboard)));
+        },
+        Some(GlobalTopAction::SetHidden(hidden)) => {
+            let known = repos.users.set_hidden_from_global_top(UserId::from(from), hidden).await?;
+            let key = match (known, hidden) {
+                (false, _) => "commands.globaltop.visibility.unknown",
+                (true, true) => "commands.globaltop.visibility.hidden",
+                (true, false) => "commands.globaltop.visibility.shown",
+            };
+            reply_html!(bot, msg, t!(key, locale = &lang_code));
+        },
+        None => {
+            let rankings = GlobalRanking::iter().map(|r| format!("<code>{r}</code>")).collect::<Vec<_>>().join(", ");
+            let text = t!("commands.globaltop.errors.unknown_argument", locale = &lang_code, rankings = rankings);
+            reply_html!(bot, msg, text);
+        },
+    }
+    Ok(())
+}
+
+/// A bare command shows the default ranking; `hide` and `show` switch the caller's visibility.
+fn parse_args(args: &str) -> Option<GlobalTopAction> {
+    let args = args.trim().to_lowercase();
+    match args.as_str() {
+        "" => Some(GlobalTopAction::Show(GlobalRanking::default())),
+        "hide" => Some(GlobalTopAction::SetHidden(true)),
+        "show" => Some(GlobalTopAction::SetHidden(false)),
+        other => GlobalRanking::from_str(other).ok().map(GlobalTopAction::Show),
+    }
+}
+
+pub(crate) async fn global_top_impl(
+    repos: &Repositories,
+    config: &AppConfig,
+    caller: UserId,
+    lang_code: &LanguageCode,
+    ranking: GlobalRanking,
+    page: Page,
+) -> anyhow::Result<(Top, InlineKeyboardMarkup)> {
+    let offset = Offset::calculate(page, config.top_limit);
+    let query_limit = config.top_limit + 1; // fetch +1 row to know whether more rows exist or not
+    let users = repos.personal_stats.get_global_top(ranking, offset, query_limit).await?;
+    let has_more_pages = users.len() > usize::from(config.top_limit);
+    let lines = users.into_iter()
+        .take(usize::from(config.top_limit))
+        .map(|user| {
+            let is_caller = user.uid == caller;
+            render_line(user, is_caller, lang_code)
+        })
+        .collect::<Vec<String>>();
+
+    let top = if lines.is_empty() {
+        Top::from(t!("commands.globaltop.empty", locale = lang_code))
+    } else {
+        let ranking_name = t!(&format!("commands.globaltop.rankings.{ranking}"), locale = lang_code);
+        let title = t!("commands.globaltop.title", locale = lang_code, ranking = ranking_name);
+        let ending = t!("commands.globaltop.ending", locale = lang_code);
+        let text = format!("{}\n\n{}\n\n{}", title, lines.join("\n"), ending);
+        if has_more_pages {
+            Top::with_more_pages(text)
+        } else {
+            Top::from(text)
+        }
+    };
+    let keyboard = build_keyboard(ranking, page, top.has_more_pages, config.features.top_unlimited, lang_code);
+    Ok((top, keyboard))
+}
+
+fn render_line(user: RankedUser, is_caller: bool, lang_code: &LanguageCode) -> String {
+    let escaped_name = Username::new(user.name).escaped();
+    let name = if is_caller {
+        format!("<u>{escaped_name}</u>")
+    } else {
+        escaped_name
+    };
+    t!("commands.globaltop.line", locale = lang_code, n = user.position, name = name, length = user.length).to_string()
+}
+
+/// The same layout as the one of `/pvptop`: the pages, if the top is unlimited, and a switch to
+/// the other ranking, which starts over from the first page.
+fn build_keyboard(
+    ranking: GlobalRanking,
+    page: Page,
+    has_more_pages: bool,
+    top_unlimited: bool,
+    lang_code: &LanguageCode,
+) -> InlineKeyboardMarkup {
+    let page_row = if top_unlimited {
+        dick::pagination_buttons(page, has_more_pages, |page| GlobalTopCallbackData { ranking, page }.to_data_string())
+    } else {
+        Vec::new()
+    };
+    let ranking_row = GlobalRanking::iter()
+        .filter(|r| *r != ranking)
+        .map(|r| {
+            let label = t!(&format!("commands.globaltop.buttons.{r}"), locale = lang_code);
+            InlineKeyboardButton::callback(label, GlobalTopCallbackData { ranking: r, page: Page::first() }.to_data_string())
+        })
+        .collect();
+    let rows: Vec<_> = [page_row, ranking_row].into_iter().filter(|row| !row.is_empty()).collect();
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    GlobalTopCallbackData::check_prefix(query)
+}
+
+/// No chat settings are looked up here, unlike in `/pvptop`: the top belongs to no chat, so its
+/// pages are always cut by the global limit.
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&q), uid = q.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn globaltop_callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let edit_msg_req_params = callbacks::get_params_for_message_edit(&q)?;
+    let data = GlobalTopCallbackData::parse(&q).map_err(|e| anyhow!(e))?;
+    if data.page > 0 && !config.features.top_unlimited {
+        return answer_callback_feature_disabled(bot, &q, edit_msg_req_params, lang_code).await
+    }
+
+    let (top, keyboard) = global_top_impl(&repos, &config, UserId::from(&q.from), &lang_code, data.ranking, data.page).await?;
+    callbacks::answer_and_edit_page(&bot, &q, &edit_msg_req_params, top.lines, keyboard).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::enums::GlobalRanking;
+    use crate::domain::primitives::Page;
+    use crate::handlers::utils::callbacks::CallbackDataWithPrefix;
+    use super::{parse_args, GlobalTopAction, GlobalTopCallbackData};
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(""), Some(GlobalTopAction::Show(GlobalRanking::Max)));
+        assert_eq!(parse_args(" Total "), Some(GlobalTopAction::Show(GlobalRanking::Total)));
+        assert_eq!(parse_args("max"), Some(GlobalTopAction::Show(GlobalRanking::Max)));
+        assert_eq!(parse_args("HIDE"), Some(GlobalTopAction::SetHidden(true)));
+        assert_eq!(parse_args("show"), Some(GlobalTopAction::SetHidden(false)));
+        assert_eq!(parse_args("length"), None);
+    }
+
+    #[test]
+    fn test_callback_data_round_trip() {
+        let data = GlobalTopCallbackData { ranking: GlobalRanking::Total, page: Page::new(2) };
+        let s = data.to_data_string();
+        assert_eq!(s, "globaltop:total:2");
+        let stripped = s.strip_prefix("globaltop:").expect("no prefix").to_owned();
+        let parsed = GlobalTopCallbackData::try_from(stripped).expect("couldn't parse the data back");
+        assert_eq!(parsed.ranking, GlobalRanking::Total);
+        assert_eq!(parsed.page, Page::new(2));
+    }
+}
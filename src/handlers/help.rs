@@ -1,4 +1,5 @@
 use autometrics::autometrics;
+use rust_i18n::t;
 use teloxide::Bot;
 use teloxide::macros::BotCommands;
 use teloxide::prelude::Message;
@@ -7,6 +8,8 @@ use crate::handlers::{HandlerDeps, HandlerResult, reply_html};
 use crate::help::HelpContainer;
 use crate::{metrics, reply_html_ephemeral};
 
+const EVENT_DATE_FORMAT: &str = "%d.%m.%Y";
+
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
 pub enum HelpCommands {
@@ -22,10 +25,16 @@ pub async fn help_cmd_handler(
     container: HelpContainer,
     deps: HandlerDeps,
 ) -> HandlerResult {
-    let HandlerDeps { self_destruction, lang_resolver, .. } = deps;
+    let HandlerDeps { config, self_destruction, lang_resolver, .. } = deps;
     let lang_code = lang_resolver.execute().await;
     metrics::CMD_HELP_COUNTER.inc();
-    let help = container.get_help_message(&lang_code);
+    // The help is rendered once at startup, while the events come and go with the file.
+    let help = match config.events.active() {
+        Some(event) => format!("{}\n\n{}", container.get_help_message(&lang_code),
+            t!("titles.event_with_dates", locale = &lang_code, name = event.name(&lang_code),
+                until = event.until.format(EVENT_DATE_FORMAT))),
+        None => container.get_help_message(&lang_code),
+    };
     reply_html_ephemeral!(bot, msg, help, self_destruction, Notice, lang_code);
     Ok(())
 }
@@ -0,0 +1,162 @@
+//! The trajectory of the dick of the caller over the last days, drawn from `Length_Events` as a
+//! text sparkline: `Dicks` keeps only the current length, and `/grow` shows only the last change.
+
+use anyhow::anyhow;
+use autometrics::autometrics;
+use num_traits::ToPrimitive;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::MessageGroup;
+use crate::domain::objects::LengthHistory;
+use crate::domain::primitives::{DaysCount, LanguageCode, UserId};
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::handlers::{reply_html, HandlerDeps, HandlerResult};
+
+const DATE_FORMAT: &str = "%d.%m";
+const DEFAULT_DAYS: DaysCount = DaysCount::new(14);
+/// A day is a character of the sparkline, which must fit into a line on a phone.
+const MAX_DAYS: DaysCount = DaysCount::new(30);
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum HistoryCommands {
+    #[command(description = "history")]
+    History(String),
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn history_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: HistoryCommands,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, self_destruction, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_HISTORY.inc();
+
+    let HistoryCommands::History(args) = cmd;
+    let Some(days) = parse_days(&args) else {
+        let text = t!("commands.history.errors.invalid_days", locale = &lang_code, max = MAX_DAYS);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    };
+
+    let uid = UserId::from(msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?);
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let text = match repos.dicks.get_history(uid, &chat_id.kind(), days).await? {
+        Some(history) => render_history(&history, days, &lang_code),
+        None => t!("commands.history.no_dick", locale = &lang_code).to_string(),
+    };
+    reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Report, lang_code);
+    Ok(())
+}
+
+/// A bare command shows the default period; a longer one than the sparkline can hold is refused
+/// rather than cut silently.
+fn parse_days(args: &str) -> Option<DaysCount> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Some(DEFAULT_DAYS)
+    }
+    args.parse::<u32>().ok()
+        .filter(|days| (1..=MAX_DAYS.value()).contains(days))
+        .map(DaysCount::new)
+}
+
+fn render_history(history: &LengthHistory, days: DaysCount, lang_code: &LanguageCode) -> String {
+    let lengths: Vec<i64> = history.days.iter().map(|day| day.length.value()).collect();
+    let (Some(first_day), Some(last_day)) = (history.days.first(), history.days.last()) else {
+        return t!("commands.history.no_dick", locale = lang_code).to_string()
+    };
+    let (min, max) = (lengths.iter().min().copied().unwrap_or_default(), lengths.iter().max().copied().unwrap_or_default());
+
+    let title = t!("commands.history.title", locale = lang_code, days = days);
+    let period = t!("commands.history.period", locale = lang_code,
+        since = first_day.day.format(DATE_FORMAT), until = last_day.day.format(DATE_FORMAT));
+    let summary = t!("commands.history.summary", locale = lang_code,
+        from = first_day.length, to = last_day.length, min = min, max = max);
+    let changes = if history.changes.is_empty() {
+        t!("commands.history.no_changes", locale = lang_code).to_string()
+    } else {
+        let lines = history.changes.iter()
+            .map(|change| {
+                let reason = t!(&format!("commands.history.reasons.{}", change.reason), locale = lang_code);
+                t!("commands.history.change", locale = lang_code, reason = reason, change = format!("{:+}", change.change.value())).to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!("{}\n{lines}", t!("commands.history.changes", locale = lang_code))
+    };
+    format!("{title}\n\n<code>{}</code>\n{period}\n\n{summary}\n\n{changes}", sparkline(&lengths))
+}
+
+/// One bar per value, scaled between the lowest and the highest of them. A flat line is drawn at
+/// the middle height: the bottom would look like a zero length.
+fn sparkline(values: &[i64]) -> String {
+    let (Some(min), Some(max)) = (values.iter().min().copied(), values.iter().max().copied()) else {
+        return String::new()
+    };
+    let span = i128::from(max.saturating_sub(min));
+    let top = BARS.len() - 1;
+    let levels = top.to_i128().unwrap_or_default();
+    values.iter()
+        .map(|value| {
+            let index = if span == 0 {
+                top / 2
+            } else {
+                (i128::from(value.saturating_sub(min)) * levels / span).to_usize().unwrap_or_default()
+            };
+            BARS[index.min(top)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use crate::domain::enums::LengthChangeReason;
+    use crate::domain::objects::{DailyLength, LengthHistory, ReasonedChange};
+    use crate::domain::primitives::{DaysCount, LanguageCode, Length};
+    use super::{parse_days, render_history, sparkline, DEFAULT_DAYS};
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[0, 7, 14]), "▁▄█");
+        assert_eq!(sparkline(&[-5, 2]), "▁█");
+        assert_eq!(sparkline(&[3, 3, 3]), "▄▄▄");
+        assert_eq!(sparkline(&[i64::MIN, i64::MAX]), "▁█");
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days(""), Some(DEFAULT_DAYS));
+        assert_eq!(parse_days(" 7 "), Some(DaysCount::new(7)));
+        assert_eq!(parse_days("30"), Some(DaysCount::new(30)));
+        assert_eq!(parse_days("31"), None);
+        assert_eq!(parse_days("0"), None);
+        assert_eq!(parse_days("week"), None);
+    }
+
+    #[test]
+    fn test_render_history() {
+        let day = |d, length| DailyLength { day: NaiveDate::from_ymd_opt(2026, 10, d).unwrap(), length: Length::new(length) };
+        let history = LengthHistory {
+            days: vec![day(1, 2), day(2, 10), day(3, 7)],
+            changes: vec![
+                ReasonedChange { reason: LengthChangeReason::Grow, change: Length::new(8) },
+                ReasonedChange { reason: LengthChangeReason::Pvp, change: Length::new(-3) },
+            ],
+        };
+        let text = render_history(&history, DaysCount::new(3), &LanguageCode::new("en".to_owned()));
+        assert!(text.contains("<code>▁█▅</code>"), "{text}");
+        assert!(text.contains("01.10") && text.contains("03.10"), "{text}");
+        assert!(text.contains("+8") && text.contains("-3"), "the changes must be signed: {text}");
+    }
+}
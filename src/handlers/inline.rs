@@ -14,24 +14,30 @@ use teloxide::requests::Requester;
 use teloxide::types::*;
 use teloxide::types::ParseMode::Html;
 use crate::config::{AppConfig, MessageGroup};
+use crate::domain::enums::{BattleRanking, GlobalRanking};
 use crate::domain::objects::InlineMessageIdInfo;
 use crate::domain::primitives::{CharCount, LanguageCode, Page, UserId as DomainUserId, Username};
 use crate::domain::primitives::chat::{ChatIdFull, ChatIdSource, InlineMessageId, TelegramChatInstanceId};
-use crate::handlers::{banned_until_of, dick, dod, FromRefs, HandlerDeps, HandlerImplResult, HandlerResult, loan, shrink, stats, utils, pvp};
+use crate::handlers::{achievements, banned_until_of, dick, dod, FromRefs, HandlerDeps, HandlerImplResult, HandlerResult, loan, shop, shrink, stats, utils, pvp, pvptop, globaltop};
 use crate::handlers::utils::callbacks::CallbackDataWithPrefix;
 use crate::handlers::utils::Incrementor;
 use crate::metrics;
 use crate::repo::{NoChatIdError, Repositories};
+use crate::settings::GameSettingsPolicy;
 
 #[derive(Debug, strum_macros::Display, EnumIter, EnumString)]
 #[strum(serialize_all = "snake_case")]
 enum InlineCommand {
     Grow,
     Top,
+    Pvptop,
+    Globaltop,
     DickOfDay,
     Loan,
     Stats,
     Shrinks,
+    Achievements,
+    Shop,
 }
 
 struct InlineResult {
@@ -87,6 +93,26 @@ impl InlineCommand {
                         res
                     })
             },
+            InlineCommand::Pvptop => {
+                metrics::CMD_PVPTOP.inline.inc();
+                pvptop::pvp_top_impl(repos, &config, from_refs, lang_code, BattleRanking::default(), Page::first())
+                    .await
+                    .map(|(top, keyboard)| {
+                        let mut res = InlineResult::text(top.lines, MessageGroup::Report);
+                        res.keyboard = Some(keyboard);
+                        res
+                    })
+            },
+            InlineCommand::Globaltop => {
+                metrics::CMD_GLOBALTOP.inline.inc();
+                globaltop::global_top_impl(repos, &config, DomainUserId::from(from_refs.0), lang_code, GlobalRanking::default(), Page::first())
+                    .await
+                    .map(|(top, keyboard)| {
+                        let mut res = InlineResult::text(top.lines, MessageGroup::Report);
+                        res.keyboard = Some(keyboard);
+                        res
+                    })
+            },
             InlineCommand::DickOfDay => {
                 metrics::CMD_DOD_COUNTER.inline.inc();
                 dod::dick_of_day_impl(config, repos, incr, from_refs, lang_code)
@@ -115,6 +141,18 @@ impl InlineCommand {
                         res
                     })
             },
+            InlineCommand::Achievements => {
+                metrics::CMD_ACHIEVEMENTS.inline.inc();
+                achievements::achievements_impl(repos, from_refs, lang_code)
+                    .await
+                    .map(|text| InlineResult::text(text, MessageGroup::Report))
+            },
+            InlineCommand::Shop => {
+                metrics::CMD_SHOP_COUNTER.invoked.inline.inc();
+                shop::shop_impl(repos, from_refs, &config.shop, lang_code)
+                    .await
+                    .map(|result| InlineResult::with_keyboard(result, MessageGroup::Application))
+            },
         }
     }
 }
@@ -163,7 +201,7 @@ pub async fn inline_handler(
     metrics::INLINE_COUNTER.invoked();
 
     let name = utils::get_full_name(&query.from);
-    match repos.users.create_or_update(DomainUserId::from(&query.from), &name).await {
+    match repos.users.create_or_update_with_username(DomainUserId::from(&query.from), &name, query.from.username.as_deref()).await {
         Ok(_) => {},
         Err(e) if banned_until_of(&e).is_some() => {
             bot.answer_inline_query(query.id, vec![])
@@ -187,6 +225,8 @@ pub async fn inline_handler(
     let mut results: Vec<InlineQueryResult> = InlineCommand::iter()
         // The `shrinks` command only makes sense when the daily shrink feature is on.
         .filter(|cmd| !matches!(cmd, InlineCommand::Shrinks) || app_config.daily_shrink.enabled())
+        // Neither does `pvptop` while the battle stats are hidden.
+        .filter(|cmd| !matches!(cmd, InlineCommand::Pvptop) || app_config.features.pvp.show_stats)
         .map(|cmd| cmd.to_string())
         .filter(|cmd| app_config.command_toggles.enabled(cmd))
         .map(|key| {
@@ -223,6 +263,7 @@ pub async fn inline_chosen_handler(
     bot: Bot,
     result: ChosenInlineResult,
     incr: Incrementor,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
@@ -247,6 +288,7 @@ pub async fn inline_chosen_handler(
                 .context(format!("couldn't parse inline command '{}'", result.result_id))?;
             let chat_id = chat.try_into().map_err(|e: NoChatIdError| anyhow!(e))?;
             let from_refs = FromRefs(&result.from, &chat_id);
+            let config = settings.config_for(&chat_id.kind(), &config).await;
             let inline_result = cmd.execute(&repos, config, incr, from_refs, &lang_code).await?;
 
             let inline_message_id = result.inline_message_id
@@ -273,6 +315,7 @@ pub async fn inline_callback_handler(
     bot: Bot,
     query: CallbackQuery,
     incr: Incrementor,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
@@ -299,6 +342,7 @@ pub async fn inline_callback_handler(
         let parse_res = parse_callback_data(data, query.from.id);
         if let Ok(CallbackDataParseResult::Ok(cmd)) = parse_res {
             let from_refs = FromRefs(&query.from, &chat_id);
+            let config = settings.config_for(&chat_id.kind(), &config).await;
             let inline_result = cmd.execute(&repos, config, incr, from_refs, &lang_code).await?;
             let char_count = CharCount::of(&inline_result.text);
             let mut edit = bot.edit_message_text_inline(inline_msg_id, inline_result.text);
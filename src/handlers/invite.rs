@@ -0,0 +1,89 @@
+use autometrics::autometrics;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngExt;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::{Me, Message};
+use crate::config::ReferralConfig;
+use crate::domain::primitives::{LanguageCode, UserId};
+use crate::handlers::{HandlerDeps, HandlerResult, PROMO_START_PARAM_PREFIX, reply_html};
+use crate::{metrics, reply_html, repo};
+
+const REFERRAL_CODE_PREFIX: &str = "ref";
+const REFERRAL_CODE_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+const REFERRAL_CODE_RANDOM_PART_LENGTH: usize = 8;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum InviteCommands {
+    #[command(description = "invite")]
+    Invite,
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn invite_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    me: Me,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_INVITE.inc();
+
+    let user = msg.from.as_ref().ok_or("no from user")?;
+    let answer = invite_impl(&repos.promo, UserId::from(user), config.referral, me.username(), &lang_code).await?;
+    reply_html!(bot, msg, answer);
+    Ok(())
+}
+
+/// The personal code is an ordinary promo code under the hood, so the deeplink is the same one
+/// `/start` already knows how to activate.
+pub(crate) async fn invite_impl(
+    promo: &repo::Promo,
+    uid: UserId,
+    config: ReferralConfig,
+    bot_username: &str,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<String> {
+    if !config.enabled() {
+        return Ok(t!("errors.feature_disabled", locale = lang_code).to_string())
+    }
+    let referral_code = promo.get_or_create_referral_code(uid, &generate_referral_code(), config.into()).await?;
+    let start_param = format!("{PROMO_START_PARAM_PREFIX}{}", URL_SAFE_NO_PAD.encode(referral_code.code.as_bytes()));
+    let link = format!("https://t.me/{bot_username}?start={start_param}");
+    let answer = t!("commands.invite.result", locale = lang_code,
+        code = referral_code.code, link = link,
+        invitee_bonus = config.invitee_bonus, inviter_bonus = config.inviter_bonus,
+        invites_left = referral_code.invites_left);
+    Ok(answer.to_string())
+}
+
+/// Lowercase only: the codes are matched case-insensitively anyway, and a reader copying the code
+/// by hand shouldn't have to tell `l` from `I`.
+fn generate_referral_code() -> String {
+    let mut rng = rand::rng();
+    let random_part: String = (0..REFERRAL_CODE_RANDOM_PART_LENGTH)
+        .map(|_| char::from(REFERRAL_CODE_ALPHABET[rng.random_range(0..REFERRAL_CODE_ALPHABET.len())]))
+        .collect();
+    format!("{REFERRAL_CODE_PREFIX}{random_part}")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::primitives::PromoCode;
+    use super::generate_referral_code;
+
+    /// The code goes into `Promo_Codes` and comes back through `/promo`, so it must pass the same
+    /// validation as a code typed by hand.
+    #[test]
+    fn a_generated_code_is_a_valid_promo_code() {
+        let code = generate_referral_code();
+        assert!(code.starts_with("ref"));
+        assert_eq!(code.len(), 11);
+        assert!(PromoCode::new(code).is_ok());
+    }
+}
@@ -94,7 +94,7 @@ async fn handle_chat_language(
     ls: LanguageService,
     lang_code: &LanguageCode,
 ) -> HandlerResult {
-    if !is_chat_admin(&bot, &msg, from_id).await? {
+    if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
         reply_html!(bot, msg, t!("commands.language.errors.admins_only", locale = lang_code));
         return Ok(());
     }
@@ -0,0 +1,153 @@
+//! The league of the chats: those whose admins have joined it are ranked against each other by the
+//! lengths of their members, and `/league` shows where the current chat stands among them.
+//!
+//! Joining is up to the admins, like the rest of `Chats.settings`, since the title of the chat is
+//! then shown in every other chat of the league.
+
+use std::str::FromStr;
+use anyhow::anyhow;
+use autometrics::autometrics;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use teloxide::utils::html;
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::{AppConfig, MessageGroup};
+use crate::domain::enums::LeagueRanking;
+use crate::domain::objects::LeagueStanding;
+use crate::domain::primitives::LanguageCode;
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
+use crate::handlers::{reply_html, HandlerDeps, HandlerResult};
+use crate::handlers::utils::is_chat_admin;
+use crate::repo::Repositories;
+use crate::settings::GameSettingsPolicy;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum LeagueCommands {
+    #[command(description = "league")]
+    League(String),
+}
+
+/// What the argument of the command asks for: the standings by some ranking, or, for an admin,
+/// joining or leaving the league.
+#[derive(Debug, PartialEq)]
+enum LeagueAction {
+    Show(LeagueRanking),
+    SetMembership(bool),
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn league_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: LeagueCommands,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_LEAGUE.inc();
+
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let LeagueCommands::League(args) = cmd;
+    let answer = match parse_args(&args) {
+        Some(LeagueAction::Show(ranking)) => {
+            let config = settings.config_for(&chat_id.kind(), &config).await;
+            league_impl(&repos, &config, &chat_id.kind(), &lang_code, ranking).await?
+        },
+        Some(LeagueAction::SetMembership(join)) => {
+            let from_id = msg.from.as_ref().map(|user| user.id)
+                .ok_or(anyhow!("unexpected absence of a FROM field"))?;
+            if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
+                t!("commands.league.errors.admins_only", locale = &lang_code).to_string()
+            } else if join {
+                let title = msg.chat.title().unwrap_or_default();
+                repos.chats.join_league(&chat_id, title).await?;
+                t!("commands.league.membership.joined", locale = &lang_code).to_string()
+            } else {
+                repos.chats.leave_league(&chat_id).await?;
+                t!("commands.league.membership.left", locale = &lang_code).to_string()
+            }
+        },
+        None => {
+            let rankings = LeagueRanking::iter().map(|r| format!("<code>{r}</code>")).collect::<Vec<_>>().join(", ");
+            t!("commands.league.errors.unknown_argument", locale = &lang_code, rankings = rankings).to_string()
+        },
+    };
+    reply_html_ephemeral!(bot, msg, answer, self_destruction, MessageGroup::Report, lang_code);
+    Ok(())
+}
+
+/// A bare command shows the default ranking; `join` and `leave` are for the admins.
+fn parse_args(args: &str) -> Option<LeagueAction> {
+    let args = args.trim().to_lowercase();
+    match args.as_str() {
+        "" => Some(LeagueAction::Show(LeagueRanking::default())),
+        "join" => Some(LeagueAction::SetMembership(true)),
+        "leave" => Some(LeagueAction::SetMembership(false)),
+        other => LeagueRanking::from_str(other).ok().map(LeagueAction::Show),
+    }
+}
+
+/// The first chats of the league, as many as the top of the chat has lines, and the current one
+/// after them if it stands lower.
+pub(crate) async fn league_impl(
+    repos: &Repositories,
+    config: &AppConfig,
+    chat_id: &ChatIdKind,
+    lang_code: &LanguageCode,
+    ranking: LeagueRanking,
+) -> anyhow::Result<String> {
+    let standings = repos.chats.get_league_standings(chat_id, ranking, config.top_limit).await?;
+    let joined = standings.iter().any(|s| s.is_current);
+    let hint = if joined {
+        String::new()
+    } else {
+        format!("\n\n{}", t!("commands.league.not_joined", locale = lang_code))
+    };
+    if standings.is_empty() {
+        return Ok(format!("{}{hint}", t!("commands.league.empty", locale = lang_code)))
+    }
+
+    let mut lines = Vec::with_capacity(standings.len() + 1);
+    for standing in standings {
+        if standing.position.value() > u64::from(config.top_limit.value()) {
+            lines.push("…".to_owned());
+        }
+        lines.push(render_line(standing, lang_code));
+    }
+    let ranking_name = t!(&format!("commands.league.rankings.{ranking}"), locale = lang_code);
+    let title = t!("commands.league.title", locale = lang_code, ranking = ranking_name);
+    Ok(format!("{}\n\n{}{hint}", title, lines.join("\n")))
+}
+
+fn render_line(standing: LeagueStanding, lang_code: &LanguageCode) -> String {
+    let escaped_title = html::escape(&standing.title);
+    let title = if standing.is_current {
+        format!("<u>{escaped_title}</u>")
+    } else {
+        escaped_title
+    };
+    t!("commands.league.line", locale = lang_code,
+        n = standing.position, title = title, length = standing.length, members = standing.members.value()).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::enums::LeagueRanking;
+    use super::{parse_args, LeagueAction};
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(""), Some(LeagueAction::Show(LeagueRanking::Average)));
+        assert_eq!(parse_args(" Sum "), Some(LeagueAction::Show(LeagueRanking::Sum)));
+        assert_eq!(parse_args("average"), Some(LeagueAction::Show(LeagueRanking::Average)));
+        assert_eq!(parse_args("JOIN"), Some(LeagueAction::SetMembership(true)));
+        assert_eq!(parse_args("leave"), Some(LeagueAction::SetMembership(false)));
+        assert_eq!(parse_args("max"), None);
+    }
+}
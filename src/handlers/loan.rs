@@ -1,6 +1,8 @@
 use autometrics::autometrics;
 use anyhow::anyhow;
+use chrono::{Days, NaiveDate, Utc};
 use derive_more::Display;
+use domain_types::traits::SaturatingInto;
 use rust_i18n::t;
 use teloxide::Bot;
 use teloxide::macros::BotCommands;
@@ -12,13 +14,16 @@ use callbacks::{EditMessageReqParamsKind, InvalidCallbackData};
 use crate::{check_invoked_by_owner_and_get_answer_params, metrics, reply_html_ephemeral, repo};
 use crate::config::{AppConfig, MessageGroup};
 use crate::domain::objects::Loan;
-use crate::domain::primitives::{Debt, FloatPercentage, LanguageCode, PayoutRatio, UserId as DomainUserId};
+use crate::domain::primitives::{Coefficient, Debt, FloatPercentage, LanguageCode, LoanPayout, PayoutRatio, UserId as DomainUserId};
 use domain_types::literal;
 use crate::domain::primitives::chat::{ChatIdPartiality, InlineMessageId};
 use crate::handlers::{CallbackButton, FromRefs, HandlerDeps, HandlerImplResult, HandlerResult, reply_html};
-use crate::handlers::utils::try_resolve_chat_id;
+use crate::handlers::perks::LoanPayoutPerk;
 use crate::handlers::utils::callbacks;
 use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackDataBuilder};
+use crate::settings::GameSettingsPolicy;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
@@ -28,11 +33,19 @@ pub enum LoanCommands {
     Borrow,
 }
 
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum RepayCommands {
+    #[command(description = "repay")]
+    Repay(String),
+}
+
 #[autometrics]
 #[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
 pub async fn loan_cmd_handler(
     bot: Bot,
     msg: Message,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
@@ -42,6 +55,7 @@ pub async fn loan_cmd_handler(
     let from = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?;
     let chat_id = msg.chat.id.into();
     let from_refs = FromRefs(from, &chat_id);
+    let config = settings.config_for(&chat_id.kind(), &config).await;
 
     let result = loan_impl(&repos, from_refs, config, &lang_code).await?;
     let markup = result.keyboard().map(ReplyMarkup::InlineKeyboard);
@@ -61,11 +75,26 @@ pub(crate) async fn loan_impl(
     let chat_id_kind = chat_id_part.kind();
 
     let maybe_loan = repos.loans.get_active_loan(DomainUserId::from(from), &chat_id_kind).await?;
-    if let Some(Loan { debt, .. }) = maybe_loan
+    if let Some(loan) = maybe_loan
         && !config.features.multiple_loans
     {
-        let left_to_pay = t!("commands.loan.debt", locale = lang_code, debt = debt).to_string();
-        return Ok(HandlerImplResult::OnlyText(left_to_pay))
+        let today = Utc::now().date_naive();
+        let text = loan_status(&loan, &config, today, lang_code);
+        // as much as the length covers at once; a smaller part is for /repay
+        let length: u64 = repos.dicks.fetch_length(DomainUserId::from(from), &chat_id_kind).await?
+            .value().saturating_into();
+        let repayable: u32 = loan.debt.value().min(length).saturating_into();
+        if repayable == 0 {
+            return Ok(HandlerImplResult::OnlyText(text))
+        }
+        let btn_repay = CallbackButton::new(
+            t!("commands.loan.buttons.repay", locale = lang_code, value = repayable).to_string(),
+            LoanCallbackData {
+                uid: from.id,
+                action: LoanCallbackAction::Repay { value: LoanPayout::new(repayable) }
+            }
+        );
+        return Ok(HandlerImplResult::WithKeyboard { text, buttons: vec![btn_repay] })
     }
 
     if config.loan_payout_ratio <= 0.0 || config.loan_payout_ratio >= 1.0 {
@@ -99,13 +128,92 @@ pub(crate) async fn loan_impl(
             action: LoanCallbackAction::Refused
         }
     );
+    let mut text = t!("commands.loan.confirmation.text", locale = lang_code,
+        debt = debt, payout_percentage = payout_percentage).to_string();
+    if !config.loan.interest_rate.is_zero() {
+        let interest = t!("commands.loan.confirmation.interest", locale = lang_code,
+            interest_percentage = config.loan.interest_rate.float_percentage());
+        text = format!("{text}\n\n{interest}");
+    }
+    if !config.loan.term.is_zero() {
+        text = format!("{text}\n{}", t!("commands.loan.confirmation.term", locale = lang_code, days = config.loan.term));
+    }
     Ok(HandlerImplResult::WithKeyboard {
-        text: t!("commands.loan.confirmation.text", locale = lang_code,
-            debt = debt, payout_percentage = payout_percentage).to_string(),
+        text,
         buttons: vec![btn_agree, btn_disagree]
     })
 }
 
+/// The balance with the interest accrued until today, the terms of the loan and when it's going to
+/// be repaid if its owner grows every day.
+fn loan_status(loan: &Loan, config: &AppConfig, today: NaiveDate, lang_code: &LanguageCode) -> String {
+    let mut lines = vec![t!("commands.loan.debt", locale = lang_code, debt = loan.debt).to_string()];
+    if !loan.interest_rate.is_zero() {
+        lines.push(t!("commands.loan.status.interest", locale = lang_code,
+            interest_percentage = loan.interest_rate.float_percentage()).to_string());
+    }
+    match loan.due_date {
+        Some(due_date) if loan.overdue => lines.push(t!("commands.loan.status.overdue", locale = lang_code,
+            date = due_date.format(DATE_FORMAT)).to_string()),
+        Some(due_date) => lines.push(t!("commands.loan.status.due_date", locale = lang_code,
+            date = due_date.format(DATE_FORMAT)).to_string()),
+        None => {}
+    }
+    let daily_payout = LoanPayoutPerk::expected_daily_payout(&config.incrementor, loan.payout_ratio);
+    let payoff_date = loan.days_to_repay(daily_payout)
+        .and_then(|days| today.checked_add_days(Days::new(u64::from(days.value()))));
+    let payoff = match payoff_date {
+        Some(date) => t!("commands.loan.status.payoff", locale = lang_code, date = date.format(DATE_FORMAT)),
+        None => t!("commands.loan.status.never", locale = lang_code),
+    };
+    lines.push(String::new());
+    lines.push(payoff.to_string());
+    lines.join("\n")
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn repay_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: RepayCommands,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, self_destruction, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_REPAY.inc();
+
+    let RepayCommands::Repay(args) = cmd;
+    let Some(value) = parse_repayment(&args) else {
+        let text = t!("commands.repay.usage", locale = &lang_code);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    };
+    let uid = DomainUserId::from(msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?);
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let result = repos.loans.repay(uid, &chat_id.kind(), value).await?;
+    let text = repay_result_to_text(result, &lang_code);
+    reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Report, lang_code);
+    Ok(())
+}
+
+fn parse_repayment(args: &str) -> Option<LoanPayout> {
+    args.trim().parse::<u32>().ok()
+        .filter(|value| *value > 0)
+        .map(LoanPayout::new)
+}
+
+fn repay_result_to_text(result: repo::RepayResult, lang_code: &LanguageCode) -> String {
+    match result {
+        repo::RepayResult::Repaid { paid, debt_left, length } if debt_left.is_zero() =>
+            t!("commands.repay.repaid", locale = lang_code, paid = paid, length = length),
+        repo::RepayResult::Repaid { paid, debt_left, length } =>
+            t!("commands.repay.success", locale = lang_code, paid = paid, debt = debt_left, length = length),
+        repo::RepayResult::NoLoan => t!("commands.repay.no_loan", locale = lang_code),
+        repo::RepayResult::NotEnoughLength(length) => t!("commands.repay.not_enough_length", locale = lang_code, length = length),
+    }.to_string()
+}
+
 pub fn callback_filter(query: CallbackQuery) -> bool {
     LoanCallbackData::check_prefix(query)
 }
@@ -115,6 +223,7 @@ pub fn callback_filter(query: CallbackQuery) -> bool {
 pub async fn loan_callback_handler(
     bot: Bot,
     query: CallbackQuery,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
@@ -132,39 +241,27 @@ pub async fn loan_callback_handler(
             self_destruction.cancel_inline(InlineMessageId::new(inline_message_id.clone())).await,
     };
     match data.action {
-        LoanCallbackAction::Confirmed { .. } if config.loan_payout_ratio.is_zero() => {
-            answer.show_alert.replace(true);
-            answer.text.replace(t!("errors.feature_disabled", locale = &lang_code).to_string());
-        }
-        LoanCallbackAction::Confirmed { value: debt, payout_ratio } if payout_ratio == config.loan_payout_ratio => {
-            match edit_msg_params {
-                EditMessageReqParamsKind::Chat(chat_id, message_id) => {
-                    let borrow_result = repos.loans.borrow(data.uid.into(), &chat_id.into(), debt).await?;
-                    let updated_text = borrow_result_to_text(borrow_result, &lang_code);
-                    bot.edit_message_text(chat_id, message_id, updated_text).await?;
-                }
-                EditMessageReqParamsKind::Inline { chat_instance, inline_message_id } => {
-                    let maybe_chat_id = try_resolve_chat_id(&inline_message_id)
-                        // normally, it should be always enabled but let's keep it here for now, just in case
-                        .filter(|_| config.features.chats_merging);
-                    let chat_id: ChatIdPartiality = if let Some(chat_id) = maybe_chat_id {
-                        repos.chats.get_chat(chat_id.into())
-                            .await?
-                            .and_then(|c| c.try_into().ok())
-                            .unwrap_or_else(|| chat_instance.into())
-                    } else {
-                        chat_instance.into()
-                    };
-
-                    let borrow_result = repos.loans.borrow(data.uid.into(), &chat_id.kind(), debt).await?;
-                    let updated_text = borrow_result_to_text(borrow_result, &lang_code);
-                    bot.edit_message_text_inline(inline_message_id, updated_text).await?;
-                }
+        LoanCallbackAction::Confirmed { value: debt, payout_ratio } => {
+            let chat_id = callbacks::resolve_chat_id(&repos, config.features.chats_merging, &edit_msg_params).await?;
+            let config = settings.config_for(&chat_id, &config).await;
+            if config.loan_payout_ratio.is_zero() {
+                answer.show_alert.replace(true);
+                answer.text.replace(t!("errors.feature_disabled", locale = &lang_code).to_string());
+            } else if payout_ratio == config.loan_payout_ratio {
+                let borrow_result = repos.loans.borrow(data.uid.into(), &chat_id, debt, payout_ratio).await?;
+                let updated_text = borrow_result_to_text(borrow_result, &lang_code);
+                callbacks::edit_message_text(&bot, edit_msg_params, updated_text).await?;
+            } else {
+                // an admin may have changed the ratio of the chat since the offer was made
+                let updated_text = t!("commands.loan.callback.payout_ratio_changed", locale = &lang_code);
+                callbacks::edit_message_text(&bot, edit_msg_params, updated_text).await?;
             }
         }
-        LoanCallbackAction::Confirmed { .. } => {
-            let updated_text = t!("commands.loan.callback.payout_ratio_changed", locale = &lang_code);
-            callbacks::edit_message_text(&bot, edit_msg_params, updated_text).await?;
+        LoanCallbackAction::Repay { value } => {
+            metrics::CMD_REPAY.inc();
+            let chat_id = callbacks::resolve_chat_id(&repos, config.features.chats_merging, &edit_msg_params).await?;
+            let result = repos.loans.repay(data.uid.into(), &chat_id, value).await?;
+            callbacks::edit_message_text(&bot, edit_msg_params, repay_result_to_text(result, &lang_code)).await?;
         }
         LoanCallbackAction::Refused => {
             let updated_text = t!("commands.loan.callback.refused", locale = &lang_code);
@@ -211,6 +308,8 @@ pub(crate) struct LoanCallbackData {
 pub(crate) enum LoanCallbackAction {
     #[display("confirmed:{value}:{payout_ratio}")]
     Confirmed { value: Debt, payout_ratio: PayoutRatio },
+    #[display("repay:{value}")]
+    Repay { value: LoanPayout },
     #[display("refused")]
     Refused
 }
@@ -243,6 +342,9 @@ impl TryFrom<String> for LoanCallbackData {
                 };
                 LoanCallbackAction::Confirmed { value, payout_ratio }
             }
+            "repay" => LoanCallbackAction::Repay {
+                value: callbacks::parse_part(&mut parts, &err, "value")?
+            },
             "refused" => LoanCallbackAction::Refused,
             _ => return Err(err.split_err())
         };
@@ -253,12 +355,39 @@ impl TryFrom<String> for LoanCallbackData {
 
 #[cfg(test)]
 mod test {
+    use chrono::NaiveDate;
     use teloxide::types::UserId;
     use domain_types::literal;
-    use crate::domain::primitives::{Debt, PayoutRatio};
-    use crate::handlers::loan::{LoanCallbackAction, LoanCallbackData};
+    use crate::config::AppConfig;
+    use crate::domain::objects::Loan;
+    use crate::domain::primitives::{Debt, LanguageCode, LoanPayout, PayoutRatio, Ratio};
+    use crate::handlers::loan::{loan_status, parse_repayment, LoanCallbackAction, LoanCallbackData};
     use crate::handlers::utils::callbacks::{build_callback_query, CallbackDataWithPrefix};
 
+    #[test]
+    fn test_loan_status() {
+        let config = AppConfig::default();
+        let today = NaiveDate::from_ymd_opt(2026, 10, 1).expect("invalid date in the test");
+        let lang_code = LanguageCode::new("en".to_owned());
+        let mut loan = Loan {
+            debt: Debt::new(11),
+            payout_ratio: literal!(PayoutRatio = 0.5),
+            interest_rate: literal!(Ratio = 0.0),
+            due_date: None,
+            overdue: false,
+        };
+        // 1.375 cm a day with the default growth range
+        let text = loan_status(&loan, &config, today, &lang_code);
+        assert!(text.contains("11 cm") && text.contains("09.10.2026"), "{text}");
+
+        loan.due_date = NaiveDate::from_ymd_opt(2026, 9, 30);
+        loan.overdue = true;
+        loan.interest_rate = literal!(Ratio = 0.5);
+        let text = loan_status(&loan, &config, today, &lang_code);
+        assert!(text.contains("30.09.2026") && text.contains("50.00%"), "{text}");
+        assert!(!text.contains(".10.2026"), "the interest outgrows the payouts, so there must be no payoff date: {text}");
+    }
+
     #[test]
     fn test_parse() {
         let (uid, value, payout_ratio) = get_test_params();
@@ -277,6 +406,26 @@ mod test {
         }
     }
     
+    #[test]
+    fn test_parse_repay() {
+        let uid = UserId(123456);
+        let query = build_callback_query(format!("loan:{uid}:repay:7"));
+        let data = LoanCallbackData::parse(&query)
+            .expect("callback data for 'repay' must be parsed successfully");
+        assert_eq!(data.uid, uid);
+        assert_eq!(data.action, LoanCallbackAction::Repay { value: LoanPayout::new(7) });
+        assert_eq!(data.to_data_string(), format!("loan:{uid}:repay:7"));
+    }
+
+    #[test]
+    fn test_parse_repayment() {
+        assert_eq!(parse_repayment(" 5 "), Some(LoanPayout::new(5)));
+        assert_eq!(parse_repayment(""), None);
+        assert_eq!(parse_repayment("0"), None);
+        assert_eq!(parse_repayment("-5"), None);
+        assert_eq!(parse_repayment("all"), None);
+    }
+
     #[test]
     fn test_parse_old() {
         let (uid, value, _) = get_test_params();
@@ -11,13 +11,28 @@ pub mod shrink;
 pub mod language;
 pub mod utils;
 pub mod pvp;
+pub mod pvptop;
+pub mod globaltop;
+pub mod league;
+pub mod seasons;
+pub mod timezone;
+pub mod rivalry;
+pub mod history;
+pub mod tournament;
+pub mod royale;
 pub mod perks;
 pub mod loan;
+pub mod gift;
 pub mod stats;
 pub mod setup;
 pub mod topics;
 pub mod cleanup;
+pub mod settings;
 pub mod rights;
+pub mod achievements;
+pub mod shop;
+pub mod invite;
+pub mod promo_admin;
 
 use derive_more::Constructor;
 use rust_i18n::t;
@@ -38,9 +53,24 @@ pub use import::*;
 pub use inline::*;
 pub use promo::*;
 pub use language::LanguageCommands;
-pub use loan::LoanCommands;
+pub use loan::{LoanCommands, RepayCommands};
+pub use gift::GiftCommands;
 pub use topics::TopicsCommands;
 pub use cleanup::CleanupCommands;
+pub use settings::SettingsCommands;
+pub use achievements::AchievementsCommands;
+pub use shop::ShopCommands;
+pub use invite::InviteCommands;
+pub use promo_admin::PromoAdminCommands;
+pub use tournament::TournamentCommands;
+pub use royale::RoyaleCommands;
+pub use pvptop::PvpTopCommands;
+pub use globaltop::GlobalTopCommands;
+pub use league::LeagueCommands;
+pub use seasons::SeasonsCommands;
+pub use timezone::TimeZoneCommands;
+pub use rivalry::RivalryCommands;
+pub use history::HistoryCommands;
 use crate::config::{AppConfig, MessageGroup};
 use crate::domain::primitives::LanguageCode;
 use crate::handlers::utils::callbacks::CallbackDataWithPrefix;
@@ -4,17 +4,37 @@ use crate::domain::primitives::Coefficient;
 use sqlx::{Pool, Postgres};
 use crate::handlers::utils::{AdditionalChange, ChangeIntent, ConfigurablePerk, DickId, Perk};
 use crate::{config, repo};
-use crate::domain::primitives::{Length, LengthChange, LoanPayout, Ratio};
+use crate::config::GrowStreakConfig;
+use crate::domain::enums::LengthChangeReason;
+use crate::domain::objects::GrowthSettlement;
+use crate::domain::primitives::{Length, LengthChange, LengthIncrement, LoanPayout, PayoutRatio, Ratio};
 use domain_types::literal;
 
 pub fn all(pool: &Pool<Postgres>, cfg: &config::AppConfig) -> Vec<Box<dyn Perk>> {
     let loans = repo::Loans::new(pool.clone(), cfg);
+    let battle_stats = repo::BattleStatsRepo::new(pool.clone(), cfg.features);
+    let gifts = repo::Gifts::new(pool.clone(), cfg);
+    let dicks = repo::Dicks::new(pool.clone(), cfg.features);
 
     vec![
         Box::new(HelpPussiesPerk {
             coefficient: cfg.incrementor.perks.help_pussies_ratio,
         }),
-        Box::new(LoanPayoutPerk { loans })
+        Box::new(LoserSupportPerk {
+            config: cfg.incrementor.perks.loser_support,
+            battle_stats,
+        }),
+        Box::new(LoanPayoutPerk { loans: loans.clone() }),
+        // after the payout: a growth that repays the loan at last isn't fined for the delay
+        Box::new(LoanOverduePenaltyPerk {
+            ratio: cfg.incrementor.perks.loan_overdue_penalty_ratio,
+            loans,
+        }),
+        Box::new(MemberLoanPayoutPerk { gifts }),
+        Box::new(GrowStreakPerk {
+            config: cfg.incrementor.perks.grow_streak,
+            dicks,
+        }),
     ]
 }
 
@@ -35,7 +55,7 @@ impl Perk for HelpPussiesPerk {
 
         let current_deepness: f64 = change_intent.current_length.abs().approx_into();
         let change: i64 = self.coefficient.scale(current_deepness).round().saturating_into();
-        AdditionalChange(LengthChange::signed(change))
+        AdditionalChange::of(LengthChange::signed(change))
     }
 
     fn enabled(&self) -> bool {
@@ -51,10 +71,94 @@ impl ConfigurablePerk for HelpPussiesPerk {
     }
 }
 
+/// Gives back a share of the net PvP loss to those who keep losing battles. A single lost battle is
+/// just bad luck, so the support starts only with a losing streak and ends with the first win. All
+/// the support paid together never exceeds the net loss.
+pub struct LoserSupportPerk {
+    config: config::LoserSupportConfig,
+    battle_stats: repo::BattleStatsRepo,
+}
+
+#[async_trait]
+impl Perk for LoserSupportPerk {
+    fn name(&self) -> &str {
+        "loser-support"
+    }
+
+    async fn apply(&self, dick_id: &DickId, _: ChangeIntent) -> AdditionalChange {
+        let stats = match self.battle_stats.get_stats(&dick_id.1, dick_id.0).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                tracing::error!(dick_id = %dick_id, error = %e, "couldn't check whether a perk is active");
+                return AdditionalChange::zero()
+            }
+        };
+        if stats.lose_streak_current < self.config.min_lose_streak {
+            return AdditionalChange::zero()
+        }
+
+        // the acquired length is subtracted, so that somebody who has won a lot before can't farm the perk
+        let net_loss = stats.lost_length.value().saturating_sub(stats.acquired_length.value());
+        if net_loss <= 0 {
+            return AdditionalChange::zero()
+        }
+        // and so is what has been refunded already: the streak lasts until the next win, which
+        // never comes to whoever stops fighting, so the support would be paid forever otherwise
+        let paid = match self.battle_stats.get_support_paid(&dick_id.1, dick_id.0).await {
+            Ok(paid) => paid.value(),
+            Err(e) => {
+                tracing::error!(dick_id = %dick_id, error = %e, "couldn't check whether a perk is active");
+                return AdditionalChange::zero()
+            }
+        };
+        let unpaid = net_loss.saturating_sub(paid);
+        if unpaid <= 0 {
+            return AdditionalChange::zero()
+        }
+        let change: i64 = self.config.ratio.scale(unpaid.approx_into()).round().saturating_into();
+        let change = change.min(unpaid);
+        if change <= 0 {
+            return AdditionalChange::zero()
+        }
+        // recorded along with the growth, so that a rejected one doesn't use up the refunds
+        AdditionalChange(LengthChange::signed(change), vec![GrowthSettlement::SupportPaid(Length::new(change))])
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.ratio > literal!(Ratio = 0.0)
+    }
+}
+
+impl ConfigurablePerk for LoserSupportPerk {
+    type Config = config::LoserSupportConfig;
+
+    fn get_config(&self) -> Self::Config {
+        self.config
+    }
+}
+
 pub struct LoanPayoutPerk {
     loans: repo::Loans,
 }
 
+impl LoanPayoutPerk {
+    /// What the perk takes on average from a daily growth: the chance of the growth being positive
+    /// times the share of its mean value. The base for the projected payoff date of `/loan`.
+    pub fn expected_daily_payout(config: &config::IncrementorConfig, payout_ratio: PayoutRatio) -> f64 {
+        let (start, end) = (i32::from(*config.growth_range.start()), i32::from(*config.growth_range.end()));
+        let (growth_chance, lowest_growth) = if start > 0 {
+            (1.0, start)
+        } else {
+            (config.grow_shrink_ratio.value(), 1)
+        };
+        if end < lowest_growth {
+            return 0.0
+        }
+        let mean_growth = f64::from(lowest_growth + end) / 2.0;
+        payout_ratio.scale(growth_chance * mean_growth)
+    }
+}
+
 #[async_trait]
 impl Perk for LoanPayoutPerk {
     fn name(&self) -> &str {
@@ -88,7 +192,7 @@ impl Perk for LoanPayoutPerk {
                 LoanPayout::new(0)
             });
         match self.loans.pay(dick_id.0, &dick_id.1, payout).await {
-            Ok(()) => AdditionalChange(LengthChange::signed(-i64::from(payout.value()))),
+            Ok(()) => AdditionalChange::of(LengthChange::signed(-i64::from(payout.value()))),
             Err(e) => {
                 tracing::error!(payout = %payout, dick_id = %dick_id, error = %e, "couldn't pay for the loan");
                 AdditionalChange::zero()
@@ -97,14 +201,122 @@ impl Perk for LoanPayoutPerk {
     }
 }
 
+/// Takes a share of every growth from those who haven't repaid a loan by its due date. The loan
+/// itself stays as it was: the penalty is a fine, not a payout.
+pub struct LoanOverduePenaltyPerk {
+    ratio: Ratio,
+    loans: repo::Loans,
+}
+
+#[async_trait]
+impl Perk for LoanOverduePenaltyPerk {
+    fn name(&self) -> &str {
+        "loan-overdue"
+    }
+
+    async fn apply(&self, dick_id: &DickId, change_intent: ChangeIntent) -> AdditionalChange {
+        let base_increment = change_intent.base_increment.value();
+        if !base_increment.is_positive() {
+            return AdditionalChange::zero()
+        }
+        let overdue = self.loans.get_active_loan(dick_id.0, &dick_id.1)
+            .await
+            .inspect_err(|e| tracing::error!(error = %e, "couldn't check whether a perk is active"))
+            .ok()
+            .flatten()
+            .is_some_and(|loan| loan.overdue);
+        if !overdue {
+            return AdditionalChange::zero()
+        }
+        let penalty: i64 = self.ratio.scale(base_increment.approx_into()).round().saturating_into();
+        AdditionalChange::of(LengthChange::signed(-penalty))
+    }
+
+    fn enabled(&self) -> bool {
+        self.ratio > literal!(Ratio = 0.0)
+    }
+}
+
+/// Pays the loans taken from other members with `/gift` back out of every growth. Unlike the loan of
+/// the bank, it's the lender who gets the payout, settled along with the growth.
+pub struct MemberLoanPayoutPerk {
+    gifts: repo::Gifts,
+}
+
+#[async_trait]
+impl Perk for MemberLoanPayoutPerk {
+    fn name(&self) -> &str {
+        "member-loan-payout"
+    }
+
+    async fn apply(&self, dick_id: &DickId, change_intent: ChangeIntent) -> AdditionalChange {
+        let base_increment = change_intent.base_increment.value();
+        if !base_increment.is_positive() {
+            return AdditionalChange::zero()
+        }
+        let growth = LengthIncrement::new(base_increment.unsigned_abs());
+        match self.gifts.plan_member_loan_payouts(dick_id.0, &dick_id.1, growth).await {
+            Ok(payouts) => {
+                let paid: i64 = payouts.iter()
+                    .map(|settlement| match settlement {
+                        GrowthSettlement::MemberLoanPayout { payout, .. } => i64::from(payout.value()),
+                        _ => 0,
+                    })
+                    .sum();
+                AdditionalChange(LengthChange::signed(-paid), payouts)
+            },
+            Err(e) => {
+                tracing::error!(dick_id = %dick_id, error = %e, "couldn't check the loans from other members");
+                AdditionalChange::zero()
+            }
+        }
+    }
+}
+
+/// Rewards those who grow every day. The bonus comes with the growth that extends the streak, so
+/// the streak it's counted by includes today; a bonus attempt on the same day extends nothing and
+/// earns nothing, and neither does the Dick of the Day, which isn't a growth of the owner's own.
+pub struct GrowStreakPerk {
+    config: GrowStreakConfig,
+    dicks: repo::Dicks,
+}
+
+#[async_trait]
+impl Perk for GrowStreakPerk {
+    fn name(&self) -> &str {
+        "grow-streak"
+    }
+
+    async fn apply(&self, dick_id: &DickId, _change_intent: ChangeIntent) -> AdditionalChange {
+        match self.dicks.fetch_grow_streak(dick_id.0, &dick_id.1).await {
+            Ok(streak) if streak.grown_today => AdditionalChange::zero(),
+            Ok(streak) => AdditionalChange::of(LengthChange::signed(self.config.bonus(streak.with_today()))),
+            Err(e) => {
+                tracing::error!(dick_id = %dick_id, error = %e, "couldn't check whether a perk is active");
+                AdditionalChange::zero()
+            }
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.config.bonus_per_day.is_zero() && !self.config.max_days.is_zero()
+    }
+
+    fn applies_to(&self, reason: LengthChangeReason) -> bool {
+        reason == LengthChangeReason::Grow
+    }
+}
+
 #[cfg(test)]
 mod test {
     use domain_types::literal;
-    use crate::handlers::perks::{HelpPussiesPerk, LoanPayoutPerk};
-    use crate::handlers::utils::{ChangeIntent, DickId, Perk};
+    use crate::handlers::perks::{GrowStreakPerk, HelpPussiesPerk, LoanOverduePenaltyPerk, LoanPayoutPerk, LoserSupportPerk};
+    use std::time::Duration;
+    use crate::handlers::utils::{ChangeIntent, DickId, Incrementor, Perk};
+    use crate::settings::GameSettingsPolicy;
     use crate::{config, repo};
-    use crate::domain::primitives::{Debt, Length, LengthChange, LengthIncrement, PayoutRatio, Ratio, SignedLengthChange};
-    use crate::repo::test::{CHAT_ID_KIND, fresh_db, USER_ID};
+    use crate::domain::primitives::{DaysCount, Debt, Length, LengthChange, LengthIncrement, LoseStreak, PayoutRatio, Ratio, SignedLengthChange};
+    use crate::repo::test::{CHAT_ID_KIND, fresh_db, internal_chat_id, UID, USER_ID};
 
     #[tokio::test]
     async fn test_help_pussies() {
@@ -156,7 +368,7 @@ mod test {
         assert!(perk.enabled());
         assert_eq!(perk.apply(&dick_id, change_intent_positive_increment).await.0.value(), 0);
 
-        let borrow_result = loans.borrow(USER_ID, &CHAT_ID_KIND, Debt::new(10))
+        let borrow_result = loans.borrow(USER_ID, &CHAT_ID_KIND, Debt::new(10), literal!(PayoutRatio = 0.1))
             .await.expect("couldn't create a loan");
         assert_eq!(borrow_result, repo::BorrowResult::Granted);
 
@@ -175,4 +387,176 @@ mod test {
             .debt;
         assert_eq!(debt, Debt::new(9));
     }
+
+    #[test]
+    fn test_expected_daily_payout() {
+        let payout_ratio = literal!(PayoutRatio = 0.5);
+        let config = |growth_range, grow_shrink_ratio| config::IncrementorConfig {
+            growth_range, grow_shrink_ratio, ..Default::default()
+        };
+        // a half of the growths, 5.5 cm on average, a half of it paid out
+        assert_eq!(LoanPayoutPerk::expected_daily_payout(&config(-5..=10, literal!(Ratio = 0.5)), payout_ratio), 1.375);
+        // every growth is positive whatever the ratio
+        assert_eq!(LoanPayoutPerk::expected_daily_payout(&config(2..=4, literal!(Ratio = 0.0)), payout_ratio), 1.5);
+        // nothing to pay out of
+        assert_eq!(LoanPayoutPerk::expected_daily_payout(&config(-5..=0, literal!(Ratio = 0.5)), payout_ratio), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_loan_overdue_penalty() {
+        let db = fresh_db().await;
+        let loans = {
+            let cfg = config::AppConfig {
+                loan: config::LoanConfig { interest_rate: literal!(Ratio = 0.0), term: DaysCount::new(3) },
+                ..Default::default()
+            };
+            repo::Loans::new(db.clone(), &cfg)
+        };
+        {
+            let users = repo::Users::new(db.clone());
+            users.create_or_update(USER_ID, "")
+                .await.expect("couldn't create a user");
+            let dicks = repo::Dicks::new(db.clone(), Default::default());
+            dicks.create_or_grow(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(-10))
+                .await.expect("couldn't create a dick");
+        }
+        {
+            let invalid_perk = LoanOverduePenaltyPerk { ratio: literal!(Ratio = 0.0), loans: loans.clone() };
+            assert!(!invalid_perk.enabled())
+        }
+        let perk = LoanOverduePenaltyPerk { ratio: literal!(Ratio = 0.5), loans: loans.clone() };
+        assert!(perk.enabled());
+
+        let dick_id = DickId(USER_ID, CHAT_ID_KIND);
+        let change_intent_positive_increment = ChangeIntent { current_length: Length::new(1), base_increment: LengthIncrement::new(10).into() };
+        let change_intent_negative_increment = ChangeIntent { current_length: Length::new(1), base_increment: SignedLengthChange::new(-2).into() };
+        // no loan
+        assert_eq!(perk.apply(&dick_id, change_intent_positive_increment).await.0.value(), 0);
+
+        loans.borrow(USER_ID, &CHAT_ID_KIND, Debt::new(10), literal!(PayoutRatio = 0.1))
+            .await.expect("couldn't create a loan");
+        // not due yet
+        assert_eq!(perk.apply(&dick_id, change_intent_positive_increment).await.0.value(), 0);
+
+        sqlx::query!("UPDATE Loans SET due_date = current_date - 1 WHERE uid = $1", UID)
+            .execute(&db).await.expect("couldn't move the due date");
+        assert_eq!(perk.apply(&dick_id, change_intent_positive_increment).await.0.value(), -5);
+        assert_eq!(perk.apply(&dick_id, change_intent_negative_increment).await.0.value(), 0, "a shrink is not fined");
+    }
+
+    #[tokio::test]
+    async fn test_loser_support() {
+        let db = fresh_db().await;
+        let dicks = repo::Dicks::new(db.clone(), Default::default());
+        {
+            let users = repo::Users::new(db.clone());
+            users.create_or_update(USER_ID, "")
+                .await.expect("couldn't create a user");
+            dicks.create_or_grow(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(1))
+                .await.expect("couldn't create a dick");
+        }
+        let config = config::LoserSupportConfig {
+            ratio: literal!(Ratio = 0.1),
+            min_lose_streak: LoseStreak::new(3),
+        };
+        let battle_stats = repo::BattleStatsRepo::new(db.clone(), Default::default());
+        {
+            let invalid_perk = LoserSupportPerk { config: Default::default(), battle_stats: battle_stats.clone() };
+            assert!(!invalid_perk.enabled())
+        }
+        let perk = LoserSupportPerk { config, battle_stats };
+        assert!(perk.enabled());
+
+        let dick_id = DickId(USER_ID, CHAT_ID_KIND);
+        let change_intent = ChangeIntent { current_length: Length::new(1), base_increment: LengthIncrement::new(1).into() };
+        // no battles at all
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0);
+
+        let chat_id = internal_chat_id(&db).await;
+        sqlx::query!("INSERT INTO Battle_Stats (uid, chat_id, battles_total, lose_streak_current, acquired_length, lost_length) VALUES ($1, $2, 2, 2, 10, 60)",
+                UID, chat_id)
+            .execute(&db).await.expect("couldn't create the battle stats");
+        // the streak is too short yet
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0);
+
+        sqlx::query!("UPDATE Battle_Stats SET battles_total = 3, lose_streak_current = 3 WHERE uid = $1 AND chat_id = $2", UID, chat_id)
+            .execute(&db).await.expect("couldn't update the battle stats");
+        let support = perk.apply(&dick_id, change_intent).await;
+        assert_eq!(support.0.value(), 5);
+
+        // the growth has been made today already, so it's rejected and nothing is refunded
+        dicks.create_or_grow_with_settlements(USER_ID, &CHAT_ID_KIND.into(), support.0, &support.1)
+            .await.expect_err("the dick must not grow twice a day");
+        let paid = sqlx::query_scalar!("SELECT support_paid FROM Battle_Stats WHERE uid = $1 AND chat_id = $2", UID, chat_id)
+            .fetch_one(&db).await.expect("couldn't fetch the paid support");
+        assert_eq!(paid, 0, "a rejected growth must not use up the refunds");
+
+        // every refund is taken out of the rest of the loss, so the support runs dry instead of being
+        // paid every day for as long as the streak isn't broken
+        sqlx::query!("UPDATE Dicks SET bonus_attempts = 100 WHERE uid = $1", UID)
+            .execute(&db).await.expect("couldn't give bonus attempts");
+        let mut refunded = 0;
+        for _ in 0..100 {
+            let support = perk.apply(&dick_id, change_intent).await;
+            dicks.create_or_grow_with_settlements(USER_ID, &CHAT_ID_KIND.into(), support.0, &support.1)
+                .await.expect("couldn't grow with the support");
+            refunded += support.0.value();
+        }
+        assert!(refunded <= 50, "{refunded} is refunded for the net loss of 50");
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0);
+
+        // whoever has won more than lost gets nothing, whatever the streak
+        sqlx::query!("UPDATE Battle_Stats SET acquired_length = 100 WHERE uid = $1 AND chat_id = $2", UID, chat_id)
+            .execute(&db).await.expect("couldn't update the battle stats");
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_grow_streak() {
+        let db = fresh_db().await;
+        let dicks = repo::Dicks::new(db.clone(), Default::default());
+        {
+            let invalid_perk = GrowStreakPerk { config: Default::default(), dicks: dicks.clone() };
+            assert!(!invalid_perk.enabled())
+        }
+        let config = config::GrowStreakConfig {
+            bonus_per_day: LengthIncrement::new(2),
+            max_days: DaysCount::new(3),
+        };
+        let perk = GrowStreakPerk { config, dicks: dicks.clone() };
+        assert!(perk.enabled());
+
+        let dick_id = DickId(USER_ID, CHAT_ID_KIND);
+        let change_intent = ChangeIntent { current_length: Length::new(1), base_increment: LengthIncrement::new(1).into() };
+        // no dick, so the first day of a streak
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0);
+
+        repo::Users::new(db.clone()).create_or_update(USER_ID, "")
+            .await.expect("couldn't create a user");
+        dicks.create_or_grow(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(1))
+            .await.expect("couldn't create a dick");
+        // a bonus attempt the same day doesn't extend the streak
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0);
+
+        sqlx::query!("UPDATE Dicks SET updated_at = current_timestamp - interval '1 day', grow_streak = 3, bonus_attempts = bonus_attempts + 1 WHERE uid = $1", UID)
+            .execute(&db).await.expect("couldn't move the last growth");
+        // the fourth day in a row, capped by the maximum
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 6);
+
+        // only the growth of a new day is rewarded, and only a growth is
+        let incr = Incrementor::new(Default::default(), Default::default(),
+            GameSettingsPolicy::new(Duration::ZERO, repo::Chats::new(db.clone(), Default::default())),
+            &dicks, vec![Box::new(GrowStreakPerk { config, dicks: dicks.clone() })]);
+        let dod = incr.dod_increment(USER_ID, CHAT_ID_KIND).await;
+        assert!(dod.by_perks.is_empty(), "the Dick of the Day must not get the bonus of the streak");
+        let growth = incr.growth_increment(USER_ID, CHAT_ID_KIND, DaysCount::new(0)).await;
+        assert_eq!(growth.by_perks.get(perk.name()).map(|change| change.value()), Some(6));
+        dicks.create_or_grow(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(1))
+            .await.expect("couldn't grow the dick");
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0, "a second growth a day must not get the bonus again");
+
+        sqlx::query!("UPDATE Dicks SET updated_at = current_timestamp - interval '3 days' WHERE uid = $1", UID)
+            .execute(&db).await.expect("couldn't move the last growth");
+        assert_eq!(perk.apply(&dick_id, change_intent).await.0.value(), 0, "a missed day must reset the streak");
+    }
 }
@@ -0,0 +1,226 @@
+//! The promo codes used to be inserted by hand right into the database. These commands let the
+//! owner manage them from the support chat (`SUPPORT_CHAT_ID`) or a private chat of the account set
+//! in `OWNER_ID`. Nobody else sees them: they are left out of the menu and any other sender falls
+//! through to the ordinary branches.
+//!
+//! The owner reads the replies, so they aren't localized.
+
+use autometrics::autometrics;
+use chrono::NaiveDate;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use teloxide::utils::html;
+use crate::config::AppConfig;
+use crate::domain::primitives::{PromoBonus, PromoCapacity, PromoCode, UserId};
+use crate::handlers::{HandlerDeps, HandlerResult, reply_html};
+use crate::{metrics, reply_html, repo};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const NO_END_DATE: &str = "-";
+const LIST_LIMIT: i64 = 30;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "snake_case")]
+pub enum PromoAdminCommands {
+    #[command(description = "CODE BONUS CAPACITY [SINCE] [UNTIL]")]
+    PromoCreate(String),
+    #[command(description = "the latest promo codes")]
+    PromoList,
+    #[command(description = "CODE UNTIL|- [EXTRA_CAPACITY]")]
+    PromoExtend(String),
+    #[command(description = "CODE")]
+    PromoDisable(String),
+}
+
+pub fn is_owner(msg: Message, config: AppConfig) -> bool {
+    let in_support_chat = config.support_chat_id
+        .is_some_and(|chat_id| chat_id.value() == msg.chat.id.0);
+    let by_owner = config.owner_id
+        .zip(msg.from.as_ref())
+        .is_some_and(|(owner_id, from)| owner_id == UserId::from(from));
+    in_support_chat || by_owner
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg)))]
+pub async fn promo_admin_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: PromoAdminCommands,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    metrics::CMD_PROMO_ADMIN.inc();
+    let answer = promo_admin_impl(&deps.repos.promo, cmd).await?;
+    reply_html!(bot, msg, answer);
+    Ok(())
+}
+
+pub(crate) async fn promo_admin_impl(promo: &repo::Promo, cmd: PromoAdminCommands) -> anyhow::Result<String> {
+    let answer = match cmd {
+        PromoAdminCommands::PromoCreate(args) => match parse_create_args(&args) {
+            Ok(params) => {
+                let code = params.code.to_string();
+                if promo.create_promo_code(params).await? {
+                    format!("✅ The promo code <code>{}</code> has been created.", html::escape(&code))
+                } else {
+                    format!("❌ The promo code <code>{}</code> exists already.", html::escape(&code))
+                }
+            }
+            Err(usage) => usage,
+        },
+        PromoAdminCommands::PromoList => render_list(&promo.list_promo_codes(LIST_LIMIT).await?),
+        PromoAdminCommands::PromoExtend(args) => match parse_extend_args(&args) {
+            Ok(ExtendArgs { code, until, extra_capacity }) => {
+                if promo.extend_promo_code(&code, until, extra_capacity).await? {
+                    format!("✅ The promo code <code>{}</code> has been extended.", html::escape(&code))
+                } else {
+                    not_found(&code)
+                }
+            }
+            Err(usage) => usage,
+        },
+        PromoAdminCommands::PromoDisable(args) => match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [code] => {
+                if promo.disable_promo_code(code).await? {
+                    format!("✅ The promo code <code>{}</code> has been disabled.", html::escape(code))
+                } else {
+                    not_found(code)
+                }
+            }
+            _ => "Usage: /promo_disable CODE".to_owned(),
+        },
+    };
+    Ok(answer)
+}
+
+fn not_found(code: &str) -> String {
+    format!("❌ There is no ordinary promo code <code>{}</code>.", html::escape(code))
+}
+
+fn render_list(codes: &[repo::PromoCodeStats]) -> String {
+    if codes.is_empty() {
+        return "There are no promo codes yet.".to_owned()
+    }
+    let lines = codes.iter()
+        .map(|c| {
+            let until = c.until
+                .map(|date| date.format(DATE_FORMAT).to_string())
+                .unwrap_or_else(|| "∞".to_owned());
+            format!("<code>{}</code>: {:+} cm, {} left, {} used, {} — {until}",
+                html::escape(&c.code), c.bonus_length.value(), c.capacity, c.activations,
+                c.since.format(DATE_FORMAT))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("<b>Promo codes</b> (the latest {LIST_LIMIT} at most):\n\n{lines}")
+}
+
+/// One date is the last day of a code that starts today; two dates are the first and the last day.
+fn parse_create_args(args: &str) -> Result<repo::PromoCodeParams, String> {
+    const USAGE: &str = "Usage: /promo_create CODE BONUS CAPACITY [SINCE] [UNTIL], the dates as YYYY-MM-DD";
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let (code, bonus, capacity, dates) = match parts[..] {
+        [code, bonus, capacity, ref dates @ ..] if dates.len() <= 2 => (code, bonus, capacity, dates),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let code = PromoCode::new(code.to_owned())
+        .map_err(|e| format!("Invalid code: {}", html::escape(&e.to_string())))?;
+    let bonus_length = bonus.parse().map(PromoBonus::new)
+        .map_err(|_| USAGE.to_owned())?;
+    let capacity = capacity.parse().map(PromoCapacity::new)
+        .map_err(|_| USAGE.to_owned())?;
+    let dates = dates.iter()
+        .map(|date| parse_date(date))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (since, until) = match dates[..] {
+        [] => (None, None),
+        [until] => (None, Some(until)),
+        [since, until] if since <= until => (Some(since), Some(until)),
+        _ => return Err("The code would end before it starts.".to_owned()),
+    };
+    Ok(repo::PromoCodeParams { code, bonus_length, capacity, since, until })
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+struct ExtendArgs {
+    code: String,
+    until: Option<NaiveDate>,
+    extra_capacity: PromoCapacity,
+}
+
+fn parse_extend_args(args: &str) -> Result<ExtendArgs, String> {
+    const USAGE: &str = "Usage: /promo_extend CODE UNTIL|- [EXTRA_CAPACITY], the date as YYYY-MM-DD";
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let (code, until, extra_capacity) = match parts[..] {
+        [code, until] => (code, until, None),
+        [code, until, extra_capacity] => (code, until, Some(extra_capacity)),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let until = match until {
+        NO_END_DATE => None,
+        date => Some(parse_date(date)?),
+    };
+    let extra_capacity = match extra_capacity {
+        Some(value) => value.parse().map(PromoCapacity::new).map_err(|_| USAGE.to_owned())?,
+        None => PromoCapacity::new(0),
+    };
+    Ok(ExtendArgs { code: code.to_owned(), until, extra_capacity })
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, DATE_FORMAT)
+        .map_err(|_| format!("Invalid date: {}, expected YYYY-MM-DD", html::escape(date)))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use crate::domain::primitives::PromoCapacity;
+    use super::{parse_create_args, parse_extend_args, ExtendArgs};
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").expect("invalid date in the test")
+    }
+
+    #[test]
+    fn test_parse_create_args() {
+        let params = parse_create_args("newyear 10 100").expect("must be parsed");
+        assert_eq!(params.code.to_string(), "newyear");
+        assert_eq!(params.bonus_length.value(), 10);
+        assert_eq!(params.capacity.value(), 100);
+        assert_eq!((params.since, params.until), (None, None));
+
+        let params = parse_create_args("penalty -5 1 2027-01-07").expect("must be parsed");
+        assert_eq!(params.bonus_length.value(), -5);
+        assert_eq!((params.since, params.until), (None, Some(date("2027-01-07"))));
+
+        let params = parse_create_args("newyear 10 100 2026-12-31 2027-01-07").expect("must be parsed");
+        assert_eq!((params.since, params.until), (Some(date("2026-12-31")), Some(date("2027-01-07"))));
+
+        assert!(parse_create_args("newyear 10").is_err());
+        assert!(parse_create_args("newyear ten 100").is_err());
+        assert!(parse_create_args("newyear 10 -1").is_err());
+        assert!(parse_create_args("new year 10 100").is_err());
+        assert!(parse_create_args("newyear 10 100 31.12.2026").is_err());
+        assert!(parse_create_args("newyear 10 100 2027-01-07 2026-12-31").is_err());
+        assert!(parse_create_args("newyear 10 100 2026-12-31 2027-01-07 2027-01-08").is_err());
+    }
+
+    #[test]
+    fn test_parse_extend_args() {
+        assert_eq!(parse_extend_args("newyear 2027-01-14"), Ok(ExtendArgs {
+            code: "newyear".to_owned(),
+            until: Some(date("2027-01-14")),
+            extra_capacity: PromoCapacity::new(0),
+        }));
+        assert_eq!(parse_extend_args("newyear - 50"), Ok(ExtendArgs {
+            code: "newyear".to_owned(),
+            until: None,
+            extra_capacity: PromoCapacity::new(50),
+        }));
+        assert!(parse_extend_args("newyear").is_err());
+        assert!(parse_extend_args("newyear tomorrow").is_err());
+        assert!(parse_extend_args("newyear - many").is_err());
+    }
+}
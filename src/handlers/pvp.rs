@@ -1,38 +1,55 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use autometrics::autometrics;
 use anyhow::{anyhow, Context};
-use domain_types::traits::SaturatingInto;
+use domain_types::literal;
+use domain_types::traits::{ApproxInto, SaturatingInto};
 use crate::domain::primitives::Coefficient;
 use futures::join;
 use rand::RngExt;
 use rust_i18n::t;
+use strum::IntoEnumIterator;
 use teloxide::Bot;
 use teloxide::macros::BotCommands;
 use teloxide::payloads::AnswerInlineQuerySetters;
 use teloxide::requests::Requester;
-use teloxide::types::{CallbackQuery, ChosenInlineResult, InlineKeyboardButton, InlineKeyboardMarkup, InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputMessageContentText, Message, ParseMode, ReplyMarkup};
+use teloxide::types::{CallbackQuery, ChosenInlineResult, InlineKeyboardButton, InlineKeyboardMarkup, InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputMessageContentText, Message, MessageEntityKind, ParseMode, ReplyMarkup};
 use teloxide::types::User as TeloxideUser;
-use crate::handlers::{reply_html, send_error_callback_answer, utils, CallbackResult, HandlerDeps, HandlerResult};
+use teloxide::utils::command::ParseError;
+use teloxide::utils::html;
+use crate::handlers::{achievements, reply_html, send_error_callback_answer, utils, CallbackResult, HandlerDeps, HandlerResult};
 use crate::{metrics, reply_html, reply_html_ephemeral, repo};
-use crate::config::{BattlesFeatureToggles, MessageGroup};
+use crate::config::{BattlesFeatureToggles, Event, MessageGroup, PvpOutcomeMode};
+use crate::domain::enums::{LengthChangeReason, ShopItem};
 use crate::domain::objects::{BattleStats, GrowthResult, User, WinRateAware};
-use crate::domain::primitives::{Bet, CharCount, LanguageCode, LengthChange, LoanPayout, UserId, Username};
+use crate::domain::primitives::{Bet, CharCount, LanguageCode, Length, LengthChange, LoanPayout, Percentage, UserId, Username};
 use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality, InlineMessageId, TelegramChatId};
 use crate::handlers::utils::callbacks;
-use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackDataBuilder, NewLayoutValue};
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, EditMessageReqParamsKind, InvalidCallbackDataBuilder, NewLayoutValue};
 use crate::handlers::utils::locks::LockCallbackServiceFacade;
+use crate::handlers::utils::rps::{Hand, PickResult, RpsGame, RpsGames};
+use crate::handlers::utils::SelfDestructionService;
 use crate::repo::Repositories;
+use crate::settings::GameSettingsPolicy;
 
 // let's calculate time offsets from 22.06.2024
 const TIMESTAMP_MILLIS_SINCE_2024: i64 = 1719014400000;
 
-#[derive(BotCommands, Clone, Copy)]
+/// Where the lengths decide the odds, a battle is still a battle rather than a tax on the shorter
+/// dick (or on the longer one): nobody's chance drops below this many percents.
+const MIN_CHANCE: i32 = 10;
+
+#[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
 pub enum BattleCommands {
-    #[command(description = "pvp")]
-    Pvp(u16),
-    Battle(u16),
-    Attack(u16),
-    Fight(u16),
+    #[command(description = "pvp", parse_with = parse_bet_and_mention)]
+    Pvp(u16, Option<String>),
+    #[command(parse_with = parse_bet_and_mention)]
+    Battle(u16, Option<String>),
+    #[command(parse_with = parse_bet_and_mention)]
+    Attack(u16, Option<String>),
+    #[command(parse_with = parse_bet_and_mention)]
+    Fight(u16, Option<String>),
 }
 
 #[derive(BotCommands, Clone)]
@@ -47,31 +64,87 @@ pub enum BattleCommandsNoArgs {
 impl BattleCommands {
     fn bet(&self) -> u16 {
         match *self {
-            Self::Battle(bet) => bet,
-            Self::Pvp(bet) => bet,
-            Self::Attack(bet) => bet,
-            Self::Fight(bet) => bet,
+            Self::Battle(bet, _) => bet,
+            Self::Pvp(bet, _) => bet,
+            Self::Attack(bet, _) => bet,
+            Self::Fight(bet, _) => bet,
+        }
+    }
+
+    fn mention(&self) -> Option<&str> {
+        match self {
+            Self::Battle(_, mention) => mention.as_deref(),
+            Self::Pvp(_, mention) => mention.as_deref(),
+            Self::Attack(_, mention) => mention.as_deref(),
+            Self::Fight(_, mention) => mention.as_deref(),
         }
     }
 }
 
-#[derive(derive_more::Display)]
-#[display("{initiator}:{bet}:{timestamp}")]
+/// `/pvp <bet> [opponent]`. Everything after the bet is the mention of the opponent: a user without
+/// a username is mentioned by the name, which may take several words.
+fn parse_bet_and_mention(input: String) -> Result<(u16, Option<String>), ParseError> {
+    let input = input.trim();
+    let (bet, mention) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    let bet = bet.parse().map_err(|e| ParseError::IncorrectFormat(Box::new(e)))?;
+    let mention = Some(mention.trim())
+        .filter(|mention| !mention.is_empty())
+        .map(ToOwned::to_owned);
+    Ok((bet, mention))
+}
+
+/// An open offer may be accepted by anyone, while a challenge names the only opponent who may.
+/// The opponent goes last, so an old button, which has no such part, is an open offer.
 pub(crate) struct BattleCallbackData {
     initiator: UserId,
     bet: Bet,
 
-    // used to prevent repeated clicks on the same button
-    timestamp: NewLayoutValue<i64>
+    // used to prevent repeated clicks on the same button, and to let a challenge expire
+    timestamp: NewLayoutValue<i64>,
+    opponent: NewLayoutValue<UserId>,
 }
 
 impl BattleCallbackData {
     fn new(initiator: UserId, bet: Bet) -> Self {
         Self {
             initiator, bet,
-            timestamp: new_short_timestamp()
+            timestamp: new_short_timestamp(),
+            opponent: NewLayoutValue::None,
+        }
+    }
+
+    fn challenge(initiator: UserId, opponent: UserId, bet: Bet) -> Self {
+        Self {
+            opponent: NewLayoutValue::Some(opponent),
+            ..Self::new(initiator, bet)
         }
     }
+
+    fn opponent(&self) -> Option<UserId> {
+        match self.opponent {
+            NewLayoutValue::Some(opponent) => Some(opponent),
+            NewLayoutValue::None => None,
+        }
+    }
+
+    /// Open offers don't expire: they are simply deleted along with the other messages of the bot.
+    fn is_expired(&self, expiry: Duration) -> bool {
+        match (&self.opponent, &self.timestamp) {
+            (NewLayoutValue::Some(_), NewLayoutValue::Some(timestamp)) => elapsed_since(*timestamp) > expiry,
+            _ => false,
+        }
+    }
+}
+
+impl Display for BattleCallbackData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.initiator, self.bet, self.timestamp)?;
+        // open offers keep the layout they had before the challenges
+        if let NewLayoutValue::Some(opponent) = &self.opponent {
+            write!(f, ":{opponent}")?;
+        }
+        Ok(())
+    }
 }
 
 impl CallbackDataWithPrefix for BattleCallbackData {
@@ -89,7 +162,169 @@ impl TryFrom<String> for BattleCallbackData {
         let initiator = callbacks::parse_part(&mut parts, &err, "uid").map(UserId::new)?;
         let bet = callbacks::parse_part(&mut parts, &err, "bet").map(Bet::new)?;
         let timestamp = callbacks::parse_optional_part(&mut parts, &err)?;
-        Ok(Self { initiator, bet, timestamp })
+        let opponent = callbacks::parse_optional_part(&mut parts, &err)?;
+        Ok(Self { initiator, bet, timestamp, opponent })
+    }
+}
+
+/// The button to decline a challenge. It carries the whole challenge to take the same lock as the
+/// button to accept it: a challenge is either fought or declined, never both.
+#[derive(derive_more::Display)]
+pub(crate) struct DeclineCallbackData(BattleCallbackData);
+
+impl CallbackDataWithPrefix for DeclineCallbackData {
+    fn prefix() -> &'static str {
+        "decline"
+    }
+}
+
+impl TryFrom<String> for DeclineCallbackData {
+    type Error = callbacks::InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        BattleCallbackData::try_from(data).map(Self)
+    }
+}
+
+/// The button the winner gets under the result of a battle. The time of the battle is a part of it,
+/// so that the button stops working once the window is over, even if nobody has taken it away.
+#[derive(derive_more::Display)]
+#[display("{winner}:{loser}:{bet}:{timestamp}")]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) struct MercyCallbackData {
+    winner: UserId,
+    loser: UserId,
+    bet: Bet,
+    timestamp: i64,
+}
+
+impl MercyCallbackData {
+    fn new(winner: UserId, loser: UserId, bet: Bet) -> Self {
+        Self {
+            winner, loser, bet,
+            timestamp: short_timestamp_now(),
+        }
+    }
+
+    fn is_within(&self, window: Duration) -> bool {
+        elapsed_since(self.timestamp) <= window
+    }
+}
+
+impl CallbackDataWithPrefix for MercyCallbackData {
+    fn prefix() -> &'static str {
+        "mercy"
+    }
+}
+
+impl TryFrom<String> for MercyCallbackData {
+    type Error = callbacks::InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let winner = callbacks::parse_part(&mut parts, &err, "winner").map(UserId::new)?;
+        let loser = callbacks::parse_part(&mut parts, &err, "loser").map(UserId::new)?;
+        let bet = callbacks::parse_part(&mut parts, &err, "bet").map(Bet::new)?;
+        let timestamp = callbacks::parse_part(&mut parts, &err, "timestamp")?;
+        Ok(Self { winner, loser, bet, timestamp })
+    }
+}
+
+/// The buttons both players get under the result of a battle. Like the mercy, they stop working once
+/// the window is over. They also carry the stake of the mercy button next to them, zero if there's
+/// none, because an offer takes their place and the winner must not lose the mercy button with them.
+#[derive(derive_more::Display)]
+#[display("{first}:{second}:{bet}:{timestamp}:{mercy}")]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) struct RematchCallbackData {
+    first: UserId,
+    second: UserId,
+    bet: Bet,
+    timestamp: i64,
+    mercy: Bet,
+}
+
+impl RematchCallbackData {
+    fn new(first: UserId, second: UserId, bet: Bet, mercy: Bet) -> Self {
+        Self {
+            first, second, bet, mercy,
+            timestamp: short_timestamp_now(),
+        }
+    }
+
+    /// The mercy button under the same result, as old as the battle, so that its window isn't
+    /// prolonged by the offer.
+    fn mercy(&self) -> Option<MercyCallbackData> {
+        (self.mercy.value() > 0).then(|| MercyCallbackData {
+            winner: self.first,
+            loser: self.second,
+            bet: self.mercy,
+            timestamp: self.timestamp,
+        })
+    }
+
+    /// `None` for anybody but the players.
+    fn opponent_of(&self, player: UserId) -> Option<UserId> {
+        if player == self.first {
+            Some(self.second)
+        } else if player == self.second {
+            Some(self.first)
+        } else {
+            None
+        }
+    }
+}
+
+impl CallbackDataWithPrefix for RematchCallbackData {
+    fn prefix() -> &'static str {
+        "rematch"
+    }
+}
+
+impl TryFrom<String> for RematchCallbackData {
+    type Error = callbacks::InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let first = callbacks::parse_part(&mut parts, &err, "first").map(UserId::new)?;
+        let second = callbacks::parse_part(&mut parts, &err, "second").map(UserId::new)?;
+        let bet = callbacks::parse_part(&mut parts, &err, "bet").map(Bet::new)?;
+        let timestamp = callbacks::parse_part(&mut parts, &err, "timestamp")?;
+        let mercy = callbacks::parse_part(&mut parts, &err, "mercy").map(Bet::new)?;
+        Ok(Self { first, second, bet, timestamp, mercy })
+    }
+}
+
+/// A move of one of the players of a battle of rock-paper-scissors. Both players get the same
+/// buttons, so the player is the one who clicks.
+#[derive(derive_more::Display)]
+#[display("{game}:{hand}")]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) struct RpsCallbackData {
+    game: RpsGame,
+    hand: Hand,
+}
+
+impl CallbackDataWithPrefix for RpsCallbackData {
+    fn prefix() -> &'static str {
+        "rps"
+    }
+}
+
+impl TryFrom<String> for RpsCallbackData {
+    type Error = callbacks::InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let initiator = callbacks::parse_part(&mut parts, &err, "initiator").map(UserId::new)?;
+        let acceptor = callbacks::parse_part(&mut parts, &err, "acceptor").map(UserId::new)?;
+        let bet = callbacks::parse_part(&mut parts, &err, "bet").map(Bet::new)?;
+        let timestamp = callbacks::parse_part(&mut parts, &err, "timestamp")?;
+        let hand = callbacks::parse_part(&mut parts, &err, "hand")?;
+        Ok(Self { game: RpsGame { initiator, acceptor, bet, timestamp }, hand })
     }
 }
 
@@ -99,27 +334,96 @@ pub async fn pvp_cmd_handler(
     bot: Bot,
     msg: Message,
     cmd: BattleCommands,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
     let lang_code = lang_resolver.execute().await;
     metrics::CMD_PVP_COUNTER.chat.inc();
 
-    let user = msg.from.as_ref().ok_or(anyhow!("no FROM field in the PVP command handler"))?.into();
+    let user: UserInfo = msg.from.as_ref().ok_or(anyhow!("no FROM field in the PVP command handler"))?.into();
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let opponent = match find_opponent(&repos.users, &msg, &chat_id.kind(), user.uid, cmd.mention()).await? {
+        Ok(opponent) => opponent,
+        Err(e) => {
+            let text = e.text(&lang_code);
+            reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Application, lang_code);
+            return Ok(())
+        }
+    };
+    let config = settings.config_for(&chat_id.kind(), &config).await;
     let params = BattleParams {
         repos,
         features: config.features.pvp,
-        chat_id: msg.chat.id.into(),
+        mercy_window: config.pvp_mercy_window,
+        challenge_expiry: config.pvp_challenge_expiry,
+        rematch_window: config.pvp_rematch_window,
+        event: config.events.active(),
+        chat_id,
         lang_code: lang_code.clone(),
     };
     let bet = Bet::new(cmd.bet().into());
-    let (text, keyboard) = pvp_impl_start(params, user, bet).await?;
+    let (text, keyboard) = pvp_impl_start(params, user, opponent, bet).await?;
 
     reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Application, lang_code,
         reply_markup = keyboard.map(ReplyMarkup::InlineKeyboard));
     Ok(())
 }
 
+enum OpponentError {
+    Unknown(String),
+    SamePerson,
+}
+
+impl OpponentError {
+    fn text(&self, lang_code: &LanguageCode) -> String {
+        match self {
+            Self::Unknown(mention) => t!("commands.pvp.challenge.errors.unknown", locale = lang_code, mention = html::escape(mention)),
+            Self::SamePerson => t!("commands.pvp.errors.same_person", locale = lang_code),
+        }.to_string()
+    }
+}
+
+/// The opponent of a challenge: the one mentioned after the bet or, without a mention, the author
+/// of the message the command replies to. A reply to a bot or to oneself is taken for an open offer,
+/// since the reply may well be unintended, unlike a mention.
+async fn find_opponent(
+    users: &repo::Users,
+    msg: &Message,
+    chat_id: &ChatIdKind,
+    initiator: UserId,
+    mention: Option<&str>,
+) -> anyhow::Result<Result<Option<UserInfo>, OpponentError>> {
+    let Some(mention) = mention else {
+        // in a forum, a message without a reply is still a reply to the first one of the topic
+        let opponent = msg.reply_to_message()
+            .filter(|reply| reply.forum_topic_created().is_none())
+            .and_then(|reply| reply.from.as_ref())
+            .filter(|user| !user.is_bot && initiator != user.id)
+            .map(UserInfo::from);
+        return Ok(Ok(opponent))
+    };
+
+    // a user without a username is mentioned by a link carrying the user itself
+    let text_mentioned = msg.entities().unwrap_or_default().iter()
+        .find_map(|entity| match &entity.kind {
+            MessageEntityKind::TextMention { user } => Some(user),
+            _ => None,
+        });
+    let opponent: UserInfo = match text_mentioned {
+        Some(user) => user.into(),
+        // bots don't grow, so they are never found among the members either
+        None => match users.get_chat_member_by_username(chat_id, mention.trim_start_matches('@')).await? {
+            Some(user) => user.into(),
+            None => return Ok(Err(OpponentError::Unknown(mention.to_owned()))),
+        }
+    };
+    if opponent.uid == initiator {
+        return Ok(Err(OpponentError::SamePerson))
+    }
+    Ok(Ok(Some(opponent)))
+}
+
 #[autometrics]
 #[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
 pub async fn pvp_cmd_handler_no_args(bot: Bot, msg: Message, deps: HandlerDeps) -> HandlerResult {
@@ -212,14 +516,72 @@ pub async fn pvp_callback_handler(
     bot: Bot,
     query: CallbackQuery,
     mut battle_locker: LockCallbackServiceFacade,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
     let lang_code = lang_resolver.execute().await;
-    let chat_id: ChatIdPartiality = query.message.as_ref()
+    let chat_id = battle_chat_id(&query, config.features.chats_merging);
+    let config = settings.config_for(&chat_id.kind(), &config).await;
+
+    let callback_data = BattleCallbackData::parse(&query)?;
+    if callback_data.initiator == query.from.id {
+        return send_error_callback_answer(bot, query, "commands.pvp.errors.same_person").await;
+    }
+    if callback_data.opponent().is_some_and(|opponent| opponent != query.from.id) {
+        return send_error_callback_answer(bot, query, "commands.pvp.challenge.errors.not_you").await;
+    }
+    if callback_data.is_expired(config.pvp_challenge_expiry) {
+        let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+        return answer_too_late(bot, &query, edit_msg_params, "commands.pvp.challenge.expired", &lang_code).await;
+    }
+    let _battle_guard = match battle_locker.try_lock(&callback_data) {
+        Some(lock) => lock,
+        None => return send_error_callback_answer(bot, query, "commands.pvp.errors.battle_already_in_progress").await
+    };
+
+    let params = BattleParams {
+        repos,
+        features: config.features.pvp,
+        mercy_window: config.pvp_mercy_window,
+        challenge_expiry: config.pvp_challenge_expiry,
+        rematch_window: config.pvp_rematch_window,
+        event: config.events.active(),
+        lang_code,
+        chat_id: chat_id.clone(),
+    };
+    let acceptor: UserInfo = query.from.clone().into();
+    let attack_result = if config.features.pvp.outcome_mode == PvpOutcomeMode::RPS {
+        // the moves are yet to be made, and a battle abandoned halfway may go away with the offer
+        rps_impl_start(params, callback_data.initiator, acceptor, callback_data.bet).await?
+    } else {
+        let result = pvp_impl_attack(params, callback_data.initiator, acceptor, callback_data.bet, None).await?;
+        keep_as_record(&self_destruction, &query).await;
+        result
+    };
+    attack_result.apply(bot, query).await?;
+
+    metrics::CMD_PVP_COUNTER.inline.inc();
+    Ok(())
+}
+
+/// A fought battle is a record of itself, not an offer waiting to expire.
+async fn keep_as_record(self_destruction: &SelfDestructionService, query: &CallbackQuery) {
+    match (query.message.as_ref(), query.inline_message_id.as_ref()) {
+        (Some(message), _) => self_destruction.cancel_message(message.chat().id, message.id()).await,
+        (None, Some(inline_message_id)) =>
+            self_destruction.cancel_inline(InlineMessageId::new(inline_message_id.clone())).await,
+        (None, None) => {},
+    };
+}
+
+/// The chat a battle button was clicked in. An inline message knows only its `chat_instance`,
+/// unless the chat can be learned from the id of the message itself.
+fn battle_chat_id(query: &CallbackQuery, chats_merging: bool) -> ChatIdPartiality {
+    query.message.as_ref()
         .map(|msg| msg.chat().id)
         .map(TelegramChatId::from)
-        .or_else(|| config.features.chats_merging
+        .or_else(|| chats_merging
             .then_some(query.inline_message_id.as_ref())
             .flatten()
             .and_then(|msg_id| utils::resolve_inline_message_id(msg_id)
@@ -229,48 +591,401 @@ pub async fn pvp_callback_handler(
             .and_then(|info| info.chat_id)
         )
         .map(ChatIdPartiality::from)
-        .unwrap_or(ChatIdPartiality::from(query.chat_instance.clone()));
+        .unwrap_or(ChatIdPartiality::from(query.chat_instance.clone()))
+}
 
-    let callback_data = BattleCallbackData::parse(&query)?;
-    if callback_data.initiator == query.from.id {
-        return send_error_callback_answer(bot, query, "commands.pvp.errors.same_person").await;
+pub fn decline_callback_filter(query: CallbackQuery) -> bool {
+    DeclineCallbackData::check_prefix(query)
+}
+
+/// The opponent declines a challenge, or the initiator takes it back.
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn decline_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    mut battle_locker: LockCallbackServiceFacade,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let DeclineCallbackData(challenge) = DeclineCallbackData::parse(&query)?;
+    let key = if challenge.opponent().is_some_and(|opponent| opponent == query.from.id) {
+        "commands.pvp.challenge.declined"
+    } else if challenge.initiator == query.from.id {
+        "commands.pvp.challenge.withdrawn"
+    } else {
+        return send_error_callback_answer(bot, query, "commands.pvp.challenge.errors.not_you").await
+    };
+    let _battle_guard = match battle_locker.try_lock(&challenge) {
+        Some(lock) => lock,
+        None => return send_error_callback_answer(bot, query, "commands.pvp.errors.battle_already_in_progress").await
+    };
+
+    let name = utils::get_full_name(&query.from);
+    let text = t!(key, locale = &lang_code, name = name.escaped()).to_string();
+    CallbackResult::EditMessage(text, None).apply(bot, query).await?;
+    Ok(())
+}
+
+pub fn mercy_callback_filter(query: CallbackQuery) -> bool {
+    MercyCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn mercy_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    mut battle_locker: LockCallbackServiceFacade,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let callback_data = MercyCallbackData::parse(&query)?;
+    if callback_data.winner != query.from.id {
+        return send_error_callback_answer(bot, query, "commands.pvp.mercy.errors.not_winner").await;
     }
-    let _battle_guard = match battle_locker.try_lock(&callback_data) {
+    if !callback_data.is_within(config.pvp_mercy_window) {
+        let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+        return answer_too_late(bot, &query, edit_msg_params, "commands.pvp.mercy.errors.too_late", &lang_code).await;
+    }
+    // The guard is held until the message is edited, and the edit takes the button away, so that
+    // a double click cannot give the same award back twice.
+    let _mercy_guard = match battle_locker.try_lock(&callback_data) {
+        Some(lock) => lock,
+        None => return send_error_callback_answer(bot, query, "commands.pvp.mercy.errors.in_progress").await
+    };
+
+    let chat_id = battle_chat_id(&query, config.features.chats_merging);
+    let result = mercy_impl(&repos, &chat_id, &callback_data, query.from.clone().into(), &lang_code).await?;
+    result.apply(bot, query).await?;
+    Ok(())
+}
+
+pub fn rematch_callback_filter(query: CallbackQuery) -> bool {
+    RematchCallbackData::check_prefix(query)
+}
+
+/// Either of the players offers the other one the same battle once again, or the one for the double
+/// stake. The offer is a challenge the other player may accept or decline, and it takes the place of
+/// the rematch buttons under the result, while the result itself and the mercy button stay.
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn rematch_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    mut battle_locker: LockCallbackServiceFacade,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let chat_id = battle_chat_id(&query, config.features.chats_merging);
+    let config = settings.config_for(&chat_id.kind(), &config).await;
+    let data = RematchCallbackData::parse(&query)?;
+    let initiator = UserId::from(query.from.id);
+    let Some(opponent) = data.opponent_of(initiator) else {
+        return send_error_callback_answer(bot, query, "commands.pvp.rematch.errors.not_player").await
+    };
+    if elapsed_since(data.timestamp) > config.pvp_rematch_window {
+        // unlike the mercy, the buttons stay: the mercy may still be shown by the one next to them
+        return send_error_callback_answer(bot, query, "commands.pvp.rematch.errors.too_late").await
+    }
+    let _rematch_guard = match battle_locker.try_lock(&data) {
         Some(lock) => lock,
         None => return send_error_callback_answer(bot, query, "commands.pvp.errors.battle_already_in_progress").await
     };
 
+    // the accepted challenge is fought for the stake as multiplied, so it's that stake to check
     let params = BattleParams {
         repos,
         features: config.features.pvp,
+        mercy_window: config.pvp_mercy_window,
+        challenge_expiry: config.pvp_challenge_expiry,
+        rematch_window: config.pvp_rematch_window,
+        event: config.events.active(),
         lang_code,
-        chat_id: chat_id.clone(),
+        chat_id,
     };
-    let attack_result = pvp_impl_attack(params, callback_data.initiator, query.from.clone().into(), callback_data.bet).await?;
-    // A fought battle is a record of itself, not an offer waiting to expire.
-    match (query.message.as_ref(), query.inline_message_id.as_ref()) {
-        (Some(message), _) => self_destruction.cancel_message(message.chat().id, message.id()).await,
-        (None, Some(inline_message_id)) =>
-            self_destruction.cancel_inline(InlineMessageId::new(inline_message_id.clone())).await,
-        (None, None) => {},
+    let chat_id_kind = params.chat_id.kind();
+    let bet = params.effective_bet(data.bet);
+    let (enough_initiator, enough_opponent) = join!(
+        params.repos.dicks.check_dick(&chat_id_kind, initiator, bet),
+        params.repos.dicks.check_dick(&chat_id_kind, opponent, bet),
+    );
+    if !enough_initiator? {
+        return send_error_callback_answer(bot, query, "commands.pvp.rematch.errors.not_enough.initiator").await
+    }
+    if !enough_opponent? {
+        return send_error_callback_answer(bot, query, "commands.pvp.rematch.errors.not_enough.opponent").await
+    }
+    tracing::debug!(initiator = %initiator, opponent = %opponent, bet = %data.bet, "offering a rematch");
+
+    let lang_code = &params.lang_code;
+    let challenge = BattleCallbackData::challenge(initiator, opponent, data.bet);
+    let accept_label = t!("commands.pvp.rematch.accept", locale = lang_code,
+        name = utils::get_full_name(&query.from).value(), bet = data.bet);
+    let accept_data = challenge.to_data_string();
+    let decline_label = t!("commands.pvp.challenge.decline", locale = lang_code);
+    let challenge_row = vec![
+        InlineKeyboardButton::callback(accept_label, accept_data),
+        InlineKeyboardButton::callback(decline_label, DeclineCallbackData(challenge).to_data_string()),
+    ];
+    let kept_mercy = data.mercy()
+        .filter(|mercy| mercy.is_within(params.mercy_window))
+        .map(|mercy| mercy_row(&mercy, lang_code));
+    let keyboard = result_keyboard([kept_mercy, Some(challenge_row)]);
+
+    let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+    let mut answer = bot.answer_callback_query(query.id.clone());
+    answer.text.replace(t!("commands.pvp.rematch.offered", locale = lang_code).to_string());
+    answer.await?;
+    edit_keyboard(&bot, edit_msg_params, keyboard).await
+}
+
+pub fn rps_callback_filter(query: CallbackQuery) -> bool {
+    RpsCallbackData::check_prefix(query)
+}
+
+/// A move of rock-paper-scissors. The battle is fought once both moves are made, and the moves are
+/// shown along with its result; until then, the players see only who has made a move.
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn rps_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    rps_games: RpsGames,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let RpsCallbackData { game, hand } = RpsCallbackData::parse(&query)?;
+    let player = UserId::from(query.from.id);
+    if !game.is_player(player) {
+        return send_error_callback_answer(bot, query, "commands.pvp.rps.errors.not_player").await
+    }
+    let chat_id = battle_chat_id(&query, config.features.chats_merging);
+    let config = settings.config_for(&chat_id.kind(), &config).await;
+    if elapsed_since(game.timestamp) > config.pvp_challenge_expiry {
+        // nothing has been paid yet, so the battle is simply called off
+        rps_games.forget(&game);
+        let text = t!("commands.pvp.rps.expired", locale = &lang_code).to_string();
+        CallbackResult::EditMessage(text, None).apply(bot, query).await?;
+        return Ok(())
+    }
+
+    let clicker: UserInfo = query.from.clone().into();
+    let other = if player == game.initiator { game.acceptor } else { game.initiator };
+    let other = get_user_info(&repos.users, other, &clicker).await?;
+    let (initiator, acceptor) = if player == game.initiator { (clicker, other) } else { (other, clicker) };
+    let params = BattleParams {
+        repos,
+        features: config.features.pvp,
+        mercy_window: config.pvp_mercy_window,
+        challenge_expiry: config.pvp_challenge_expiry,
+        rematch_window: config.pvp_rematch_window,
+        event: config.events.active(),
+        lang_code,
+        chat_id,
     };
-    attack_result.apply(bot, query).await?;
 
-    metrics::CMD_PVP_COUNTER.inline.inc();
+    let (initiator_hand, acceptor_hand) = match rps_games.pick(game, player, hand, config.pvp_challenge_expiry) {
+        PickResult::AlreadyPicked => return send_error_callback_answer(bot, query, "commands.pvp.rps.errors.already_picked").await,
+        PickResult::Waiting => {
+            let text = rps_prompt(&params, &initiator, &acceptor, game.bet, rps_games.picked(&game), None);
+            let mut answer = bot.answer_callback_query(query.id.clone());
+            answer.text.replace(t!("commands.pvp.rps.picked", locale = &params.lang_code).to_string());
+            answer.await?;
+            let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+            callbacks::edit_message_text_with_keyboard(&bot, edit_msg_params, text, Some(rps_keyboard(game, &params.lang_code))).await?;
+            return Ok(())
+        }
+        PickResult::Draw(hand) => {
+            let text = rps_prompt(&params, &initiator, &acceptor, game.bet, (false, false), Some(hand));
+            let keyboard = rps_keyboard(game, &params.lang_code);
+            CallbackResult::EditMessage(text, Some(keyboard)).apply(bot, query).await?;
+            return Ok(())
+        }
+        PickResult::Revealed { initiator, acceptor } => (initiator, acceptor),
+    };
+    tracing::debug!(initiator = %game.initiator, acceptor = %game.acceptor, %initiator_hand, %acceptor_hand, "the moves are revealed");
+
+    let lang_code = params.lang_code.clone();
+    let reveal = t!("commands.pvp.rps.reveal", locale = &lang_code,
+        initiator = initiator.name.escaped(), initiator_hand = hand_name(initiator_hand, &lang_code),
+        acceptor = acceptor.name.escaped(), acceptor_hand = hand_name(acceptor_hand, &lang_code));
+    let initiator_won = initiator_hand.beats(acceptor_hand);
+    let acceptor_name = acceptor.name.clone();
+    let result = match pvp_impl_attack(params, game.initiator, acceptor, game.bet, Some(initiator_won)).await? {
+        CallbackResult::EditMessage(text, keyboard) => CallbackResult::EditMessage(format!("{reveal}\n\n{text}"), keyboard),
+        // the moves are spent, so the battle is off for everyone, not only for the one who clicked last
+        CallbackResult::ShowError(_) => {
+            let text = t!("commands.pvp.rps.errors.not_enough", locale = &lang_code, name = acceptor_name.escaped());
+            CallbackResult::EditMessage(format!("{reveal}\n\n{text}"), None)
+        }
+    };
+    keep_as_record(&self_destruction, &query).await;
+    result.apply(bot, query).await?;
+    Ok(())
+}
+
+/// The stakes are checked as for any other battle, but nothing moves until both moves are made.
+async fn rps_impl_start(
+    p: BattleParams,
+    initiator: UserId,
+    acceptor: UserInfo,
+    bet: Bet,
+) -> anyhow::Result<CallbackResult> {
+    if let Some(refusal) = refuse_stakes(&p, initiator, acceptor.uid, p.effective_bet(bet)).await? {
+        return Ok(refusal)
+    }
+    let initiator_info = get_user_info(&p.repos.users, initiator, &acceptor).await?;
+    let game = RpsGame { initiator, acceptor: acceptor.uid, bet, timestamp: short_timestamp_now() };
+    let text = rps_prompt(&p, &initiator_info, &acceptor, bet, (false, false), None);
+    Ok(CallbackResult::EditMessage(text, Some(rps_keyboard(game, &p.lang_code))))
+}
+
+/// The invitation to make a move, followed by who has made theirs, or by the hand both have shown.
+fn rps_prompt(
+    p: &BattleParams,
+    initiator: &UserInfo,
+    acceptor: &UserInfo,
+    bet: Bet,
+    (initiator_picked, acceptor_picked): (bool, bool),
+    draw: Option<Hand>,
+) -> String {
+    let lang_code = &p.lang_code;
+    let mut text = t!("commands.pvp.rps.prompt", locale = lang_code,
+        initiator = initiator.name.escaped(), acceptor = acceptor.name.escaped(), bet = p.effective_bet(bet)).to_string();
+    if let Some(hand) = draw {
+        text.push_str("\n\n");
+        text.push_str(&t!("commands.pvp.rps.draw", locale = lang_code, hand = hand_name(hand, lang_code)));
+    }
+    let picked = [(initiator, initiator_picked), (acceptor, acceptor_picked)].into_iter()
+        .filter(|(_, picked)| *picked)
+        .map(|(player, _)| t!("commands.pvp.rps.made", locale = lang_code, name = player.name.escaped()).to_string())
+        .collect::<Vec<_>>();
+    if !picked.is_empty() {
+        text.push_str("\n\n");
+        text.push_str(&picked.join("\n"));
+    }
+    format!("{text}{}", p.event_part())
+}
+
+fn rps_keyboard(game: RpsGame, lang_code: &LanguageCode) -> InlineKeyboardMarkup {
+    let buttons: Vec<_> = Hand::iter()
+        .map(|hand| {
+            let data = RpsCallbackData { game, hand }.to_data_string();
+            InlineKeyboardButton::callback(hand_name(hand, lang_code), data)
+        })
+        .collect();
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
+fn hand_name(hand: Hand, lang_code: &LanguageCode) -> String {
+    let key = format!("commands.pvp.rps.hands.{hand}");
+    t!(&key, locale = lang_code).to_string()
+}
+
+/// The award is returned in full, even if a part of it went to pay off the winner's loan: the loan
+/// stays paid, and the mercy costs exactly what the loser has lost.
+async fn mercy_impl(
+    repos: &Repositories,
+    chat_id: &ChatIdPartiality,
+    data: &MercyCallbackData,
+    winner: UserInfo,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<CallbackResult> {
+    let chat_id_kind = chat_id.kind();
+    if !repos.dicks.check_dick(&chat_id_kind, data.winner, data.bet).await? {
+        let text = t!("commands.pvp.mercy.errors.not_enough", locale = lang_code).to_string();
+        return Ok(CallbackResult::ShowError(text))
+    }
+    let (winner_res, loser_res) = repos.dicks.move_length(chat_id, data.winner, data.loser, data.bet, LengthChangeReason::Pvp).await?;
+    metrics::PVP_MERCY_SHOWN.inc();
+    repos.pvp_stats.send_mercy(&chat_id_kind, data.winner, data.loser, data.bet).await
+        .inspect_err(|e| tracing::error!(winner = %data.winner, loser = %data.loser, error = %e, "couldn't send the mercy statistics"))
+        .ok();
+
+    let loser_info = get_user_info(&repos.users, data.loser, &winner).await?;
+    let text = t!("commands.pvp.results.mercy", locale = lang_code,
+        winner_name = winner.name.escaped(), loser_name = loser_info.name.escaped(), bet = data.bet,
+        winner_length = winner_res.new_length, loser_length = loser_res.new_length).to_string();
+    Ok(CallbackResult::EditMessage(text, None))
+}
+
+/// The button outlives its window, so the one who clicks it too late takes it away. The text stays:
+/// it's the record of the battle.
+async fn answer_too_late(
+    bot: Bot,
+    query: &CallbackQuery,
+    edit_msg_params: EditMessageReqParamsKind,
+    text_key: &str,
+    lang_code: &LanguageCode,
+) -> HandlerResult {
+    let mut answer = bot.answer_callback_query(query.id.clone());
+    answer.show_alert.replace(true);
+    answer.text.replace(t!(text_key, locale = lang_code).to_string());
+    answer.await?;
+    edit_keyboard(&bot, edit_msg_params, None).await
+}
+
+async fn edit_keyboard(
+    bot: &Bot,
+    edit_msg_params: EditMessageReqParamsKind,
+    keyboard: Option<InlineKeyboardMarkup>,
+) -> HandlerResult {
+    match edit_msg_params {
+        EditMessageReqParamsKind::Chat(chat_id, message_id) => {
+            let mut request = bot.edit_message_reply_markup(chat_id, message_id);
+            request.reply_markup = keyboard;
+            request.await.map(|_| ())?
+        },
+        EditMessageReqParamsKind::Inline { inline_message_id, .. } => {
+            let mut request = bot.edit_message_reply_markup_inline(inline_message_id);
+            request.reply_markup = keyboard;
+            request.await.map(|_| ())?
+        },
+    };
     Ok(())
 }
 
 pub(crate) struct BattleParams {
     repos: Repositories,
     features: BattlesFeatureToggles,
+    mercy_window: Duration,
+    challenge_expiry: Duration,
+    rematch_window: Duration,
+    event: Option<Event>,
     chat_id: ChatIdPartiality,
     lang_code: LanguageCode,
 }
 
+impl BattleParams {
+    /// The stake actually fought for: an event may multiply what the initiator has offered.
+    fn effective_bet(&self, bet: Bet) -> Bet {
+        self.event.as_ref()
+            .map(|event| event.modifiers.pvp_bet(bet))
+            .unwrap_or(bet)
+    }
+
+    /// Tells the players why the stake isn't the one offered, or nothing when it is.
+    fn event_part(&self) -> String {
+        match &self.event {
+            Some(event) if let Some(multiplier) = event.modifiers.pvp_bet_multiplier.filter(|m| *m > 1) =>
+                format!("\n\n{}", t!("commands.pvp.results.event", locale = &self.lang_code,
+                    name = event.name(&self.lang_code), multiplier = multiplier)),
+            _ => String::default(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct UserInfo {
-    uid: UserId,
-    name: Username,
+    pub(crate) uid: UserId,
+    pub(crate) name: Username,
 }
 
 impl From<&TeloxideUser> for UserInfo {
@@ -307,45 +1022,97 @@ impl Into<UserId> for UserInfo {
 pub(crate) async fn pvp_impl_start(
     p: BattleParams,
     initiator: UserInfo,
+    opponent: Option<UserInfo>,
     bet: Bet,
 ) -> anyhow::Result<(String, Option<InlineKeyboardMarkup>)> {
     let enough = p.repos.dicks.check_dick(&p.chat_id.kind(), initiator.uid, bet).await?;
-    tracing::debug!(uid = %initiator.uid, chat_id = %p.chat_id, bet = %bet, enough, "starting a PvP");
+    tracing::debug!(uid = %initiator.uid, opponent = ?opponent.as_ref().map(|o| o.uid), chat_id = %p.chat_id, bet = %bet, enough, "starting a PvP");
 
     let data = if enough {
-        let text = t!("commands.pvp.results.start", locale = &p.lang_code, name = initiator.name.escaped(), bet = bet).to_string();
+        let odds_part = odds_part(&p, &initiator, opponent.as_ref()).await?;
         let btn_label = t!("commands.pvp.button", locale = &p.lang_code);
-        let btn_data = BattleCallbackData::new(initiator.uid, bet).to_data_string();
-        let keyboard = InlineKeyboardMarkup::new(vec![vec![
-            InlineKeyboardButton::callback(btn_label, btn_data)
-        ]]);
-        (text, Some(keyboard))
+        let (text, buttons) = match opponent {
+            Some(opponent) => {
+                let text = t!("commands.pvp.challenge.start", locale = &p.lang_code,
+                    name = initiator.name.escaped(), opponent = opponent.name.escaped(), uid = opponent.uid, bet = bet,
+                    minutes = p.challenge_expiry.as_secs().div_ceil(60));
+                let challenge = BattleCallbackData::challenge(initiator.uid, opponent.uid, bet);
+                let decline_label = t!("commands.pvp.challenge.decline", locale = &p.lang_code);
+                let buttons = vec![
+                    InlineKeyboardButton::callback(btn_label, challenge.to_data_string()),
+                    InlineKeyboardButton::callback(decline_label, DeclineCallbackData(challenge).to_data_string()),
+                ];
+                (text, buttons)
+            },
+            None => {
+                let text = t!("commands.pvp.results.start", locale = &p.lang_code, name = initiator.name.escaped(), bet = bet);
+                let btn_data = BattleCallbackData::new(initiator.uid, bet).to_data_string();
+                (text, vec![InlineKeyboardButton::callback(btn_label, btn_data)])
+            }
+        };
+        let text = format!("{text}{odds_part}{}", p.event_part());
+        (text, Some(InlineKeyboardMarkup::new(vec![buttons])))
     } else {
         (t!("commands.pvp.errors.not_enough.initiator", locale = &p.lang_code).to_string(), None)
     };
     Ok(data)
 }
 
+/// How the winner is going to be chosen, or nothing for a coin flip, as it has always been. The exact
+/// odds are known for a challenge only: anybody may accept an open offer.
+async fn odds_part(p: &BattleParams, initiator: &UserInfo, opponent: Option<&UserInfo>) -> anyhow::Result<String> {
+    let mode = p.features.outcome_mode;
+    if mode == PvpOutcomeMode::RANDOM {
+        return Ok(String::default())
+    }
+    let chance = match opponent {
+        Some(opponent) => initiator_chance(p, initiator.uid, opponent.uid).await?
+            .map(|chance| (opponent, chance)),
+        None => None,
+    };
+    let line = match chance {
+        Some((opponent, chance)) => t!("commands.pvp.odds.chances", locale = &p.lang_code,
+            name = initiator.name.escaped(), chance = chance,
+            opponent = opponent.name.escaped(), opponent_chance = complement(chance)),
+        None => {
+            let key = format!("commands.pvp.odds.{mode}");
+            t!(&key, locale = &p.lang_code)
+        }
+    };
+    Ok(format!("\n\n{line}"))
+}
+
+/// `initiator_won` is `Some` when the players have settled the battle themselves. Otherwise, the
+/// winner is drawn by the odds of the mode of the chat.
 async fn pvp_impl_attack(
     p: BattleParams,
     initiator: UserId,
     acceptor: UserInfo,
     bet: Bet,
+    initiator_won: Option<bool>,
 ) -> anyhow::Result<CallbackResult> {
     let chat_id_kind = p.chat_id.kind();
-    let (enough_initiator, enough_acceptor) = join!(
-       p.repos.dicks.check_dick(&chat_id_kind, initiator, bet),
-       p.repos.dicks.check_dick(&chat_id_kind, acceptor.uid, if p.features.check_acceptor_length { bet } else { Bet::new(0) }),
-    );
-    let (enough_initiator, enough_acceptor) = (enough_initiator?, enough_acceptor?);
-
-    tracing::debug!(chat_id = %p.chat_id, initiator = %initiator, acceptor = %acceptor.uid,
-        enough_initiator, enough_acceptor, bet = %bet, "executing the battle");
-
-    let result = if enough_initiator && enough_acceptor {
-        let acceptor_uid = acceptor.clone().into();
-        let (winner, loser) = choose_winner(initiator, acceptor_uid);
-        let (loser_res, winner_res) = p.repos.dicks.move_length(&p.chat_id, loser, winner, bet).await?;
+    // Everything from here on, the mercy button included, deals with the stake as multiplied. A
+    // rematch is offered for the stake as it was offered, or an event would multiply it once again.
+    let offered_bet = bet;
+    let bet = p.effective_bet(bet);
+    let result = if let Some(refusal) = refuse_stakes(&p, initiator, acceptor.uid, bet).await? {
+        refusal
+    } else {
+        let initiator_won = match initiator_won {
+            Some(initiator_won) => initiator_won,
+            None => {
+                let chance = initiator_chance(&p, initiator, acceptor.uid).await?
+                    .unwrap_or(literal!(Percentage = 50));
+                rand::rng().random_range(0..100) < chance.value()
+            }
+        };
+        let acceptor_uid = acceptor.uid;
+        let (winner, loser) = if initiator_won { (initiator, acceptor_uid) } else { (acceptor_uid, initiator) };
+        if consume_shield(&p, loser).await {
+            return shielded_battle_result(&p, winner, loser, &acceptor, bet, offered_bet).await
+        }
+        let (loser_res, winner_res) = p.repos.dicks.move_length(&p.chat_id, loser, winner, bet, LengthChangeReason::Pvp).await?;
 
         let battle_stats = p.repos.pvp_stats.send_battle_result(&p.chat_id.kind(), winner, loser, bet).await
             .inspect_err(|e| tracing::error!(winner = %winner, loser = %loser, error = %e, "couldn't send the battle statistics"))
@@ -366,7 +1133,7 @@ async fn pvp_impl_attack(
             .map(|s| format!("\n\n{s}"))
             .unwrap_or_default();
         
-        let (winner_res, withheld_part) = pay_for_loan_if_needed(&p, winner, bet).await
+        let (winner_res, withheld_part) = pay_for_loan_if_needed(&p.repos, &chat_id_kind, winner, bet).await
             .inspect_err(|e| tracing::error!(error = %e, "couldn't pay for a loan from a battle award"))
             .ok().flatten()
             .filter(|(_, withheld)| *withheld > 0)
@@ -387,18 +1154,81 @@ async fn pvp_impl_attack(
         } else {
             main_part.to_string()
         };
-        CallbackResult::EditMessage(format!("{text}{withheld_part}{battle_stats}"), None)
+        // Only the winner's conditions have moved: a lost battle takes length and a streak away.
+        let achievements_part = achievements::unlock_achievements(&p.repos, &chat_id_kind, winner, &p.lang_code).await;
+        let keyboard = result_keyboard([
+            mercy_buttons(&p, winner, loser, bet),
+            rematch_buttons(&p, winner, loser, offered_bet, Some(bet)),
+        ]);
+        let event_part = p.event_part();
+        CallbackResult::EditMessage(format!("{text}{withheld_part}{battle_stats}{event_part}{achievements_part}"), keyboard)
+    };
+    Ok(result)
+}
+
+/// Tells why the battle can't be fought for the stake, if it can't: the lengths may have changed
+/// since the offer was made.
+async fn refuse_stakes(p: &BattleParams, initiator: UserId, acceptor: UserId, bet: Bet) -> anyhow::Result<Option<CallbackResult>> {
+    let chat_id_kind = p.chat_id.kind();
+    let (enough_initiator, enough_acceptor) = join!(
+       p.repos.dicks.check_dick(&chat_id_kind, initiator, bet),
+       p.repos.dicks.check_dick(&chat_id_kind, acceptor, if p.features.check_acceptor_length { bet } else { Bet::new(0) }),
+    );
+    let (enough_initiator, enough_acceptor) = (enough_initiator?, enough_acceptor?);
+
+    tracing::debug!(chat_id = %p.chat_id, initiator = %initiator, acceptor = %acceptor,
+        enough_initiator, enough_acceptor, bet = %bet, "executing the battle");
+
+    let refusal = if enough_initiator && enough_acceptor {
+        None
     } else if enough_acceptor {
         let text = t!("commands.pvp.errors.not_enough.initiator", locale = &p.lang_code).to_string();
-        CallbackResult::EditMessage(text, None)
+        Some(CallbackResult::EditMessage(text, None))
     } else {
         let text = t!("commands.pvp.errors.not_enough.acceptor", locale = &p.lang_code).to_string();
-        CallbackResult::ShowError(text)
+        Some(CallbackResult::ShowError(text))
     };
-    Ok(result)
+    Ok(refusal)
+}
+
+/// The chance of the initiator to win, as the lengths are now. `None` in the mode where the players
+/// settle the battle themselves.
+async fn initiator_chance(p: &BattleParams, initiator: UserId, acceptor: UserId) -> anyhow::Result<Option<Percentage>> {
+    let mode = p.features.outcome_mode;
+    let chance = match mode {
+        PvpOutcomeMode::RANDOM => Some(literal!(Percentage = 50)),
+        PvpOutcomeMode::RPS => None,
+        PvpOutcomeMode::WEIGHTS | PvpOutcomeMode::UNDERDOG => {
+            let chat_id_kind = p.chat_id.kind();
+            let (initiator_length, acceptor_length) = join!(
+                p.repos.dicks.fetch_length(initiator, &chat_id_kind),
+                p.repos.dicks.fetch_length(acceptor, &chat_id_kind),
+            );
+            let underdog = mode == PvpOutcomeMode::UNDERDOG;
+            Some(chance_by_lengths(underdog, initiator_length?, acceptor_length?))
+        }
+    };
+    Ok(chance)
+}
+
+/// The odds in proportion to the lengths: the longer dick is favoured, or the shorter one if
+/// `underdog`. A dick of zero or negative length weighs as one of a centimeter, to have a chance at
+/// all. Whole percents, so that the odds drawn are the odds shown.
+fn chance_by_lengths(underdog: bool, initiator: Length, acceptor: Length) -> Percentage {
+    let weight = |length: Length| -> f64 { length.value().max(0).saturating_add(1).approx_into() };
+    let (initiator, acceptor) = (weight(initiator), weight(acceptor));
+    let favoured = if underdog { acceptor } else { initiator };
+    let chance: i32 = (favoured / (initiator + acceptor) * 100.0).round().saturating_into();
+    Percentage::new(chance.clamp(MIN_CHANCE, 100 - MIN_CHANCE))
+        .expect("a clamped chance must be a correct percentage")
 }
 
-fn choose_winner<T>(initiator: T, acceptor: T) -> (T, T) {
+fn complement(chance: Percentage) -> Percentage {
+    Percentage::new(100 - chance.value())
+        .expect("a complement of a percentage must be a percentage")
+}
+
+pub(crate) fn choose_winner<T>(initiator: T, acceptor: T) -> (T, T) {
     if rand::rng().random_bool(0.5) {
         (acceptor, initiator)
     } else {
@@ -417,13 +1247,78 @@ async fn get_user_info(users: &repo::Users, user_uid: UserId, acceptor: &UserInf
     Ok(user)
 }
 
-async fn pay_for_loan_if_needed(
+/// One row per kind of the buttons, none at all if every kind is turned off.
+fn result_keyboard<const N: usize>(rows: [Option<Vec<InlineKeyboardButton>>; N]) -> Option<InlineKeyboardMarkup> {
+    let rows: Vec<_> = rows.into_iter().flatten().collect();
+    (!rows.is_empty()).then(|| InlineKeyboardMarkup::new(rows))
+}
+
+fn mercy_buttons(p: &BattleParams, winner: UserId, loser: UserId, bet: Bet) -> Option<Vec<InlineKeyboardButton>> {
+    if p.mercy_window.is_zero() {
+        return None
+    }
+    Some(mercy_row(&MercyCallbackData::new(winner, loser, bet), &p.lang_code))
+}
+
+fn mercy_row(data: &MercyCallbackData, lang_code: &LanguageCode) -> Vec<InlineKeyboardButton> {
+    let btn_label = t!("commands.pvp.mercy.button", locale = lang_code);
+    vec![InlineKeyboardButton::callback(btn_label, data.to_data_string())]
+}
+
+/// `mercy` is the stake of the mercy button in the row above, if there's one.
+fn rematch_buttons(p: &BattleParams, winner: UserId, loser: UserId, bet: Bet, mercy: Option<Bet>) -> Option<Vec<InlineKeyboardButton>> {
+    if p.rematch_window.is_zero() {
+        return None
+    }
+    let mercy = mercy.filter(|_| !p.mercy_window.is_zero()).unwrap_or(Bet::new(0));
+    let rematch_label = t!("commands.pvp.rematch.button", locale = &p.lang_code);
+    let rematch_data = RematchCallbackData::new(winner, loser, bet, mercy).to_data_string();
+    let double_label = t!("commands.pvp.rematch.double_button", locale = &p.lang_code);
+    let double_bet = Bet::new(bet.value().saturating_mul(2));
+    let double_data = RematchCallbackData::new(winner, loser, double_bet, mercy).to_data_string();
+    Some(vec![
+        InlineKeyboardButton::callback(rematch_label, rematch_data),
+        InlineKeyboardButton::callback(double_label, double_data),
+    ])
+}
+
+/// Whether the loser had a shield from the `/shop` and has just spent it. A failure to tell counts
+/// as no shield: the battle is fought as if the shop didn't exist.
+async fn consume_shield(p: &BattleParams, loser: UserId) -> bool {
+    let consumed = p.repos.inventory.consume(&p.chat_id.kind(), loser, ShopItem::Shield).await
+        .inspect_err(|e| tracing::error!(loser = %loser, error = %e, "couldn't check the shield of the loser"))
+        .unwrap_or(false);
+    if consumed {
+        metrics::SHOP_ITEM_USED.record(ShopItem::Shield);
+    }
+    consumed
+}
+
+/// A shielded loss moves no length, so the battle leaves no trace in the statistics either.
+async fn shielded_battle_result(
     p: &BattleParams,
+    winner: UserId,
+    loser: UserId,
+    acceptor: &UserInfo,
+    bet: Bet,
+    offered_bet: Bet,
+) -> anyhow::Result<CallbackResult> {
+    let winner_info = get_user_info(&p.repos.users, winner, acceptor).await?;
+    let loser_info = get_user_info(&p.repos.users, loser, acceptor).await?;
+    let text = t!("commands.pvp.results.shielded", locale = &p.lang_code,
+        winner_name = winner_info.name.escaped(), loser_name = loser_info.name.escaped(), bet = bet).to_string();
+    // nothing to give back, but the loser may well want to try once again
+    let keyboard = result_keyboard([rematch_buttons(p, winner, loser, offered_bet, None)]);
+    Ok(CallbackResult::EditMessage(text, keyboard))
+}
+
+pub(crate) async fn pay_for_loan_if_needed(
+    repos: &Repositories,
+    chat_id_kind: &ChatIdKind,
     winner_id: UserId,
     award: Bet,
 ) -> anyhow::Result<Option<(GrowthResult, LoanPayout)>> {
-    let chat_id_kind = p.chat_id.kind();
-    let loan = match p.repos.loans.get_active_loan(winner_id, &chat_id_kind).await? {
+    let loan = match repos.loans.get_active_loan(winner_id, chat_id_kind).await? {
         Some(loan) => loan,
         None => return Ok(None)
     };
@@ -432,13 +1327,180 @@ async fn pay_for_loan_if_needed(
     let payout_value = payout_value.min(loan.debt.saturating_into());
     let payout = LoanPayout::new(payout_value.saturating_into());
 
-    p.repos.loans.pay(winner_id, &chat_id_kind, payout).await?;
+    repos.loans.pay(winner_id, chat_id_kind, payout).await?;
 
     let withheld = LengthChange::signed(-i64::from(payout.value()));
-    let growth_res = p.repos.dicks.grow_no_attempts_check(&chat_id_kind, winner_id, withheld).await?;
+    let growth_res = repos.dicks.grow_no_attempts_check(chat_id_kind, winner_id, withheld, LengthChangeReason::LoanPayout).await?;
     Ok(Some((growth_res, payout)))
 }
 
 pub fn new_short_timestamp() -> NewLayoutValue<i64> {
-    NewLayoutValue::Some(chrono::Utc::now().timestamp_millis() - TIMESTAMP_MILLIS_SINCE_2024)
+    NewLayoutValue::Some(short_timestamp_now())
+}
+
+pub(crate) fn short_timestamp_now() -> i64 {
+    chrono::Utc::now().timestamp_millis() - TIMESTAMP_MILLIS_SINCE_2024
+}
+
+/// A clock moved backwards counts as no time at all.
+fn elapsed_since(short_timestamp: i64) -> Duration {
+    let elapsed = short_timestamp_now().saturating_sub(short_timestamp).max(0);
+    Duration::from_millis(elapsed.unsigned_abs())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::domain::primitives::{Bet, Length, UserId};
+    use crate::handlers::pvp::{chance_by_lengths, complement, parse_bet_and_mention, short_timestamp_now, BattleCallbackData, DeclineCallbackData, MercyCallbackData, RematchCallbackData, RpsCallbackData, MIN_CHANCE};
+    use crate::handlers::utils::callbacks::{build_callback_query, CallbackDataWithPrefix};
+    use crate::handlers::utils::rps::{Hand, RpsGame};
+
+    #[test]
+    fn test_mercy_serialize_and_parse() {
+        let data = MercyCallbackData {
+            winner: UserId::new(123456),
+            loser: UserId::new(654321),
+            bet: Bet::new(42),
+            timestamp: 1000,
+        };
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "mercy:123456:654321:42:1000");
+
+        let parsed = MercyCallbackData::parse(&build_callback_query(data_string))
+            .expect("callback data of the mercy button must be parsed successfully");
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn test_mercy_window() {
+        let data = MercyCallbackData::new(UserId::new(1), UserId::new(2), Bet::new(1));
+        assert!(data.is_within(Duration::from_secs(60)));
+
+        let old = MercyCallbackData { timestamp: short_timestamp_now() - 61_000, ..data };
+        assert!(!old.is_within(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_parse_bet_and_mention() {
+        assert_eq!(parse_bet_and_mention("5".to_owned()).unwrap(), (5, None));
+        assert_eq!(parse_bet_and_mention(" 5  @someone ".to_owned()).unwrap(), (5, Some("@someone".to_owned())));
+        assert_eq!(parse_bet_and_mention("5 John Smith".to_owned()).unwrap(), (5, Some("John Smith".to_owned())));
+        assert!(parse_bet_and_mention("@someone 5".to_owned()).is_err());
+        assert!(parse_bet_and_mention("".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_battle_old_layouts_are_open_offers() {
+        for data in ["pvp:123456:42", "pvp:123456:42:1000", "pvp:123456:42:OLDVER"] {
+            let parsed = BattleCallbackData::parse(&build_callback_query(data.to_owned()))
+                .unwrap_or_else(|e| panic!("an old button {data} must be parsed successfully: {e}"));
+            assert_eq!(parsed.initiator, UserId::new(123456));
+            assert_eq!(parsed.bet, Bet::new(42));
+            assert!(parsed.opponent().is_none());
+            assert!(!parsed.is_expired(Duration::ZERO), "an open offer must never expire");
+        }
+    }
+
+    #[test]
+    fn test_challenge_serialize_and_parse() {
+        let data = BattleCallbackData {
+            timestamp: Some(1000).into(),
+            ..BattleCallbackData::challenge(UserId::new(123456), UserId::new(654321), Bet::new(42))
+        };
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "pvp:123456:42:1000:654321");
+
+        let parsed = BattleCallbackData::parse(&build_callback_query(data_string))
+            .expect("callback data of the challenge must be parsed successfully");
+        assert_eq!(parsed.to_string(), data.to_string());
+        assert_eq!(parsed.opponent(), Some(UserId::new(654321)));
+
+        let decline_string = DeclineCallbackData(parsed).to_data_string();
+        assert_eq!(decline_string, "decline:123456:42:1000:654321");
+        let DeclineCallbackData(declined) = DeclineCallbackData::parse(&build_callback_query(decline_string))
+            .expect("callback data of the decline button must be parsed successfully");
+        assert_eq!(declined.to_string(), data.to_string(), "the decline button must lock the challenge itself");
+    }
+
+    #[test]
+    fn test_challenge_expiry() {
+        let data = BattleCallbackData::challenge(UserId::new(1), UserId::new(2), Bet::new(1));
+        assert!(!data.is_expired(Duration::from_secs(60)));
+
+        let old = BattleCallbackData { timestamp: Some(short_timestamp_now() - 61_000).into(), ..data };
+        assert!(old.is_expired(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_rematch_serialize_and_parse() {
+        let data = RematchCallbackData {
+            first: UserId::new(123456),
+            second: UserId::new(654321),
+            bet: Bet::new(42),
+            timestamp: 1000,
+            mercy: Bet::new(21),
+        };
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "rematch:123456:654321:42:1000:21");
+
+        let parsed = RematchCallbackData::parse(&build_callback_query(data_string))
+            .expect("callback data of the rematch button must be parsed successfully");
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn test_rematch_is_offered_to_the_other_player() {
+        let data = RematchCallbackData::new(UserId::new(1), UserId::new(2), Bet::new(1), Bet::new(1));
+        assert_eq!(data.opponent_of(UserId::new(1)), Some(UserId::new(2)));
+        assert_eq!(data.opponent_of(UserId::new(2)), Some(UserId::new(1)));
+        assert_eq!(data.opponent_of(UserId::new(3)), None);
+    }
+
+    #[test]
+    fn test_rematch_keeps_the_mercy_of_the_battle() {
+        let data = RematchCallbackData::new(UserId::new(1), UserId::new(2), Bet::new(20), Bet::new(10));
+        let mercy = data.mercy().expect("the mercy button must be kept");
+        assert_eq!(mercy, MercyCallbackData { winner: UserId::new(1), loser: UserId::new(2), bet: Bet::new(10), timestamp: data.timestamp });
+
+        let old = RematchCallbackData { timestamp: short_timestamp_now() - 61_000, ..data };
+        assert!(!old.mercy().expect("the mercy button must be kept").is_within(Duration::from_secs(60)));
+
+        let shielded = RematchCallbackData::new(UserId::new(1), UserId::new(2), Bet::new(20), Bet::new(0));
+        assert_eq!(shielded.mercy(), None);
+    }
+
+    #[test]
+    fn test_rps_serialize_and_parse() {
+        let game = RpsGame { initiator: UserId::new(123456), acceptor: UserId::new(654321), bet: Bet::new(42), timestamp: 1000 };
+        let data = RpsCallbackData { game, hand: Hand::Scissors };
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "rps:123456:654321:42:1000:scissors");
+
+        let parsed = RpsCallbackData::parse(&build_callback_query(data_string))
+            .expect("callback data of a move must be parsed successfully");
+        assert_eq!(parsed, data);
+        assert!(RpsCallbackData::parse(&build_callback_query("rps:1:2:3:4:lizard".to_owned())).is_err());
+    }
+
+    #[test]
+    fn test_chance_by_lengths() {
+        let chance = |underdog, initiator, acceptor| chance_by_lengths(underdog, Length::new(initiator), Length::new(acceptor)).value();
+        assert_eq!(chance(false, 10, 10), 50);
+        assert_eq!(chance(false, 29, 9), 75);
+        assert_eq!(chance(true, 29, 9), 25);
+        // the shorter one is favoured the same way the longer one is in the other mode
+        assert_eq!(chance(true, 9, 29), chance(false, 29, 9));
+        // a hole weighs the same as nothing
+        assert_eq!(chance(false, -50, 0), 50);
+    }
+
+    #[test]
+    fn test_nobody_is_hopeless() {
+        let chance = chance_by_lengths(false, Length::new(1000), Length::new(0));
+        assert_eq!(chance.value(), 100 - MIN_CHANCE);
+        assert_eq!(complement(chance).value(), MIN_CHANCE);
+        let chance = chance_by_lengths(true, Length::new(1000), Length::new(-1000));
+        assert_eq!(chance.value(), MIN_CHANCE);
+    }
 }
@@ -0,0 +1,242 @@
+//! The leaderboard of the battles of a chat. `/stats` shows everybody the numbers of their own;
+//! this one puts the numbers of all the members side by side, ranked by one of them at a time.
+//!
+//! Like `/stats`, it's hidden by `PVP_STATS_SHOW`, and like `/top`, it's cut into pages only where
+//! the top isn't limited.
+
+use std::str::FromStr;
+use anyhow::anyhow;
+use autometrics::autometrics;
+use derive_more::Display;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message, ReplyMarkup};
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::{AppConfig, MessageGroup};
+use crate::domain::enums::BattleRanking;
+use crate::domain::objects::{RankedFighter, WinRateAware};
+use crate::domain::primitives::{LanguageCode, Offset, Page, Username};
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
+use crate::handlers::{answer_callback_feature_disabled, dick, reply_html, FromRefs, HandlerDeps, HandlerResult};
+use crate::handlers::dick::Top;
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackData, InvalidCallbackDataBuilder};
+use crate::repo::Repositories;
+use crate::settings::GameSettingsPolicy;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum PvpTopCommands {
+    #[command(description = "pvptop")]
+    Pvptop(String),
+}
+
+/// Callback payload of the page and ranking buttons. Wire format: `battletop:<ranking>:<page>`.
+/// Not `pvptop`: the prefixes are matched by the beginning, and that one belongs to `/pvp`.
+#[derive(Display, Clone, Copy)]
+#[display("{ranking}:{page}")]
+pub(crate) struct PvpTopCallbackData {
+    pub ranking: BattleRanking,
+    pub page: Page,
+}
+
+impl CallbackDataWithPrefix for PvpTopCallbackData {
+    fn prefix() -> &'static str {
+        "battletop"
+    }
+}
+
+impl TryFrom<String> for PvpTopCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let ranking = callbacks::parse_part(&mut parts, &err, "ranking")?;
+        let page = callbacks::parse_part(&mut parts, &err, "page")?;
+        Ok(Self { ranking, page })
+    }
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn pvptop_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: PvpTopCommands,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_PVPTOP.chat.inc();
+    if !config.features.pvp.show_stats {
+        tracing::info!("ignoring the /pvptop command since the stats are disabled");
+        return Ok(())
+    }
+
+    let PvpTopCommands::Pvptop(args) = cmd;
+    let Some(ranking) = parse_ranking(&args) else {
+        let rankings = BattleRanking::iter().map(|r| format!("<code>{r}</code>")).collect::<Vec<_>>().join(", ");
+        let text = t!("commands.pvptop.errors.unknown_ranking", locale = &lang_code, rankings = rankings);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    };
+
+    let from = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let config = settings.config_for(&chat_id.kind(), &config).await;
+    let (top, keyboard) = pvp_top_impl(&repos, &config, FromRefs(from, &chat_id), &lang_code, ranking, Page::first()).await?;
+    reply_html_ephemeral!(bot, msg, top.lines, self_destruction, MessageGroup::Report, lang_code,
+        reply_markup = Some(ReplyMarkup::InlineKeyboard(keyboard)));
+    Ok(())
+}
+
+/// A bare command shows the default ranking.
+fn parse_ranking(args: &str) -> Option<BattleRanking> {
+    let args = args.trim();
+    if args.is_empty() {
+        Some(BattleRanking::default())
+    } else {
+        BattleRanking::from_str(&args.to_lowercase()).ok()
+    }
+}
+
+pub(crate) async fn pvp_top_impl(
+    repos: &Repositories,
+    config: &AppConfig,
+    from_refs: FromRefs<'_>,
+    lang_code: &LanguageCode,
+    ranking: BattleRanking,
+    page: Page,
+) -> anyhow::Result<(Top, InlineKeyboardMarkup)> {
+    let (from, chat_id) = (from_refs.0, from_refs.1.kind());
+    let offset = Offset::calculate(page, config.top_limit);
+    let query_limit = config.top_limit + 1; // fetch +1 row to know whether more rows exist or not
+    let fighters = repos.pvp_stats.get_top(&chat_id, ranking, offset, query_limit).await?;
+    let has_more_pages = fighters.len() > usize::from(config.top_limit);
+    let lines = fighters.into_iter()
+        .take(usize::from(config.top_limit))
+        .map(|fighter| {
+            let is_caller = fighter.uid == from.id;
+            render_line(fighter, is_caller, ranking, lang_code)
+        })
+        .collect::<Vec<String>>();
+
+    let top = if lines.is_empty() {
+        Top::from(t!("commands.pvptop.empty", locale = lang_code))
+    } else {
+        let ranking_name = t!(&format!("commands.pvptop.rankings.{ranking}"), locale = lang_code);
+        let title = t!("commands.pvptop.title", locale = lang_code, ranking = ranking_name);
+        let text = format!("{}\n\n{}", title, lines.join("\n"));
+        if has_more_pages {
+            Top::with_more_pages(text)
+        } else {
+            Top::from(text)
+        }
+    };
+    let keyboard = build_keyboard(ranking, page, top.has_more_pages, config.features.top_unlimited, lang_code);
+    Ok((top, keyboard))
+}
+
+fn render_line(fighter: RankedFighter, is_caller: bool, ranking: BattleRanking, lang_code: &LanguageCode) -> String {
+    let escaped_name = Username::new(fighter.name).escaped();
+    let name = if is_caller {
+        format!("<u>{escaped_name}</u>")
+    } else {
+        escaped_name
+    };
+    let stats = &fighter.stats;
+    let value = t!(&format!("commands.pvptop.values.{ranking}"), locale = lang_code,
+        win_rate = stats.win_rate_percentage(),
+        won = stats.battles_won,
+        total = stats.battles_total,
+        streak = stats.win_streak_max,
+        length = stats.net_length());
+    t!("commands.pvptop.line", locale = lang_code, n = fighter.position, name = name, value = value).to_string()
+}
+
+/// The pages of the current ranking, if the top is unlimited, and a switch to every other ranking,
+/// which starts over from the first page.
+fn build_keyboard(
+    ranking: BattleRanking,
+    page: Page,
+    has_more_pages: bool,
+    top_unlimited: bool,
+    lang_code: &LanguageCode,
+) -> InlineKeyboardMarkup {
+    let page_row = if top_unlimited {
+        dick::pagination_buttons(page, has_more_pages, |page| PvpTopCallbackData { ranking, page }.to_data_string())
+    } else {
+        Vec::new()
+    };
+    let ranking_row = BattleRanking::iter()
+        .filter(|r| *r != ranking)
+        .map(|r| {
+            let label = t!(&format!("commands.pvptop.buttons.{r}"), locale = lang_code);
+            InlineKeyboardButton::callback(label, PvpTopCallbackData { ranking: r, page: Page::first() }.to_data_string())
+        })
+        .collect();
+    let rows: Vec<_> = [page_row, ranking_row].into_iter().filter(|row| !row.is_empty()).collect();
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    PvpTopCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&q), uid = q.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn pvptop_callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    settings: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let edit_msg_req_params = callbacks::get_params_for_message_edit(&q)?;
+    let data = PvpTopCallbackData::parse(&q).map_err(|e| anyhow!(e))?;
+    if !config.features.pvp.show_stats || (data.page > 0 && !config.features.top_unlimited) {
+        return answer_callback_feature_disabled(bot, &q, edit_msg_req_params, lang_code).await
+    }
+
+    let chat_id_kind: ChatIdKind = edit_msg_req_params.clone().into();
+    // The limit must be the one the first page was cut by, or the pages would overlap or skip rows.
+    let config = settings.config_for(&chat_id_kind, &config).await;
+    let chat_id_partiality = ChatIdPartiality::Specific(chat_id_kind);
+    let from_refs = FromRefs(&q.from, &chat_id_partiality);
+    let (top, keyboard) = pvp_top_impl(&repos, &config, from_refs, &lang_code, data.ranking, data.page).await?;
+    callbacks::answer_and_edit_page(&bot, &q, &edit_msg_req_params, top.lines, keyboard).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::enums::BattleRanking;
+    use crate::domain::primitives::Page;
+    use crate::handlers::utils::callbacks::CallbackDataWithPrefix;
+    use super::{parse_ranking, PvpTopCallbackData};
+
+    #[test]
+    fn test_parse_ranking() {
+        assert_eq!(parse_ranking(""), Some(BattleRanking::WinRate));
+        assert_eq!(parse_ranking("  "), Some(BattleRanking::WinRate));
+        assert_eq!(parse_ranking("Net_Length"), Some(BattleRanking::NetLength));
+        assert_eq!(parse_ranking(" win_streak "), Some(BattleRanking::WinStreak));
+        assert_eq!(parse_ranking("length"), None);
+    }
+
+    #[test]
+    fn test_callback_data_round_trip() {
+        let data = PvpTopCallbackData { ranking: BattleRanking::WinStreak, page: Page::new(3) };
+        let s = data.to_data_string();
+        assert_eq!(s, "battletop:win_streak:3");
+        let stripped = s.strip_prefix("battletop:").expect("no prefix").to_owned();
+        let parsed = PvpTopCallbackData::try_from(stripped).expect("couldn't parse the data back");
+        assert_eq!(parsed.ranking, BattleRanking::WinStreak);
+        assert_eq!(parsed.page, Page::new(3));
+    }
+}
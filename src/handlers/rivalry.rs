@@ -0,0 +1,133 @@
+//! The head-to-head record of two members of a chat, built from `Battle_Log`: `/stats` and
+//! `/pvptop` only know the totals of every player against everybody.
+
+use anyhow::anyhow;
+use autometrics::autometrics;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::MessageGroup;
+use crate::domain::objects::Rivalry;
+use crate::domain::primitives::{LanguageCode, Limit, UserId};
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::handlers::{reply_html, HandlerDeps, HandlerResult};
+use crate::handlers::pvp::UserInfo;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+const LAST_ENCOUNTERS: Limit = Limit::new(5);
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum RivalryCommands {
+    #[command(description = "rivalry")]
+    Rivalry,
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn rivalry_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_RIVALRY.inc();
+    if !config.features.pvp.show_stats {
+        tracing::info!("ignoring the /rivalry command since the stats are disabled");
+        return Ok(())
+    }
+
+    let me: UserInfo = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?.into();
+    // in a forum, a message without a reply is still a reply to the first one of the topic
+    let rival: Option<UserInfo> = msg.reply_to_message()
+        .filter(|reply| reply.forum_topic_created().is_none())
+        .and_then(|reply| reply.from.as_ref())
+        .filter(|user| !user.is_bot && me.uid != user.id)
+        .map(UserInfo::from);
+    let Some(rival) = rival else {
+        let text = t!("commands.rivalry.errors.no_reply", locale = &lang_code);
+        reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(())
+    };
+
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let rivalry = repos.pvp_stats.get_rivalry(&chat_id.kind(), me.uid, rival.uid, LAST_ENCOUNTERS).await?;
+    let text = render_rivalry(&rivalry, &me, &rival, &lang_code);
+    reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Report, lang_code);
+    Ok(())
+}
+
+fn render_rivalry(rivalry: &Rivalry, me: &UserInfo, rival: &UserInfo, lang_code: &LanguageCode) -> String {
+    let (name, rival_name) = (me.name.escaped(), rival.name.escaped());
+    if rivalry.battles == 0 {
+        return t!("commands.rivalry.none", locale = lang_code, name = name, rival = rival_name).to_string()
+    }
+
+    let title = t!("commands.rivalry.title", locale = lang_code, name = &name, rival = &rival_name);
+    let record = t!("commands.rivalry.record", locale = lang_code,
+        battles = rivalry.battles, wins = rivalry.wins, losses = rivalry.losses);
+    let net_length = rivalry.net_length.value();
+    let swapped = match net_length.signum() {
+        1 => t!("commands.rivalry.length.ahead", locale = lang_code, name = &name, rival = &rival_name, length = net_length),
+        -1 => t!("commands.rivalry.length.behind", locale = lang_code, name = &name, rival = &rival_name, length = net_length.saturating_abs()),
+        _ => t!("commands.rivalry.length.even", locale = lang_code, name = &name, rival = &rival_name),
+    };
+    let encounters = rivalry.last_encounters.iter()
+        .map(|encounter| {
+            let winner = if encounter.winner == me.uid { &name } else { &rival_name };
+            let mut line = t!("commands.rivalry.encounter", locale = lang_code,
+                date = encounter.fought_at.format(DATE_FORMAT), winner = winner, bet = encounter.bet).to_string();
+            if encounter.mercy_shown {
+                line.push(' ');
+                line.push_str(&t!("commands.rivalry.mercy_shown", locale = lang_code));
+            }
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let encounters_title = t!("commands.rivalry.last_encounters", locale = lang_code);
+    format!("{title}\n\n{record}\n{swapped}\n\n{encounters_title}\n{encounters}")
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use crate::domain::objects::{Encounter, Rivalry};
+    use crate::domain::primitives::{BattlesCount, Bet, LanguageCode, Length, UserId, Username};
+    use crate::handlers::pvp::UserInfo;
+    use super::render_rivalry;
+
+    fn user(uid: u64, name: &str) -> UserInfo {
+        UserInfo { uid: UserId::new(uid), name: Username::new(name.to_owned()) }
+    }
+
+    #[test]
+    fn test_render_rivalry() {
+        let (me, rival) = (user(1, "Alice"), user(2, "<Bob>"));
+        let lang_code = LanguageCode::new("en".to_owned());
+        let rivalry = Rivalry {
+            battles: BattlesCount::new(2),
+            wins: BattlesCount::new(1),
+            losses: BattlesCount::new(1),
+            net_length: Length::new(-3),
+            last_encounters: vec![
+                Encounter { winner: UserId::new(2), bet: Bet::new(5), mercy_shown: false,
+                    fought_at: Utc.with_ymd_and_hms(2026, 10, 2, 12, 0, 0).unwrap() },
+                Encounter { winner: UserId::new(1), bet: Bet::new(2), mercy_shown: true,
+                    fought_at: Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap() },
+            ],
+        };
+        let text = render_rivalry(&rivalry, &me, &rival, &lang_code);
+        assert!(text.contains("&lt;Bob&gt;"), "the names must be escaped: {text}");
+        assert!(text.contains("<b>3 cm</b>"), "the swapped length must be shown without a sign: {text}");
+        assert!(text.contains("02.10.2026"), "{text}");
+        assert_eq!(text.lines().filter(|line| line.contains("01.10.2026") && line.contains("given back")).count(), 1, "{text}");
+
+        let none = Rivalry { battles: BattlesCount::new(0), wins: BattlesCount::new(0), losses: BattlesCount::new(0),
+            net_length: Length::new(0), last_encounters: Vec::new() };
+        assert!(!render_rivalry(&none, &me, &rival, &lang_code).contains("\n"));
+    }
+}
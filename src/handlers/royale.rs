@@ -0,0 +1,280 @@
+//! A pot for the whole chat: everybody puts the same stake in, and one of the players, drawn by lot,
+//! takes it all. It's a lottery rather than a fight, so it leaves no trace in `Battle_Stats`.
+
+use anyhow::anyhow;
+use autometrics::autometrics;
+use domain_types::traits::SaturatingInto;
+use rand::RngExt;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::{CallbackQuery, Message, ReplyMarkup};
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::MessageGroup;
+use crate::domain::primitives::{Bet, LanguageCode, UserId};
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::handlers::{achievements, pvp, reply_html, send_error_callback_answer, CallbackResult, HandlerDeps, HandlerResult};
+use crate::handlers::pvp::UserInfo;
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackData, InvalidCallbackDataBuilder};
+use crate::handlers::utils::lobbies;
+use crate::handlers::utils::lobbies::{join_keyboard, names, render_registration, JoinResult, Lobbies, LobbyCallbackData, LobbyGame, LobbyRun};
+use crate::handlers::utils::locks::LockCallbackServiceFacade;
+
+/// Two players are a `/pvp`, not a royale.
+const MIN_PLAYERS: usize = 3;
+/// Keeps the list of the players within one message.
+const MAX_PLAYERS: usize = 50;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum RoyaleCommands {
+    #[command(description = "royale")]
+    Royale(String),
+}
+
+/// A marker of the lobbies of the battle royales.
+pub enum Royale {}
+
+pub type Royales = Lobbies<Royale>;
+
+type RoyaleCallbackData = LobbyCallbackData<Royale>;
+type RoyaleRun = LobbyRun<Royale>;
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn royale_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: RoyaleCommands,
+    royales: Royales,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_ROYALE.invoked();
+
+    let RoyaleCommands::Royale(args) = cmd;
+    let organizer: UserInfo = msg.from.as_ref().ok_or(anyhow!("no FROM field in the royale command handler"))?.into();
+    let id = pvp::short_timestamp_now();
+    let bet = match royales.open_for_fee(&repos.dicks, msg.chat.id, id, &organizer, &args).await? {
+        Ok(bet) => bet,
+        Err(e) => {
+            let text = t!(&format!("commands.royale.errors.{e}"), locale = &lang_code);
+            reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Application, lang_code);
+            return Ok(())
+        }
+    };
+    tracing::debug!(uid = %organizer.uid, bet = %bet, "opening a battle royale");
+
+    // Not ephemeral: the message announces the winner in the end.
+    let weighted = config.features.pvp.royale_weighted_by_length;
+    let text = render_registration::<Royale>(&[organizer], bet, config.royale_registration, Some(&odds(weighted, &lang_code)), &lang_code);
+    let mut request = reply_html(&bot, &msg, text);
+    request.reply_markup = Some(ReplyMarkup::InlineKeyboard(join_keyboard::<Royale>(id, bet, &lang_code)));
+    let sent = match request.await {
+        Ok(sent) => sent,
+        Err(e) => {
+            // nobody has seen the button, so nobody has joined
+            royales.finish(msg.chat.id);
+            return Err(anyhow::Error::new(e)
+                .context(format!("failed to announce a battle royale in chat {}", msg.chat.id.0))
+                .into())
+        }
+    };
+
+    tokio::spawn(lobbies::run(RoyaleRun {
+        bot,
+        repos,
+        lobbies: royales,
+        chat_id: msg.chat.id,
+        message_id: sent.id,
+        bet,
+        registration: config.royale_registration,
+        rules: RoyaleRules { weighted },
+        lang_code,
+    }));
+    Ok(())
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    RoyaleCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn royale_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    royales: Royales,
+    mut battle_locker: LockCallbackServiceFacade,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let data = RoyaleCallbackData::parse(&query)?;
+    let Some(chat_id) = query.message.as_ref().map(|msg| msg.chat().id) else {
+        return send_error_callback_answer(bot, query, "commands.royale.errors.closed").await
+    };
+
+    // Held till the message is edited, so that a double click is answered as a second entry
+    // instead of racing the first one through the length check.
+    let player = UserInfo::from(&query.from);
+    let _entry_guard = match battle_locker.try_lock(&RoyaleEntry { id: data.id, uid: player.uid }) {
+        Some(lock) => lock,
+        None => return send_error_callback_answer(bot, query, "commands.royale.errors.already_joined").await
+    };
+    // The stake is checked once again when the winner is drawn: the length may change in between.
+    if !repos.dicks.check_dick(&chat_id.into(), player.uid, data.bet).await? {
+        return send_error_callback_answer(bot, query, "commands.royale.errors.not_enough").await
+    }
+    let players = match royales.join(chat_id, data.id, player, MAX_PLAYERS) {
+        JoinResult::Joined(players) => players,
+        JoinResult::AlreadyJoined => return send_error_callback_answer(bot, query, "commands.royale.errors.already_joined").await,
+        JoinResult::Full => return send_error_callback_answer(bot, query, "commands.royale.errors.full").await,
+        JoinResult::Closed => return send_error_callback_answer(bot, query, "commands.royale.errors.closed").await,
+    };
+
+    let weighted = config.features.pvp.royale_weighted_by_length;
+    let text = render_registration::<Royale>(&players, data.bet, config.royale_registration, Some(&odds(weighted, &lang_code)), &lang_code);
+    let keyboard = join_keyboard::<Royale>(data.id, data.bet, &lang_code);
+    CallbackResult::EditMessage(text, Some(keyboard)).apply(bot, query).await?;
+    Ok(())
+}
+
+fn odds(weighted: bool, lang_code: &LanguageCode) -> String {
+    if weighted {
+        t!("commands.royale.odds.weighted", locale = lang_code)
+    } else {
+        t!("commands.royale.odds.equal", locale = lang_code)
+    }.to_string()
+}
+
+/// The odds are told at the registration, so they stay the same till the draw.
+struct RoyaleRules {
+    weighted: bool,
+}
+
+impl LobbyGame for Royale {
+    const NAME: &'static str = "royale";
+    type Rules = RoyaleRules;
+
+    async fn play(run: &RoyaleRun, players: Vec<UserInfo>) -> anyhow::Result<()> {
+        let chat_id = ChatIdPartiality::from(run.chat_id);
+        let chat_id_kind = chat_id.kind();
+        let lang_code = &run.lang_code;
+
+        let (mut players, mut withdrawn) = lobbies::check_fees(&run.repos.dicks, &chat_id_kind, players, run.bet).await?;
+        tracing::debug!(players = players.len(), withdrawn = withdrawn.len(), "the registration is over");
+        if players.len() < MIN_PLAYERS {
+            run.edit_message(cancelled_text(players.len(), lang_code)).await;
+            return Ok(())
+        }
+
+        let winner_index = if run.rules.weighted {
+            let mut weights = Vec::with_capacity(players.len());
+            for player in &players {
+                let length = run.repos.dicks.fetch_length(player.uid, &chat_id_kind).await?;
+                weights.push(u64::try_from(length.value()).unwrap_or(0));
+            }
+            // everybody has had the stake a moment ago, but nothing stops a dick from shrinking meanwhile
+            let total: u64 = weights.iter().sum();
+            winner_by_ticket(&weights, rand::rng().random_range(0..total.max(1)))
+        } else {
+            rand::rng().random_range(0..players.len())
+        };
+        let winner = players.swap_remove(winner_index);
+        let losers: Vec<UserId> = players.iter().map(|player| player.uid).collect();
+
+        let Some((winner_res, payers)) = run.repos.dicks.collect_pot(&chat_id, &losers, winner.uid, run.bet).await? else {
+            // the losers have all shrunk since the check, so there's nobody to fight with
+            run.edit_message(cancelled_text(1, lang_code)).await;
+            return Ok(())
+        };
+        let (players, shrunk): (Vec<_>, Vec<_>) = players.into_iter().partition(|player| payers.contains(&player.uid));
+        withdrawn.extend(shrunk);
+        let pot = Bet::new(run.bet.value().saturating_mul(payers.len().saturating_add(1).saturating_into()));
+        // The winner's own stake has never left them, so only the rest of the pot is an award.
+        let award = Bet::new(run.bet.value().saturating_mul(payers.len().saturating_into()));
+        let (length, withheld_part) = pvp::pay_for_loan_if_needed(&run.repos, &chat_id_kind, winner.uid, award).await
+            .inspect_err(|e| tracing::error!(error = %e, "couldn't pay for a loan from a battle royale pot"))
+            .ok().flatten()
+            .filter(|(_, withheld)| withheld.value() > 0)
+            .map(|(res, withheld)| {
+                let withheld_part = format!("\n\n{}", t!("commands.pvp.results.withheld", locale = lang_code, payout = withheld));
+                (res.new_length, withheld_part)
+            })
+            .unwrap_or((winner_res.new_length, String::default()));
+
+        let withdrawn_part = if withdrawn.is_empty() {
+            String::default()
+        } else {
+            format!("\n\n{}", t!("commands.royale.withdrawn", locale = lang_code, names = names(withdrawn.iter())))
+        };
+        let result = t!("commands.royale.result", locale = lang_code,
+            name = winner.name.escaped(), pot = pot, count = payers.len().saturating_add(1), length = length,
+            losers = names(players.iter()));
+        let achievements_part = achievements::unlock_achievements(&run.repos, &chat_id_kind, winner.uid, lang_code).await;
+        run.edit_message(format!("{result}{withdrawn_part}{withheld_part}{achievements_part}")).await;
+
+        metrics::CMD_ROYALE.finished();
+        Ok(())
+    }
+}
+
+fn cancelled_text(count: usize, lang_code: &LanguageCode) -> String {
+    t!("commands.royale.cancelled", locale = lang_code, count = count, min = MIN_PLAYERS).to_string()
+}
+
+/// Whose is the ticket drawn out of `0..sum(weights)`: every player owns as many tickets in a row
+/// as their weight.
+fn winner_by_ticket(weights: &[u64], mut ticket: u64) -> usize {
+    for (index, weight) in weights.iter().enumerate() {
+        if ticket < *weight {
+            return index
+        }
+        ticket -= weight;
+    }
+    weights.len().saturating_sub(1)
+}
+
+/// Never sent to Telegram: it names the lock of one player's entry to one royale, and the lock
+/// service takes its locks by callback data.
+#[derive(derive_more::Display)]
+#[display("{id}:{uid}")]
+struct RoyaleEntry {
+    id: i64,
+    uid: UserId,
+}
+
+impl CallbackDataWithPrefix for RoyaleEntry {
+    fn prefix() -> &'static str {
+        "royale-entry"
+    }
+}
+
+impl TryFrom<String> for RoyaleEntry {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let id = callbacks::parse_part(&mut parts, &err, "id")?;
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId::new)?;
+        Ok(Self { id, uid })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::winner_by_ticket;
+
+    #[test]
+    fn test_every_ticket_has_an_owner_as_many_times_as_their_weight() {
+        let weights = [3, 1, 0, 2];
+        let owners: Vec<usize> = (0..weights.iter().sum())
+            .map(|ticket| winner_by_ticket(&weights, ticket))
+            .collect();
+        assert_eq!(owners, vec![0, 0, 0, 1, 3, 3]);
+    }
+}
@@ -0,0 +1,149 @@
+//! The seasons of a chat: at their end the lengths of the members are archived and reset to zero,
+//! and `/seasons` lists who has won the past ones. The champions also get a cup in `/top`.
+//!
+//! The seasons end for every chat together, every `SEASONS_LENGTH_MONTHS` months, and the admins
+//! may set an end of their own with `/seasons end` if `SEASONS_CHAT_SCHEDULING_ENABLED` lets them.
+
+use anyhow::anyhow;
+use autometrics::autometrics;
+use chrono::{NaiveDate, Utc};
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::{AppConfig, MessageGroup};
+use crate::domain::objects::SeasonChampion;
+use crate::domain::primitives::{LanguageCode, Username};
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
+use crate::handlers::{reply_html, HandlerDeps, HandlerResult};
+use crate::handlers::utils::is_chat_admin;
+use crate::repo::Repositories;
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum SeasonsCommands {
+    #[command(description = "seasons")]
+    Seasons(String),
+}
+
+/// What the argument of the command asks for: the past champions, or, for an admin, setting or
+/// forgetting the end of the current season.
+#[derive(Debug, PartialEq)]
+enum SeasonsAction {
+    Show,
+    SetEnd(Option<NaiveDate>),
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn seasons_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: SeasonsCommands,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_SEASONS.inc();
+
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let SeasonsCommands::Seasons(args) = cmd;
+    let today = Utc::now().date_naive();
+    let answer = match parse_args(&args) {
+        _ if !config.seasons.enabled() => t!("commands.seasons.errors.disabled", locale = &lang_code).to_string(),
+        Some(SeasonsAction::Show) => seasons_impl(&repos, &config, &chat_id.kind(), &lang_code, today).await?,
+        Some(SeasonsAction::SetEnd(_)) if !config.seasons.chat_scheduling =>
+            t!("commands.seasons.errors.scheduling_disabled", locale = &lang_code).to_string(),
+        Some(SeasonsAction::SetEnd(ends_on)) => {
+            let from_id = msg.from.as_ref().map(|user| user.id)
+                .ok_or(anyhow!("unexpected absence of a FROM field"))?;
+            if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
+                t!("commands.seasons.errors.admins_only", locale = &lang_code).to_string()
+            } else {
+                match ends_on {
+                    Some(date) if date < today =>
+                        t!("commands.seasons.errors.past_date", locale = &lang_code).to_string(),
+                    Some(date) => {
+                        repos.chats.set_season_end(&chat_id, Some(date)).await?;
+                        t!("commands.seasons.schedule.set", locale = &lang_code, date = date.format(DATE_FORMAT)).to_string()
+                    },
+                    None => {
+                        repos.chats.set_season_end(&chat_id, None).await?;
+                        t!("commands.seasons.schedule.unset", locale = &lang_code).to_string()
+                    },
+                }
+            }
+        },
+        None => t!("commands.seasons.errors.unknown_argument", locale = &lang_code).to_string(),
+    };
+    reply_html_ephemeral!(bot, msg, answer, self_destruction, MessageGroup::Report, lang_code);
+    Ok(())
+}
+
+/// A bare command lists the champions; `end <date>` sets the last day of the current season and
+/// `end off` forgets it.
+fn parse_args(args: &str) -> Option<SeasonsAction> {
+    let args = args.trim().to_lowercase();
+    let mut words = args.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (None, _, _) => Some(SeasonsAction::Show),
+        (Some("end"), Some("off"), None) => Some(SeasonsAction::SetEnd(None)),
+        (Some("end"), Some(date), None) => NaiveDate::parse_from_str(date, DATE_FORMAT).ok()
+            .map(|date| SeasonsAction::SetEnd(Some(date))),
+        _ => None,
+    }
+}
+
+/// The champions of the last seasons, as many as the top of the chat has lines, and when the
+/// current one ends: on the day the admins have set, or else with the global season.
+pub(crate) async fn seasons_impl(
+    repos: &Repositories,
+    config: &AppConfig,
+    chat_id: &ChatIdKind,
+    lang_code: &LanguageCode,
+    today: NaiveDate,
+) -> anyhow::Result<String> {
+    let champions = repos.seasons.get_champions(chat_id, config.top_limit).await?;
+    let ends_on = match repos.chats.get_season_end(chat_id).await? {
+        Some(date) => Some(date),
+        None => config.seasons.global_season_end(today),
+    };
+    let end = match ends_on {
+        Some(date) => t!("commands.seasons.ends_on", locale = lang_code, date = date.format(DATE_FORMAT)),
+        None => t!("commands.seasons.no_end", locale = lang_code),
+    };
+    if champions.is_empty() {
+        return Ok(format!("{}\n\n{end}", t!("commands.seasons.empty", locale = lang_code)))
+    }
+    let lines = champions.into_iter()
+        .map(|champion| render_line(champion, lang_code))
+        .collect::<Vec<_>>();
+    let title = t!("commands.seasons.title", locale = lang_code);
+    Ok(format!("{}\n\n{}\n\n{end}", title, lines.join("\n")))
+}
+
+fn render_line(champion: SeasonChampion, lang_code: &LanguageCode) -> String {
+    t!("commands.seasons.line", locale = lang_code,
+        n = champion.number, date = champion.ended_on.format(DATE_FORMAT),
+        name = Username::new(champion.name).escaped(), length = champion.length).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use super::{parse_args, SeasonsAction};
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(""), Some(SeasonsAction::Show));
+        assert_eq!(parse_args(" End OFF "), Some(SeasonsAction::SetEnd(None)));
+        assert_eq!(parse_args("end 31.12.2026"), Some(SeasonsAction::SetEnd(NaiveDate::from_ymd_opt(2026, 12, 31))));
+        assert_eq!(parse_args("end 2026-12-31"), None);
+        assert_eq!(parse_args("end"), None);
+        assert_eq!(parse_args("end 31.12.2026 now"), None);
+        assert_eq!(parse_args("champions"), None);
+    }
+}
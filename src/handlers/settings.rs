@@ -0,0 +1,530 @@
+use std::vec as row;
+use autometrics::autometrics;
+use anyhow::anyhow;
+use itertools::Itertools;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use teloxide::{Bot, RequestError};
+use teloxide::macros::BotCommands;
+use teloxide::prelude::{CallbackQuery, Message, UserId};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ReplyMarkup};
+use crate::{check_invoked_by_owner_and_get_answer_params, reply_html, reply_html_ephemeral};
+use crate::config::{AppConfig, DickOfDaySelectionMode, MessageGroup, PvpOutcomeMode};
+use crate::domain::enums::GameSetting;
+use crate::domain::objects::ChatGameSettings;
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::domain::primitives::{Coefficient, LanguageCode};
+use crate::handlers::{reply_html, HandlerDeps, HandlerResult};
+use crate::handlers::utils::{callbacks, is_chat_admin};
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, EditMessageReqParamsKind, InvalidCallbackData, InvalidCallbackDataBuilder};
+use crate::metrics;
+use crate::settings::GameSettingsPolicy;
+
+/// The same width as the delays of `/cleanup`, for the same reason.
+const OPTIONS_PER_ROW: usize = 3;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum SettingsCommands {
+    #[command(description = "settings")]
+    Settings,
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn settings_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    policy: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { lang_resolver, config, self_destruction, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_SETTINGS.invoked();
+
+    let from_id = msg.from.as_ref().map(|user| user.id)
+        .ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
+        reply_html_ephemeral!(bot, msg, t!("commands.settings.errors.admins_only", locale = &lang_code),
+            self_destruction, MessageGroup::Notice, lang_code);
+        return Ok(());
+    }
+
+    let settings = policy.settings(&msg.chat.id.into()).await;
+    let button = ButtonBuilder { uid: from_id, lang_code: &lang_code };
+    let screen = overview(&config, &settings, &button);
+    reply_html_ephemeral!(bot, msg, screen.text,
+        self_destruction, MessageGroup::Application, lang_code,
+        reply_markup = ReplyMarkup::InlineKeyboard(screen.keyboard));
+    Ok(())
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    SettingsCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn settings_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    policy: GameSettingsPolicy,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { lang_resolver, config, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let data = SettingsCallbackData::parse(&query)?;
+    let mut answer = check_invoked_by_owner_and_get_answer_params!(bot, query, data.uid);
+    let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+
+    let message = query.message.as_ref()
+        .ok_or(anyhow!("a settings callback without an attached message"))?;
+    // the owner check only proves who opened the picker: the rights may have been taken away since
+    if !is_chat_admin(&bot, message.chat().id, query.from.id).await? {
+        answer.show_alert.replace(true);
+        answer.text.replace(t!("commands.settings.errors.admins_only", locale = &lang_code).to_string());
+        answer.await?;
+        return Ok(());
+    }
+    let chat_id: ChatIdPartiality = message.chat().id.into();
+    let button = ButtonBuilder { uid: data.uid, lang_code: &lang_code };
+
+    let settings = match &data.action {
+        // The options depend on the other settings (a growth range can't be turned inside out),
+        // so the list is built from what the chat has now, but nothing is written yet.
+        SettingsAction::Pick(setting) => {
+            let settings = policy.settings(&chat_id.kind()).await;
+            options(&config, &settings, *setting, &button).edit(&bot, edit_msg_params).await?;
+            answer.await?;
+            return Ok(());
+        }
+        SettingsAction::Set(setting, value) => policy.set(&chat_id, *setting, value).await?,
+        SettingsAction::Follow(setting) => policy.follow_the_bot(&chat_id, *setting).await?,
+        SettingsAction::Reset => policy.reset(&chat_id).await?,
+        SettingsAction::Back => policy.settings(&chat_id.kind()).await,
+    };
+    if !matches!(data.action, SettingsAction::Back) {
+        metrics::CMD_SETTINGS.finished();
+    }
+
+    overview(&config, &settings, &button).edit(&bot, edit_msg_params).await?;
+    answer.await?;
+    Ok(())
+}
+
+/// What the picker shows: the text and the buttons under it, built together as in `/cleanup`.
+struct Screen {
+    text: String,
+    keyboard: InlineKeyboardMarkup,
+}
+
+impl Screen {
+    async fn edit(self, bot: &Bot, params: EditMessageReqParamsKind) -> Result<(), RequestError> {
+        callbacks::edit_message_text_with_keyboard(bot, params, self.text, Some(self.keyboard)).await
+    }
+}
+
+/// The first level: the rules the game is played by in this chat, whether the chat chose them or
+/// follows the bot, a button per rule opening its options, and a way back to the bot's own rules.
+fn overview(config: &AppConfig, settings: &ChatGameSettings, button: &ButtonBuilder) -> Screen {
+    let lang_code = button.lang();
+    let effective = config.for_chat(settings);
+    let (lines, rows): (Vec<String>, Vec<_>) = GameSetting::iter()
+        .map(|setting| {
+            let name = setting_name(setting, lang_code);
+            let value = shown_value(&effective, setting, lang_code);
+            let key = if settings.get(setting).is_some() { "commands.settings.state.chosen" }
+                else { "commands.settings.state.default" };
+            let line = t!(key, locale = lang_code, name = &name, value = &value).to_string();
+            let label = t!("commands.settings.buttons.setting", locale = lang_code, name = &name, value = &value);
+            (line, row![button.with_label(label, SettingsAction::Pick(setting))])
+        })
+        .unzip();
+
+    let take_back = (!settings.is_default())
+        .then(|| row![button.of(ButtonKind::Reset, SettingsAction::Reset)]);
+
+    let header = t!("commands.settings.state.header", locale = lang_code);
+    let lines = lines.join("\n");
+    Screen {
+        text: format!("{header}\n\n{lines}"),
+        keyboard: InlineKeyboardMarkup::new(rows.into_iter().chain(take_back)),
+    }
+}
+
+/// The second level: the values the bot suggests for one rule, three to a row, the one in force
+/// marked, and the two ways out — back to the bot's value, or back to the list.
+fn options(config: &AppConfig, settings: &ChatGameSettings, setting: GameSetting, button: &ButtonBuilder) -> Screen {
+    let lang_code = button.lang();
+    let current = token_of(&config.for_chat(settings), setting);
+    let offered = suggested_values(config, setting).iter()
+        .filter_map(|value| {
+            let mut candidate = settings.clone();
+            if !candidate.set(setting, value) {
+                return None
+            }
+            // A value that wouldn't take effect, like a minimum above the maximum, isn't offered.
+            let candidate = config.for_chat(&candidate);
+            (token_of(&candidate, setting) == *value).then_some(candidate)
+        })
+        .map(|candidate| {
+            let value = token_of(&candidate, setting);
+            let shown = shown_value(&candidate, setting, lang_code);
+            let label = if value == current {
+                t!("commands.settings.buttons.current", locale = lang_code, value = shown).to_string()
+            } else {
+                shown
+            };
+            button.with_label(label, SettingsAction::Set(setting, value))
+        })
+        .chunks(OPTIONS_PER_ROW);
+    let always = [
+        row![button.of(ButtonKind::Follow, SettingsAction::Follow(setting))],
+        row![button.of(ButtonKind::Back, SettingsAction::Back)],
+    ];
+
+    let rows: Vec<Vec<InlineKeyboardButton>> = offered.into_iter()
+        .map(Iterator::collect)
+        .chain(always)
+        .collect();
+    Screen {
+        text: t!("commands.settings.pick.prompt", locale = lang_code,
+            name = setting_name(setting, lang_code)).to_string(),
+        keyboard: InlineKeyboardMarkup::new(rows),
+    }
+}
+
+/// What the buttons offer, spelled as [`ChatGameSettings::set`] reads it. The exclusion of the rich
+/// from the Dick of the Day needs a ratio only the bot's configuration has, so it is offered only
+/// where that is set.
+fn suggested_values(config: &AppConfig, setting: GameSetting) -> Vec<String> {
+    let values: &[&str] = match setting {
+        GameSetting::GrowthMin => &["-10", "-5", "-3", "-1", "1"],
+        GameSetting::GrowthMax => &["5", "10", "15", "20", "30"],
+        GameSetting::GrowShrinkRatio => &["0.3", "0.5", "0.7", "0.9", "1"],
+        GameSetting::DodSelectionMode if config.dod_rich_exclusion_ratio.is_some() => &["RANDOM", "WEIGHTS", "EXCLUSION"],
+        GameSetting::DodSelectionMode => &["RANDOM", "WEIGHTS"],
+        GameSetting::TopLimit => &["5", "10", "15", "20", "30"],
+        GameSetting::PvpCheckAcceptorLength => &["true", "false"],
+        GameSetting::PvpOutcomeMode => &["RANDOM", "WEIGHTS", "UNDERDOG", "RPS"],
+        GameSetting::LoanPayoutRatio => &["0.05", "0.1", "0.2", "0.3"],
+    };
+    values.iter().map(ToString::to_string).collect()
+}
+
+/// The value in force, spelled as [`ChatGameSettings::get`] spells it, so an option can be
+/// compared with it.
+fn token_of(config: &AppConfig, setting: GameSetting) -> String {
+    match setting {
+        GameSetting::GrowthMin => config.incrementor.growth_range.start().to_string(),
+        GameSetting::GrowthMax => config.incrementor.growth_range.end().to_string(),
+        GameSetting::GrowShrinkRatio => config.incrementor.grow_shrink_ratio.value().to_string(),
+        GameSetting::DodSelectionMode => config.features.dod_selection_mode.to_string(),
+        GameSetting::TopLimit => config.top_limit.to_string(),
+        GameSetting::PvpCheckAcceptorLength => config.features.pvp.check_acceptor_length.to_string(),
+        GameSetting::PvpOutcomeMode => config.features.pvp.outcome_mode.to_string(),
+        GameSetting::LoanPayoutRatio => config.loan_payout_ratio.value().to_string(),
+    }
+}
+
+/// The value in force as an admin reads it: the ratios as percentages, the rest in words.
+fn shown_value(config: &AppConfig, setting: GameSetting, lang_code: &LanguageCode) -> String {
+    match setting {
+        GameSetting::GrowShrinkRatio => config.incrementor.grow_shrink_ratio.percentage().to_string(),
+        GameSetting::LoanPayoutRatio => config.loan_payout_ratio.percentage().to_string(),
+        GameSetting::DodSelectionMode => mode_name(config.features.dod_selection_mode, lang_code),
+        GameSetting::PvpOutcomeMode => pvp_mode_name(config.features.pvp.outcome_mode, lang_code),
+        GameSetting::PvpCheckAcceptorLength => {
+            let key = if config.features.pvp.check_acceptor_length { "commands.settings.values.on" }
+                else { "commands.settings.values.off" };
+            t!(key, locale = lang_code).to_string()
+        }
+        GameSetting::GrowthMin | GameSetting::GrowthMax | GameSetting::TopLimit => token_of(config, setting),
+    }
+}
+
+fn mode_name(mode: DickOfDaySelectionMode, lang_code: &LanguageCode) -> String {
+    let key = format!("commands.settings.values.modes.{mode}");
+    t!(&key, locale = lang_code).to_string()
+}
+
+/// Apart from the modes of the Dick of the Day: the same `WEIGHTS` favours the other end of the top.
+fn pvp_mode_name(mode: PvpOutcomeMode, lang_code: &LanguageCode) -> String {
+    let key = format!("commands.settings.values.pvp_modes.{mode}");
+    t!(&key, locale = lang_code).to_string()
+}
+
+fn setting_name(setting: GameSetting, lang_code: &LanguageCode) -> String {
+    let key = format!("commands.settings.names.{setting}");
+    t!(&key, locale = lang_code).to_string()
+}
+
+/// The buttons whose label is a fixed line under `commands.settings.buttons`.
+#[derive(Clone, Copy, strum_macros::Display, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+enum ButtonKind {
+    /// Hand this rule back to the bot's own configuration.
+    Follow,
+    /// Return to the list of rules.
+    Back,
+    /// Hand every rule back at once.
+    Reset,
+}
+
+/// Builds the buttons of one keyboard for the admin who opened the picker, in their language.
+struct ButtonBuilder<'a> {
+    uid: UserId,
+    lang_code: &'a LanguageCode,
+}
+
+impl ButtonBuilder<'_> {
+    fn of(&self, kind: ButtonKind, action: SettingsAction) -> InlineKeyboardButton {
+        let key = format!("commands.settings.buttons.{kind}");
+        self.with_label(t!(&key, locale = self.lang_code), action)
+    }
+
+    fn with_label(&self, label: impl Into<String>, action: SettingsAction) -> InlineKeyboardButton {
+        let data = SettingsCallbackData { uid: self.uid, action };
+        InlineKeyboardButton::callback(label, data.to_data_string())
+    }
+
+    fn lang(&self) -> &LanguageCode {
+        self.lang_code
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, derive_more::Display)]
+#[cfg_attr(test, derive(Debug))]
+enum SettingsAction {
+    #[display("pick:{_0}")]
+    Pick(GameSetting),
+    #[display("set:{_0}:{_1}")]
+    Set(GameSetting, String),
+    #[display("follow:{_0}")]
+    Follow(GameSetting),
+    #[display("reset")]
+    Reset,
+    #[display("back")]
+    Back,
+}
+
+/// Callback payload of the settings picker. The wire format is
+/// `settings:<uid>:<pick|set|follow|reset|back>[:<setting>[:<value>]]`: `uid` is the invoker
+/// (checked on press), and the rest is the action. A value is checked when the data is parsed, so
+/// a forged one never reaches the database.
+#[derive(derive_more::Display)]
+#[display("{uid}:{action}")]
+pub struct SettingsCallbackData {
+    uid: UserId,
+    action: SettingsAction,
+}
+
+impl CallbackDataWithPrefix for SettingsCallbackData {
+    fn prefix() -> &'static str {
+        "settings"
+    }
+}
+
+impl TryFrom<String> for SettingsCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
+        let action = match parts.next().ok_or_else(|| err.missing_part("action"))? {
+            "pick" => SettingsAction::Pick(callbacks::parse_part(&mut parts, &err, "setting")?),
+            "set" => {
+                let setting = callbacks::parse_part(&mut parts, &err, "setting")?;
+                let value = parts.next().ok_or_else(|| err.missing_part("value"))?;
+                if !ChatGameSettings::default().set(setting, value) {
+                    return Err(err.split_err())
+                }
+                SettingsAction::Set(setting, value.to_owned())
+            }
+            "follow" => SettingsAction::Follow(callbacks::parse_part(&mut parts, &err, "setting")?),
+            "reset" => SettingsAction::Reset,
+            "back" => SettingsAction::Back,
+            _ => return Err(err.split_err()),
+        };
+        Ok(Self { uid, action })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use teloxide::types::{InlineKeyboardButtonKind, UserId};
+    use super::*;
+    use crate::config::IncrementorConfig;
+    use crate::domain::primitives::{Limit, PayoutRatio, Ratio};
+    use crate::handlers::utils::callbacks::build_callback_query;
+
+    fn config() -> AppConfig {
+        AppConfig {
+            top_limit: Limit::new(10),
+            loan_payout_ratio: PayoutRatio::new(0.1).expect("invalid ratio in the test"),
+            incrementor: IncrementorConfig {
+                growth_range: -5..=10,
+                grow_shrink_ratio: Ratio::new(0.5).expect("invalid ratio in the test"),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn english() -> LanguageCode {
+        LanguageCode::new("en".to_owned())
+    }
+
+    fn viewer(lang_code: &LanguageCode) -> ButtonBuilder<'_> {
+        ButtonBuilder { uid: UserId(1), lang_code }
+    }
+
+    fn actions_of(keyboard: InlineKeyboardMarkup) -> Vec<String> {
+        keyboard.inline_keyboard
+            .into_iter()
+            .flatten()
+            .filter_map(|button| match button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => Some(data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The buttons of every picker already sent carry this exact layout, see the same test of
+    /// `/cleanup`.
+    #[test]
+    fn test_callback_data_wire_format() {
+        let data = SettingsCallbackData { uid: UserId(12345), action: SettingsAction::Pick(GameSetting::TopLimit) };
+        assert_eq!(data.to_data_string(), "settings:12345:pick:top_limit");
+
+        let data = SettingsCallbackData {
+            uid: UserId(12345),
+            action: SettingsAction::Set(GameSetting::GrowShrinkRatio, "0.7".to_owned()),
+        };
+        assert_eq!(data.to_data_string(), "settings:12345:set:grow_shrink_ratio:0.7");
+
+        let data = SettingsCallbackData { uid: UserId(12345), action: SettingsAction::Follow(GameSetting::GrowthMin) };
+        assert_eq!(data.to_data_string(), "settings:12345:follow:growth_min");
+
+        let data = SettingsCallbackData { uid: UserId(12345), action: SettingsAction::Reset };
+        assert_eq!(data.to_data_string(), "settings:12345:reset");
+    }
+
+    #[test]
+    fn test_callback_data_round_trip() {
+        for action in [SettingsAction::Pick(GameSetting::DodSelectionMode),
+                       SettingsAction::Set(GameSetting::GrowthMin, "-3".to_owned()),
+                       SettingsAction::Set(GameSetting::PvpCheckAcceptorLength, "false".to_owned()),
+                       SettingsAction::Follow(GameSetting::LoanPayoutRatio),
+                       SettingsAction::Reset,
+                       SettingsAction::Back] {
+            let data = SettingsCallbackData { uid: UserId(12345), action: action.clone() };
+            let parsed = SettingsCallbackData::parse(&build_callback_query(data.to_data_string()))
+                .expect("couldn't parse the callback data");
+            assert_eq!(parsed.uid, UserId(12345));
+            assert_eq!(parsed.action, action);
+        }
+    }
+
+    #[test]
+    fn test_a_value_that_does_not_fit_is_not_parsed() {
+        let query = build_callback_query("settings:12345:set:top_limit:0".to_owned());
+        assert!(SettingsCallbackData::parse(&query).is_err());
+        let query = build_callback_query("settings:12345:set:dod_selection_mode:LOTTERY".to_owned());
+        assert!(SettingsCallbackData::parse(&query).is_err());
+    }
+
+    #[test]
+    fn test_the_first_level_opens_a_list_per_setting() {
+        let lang = english();
+        let screen = overview(&config(), &ChatGameSettings::default(), &viewer(&lang));
+        assert_eq!(actions_of(screen.keyboard), vec![
+            "settings:1:pick:growth_min", "settings:1:pick:growth_max", "settings:1:pick:grow_shrink_ratio",
+            "settings:1:pick:dod_selection_mode", "settings:1:pick:top_limit",
+            "settings:1:pick:pvp_check_acceptor_length", "settings:1:pick:pvp_outcome_mode",
+            "settings:1:pick:loan_payout_ratio",
+        ]);
+
+        let mut settings = ChatGameSettings::default();
+        settings.set(GameSetting::TopLimit, "20");
+        let screen = overview(&config(), &settings, &viewer(&lang));
+        assert!(screen.text.contains("20"), "the chosen value is shown: {}", screen.text);
+        assert_eq!(actions_of(screen.keyboard).pop().as_deref(), Some("settings:1:reset"));
+    }
+
+    /// The loans turned off for the bot stay off in every chat, so no ratio is offered either.
+    #[test]
+    fn test_loans_cannot_be_turned_on_by_a_chat() {
+        let lang = english();
+        let config = AppConfig { loan_payout_ratio: PayoutRatio::new(0.0).expect("invalid ratio in the test"), ..config() };
+        let mut settings = ChatGameSettings::default();
+        settings.set(GameSetting::LoanPayoutRatio, "0.2");
+        assert!(config.for_chat(&settings).loan_payout_ratio.is_zero());
+
+        let screen = options(&config, &ChatGameSettings::default(), GameSetting::LoanPayoutRatio, &viewer(&lang));
+        assert_eq!(actions_of(screen.keyboard), vec!["settings:1:follow:loan_payout_ratio", "settings:1:back"]);
+    }
+
+    /// A bound that would turn the range inside out would be ignored, so it isn't offered at all.
+    #[test]
+    fn test_the_growth_range_cannot_be_turned_inside_out() {
+        let lang = english();
+        let config = AppConfig {
+            incrementor: IncrementorConfig { growth_range: 7..=10, ..config().incrementor },
+            ..config()
+        };
+        let screen = options(&config, &ChatGameSettings::default(), GameSetting::GrowthMax, &viewer(&lang));
+        assert_eq!(actions_of(screen.keyboard), vec![
+            "settings:1:set:growth_max:10", "settings:1:set:growth_max:15", "settings:1:set:growth_max:20",
+            "settings:1:set:growth_max:30", "settings:1:follow:growth_max", "settings:1:back",
+        ]);
+
+        let config = AppConfig {
+            incrementor: IncrementorConfig { growth_range: -20..=-2, ..config.incrementor.clone() },
+            ..config
+        };
+        let screen = options(&config, &ChatGameSettings::default(), GameSetting::GrowthMin, &viewer(&lang));
+        assert_eq!(actions_of(screen.keyboard), vec![
+            "settings:1:set:growth_min:-10", "settings:1:set:growth_min:-5", "settings:1:set:growth_min:-3",
+            "settings:1:follow:growth_min", "settings:1:back",
+        ]);
+    }
+
+    #[test]
+    fn test_the_exclusion_is_offered_only_with_a_ratio() {
+        let lang = english();
+        let screen = options(&config(), &ChatGameSettings::default(), GameSetting::DodSelectionMode, &viewer(&lang));
+        assert_eq!(actions_of(screen.keyboard), vec![
+            "settings:1:set:dod_selection_mode:RANDOM", "settings:1:set:dod_selection_mode:WEIGHTS",
+            "settings:1:follow:dod_selection_mode", "settings:1:back",
+        ]);
+
+        let config = AppConfig { dod_rich_exclusion_ratio: Ratio::new(0.1).ok(), ..config() };
+        let screen = options(&config, &ChatGameSettings::default(), GameSetting::DodSelectionMode, &viewer(&lang));
+        assert_eq!(actions_of(screen.keyboard).len(), 3 + 2);
+    }
+
+    /// Every label the picker asks for exists; see the same test of `/cleanup` for why it matters.
+    #[test]
+    fn test_every_setting_and_value_has_a_label() {
+        let lang = english();
+        for setting in GameSetting::iter() {
+            let name = setting_name(setting, &lang);
+            assert!(!name.contains("commands.settings"), "{setting} has no name: {name}");
+        }
+        for mode in [DickOfDaySelectionMode::RANDOM, DickOfDaySelectionMode::WEIGHTS, DickOfDaySelectionMode::EXCLUSION] {
+            let name = mode_name(mode, &lang);
+            assert!(!name.contains("commands.settings"), "{mode} has no name: {name}");
+        }
+        for mode in [PvpOutcomeMode::RANDOM, PvpOutcomeMode::WEIGHTS, PvpOutcomeMode::UNDERDOG, PvpOutcomeMode::RPS] {
+            let name = pvp_mode_name(mode, &lang);
+            assert!(!name.contains("commands.settings"), "{mode} has no name: {name}");
+        }
+        for kind in ButtonKind::iter() {
+            let keyboard = InlineKeyboardMarkup::new([row![viewer(&lang).of(kind, SettingsAction::Back)]]);
+            let label = keyboard.inline_keyboard.concat().remove(0).text;
+            assert!(!label.contains("commands.settings"), "{kind} has no label: {label}");
+        }
+    }
+}
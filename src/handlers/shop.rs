@@ -0,0 +1,230 @@
+use autometrics::autometrics;
+use anyhow::anyhow;
+use derive_more::Display;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::prelude::{CallbackQuery, Message, UserId};
+use teloxide::types::ReplyMarkup;
+use callbacks::InvalidCallbackData;
+
+use crate::{check_invoked_by_owner_and_get_answer_params, metrics, reply_html_ephemeral, repo};
+use crate::config::{MessageGroup, ShopConfig};
+use crate::domain::enums::ShopItem;
+use crate::domain::objects::InventoryItem;
+use crate::domain::primitives::{LanguageCode, Price, UserId as DomainUserId};
+use crate::domain::primitives::chat::ChatIdKind;
+use crate::handlers::{CallbackButton, FromRefs, HandlerDeps, HandlerImplResult, HandlerResult, reply_html};
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackDataBuilder};
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum ShopCommands {
+    #[command(description = "shop")]
+    Shop,
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn shop_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_SHOP_COUNTER.invoked.chat.inc();
+
+    let from = msg.from.as_ref().ok_or(anyhow!("unexpected absence of a FROM field"))?;
+    let chat_id = msg.chat.id.into();
+    let from_refs = FromRefs(from, &chat_id);
+
+    let result = shop_impl(&repos, from_refs, &config.shop, &lang_code).await?;
+    let markup = result.keyboard().map(ReplyMarkup::InlineKeyboard);
+
+    reply_html_ephemeral!(bot, msg, result.text(), self_destruction, MessageGroup::Application, lang_code,
+        reply_markup = markup);
+    Ok(())
+}
+
+pub(crate) async fn shop_impl(
+    repos: &repo::Repositories,
+    from_refs: FromRefs<'_>,
+    config: &ShopConfig,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<HandlerImplResult<ShopCallbackData>> {
+    let (from, chat_id) = (from_refs.0, from_refs.1.kind());
+    render_shop(repos, &chat_id, from.id, config, lang_code).await
+}
+
+/// The shop as the user sees it at the moment: the prices, their length to pay with and what they
+/// already have. Rendered anew after every purchase, so the message never shows a stale length.
+async fn render_shop(
+    repos: &repo::Repositories,
+    chat_id: &ChatIdKind,
+    uid: UserId,
+    config: &ShopConfig,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<HandlerImplResult<ShopCallbackData>> {
+    let on_sale: Vec<ShopItem> = ShopItem::iter()
+        .filter(|item| config.sold(*item))
+        .collect();
+    if on_sale.is_empty() {
+        let err_text = t!("errors.feature_disabled", locale = lang_code).to_string();
+        return Ok(HandlerImplResult::OnlyText(err_text))
+    }
+
+    let length = repos.dicks.fetch_length(DomainUserId::from(uid), chat_id).await?;
+    let inventory = repos.inventory.get_items(chat_id, DomainUserId::from(uid)).await?;
+
+    let title = t!("commands.shop.title", locale = lang_code, length = length);
+    let lines = on_sale.iter()
+        .map(|item| t!("commands.shop.line", locale = lang_code,
+            name = item_name(*item, lang_code), price = config.price(*item),
+            description = t!(&format!("commands.shop.items.{item}.description"), locale = lang_code)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let inventory_part = render_inventory(&inventory, lang_code);
+
+    let buttons = on_sale.into_iter()
+        .map(|item| CallbackButton::new(
+            t!("commands.shop.button", locale = lang_code,
+                name = item_name(item, lang_code), price = config.price(item)).to_string(),
+            ShopCallbackData { uid, item, price: config.price(item) }
+        ))
+        .collect();
+    Ok(HandlerImplResult::WithKeyboard {
+        text: format!("{title}\n\n{lines}\n\n{inventory_part}"),
+        buttons,
+    })
+}
+
+fn render_inventory(inventory: &[InventoryItem], lang_code: &LanguageCode) -> String {
+    if inventory.is_empty() {
+        return t!("commands.shop.inventory.empty", locale = lang_code).to_string()
+    }
+    let items = inventory.iter()
+        .map(|InventoryItem { item, quantity }| format!("{} × {quantity}", item_name(*item, lang_code)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    t!("commands.shop.inventory.items", locale = lang_code, items = items).to_string()
+}
+
+fn item_name(item: ShopItem, lang_code: &LanguageCode) -> String {
+    t!(&format!("commands.shop.items.{item}.name"), locale = lang_code).to_string()
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    ShopCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn shop_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let data = ShopCallbackData::parse(&query)?;
+    let mut answer = check_invoked_by_owner_and_get_answer_params!(bot, query, data.uid);
+
+    let edit_msg_params = callbacks::get_params_for_message_edit(&query)?;
+    let chat_id = callbacks::resolve_chat_id(&repos, config.features.chats_merging, &edit_msg_params).await?;
+    let current_price = config.shop.price(data.item);
+
+    // Telegram refuses an edit that changes nothing, so the message is rendered anew only when
+    // either the shop or the user's part of it has changed.
+    let (status, changed) = if !config.shop.sold(data.item) {
+        (t!("errors.feature_disabled", locale = &lang_code).to_string(), true)
+    } else if current_price != data.price {
+        // the shop message may be an hour old, and nobody should pay a price they haven't seen
+        (t!("commands.shop.callback.price_changed", locale = &lang_code).to_string(), true)
+    } else {
+        match repos.inventory.buy(&chat_id, data.uid.into(), data.item, current_price).await? {
+            repo::PurchaseResult::Bought { quantity, .. } => {
+                metrics::CMD_SHOP_COUNTER.finished.inc();
+                metrics::SHOP_ITEM_BOUGHT.record(data.item);
+                let text = t!("commands.shop.callback.bought", locale = &lang_code,
+                    name = item_name(data.item, &lang_code), quantity = quantity).to_string();
+                (text, true)
+            }
+            repo::PurchaseResult::NotEnoughLength => {
+                let text = t!("commands.shop.callback.not_enough", locale = &lang_code, price = current_price).to_string();
+                (text, false)
+            }
+        }
+    };
+    answer.show_alert.replace(true);
+    answer.text.replace(status);
+
+    if changed {
+        let shop = render_shop(&repos, &chat_id, data.uid, &config.shop, &lang_code).await?;
+        callbacks::edit_message_text_with_keyboard(&bot, edit_msg_params, shop.text(), shop.keyboard()).await?;
+    }
+    answer.await?;
+    Ok(())
+}
+
+/// The price is a part of the data, so that a click on a button of an old message, made after the
+/// price has changed, doesn't charge what the button doesn't say.
+#[derive(Display)]
+#[display("{uid}:{item}:{price}")]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(crate) struct ShopCallbackData {
+    uid: UserId,
+    item: ShopItem,
+    price: Price,
+}
+
+impl CallbackDataWithPrefix for ShopCallbackData {
+    fn prefix() -> &'static str {
+        "shop"
+    }
+}
+
+impl TryFrom<String> for ShopCallbackData {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.as_str().split(':');
+        let uid = callbacks::parse_part(&mut parts, &err, "uid").map(UserId)?;
+        let item = callbacks::parse_part(&mut parts, &err, "item")?;
+        let price = callbacks::parse_part(&mut parts, &err, "price")?;
+        Ok(Self { uid, item, price })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use teloxide::types::UserId;
+    use crate::domain::enums::ShopItem;
+    use crate::domain::primitives::Price;
+    use crate::handlers::shop::ShopCallbackData;
+    use crate::handlers::utils::callbacks::{build_callback_query, CallbackDataWithPrefix};
+
+    #[test]
+    fn test_serialize_and_parse() {
+        let data = ShopCallbackData {
+            uid: UserId(123456),
+            item: ShopItem::DodTicket,
+            price: Price::new(30),
+        };
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "shop:123456:dod_ticket:30");
+
+        let parsed = ShopCallbackData::parse(&build_callback_query(data_string))
+            .expect("callback data of the shop must be parsed successfully");
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn test_parse_unknown_item() {
+        let query = build_callback_query("shop:123456:golden_dick:30".to_owned());
+        assert!(ShopCallbackData::parse(&query).is_err());
+    }
+}
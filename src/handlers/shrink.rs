@@ -13,6 +13,7 @@ use crate::handlers::{answer_callback_feature_disabled, FromRefs, HandlerDeps, H
 use crate::handlers::utils::callbacks;
 use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, InvalidCallbackData, InvalidCallbackDataBuilder};
 use crate::repo::{AdjacentDates, RecentShrink, Repositories};
+use crate::settings::GameSettingsPolicy;
 
 pub(crate) struct ShrinksPage {
     pub lines: String,
@@ -182,6 +183,7 @@ pub fn callback_filter(query: CallbackQuery) -> bool {
 pub async fn shrink_callback_handler(
     bot: Bot,
     q: CallbackQuery,
+    settings: GameSettingsPolicy,
     deps: HandlerDeps,
 ) -> HandlerResult {
     let HandlerDeps { repos, config, lang_resolver, .. } = deps;
@@ -193,6 +195,7 @@ pub async fn shrink_callback_handler(
 
     let data = ShrinkCallbackData::parse(&q).map_err(|e| anyhow!(e))?;
     let chat_id: ChatIdKind = edit_msg_req_params.clone().into();
+    let config = settings.config_for(&chat_id, &config).await;
     let page = shrinks_page_impl(&repos, &config, &chat_id, &lang_code, data.view, data.date, data.page).await?;
 
     let adjacent = match data.view {
@@ -54,8 +54,12 @@ async fn personal_stats_impl(
     lang_code: &LanguageCode,
 ) -> anyhow::Result<String> {
     repos.personal_stats.get_personal_stats(UserId::from(from_refs.0)).await
-        .map(|stats| t!("commands.stats.personal", locale = lang_code,
-            chats = stats.chats, max_length = stats.max_length, total_length = stats.total_length).to_string())
+        .map(|stats| {
+            let personal = t!("commands.stats.personal", locale = lang_code,
+                chats = stats.chats, max_length = stats.max_length, total_length = stats.total_length);
+            let referrals = t!("commands.stats.referrals", locale = lang_code, referrals = stats.referrals);
+            format!("{personal}\n{referrals}")
+        })
 }
 
 pub(crate) async fn chat_stats_impl(
@@ -69,16 +73,22 @@ pub(crate) async fn chat_stats_impl(
         .unwrap_or_default();
     let length_stats = t!("commands.stats.length", locale = lang_code,
         length = length, pos = position);
+    let streak = repos.dicks.fetch_grow_streak(UserId::from(from_refs.0), &from_refs.1.kind()).await?.days;
+    let streak_stats = t!("commands.stats.streak", locale = lang_code, days = streak);
     let pvp_stats = repos.pvp_stats.get_stats(&from_refs.1.kind(), UserId::from(from_refs.0)).await
-        .map(|stats| t!("commands.stats.pvp", locale = lang_code,
-            win_rate = stats.win_rate_percentage(), win_streak = stats.win_streak_max,
-            battles = stats.battles_total, wins = stats.battles_won,
-            acquired = stats.acquired_length, lost = stats.lost_length))
+        .map(|stats| {
+            let battles = t!("commands.stats.pvp", locale = lang_code,
+                win_rate = stats.win_rate_percentage(), win_streak = stats.win_streak_max,
+                battles = stats.battles_total, wins = stats.battles_won,
+                acquired = stats.acquired_length, lost = stats.lost_length);
+            let mercies = t!("commands.stats.mercies", locale = lang_code, mercies = stats.mercies_shown);
+            format!("{battles}\n{mercies}")
+        })
         .map(|s| if features.show_stats_notice {
             let notice = t!("commands.stats.notice", locale = lang_code);
             format!("{}\n\n<i>{}</i>", s, notice)
         } else {
-            s.to_string()
+            s
         })?;
-    Ok(format!("{length_stats}\n\n{pvp_stats}"))
+    Ok(format!("{length_stats}\n{streak_stats}\n\n{pvp_stats}"))
 }
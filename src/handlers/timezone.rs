@@ -0,0 +1,123 @@
+//! The time zone of a chat, whose midnight starts its days: when a dick may grow again, when a new
+//! Dick of the Day may be elected and when the inactive dicks shrink. UTC until the admins set
+//! another one with `/timezone <IANA name>`.
+
+use anyhow::anyhow;
+use autometrics::autometrics;
+use chrono::Utc;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::Message;
+use teloxide::utils::html;
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::MessageGroup;
+use crate::domain::primitives::LanguageCode;
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
+use crate::handlers::{reply_html, HandlerDeps, HandlerResult};
+use crate::handlers::utils::is_chat_admin;
+use crate::repo::Repositories;
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum TimeZoneCommands {
+    #[command(description = "timezone")]
+    Timezone(String),
+}
+
+/// What the argument of the command asks for: the time zone of the chat, or, for an admin, setting
+/// another one or going back to UTC with `None`.
+#[derive(Debug, PartialEq)]
+enum TimeZoneAction {
+    Show,
+    Change(Option<String>),
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn timezone_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: TimeZoneCommands,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, self_destruction, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_TIMEZONE.invoked();
+
+    let chat_id: ChatIdPartiality = msg.chat.id.into();
+    let TimeZoneCommands::Timezone(args) = cmd;
+    let answer = match parse_args(&args) {
+        TimeZoneAction::Show => timezone_impl(&repos, &chat_id.kind(), &lang_code).await?,
+        TimeZoneAction::Change(name) => {
+            let from_id = msg.from.as_ref().map(|user| user.id)
+                .ok_or(anyhow!("unexpected absence of a FROM field"))?;
+            if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
+                t!("commands.timezone.errors.admins_only", locale = &lang_code).to_string()
+            } else {
+                change_time_zone(&repos, &chat_id, name, &lang_code).await?
+            }
+        },
+    };
+    reply_html_ephemeral!(bot, msg, answer, self_destruction, MessageGroup::Notice, lang_code);
+    Ok(())
+}
+
+/// A bare command shows the time zone, `off` brings the chat back to UTC, and anything else is
+/// taken for the name of a zone.
+fn parse_args(args: &str) -> TimeZoneAction {
+    match args.trim() {
+        "" => TimeZoneAction::Show,
+        off if off.eq_ignore_ascii_case("off") => TimeZoneAction::Change(None),
+        name => TimeZoneAction::Change(Some(name.to_owned())),
+    }
+}
+
+async fn change_time_zone(
+    repos: &Repositories,
+    chat_id: &ChatIdPartiality,
+    name: Option<String>,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<String> {
+    let answer = match name {
+        Some(name) => match repos.chats.find_time_zone(&name).await? {
+            Some(zone) => {
+                repos.chats.set_time_zone(chat_id, Some(&zone)).await?;
+                metrics::CMD_TIMEZONE.finished();
+                t!("commands.timezone.set", locale = lang_code, zone = html::escape(&zone)).to_string()
+            },
+            None => t!("commands.timezone.errors.unknown", locale = lang_code, name = html::escape(&name)).to_string(),
+        },
+        None => {
+            repos.chats.set_time_zone(chat_id, None).await?;
+            metrics::CMD_TIMEZONE.finished();
+            t!("commands.timezone.unset", locale = lang_code).to_string()
+        },
+    };
+    Ok(answer)
+}
+
+/// The zone of the chat with its offset and the time of the day it's there now, which is the
+/// easiest way to see whether the right one has been chosen.
+async fn timezone_impl(repos: &Repositories, chat_id: &ChatIdKind, lang_code: &LanguageCode) -> anyhow::Result<String> {
+    let time_zone = repos.chats.get_time_zone(chat_id).await?;
+    let zone = time_zone.name.as_deref().map_or_else(|| "UTC".to_owned(), html::escape);
+    let local_time = Utc::now().with_timezone(&time_zone.utc_offset).format("%H:%M");
+    let current = t!("commands.timezone.current", locale = lang_code,
+        zone = zone, offset = time_zone.utc_offset, time = local_time);
+    let hint = t!("commands.timezone.hint", locale = lang_code);
+    Ok(format!("{current}\n\n{hint}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_args, TimeZoneAction};
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(""), TimeZoneAction::Show);
+        assert_eq!(parse_args(" OFF "), TimeZoneAction::Change(None));
+        assert_eq!(parse_args(" europe/moscow "), TimeZoneAction::Change(Some("europe/moscow".to_owned())));
+        assert_eq!(parse_args("America/New York"), TimeZoneAction::Change(Some("America/New York".to_owned())));
+    }
+}
@@ -41,7 +41,7 @@ pub async fn topics_cmd_handler(
     }
     let from_id = msg.from.as_ref().map(|user| user.id)
         .ok_or(anyhow!("unexpected absence of a FROM field"))?;
-    if !is_chat_admin(&bot, &msg, from_id).await? {
+    if !is_chat_admin(&bot, msg.chat.id, from_id).await? {
         reply_html!(bot, msg, t!("commands.topics.errors.admins_only", locale = &lang_code));
         return Ok(());
     }
@@ -0,0 +1,345 @@
+//! A cup for the whole chat: everybody pays the same entry fee, the bracket is drawn at random, and
+//! the champion walks away with the fees of all the others.
+//!
+//! Every bout is an ordinary battle — [`pvp::choose_winner`], `Dicks::move_length` and a row in
+//! `Battle_Stats` — fought for the stake the loser carries: their own fee and every fee they have
+//! won before. So the fees travel up the bracket, and nothing is held anywhere in between. The
+//! shields of the `/shop` guard the duels only: the fee is the price of a place in the bracket.
+
+use std::time::Duration;
+use anyhow::{anyhow, Context};
+use autometrics::autometrics;
+use domain_types::traits::SaturatingInto;
+use futures::join;
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::macros::BotCommands;
+use teloxide::types::{CallbackQuery, Message, ReplyMarkup};
+use crate::{metrics, reply_html_ephemeral};
+use crate::config::MessageGroup;
+use crate::domain::enums::LengthChangeReason;
+use crate::domain::primitives::{Bet, LanguageCode};
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::handlers::{achievements, pvp, reply_html, send_error_callback_answer, CallbackResult, HandlerDeps, HandlerResult};
+use crate::handlers::pvp::UserInfo;
+use crate::handlers::utils::callbacks::CallbackDataWithPrefix;
+use crate::handlers::utils::lobbies;
+use crate::handlers::utils::lobbies::{join_keyboard, names, render_registration, JoinResult, Lobbies, LobbyCallbackData, LobbyGame, LobbyRun};
+use crate::repo::Repositories;
+
+/// Two players are a `/pvp`, not a cup.
+const MIN_PLAYERS: usize = 3;
+/// Keeps the bracket within one message.
+const MAX_PLAYERS: usize = 32;
+/// The chat watches the bracket grow round by round instead of getting the champion at once.
+const ROUND_PAUSE: Duration = Duration::from_secs(3);
+
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum TournamentCommands {
+    #[command(description = "tournament")]
+    Tournament(String),
+}
+
+/// A marker of the lobbies of the tournaments. A restart in the middle of the bracket leaves the
+/// bouts fought so far as they are, like any other battles.
+pub enum Tournament {}
+
+pub type Tournaments = Lobbies<Tournament>;
+
+type TournamentCallbackData = LobbyCallbackData<Tournament>;
+type TournamentRun = LobbyRun<Tournament>;
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = ?crate::handlers::msg_user_id(&msg), lang_code = tracing::field::Empty))]
+pub async fn tournament_cmd_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: TournamentCommands,
+    tournaments: Tournaments,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, self_destruction, lang_resolver } = deps;
+    let lang_code = lang_resolver.execute().await;
+    metrics::CMD_TOURNAMENT.invoked();
+
+    let TournamentCommands::Tournament(args) = cmd;
+    let organizer: UserInfo = msg.from.as_ref().ok_or(anyhow!("no FROM field in the tournament command handler"))?.into();
+    let id = pvp::short_timestamp_now();
+    let bet = match tournaments.open_for_fee(&repos.dicks, msg.chat.id, id, &organizer, &args).await? {
+        Ok(bet) => bet,
+        Err(e) => {
+            let text = t!(&format!("commands.tournament.errors.{e}"), locale = &lang_code);
+            reply_html_ephemeral!(bot, msg, text, self_destruction, MessageGroup::Application, lang_code);
+            return Ok(())
+        }
+    };
+    tracing::debug!(uid = %organizer.uid, bet = %bet, "opening a tournament");
+
+    // Not ephemeral: the bracket is a record of the cup, and the message must live till its end.
+    let text = render_registration::<Tournament>(&[organizer], bet, config.tournament_registration, None, &lang_code);
+    let mut request = reply_html(&bot, &msg, text);
+    request.reply_markup = Some(ReplyMarkup::InlineKeyboard(join_keyboard::<Tournament>(id, bet, &lang_code)));
+    let sent = match request.await {
+        Ok(sent) => sent,
+        Err(e) => {
+            // nobody has seen the button, so nobody has joined
+            tournaments.finish(msg.chat.id);
+            return Err(anyhow::Error::new(e)
+                .context(format!("failed to announce a tournament in chat {}", msg.chat.id.0))
+                .into())
+        }
+    };
+
+    tokio::spawn(lobbies::run(TournamentRun {
+        bot,
+        repos,
+        lobbies: tournaments,
+        chat_id: msg.chat.id,
+        message_id: sent.id,
+        bet,
+        registration: config.tournament_registration,
+        rules: (),
+        lang_code,
+    }));
+    Ok(())
+}
+
+pub fn callback_filter(query: CallbackQuery) -> bool {
+    TournamentCallbackData::check_prefix(query)
+}
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(chat_id = ?crate::handlers::cq_chat_id(&query), uid = query.from.id.0, lang_code = tracing::field::Empty))]
+pub async fn tournament_callback_handler(
+    bot: Bot,
+    query: CallbackQuery,
+    tournaments: Tournaments,
+    deps: HandlerDeps,
+) -> HandlerResult {
+    let HandlerDeps { repos, config, lang_resolver, .. } = deps;
+    let lang_code = lang_resolver.execute().await;
+    let data = TournamentCallbackData::parse(&query)?;
+    let Some(chat_id) = query.message.as_ref().map(|msg| msg.chat().id) else {
+        return send_error_callback_answer(bot, query, "commands.tournament.errors.closed").await
+    };
+
+    // The fee is checked once again when the bracket is drawn: the length may change in between.
+    let player = UserInfo::from(&query.from);
+    if !repos.dicks.check_dick(&chat_id.into(), player.uid, data.bet).await? {
+        return send_error_callback_answer(bot, query, "commands.tournament.errors.not_enough").await
+    }
+    let players = match tournaments.join(chat_id, data.id, player, MAX_PLAYERS) {
+        JoinResult::Joined(players) => players,
+        JoinResult::AlreadyJoined => return send_error_callback_answer(bot, query, "commands.tournament.errors.already_joined").await,
+        JoinResult::Full => return send_error_callback_answer(bot, query, "commands.tournament.errors.full").await,
+        JoinResult::Closed => return send_error_callback_answer(bot, query, "commands.tournament.errors.closed").await,
+    };
+
+    let text = render_registration::<Tournament>(&players, data.bet, config.tournament_registration, None, &lang_code);
+    let keyboard = join_keyboard::<Tournament>(data.id, data.bet, &lang_code);
+    CallbackResult::EditMessage(text, Some(keyboard)).apply(bot, query).await?;
+    Ok(())
+}
+
+/// A player still in the cup, with the length they carry: their own fee and all the fees they've won.
+struct Contender {
+    player: UserInfo,
+    stake: Bet,
+}
+
+impl LobbyGame for Tournament {
+    const NAME: &'static str = "tournament";
+    type Rules = ();
+
+    async fn play(run: &TournamentRun, players: Vec<UserInfo>) -> anyhow::Result<()> {
+        let chat_id = ChatIdPartiality::from(run.chat_id);
+        let chat_id_kind = chat_id.kind();
+        let lang_code = &run.lang_code;
+
+        let (players, withdrawn) = lobbies::check_fees(&run.repos.dicks, &chat_id_kind, players, run.bet).await?;
+        let mut contenders: Vec<Contender> = players.into_iter()
+            .map(|player| Contender { player, stake: run.bet })
+            .collect();
+        tracing::debug!(players = contenders.len(), withdrawn = withdrawn.len(), "the registration is over");
+        if contenders.len() < MIN_PLAYERS {
+            let text = t!("commands.tournament.cancelled", locale = lang_code,
+                count = contenders.len(), min = MIN_PLAYERS);
+            run.edit_message(text).await;
+            return Ok(())
+        }
+        contenders.shuffle(&mut rand::rng());
+
+        // The most the champion may win: the stakes of those who forfeit never move, so what is actually
+        // won is told in the line of the champion.
+        let pool = Bet::new(run.bet.value().saturating_mul(contenders.len().saturating_into()));
+        let mut bracket = vec![t!("commands.tournament.bracket.title", locale = lang_code, bet = run.bet, pool = pool).to_string()];
+        if !withdrawn.is_empty() {
+            bracket.push(t!("commands.tournament.bracket.withdrawn", locale = lang_code, names = names(withdrawn.iter())).to_string());
+        }
+
+        let mut round_number = 1;
+        while contenders.len() > 1 {
+            let (byes, pairs) = draw_round(contenders);
+            let mut lines = vec![if byes.is_empty() && pairs.len() == 1 {
+                t!("commands.tournament.bracket.final", locale = lang_code).to_string()
+            } else {
+                t!("commands.tournament.bracket.round", locale = lang_code, number = round_number).to_string()
+            }];
+            lines.extend(byes.iter()
+                .map(|c| t!("commands.tournament.bracket.bye", locale = lang_code, name = c.player.name.escaped()).to_string()));
+            contenders = byes;
+            for (first, second) in pairs {
+                let (winner, line) = fight(&run.repos, &chat_id, first, second, lang_code).await?;
+                lines.push(line);
+                contenders.extend(winner);
+            }
+            bracket.push(lines.join("\n"));
+
+            if contenders.len() > 1 {
+                let in_progress = t!("commands.tournament.bracket.in_progress", locale = lang_code);
+                run.edit_message(format!("{}\n\n{in_progress}", bracket.join("\n\n"))).await;
+                tokio::time::sleep(ROUND_PAUSE).await;
+            }
+            round_number += 1;
+        }
+        let Some(champion) = contenders.pop() else {
+            let no_champion = t!("commands.tournament.bracket.no_champion", locale = lang_code);
+            run.edit_message(format!("{}\n\n{no_champion}", bracket.join("\n\n"))).await;
+            metrics::CMD_TOURNAMENT.finished();
+            return Ok(())
+        };
+
+        // Only what was won is an award; the champion's own fee was never anybody's to withhold from.
+        let won = Bet::new(champion.stake.value().saturating_sub(run.bet.value()));
+        let withheld_part = if won.value() > 0 {
+            pvp::pay_for_loan_if_needed(&run.repos, &chat_id_kind, champion.player.uid, won).await
+                .inspect_err(|e| tracing::error!(error = %e, "couldn't pay for a loan from a tournament award"))
+                .ok().flatten()
+                .filter(|(_, withheld)| withheld.value() > 0)
+                .map(|(_, withheld)| format!("\n\n{}", t!("commands.pvp.results.withheld", locale = lang_code, payout = withheld)))
+                .unwrap_or_default()
+        } else {
+            String::default()
+        };
+        let length = run.repos.dicks.fetch_length(champion.player.uid, &chat_id_kind).await?;
+        let champion_line = t!("commands.tournament.bracket.champion", locale = lang_code,
+            name = champion.player.name.escaped(), won = won, length = length);
+        let achievements_part = achievements::unlock_achievements(&run.repos, &chat_id_kind, champion.player.uid, lang_code).await;
+        run.edit_message(format!("{}\n\n{champion_line}{withheld_part}{achievements_part}", bracket.join("\n\n"))).await;
+
+        metrics::CMD_TOURNAMENT.finished();
+        Ok(())
+    }
+}
+
+/// Splits the contenders of a round into those who pass it without a fight and the pairs to fight.
+/// The byes are given in the first round, and after a pair has dropped out together: they make the
+/// number of those left a power of two, so the next round is full.
+fn draw_round<T>(mut contenders: Vec<T>) -> (Vec<T>, Vec<(T, T)>) {
+    let byes_count = contenders.len().next_power_of_two() - contenders.len();
+    let fighting = contenders.split_off(byes_count);
+    (contenders, fighting.into_iter().tuples().collect())
+}
+
+/// One who can't pay their stake anymore loses the bout without a fight, and no length moves. If
+/// neither can, both are out and nobody goes on: otherwise one of them could walk through the
+/// forfeits up to the cup without ever paying anything.
+async fn fight(
+    repos: &Repositories,
+    chat_id: &ChatIdPartiality,
+    first: Contender,
+    second: Contender,
+    lang_code: &LanguageCode,
+) -> anyhow::Result<(Option<Contender>, String)> {
+    let chat_id_kind = chat_id.kind();
+    let (enough_first, enough_second) = join!(
+        repos.dicks.check_dick(&chat_id_kind, first.player.uid, first.stake),
+        repos.dicks.check_dick(&chat_id_kind, second.player.uid, second.stake),
+    );
+    let (winner, loser) = match bout(enough_first?, enough_second?) {
+        Bout::Fight => pvp::choose_winner(first, second),
+        Bout::Walkover { first_goes_on: true } => return Ok(forfeit(first, second, lang_code)),
+        Bout::Walkover { first_goes_on: false } => return Ok(forfeit(second, first, lang_code)),
+        Bout::BothOut => {
+            let line = t!("commands.tournament.bracket.double_forfeit", locale = lang_code,
+                first = first.player.name.escaped(), second = second.player.name.escaped()).to_string();
+            return Ok((None, line))
+        },
+    };
+    let (winner_uid, loser_uid) = (winner.player.uid, loser.player.uid);
+    repos.dicks.move_length(chat_id, loser_uid, winner_uid, loser.stake, LengthChangeReason::Tournament).await
+        .context(format!("couldn't move the stake of {loser_uid} to {winner_uid}"))?;
+    repos.pvp_stats.send_battle_result(&chat_id_kind, winner_uid, loser_uid, loser.stake).await
+        .inspect_err(|e| tracing::error!(winner = %winner_uid, loser = %loser_uid, error = %e, "couldn't send the battle statistics"))
+        .ok();
+
+    let line = t!("commands.tournament.bracket.fight", locale = lang_code,
+        winner = winner.player.name.escaped(), loser = loser.player.name.escaped(), stake = loser.stake).to_string();
+    let stake = Bet::new(winner.stake.value().saturating_add(loser.stake.value()));
+    Ok((Some(Contender { stake, ..winner }), line))
+}
+
+/// How a bout goes, depending on which of the two can still pay their stake.
+#[derive(Debug, PartialEq)]
+enum Bout {
+    Fight,
+    Walkover { first_goes_on: bool },
+    BothOut,
+}
+
+fn bout(enough_first: bool, enough_second: bool) -> Bout {
+    match (enough_first, enough_second) {
+        (true, true) => Bout::Fight,
+        (true, false) => Bout::Walkover { first_goes_on: true },
+        (false, true) => Bout::Walkover { first_goes_on: false },
+        (false, false) => Bout::BothOut,
+    }
+}
+
+fn forfeit(winner: Contender, loser: Contender, lang_code: &LanguageCode) -> (Option<Contender>, String) {
+    let line = t!("commands.tournament.bracket.forfeit", locale = lang_code,
+        winner = winner.player.name.escaped(), loser = loser.player.name.escaped(), stake = loser.stake).to_string();
+    (Some(winner), line)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bout, draw_round, Bout};
+
+    #[test]
+    fn test_every_round_after_the_first_is_full() {
+        for players in 2..=32 {
+            let mut contenders: Vec<usize> = (0..players).collect();
+            let mut rounds = 0;
+            while contenders.len() > 1 {
+                let (byes, pairs) = draw_round(contenders);
+                assert!(rounds == 0 || byes.is_empty(), "{players} players got byes in round {}", rounds + 1);
+                assert!(!pairs.is_empty(), "{players} players had a round without a fight");
+                contenders = byes.into_iter()
+                    .chain(pairs.into_iter().map(|(first, _)| first))
+                    .collect();
+                assert!(contenders.len().is_power_of_two());
+                rounds += 1;
+            }
+            assert_eq!(rounds, players.next_power_of_two().trailing_zeros(), "{players} players");
+        }
+    }
+
+    #[test]
+    fn test_nobody_goes_on_without_paying() {
+        assert_eq!(bout(true, true), Bout::Fight);
+        assert_eq!(bout(true, false), Bout::Walkover { first_goes_on: true });
+        assert_eq!(bout(false, true), Bout::Walkover { first_goes_on: false });
+        assert_eq!(bout(false, false), Bout::BothOut);
+    }
+
+    #[test]
+    fn test_the_round_after_a_double_forfeit_is_full() {
+        // of the 4 pairs of 8 players, one has dropped out together
+        let (byes, pairs) = draw_round(vec![1, 2, 3]);
+        assert_eq!((byes, pairs), (vec![1], vec![(2, 3)]));
+    }
+}
@@ -10,8 +10,10 @@ use teloxide::payloads::AnswerCallbackQuery;
 use teloxide::prelude::ChatId;
 use teloxide::requests::{JsonRequest, Requester};
 use teloxide::types::{CallbackQuery, InlineKeyboardMarkup, MessageId, ParseMode, UserId};
-use crate::domain::primitives::chat::ChatIdKind;
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
 use crate::domain::primitives::LanguageCode;
+use crate::handlers::utils::try_resolve_chat_id;
+use crate::repo;
 
 #[derive(Debug, Display, Error)]
 pub enum InvalidCallbackData {
@@ -119,6 +121,32 @@ impl Into<ChatIdKind> for EditMessageReqParamsKind {
     }
 }
 
+/// The chat a button was pressed in. An inline message knows only its `chat_instance`, unless the
+/// chat can be learned from the id of the message itself.
+pub async fn resolve_chat_id(
+    repos: &repo::Repositories,
+    chats_merging: bool,
+    params: &EditMessageReqParamsKind,
+) -> anyhow::Result<ChatIdKind> {
+    let chat_id = match params {
+        EditMessageReqParamsKind::Chat(chat_id, _) => ChatIdKind::from(*chat_id),
+        EditMessageReqParamsKind::Inline { chat_instance, inline_message_id } => {
+            let maybe_chat_id = try_resolve_chat_id(inline_message_id)
+                .filter(|_| chats_merging);
+            let chat_id: ChatIdPartiality = if let Some(chat_id) = maybe_chat_id {
+                repos.chats.get_chat(chat_id.into())
+                    .await?
+                    .and_then(|c| c.try_into().ok())
+                    .unwrap_or_else(|| chat_instance.clone().into())
+            } else {
+                chat_instance.clone().into()
+            };
+            chat_id.kind()
+        }
+    };
+    Ok(chat_id)
+}
+
 pub fn get_params_for_message_edit(q: &CallbackQuery) -> Result<EditMessageReqParamsKind, &'static str> {
     q.message.as_ref()
         .map(|m| EditMessageReqParamsKind::Chat(m.chat().id, m.id()))
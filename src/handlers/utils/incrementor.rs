@@ -10,14 +10,19 @@ use rand::distr::uniform::SampleUniform;
 use rand::RngExt;
 use rust_i18n::t;
 use crate::repo;
-use crate::config::IncrementorConfig;
+use crate::config::{Event, Events, IncrementorConfig};
+use crate::settings::GameSettingsPolicy;
+use crate::domain::enums::LengthChangeReason;
+use crate::domain::objects::GrowthSettlement;
 use crate::domain::primitives::chat::ChatIdKind;
-use crate::domain::primitives::{DaysCount, Length, LengthChange, Ratio, SignedLengthChange, UserId};
+use crate::domain::primitives::{DaysCount, LanguageCode, Length, LengthChange, Ratio, SignedLengthChange, UserId};
 use domain_types::literal;
 
 #[derive(Clone)]
 pub struct Incrementor {
     config: IncrementorConfig,
+    events: Events,
+    settings: GameSettingsPolicy,
     perks: Vec<Arc<dyn Perk>>,
     dicks: repo::Dicks,
 }
@@ -30,6 +35,11 @@ pub trait Perk: Send + Sync + Downcast {
     fn enabled(&self) -> bool {
         true
     }
+
+    /// Whether the perk has a share in this kind of change: a daily growth or the bonus of the Dick of the Day.
+    fn applies_to(&self, _reason: LengthChangeReason) -> bool {
+        true
+    }
 }
 impl_downcast!(Perk);
 
@@ -49,12 +59,18 @@ pub struct ChangeIntent {
     pub base_increment: LengthChange,
 }
 
-#[derive(Copy, Clone)]
-pub struct AdditionalChange(pub LengthChange);
+/// The share of a perk in an increment, along with what must be written with the growth for the
+/// share to be true. A perk only counts: nothing is paid until the growth itself is written.
+#[derive(Clone)]
+pub struct AdditionalChange(pub LengthChange, pub Vec<GrowthSettlement>);
 
 impl AdditionalChange {
     pub fn zero() -> Self {
-        Self(LengthChange::signed(0))
+        Self::of(LengthChange::signed(0))
+    }
+
+    pub fn of(change: LengthChange) -> Self {
+        Self(change, Vec::new())
     }
 }
 
@@ -62,6 +78,10 @@ pub struct Increment {
     pub base: LengthChange,
     pub by_perks: HashMap<String, SignedLengthChange>,
     pub total: LengthChange,
+    /// The event whose rules the base increment was rolled by.
+    pub event: Option<Event>,
+    /// What the perks have counted on, to be written along with the growth.
+    pub settlements: Vec<GrowthSettlement>,
 }
 
 type BaseIncrement = SignedLengthChange;
@@ -73,7 +93,13 @@ impl BaseIncrement {
 }
 
 impl Incrementor {
-    pub fn new(config: IncrementorConfig, dicks: &repo::Dicks, perks: Vec<Box<dyn Perk>>) -> Self {
+    pub fn new(
+        config: IncrementorConfig,
+        events: Events,
+        settings: GameSettingsPolicy,
+        dicks: &repo::Dicks,
+        perks: Vec<Box<dyn Perk>>,
+    ) -> Self {
         let (enabled, disabled): (Vec<_>, Vec<_>) = perks
             .into_iter()
             .partition(|perk| perk.enabled() && config.perks.enabled(perk.name()));
@@ -83,6 +109,8 @@ impl Incrementor {
 
         Self {
             config,
+            events,
+            settings,
             perks: enabled.into_iter().map(Arc::from).collect(),
             dicks: dicks.clone(),
         }
@@ -112,28 +140,41 @@ impl Incrementor {
         chat_id: ChatIdKind,
         days_since_registration: DaysCount,
     ) -> Increment {
+        // An event is laid over the chat's own rules, not the other way round: it's the event
+        // the answer mentions, so it's the event that must be seen in the numbers.
+        let config = self.config.for_chat(&self.settings.settings(&chat_id).await);
         let dick_id = DickId(user_id, chat_id);
-        let grow_shrink_ratio = if days_since_registration > self.config.newcomers_grace_days {
-            self.config.grow_shrink_ratio
+        let grow_shrink_ratio = if days_since_registration > config.newcomers_grace_days {
+            config.grow_shrink_ratio
         } else {
             literal!(Ratio = 1.0)
         };
-        let base_incr = get_base_increment(self.config.growth_range.clone(), grow_shrink_ratio);
-        self.add_additional_incr(dick_id, SignedLengthChange::new(base_incr.into())).await
+        let event = self.events.active();
+        let growth_range = match &event {
+            Some(event) => event.modifiers.growth_range(config.growth_range.clone()),
+            None => config.growth_range.clone(),
+        };
+        let base_incr = get_base_increment(growth_range, grow_shrink_ratio);
+        self.add_additional_incr(dick_id, SignedLengthChange::new(base_incr.into()), LengthChangeReason::Grow, event).await
     }
 
     pub async fn dod_increment(&self, user_id: UserId, chat_id: ChatIdKind) -> Increment {
         let dick_id = DickId(user_id, chat_id);
-        let base_incr = rand::rng().random_range(self.config.dod_bonus_range.clone());
-        self.add_additional_incr(dick_id, SignedLengthChange::new(base_incr.into())).await
+        let event = self.events.active();
+        let dod_bonus_range = match &event {
+            Some(event) => event.modifiers.dod_bonus_range(self.config.dod_bonus_range.clone()),
+            None => self.config.dod_bonus_range.clone(),
+        };
+        let base_incr = rand::rng().random_range(dod_bonus_range);
+        self.add_additional_incr(dick_id, SignedLengthChange::new(base_incr.into()), LengthChangeReason::Dod, event).await
     }
 
-    async fn add_additional_incr(&self, dick: DickId, base_increment: BaseIncrement) -> Increment {
+    async fn add_additional_incr(&self, dick: DickId, base_increment: BaseIncrement, reason: LengthChangeReason, event: Option<Event>) -> Increment {
         let current_length = match self.dicks.fetch_length(dick.0, &dick.1).await {
             Ok(length) => length,
             Err(e) => {
                 tracing::error!(error = %e, "couldn't fetch the length of a dick");
-                return base_increment.only()
+                return Increment { event, ..base_increment.only() }
             }
         };
         let base = LengthChange::from(base_increment);
@@ -144,11 +185,13 @@ impl Incrementor {
 
         let mut additional_change = SignedLengthChange::new(0);
         let mut by_perks = HashMap::new();
-        for perk in self.perks.iter() {
-            let AdditionalChange(ac) = perk.apply(&dick, change_intent).await;
+        let mut settlements = Vec::new();
+        for perk in self.perks.iter().filter(|perk| perk.applies_to(reason)) {
+            let AdditionalChange(ac, perk_settlements) = perk.apply(&dick, change_intent).await;
             if !ac.is_zero() {
                 by_perks.insert(perk.name().to_owned(), SignedLengthChange::new(ac.value()));
             }
+            settlements.extend(perk_settlements);
             // saturating addition: a perk pushing the sum out of i64 bounds clamps it
             // instead of wrapping; the checked addition below still decides the outcome
             additional_change += ac.value()
@@ -161,9 +204,10 @@ impl Incrementor {
         if base == total && !additional_change.is_zero() {
             tracing::info!(perks = ?by_perks, "some perks affected the calculation");
             by_perks.clear();
+            settlements.clear();
         }
 
-        Increment { base, by_perks, total }
+        Increment { base, by_perks, total, event, settlements }
     }
 }
 
@@ -173,6 +217,16 @@ impl Increment {
             base,
             by_perks: HashMap::default(),
             total: base,
+            event: None,
+            settlements: Vec::new(),
+        }
+    }
+
+    /// A reminder of why the numbers are unusual today, or an empty string on an ordinary day.
+    pub fn event_part_of_answer(&self, lang_code: &LanguageCode) -> String {
+        match &self.event {
+            Some(event) => format!("\n\n{}", t!("titles.event", locale = lang_code, name = event.name(lang_code))),
+            None => String::default(),
         }
     }
 
@@ -253,6 +307,7 @@ mod test {
 mod test_incrementor {
     use domain_types::literal;
     use std::iter::zip;
+    use std::time::Duration;
 
     use async_trait::async_trait;
     use futures::future::join_all;
@@ -261,6 +316,7 @@ mod test_incrementor {
     use crate::handlers::utils::{AdditionalChange, ChangeIntent, DickId, Incrementor, Perk};
     use crate::repo;
     use crate::repo::test::{CHAT_ID_KIND, fresh_db, USER_ID};
+    use crate::settings::GameSettingsPolicy;
 
     #[tokio::test]
     async fn test_incrementor() {
@@ -274,6 +330,8 @@ mod test_incrementor {
                 dod_bonus_range: 1..=2,
                 perks: Default::default(),
             },
+            events: Default::default(),
+            settings: GameSettingsPolicy::new(Duration::ZERO, repo::Chats::new(db.clone(), Default::default())),
             dicks,
             perks: Vec::default()
         };
@@ -334,7 +392,7 @@ mod test_incrementor {
         }
 
         async fn apply(&self, _: &DickId, _: ChangeIntent) -> AdditionalChange {
-            AdditionalChange(LengthChange::signed(self.value))
+            AdditionalChange::of(LengthChange::signed(self.value))
         }
 
         fn enabled(&self) -> bool {
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use derive_where::derive_where;
+use rust_i18n::t;
+use teloxide::Bot;
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageId};
+use crate::domain::primitives::{Bet, LanguageCode};
+use crate::domain::primitives::chat::ChatIdKind;
+use crate::handlers::pvp::UserInfo;
+use crate::handlers::utils::callbacks;
+use crate::handlers::utils::callbacks::{CallbackDataWithPrefix, EditMessageReqParamsKind, InvalidCallbackData, InvalidCallbackDataBuilder};
+use crate::repo;
+use crate::repo::Repositories;
+
+/// A kind of the games played in [`Lobbies`]. Everything but the game itself is the same for all
+/// of them: the button to join, the list of the players and the wait for the registration to end.
+pub trait LobbyGame: Sized + Send + Sync + 'static {
+    /// Both the prefix of the callback data and the key of the texts, under `commands.`.
+    const NAME: &'static str;
+
+    /// Whatever else the game needs to know, taken from the config when the game is opened.
+    type Rules: Send + Sync;
+
+    /// Plays the game for those who have joined it. Nothing has been paid by then, and the
+    /// players may well be too few.
+    fn play(run: &LobbyRun<Self>, players: Vec<UserInfo>) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// The games of all the chats that gather their players by a button before they start, one game of
+/// a kind per chat at most, from the registration till the end. `G` is a marker telling the kinds
+/// apart, so that a chat may hold a tournament and a battle royale at once.
+///
+/// Kept in memory: nothing is paid while the registration is open, so a restart costs the players
+/// a registration and no length.
+#[derive_where(Clone, Default)]
+pub struct Lobbies<G> {
+    chats: Arc<Mutex<HashMap<ChatId, Lobby>>>,
+    game: PhantomData<G>,
+}
+
+struct Lobby {
+    id: i64,
+    /// `None` once the registration is over and the game is being played.
+    players: Option<Vec<UserInfo>>,
+}
+
+/// Why a game couldn't be opened, spelled as the keys of the error texts of every game.
+#[derive(Debug, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum OpenError {
+    InvalidBet,
+    NotEnough,
+    AlreadyRunning,
+}
+
+pub enum JoinResult {
+    Joined(Vec<UserInfo>),
+    AlreadyJoined,
+    Full,
+    Closed,
+}
+
+impl<G> Lobbies<G> {
+    /// Returns `false` if the chat has a game of this kind going on already.
+    fn open(&self, chat_id: ChatId, id: i64, organizer: UserInfo) -> bool {
+        let mut chats = self.lock();
+        if chats.contains_key(&chat_id) {
+            return false
+        }
+        chats.insert(chat_id, Lobby { id, players: Some(vec![organizer]) });
+        true
+    }
+
+    /// Opens a game of the organizer for the entry fee given as the argument of the command. The
+    /// organizer pays the fee as everybody else, so they must have it as well.
+    pub async fn open_for_fee(
+        &self,
+        dicks: &repo::Dicks,
+        chat_id: ChatId,
+        id: i64,
+        organizer: &UserInfo,
+        args: &str,
+    ) -> anyhow::Result<Result<Bet, OpenError>> {
+        let Some(bet) = args.trim().parse().ok().filter(|bet| *bet > 0).map(Bet::new) else {
+            return Ok(Err(OpenError::InvalidBet))
+        };
+        let result = if !dicks.check_dick(&chat_id.into(), organizer.uid, bet).await? {
+            Err(OpenError::NotEnough)
+        } else if !self.open(chat_id, id, organizer.clone()) {
+            Err(OpenError::AlreadyRunning)
+        } else {
+            Ok(bet)
+        };
+        Ok(result)
+    }
+
+    /// The id tells this game from an earlier one of the same chat, whose button may still be there
+    /// to click.
+    pub fn join(&self, chat_id: ChatId, id: i64, player: UserInfo, max_players: usize) -> JoinResult {
+        let mut chats = self.lock();
+        let players = match chats.get_mut(&chat_id) {
+            Some(Lobby { id: current_id, players: Some(players) }) if *current_id == id => players,
+            _ => return JoinResult::Closed,
+        };
+        if players.iter().any(|p| p.uid == player.uid) {
+            JoinResult::AlreadyJoined
+        } else if players.len() >= max_players {
+            JoinResult::Full
+        } else {
+            players.push(player);
+            JoinResult::Joined(players.clone())
+        }
+    }
+
+    /// Ends the registration. The chat stays busy until [`Self::finish`].
+    pub fn close(&self, chat_id: ChatId) -> Vec<UserInfo> {
+        self.lock().get_mut(&chat_id)
+            .and_then(|lobby| lobby.players.take())
+            .unwrap_or_default()
+    }
+
+    pub fn finish(&self, chat_id: ChatId) {
+        self.lock().remove(&chat_id);
+    }
+
+    /// Nothing is ever left half-written under the lock, so a poisoned one is still good to use.
+    fn lock(&self) -> MutexGuard<'_, HashMap<ChatId, Lobby>> {
+        self.chats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Splits the players into those who still have the fee and those who don't anymore: the length
+/// may change between the click on the button and the start of the game.
+pub async fn check_fees(
+    dicks: &repo::Dicks,
+    chat_id: &ChatIdKind,
+    players: Vec<UserInfo>,
+    bet: Bet,
+) -> anyhow::Result<(Vec<UserInfo>, Vec<UserInfo>)> {
+    let mut paying = Vec::with_capacity(players.len());
+    let mut withdrawn = Vec::new();
+    for player in players {
+        if dicks.check_dick(chat_id, player.uid, bet).await? {
+            paying.push(player);
+        } else {
+            withdrawn.push(player);
+        }
+    }
+    Ok((paying, withdrawn))
+}
+
+pub fn names<'a>(players: impl Iterator<Item = &'a UserInfo>) -> String {
+    players
+        .map(|player| player.name.escaped())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The button of a game, one for every player. The id is the moment the game was opened, so that
+/// the button of an earlier game of the same chat can't sign anybody up for the current one.
+#[cfg_attr(test, derive_where(PartialEq, Debug))]
+#[derive(derive_more::Display)]
+#[display("{id}:{bet}")]
+pub struct LobbyCallbackData<G> {
+    pub id: i64,
+    pub bet: Bet,
+    _game: PhantomData<G>,
+}
+
+impl <G> LobbyCallbackData<G> {
+    pub fn new(id: i64, bet: Bet) -> Self {
+        Self { id, bet, _game: PhantomData }
+    }
+}
+
+impl <G: LobbyGame> CallbackDataWithPrefix for LobbyCallbackData<G> {
+    fn prefix() -> &'static str {
+        G::NAME
+    }
+}
+
+impl <G> TryFrom<String> for LobbyCallbackData<G> {
+    type Error = InvalidCallbackData;
+
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        let err = InvalidCallbackDataBuilder(&data);
+        let mut parts = data.split(':');
+        let id = callbacks::parse_part(&mut parts, &err, "id")?;
+        let bet = callbacks::parse_part(&mut parts, &err, "bet").map(Bet::new)?;
+        Ok(Self::new(id, bet))
+    }
+}
+
+pub fn join_keyboard<G: LobbyGame>(id: i64, bet: Bet, lang_code: &LanguageCode) -> InlineKeyboardMarkup {
+    let btn_label = t!(&format!("commands.{}.button", G::NAME), locale = lang_code);
+    let btn_data = LobbyCallbackData::<G>::new(id, bet).to_data_string();
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(btn_label, btn_data)
+    ]])
+}
+
+/// The organizer is always the first one to join. The rules, if any, go right under the header.
+pub fn render_registration<G: LobbyGame>(
+    players: &[UserInfo],
+    bet: Bet,
+    registration: Duration,
+    rules: Option<&str>,
+    lang_code: &LanguageCode,
+) -> String {
+    let organizer = players.first()
+        .map(|player| player.name.escaped())
+        .unwrap_or_default();
+    let header = t!(&format!("commands.{}.registration", G::NAME), locale = lang_code,
+        name = organizer, bet = bet, seconds = registration.as_secs());
+    let rules = rules.map(|rules| format!("\n{rules}")).unwrap_or_default();
+    let players_line = t!(&format!("commands.{}.players", G::NAME), locale = lang_code,
+        count = players.len(), names = names(players.iter()));
+    format!("{header}{rules}\n\n{players_line}")
+}
+
+/// A game from the moment its message with the button is sent till the end.
+pub struct LobbyRun<G: LobbyGame> {
+    pub bot: Bot,
+    pub repos: Repositories,
+    pub lobbies: Lobbies<G>,
+    pub chat_id: ChatId,
+    pub message_id: MessageId,
+    pub bet: Bet,
+    pub registration: Duration,
+    pub rules: G::Rules,
+    pub lang_code: LanguageCode,
+}
+
+impl <G: LobbyGame> LobbyRun<G> {
+    /// Whatever has been paid by then stays paid, so a message that fails to update is only logged
+    /// and mustn't stop the game halfway.
+    pub async fn edit_message(&self, text: impl Into<String>) {
+        let params = EditMessageReqParamsKind::Chat(self.chat_id, self.message_id);
+        callbacks::edit_message_text_with_keyboard(&self.bot, params, text, None).await
+            .unwrap_or_else(|e| tracing::error!(message_id = self.message_id.0, error = %e, "couldn't update the message of a {}", G::NAME));
+    }
+}
+
+/// Waits for the registration to end, plays the game and frees the chat for the next one.
+#[tracing::instrument(skip_all, fields(game = G::NAME, chat_id = run.chat_id.0, bet = %run.bet))]
+pub async fn run<G: LobbyGame>(run: LobbyRun<G>) {
+    tokio::time::sleep(run.registration).await;
+    let players = run.lobbies.close(run.chat_id);
+    G::play(&run, players).await
+        .unwrap_or_else(|e| tracing::error!(error = format!("{e:#}"), "the {} failed", G::NAME));
+    run.lobbies.finish(run.chat_id);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::primitives::Bet;
+    use crate::handlers::royale::Royale;
+    use crate::handlers::tournament::Tournament;
+    use crate::handlers::utils::callbacks::{build_callback_query, CallbackDataWithPrefix};
+    use super::LobbyCallbackData;
+
+    #[test]
+    fn test_serialize_and_parse() {
+        let data = LobbyCallbackData::<Tournament>::new(1000, Bet::new(5));
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "tournament:1000:5");
+
+        let parsed = LobbyCallbackData::<Tournament>::parse(&build_callback_query(data_string))
+            .expect("callback data of the tournament must be parsed successfully");
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn test_every_game_has_its_own_prefix() {
+        let data = LobbyCallbackData::<Royale>::new(1000, Bet::new(5));
+        let data_string = data.to_data_string();
+        assert_eq!(data_string, "royale:1000:5");
+
+        assert!(!LobbyCallbackData::<Tournament>::check_prefix(build_callback_query(data_string.clone())));
+        let parsed = LobbyCallbackData::<Royale>::parse(&build_callback_query(data_string))
+            .expect("callback data of the royale must be parsed successfully");
+        assert_eq!(parsed, data);
+    }
+}
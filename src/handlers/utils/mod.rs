@@ -1,5 +1,7 @@
 pub mod callbacks;
 pub mod locks;
+pub mod lobbies;
+pub mod rps;
 mod tghack;
 mod incrementor;
 mod self_destruction;
@@ -10,7 +12,7 @@ pub use self_destruction::*;
 
 use teloxide::Bot;
 use teloxide::prelude::{Requester, UserId};
-use teloxide::types::{Chat, ChatKind, Message, PublicChatKind, User};
+use teloxide::types::{Chat, ChatId, ChatKind, PublicChatKind, User};
 use crate::domain::primitives::Username;
 
 /// Whether the chat is a forum, i.e. a supergroup with topics turned on.
@@ -30,9 +32,9 @@ pub fn is_forum(chat: &Chat) -> bool {
 /// Whether the user may change a chat-wide setting. Asked again on every button press: the
 /// callback's owner check only proves who invoked the picker, and rights can be taken away
 /// between the message and the tap.
-#[tracing::instrument(skip_all, fields(chat_id = msg.chat.id.0, uid = user_id.0))]
-pub async fn is_chat_admin(bot: &Bot, msg: &Message, user_id: UserId) -> anyhow::Result<bool> {
-    let admins = bot.get_chat_administrators(msg.chat.id).await?;
+#[tracing::instrument(skip_all, fields(chat_id = chat_id.0, uid = user_id.0))]
+pub async fn is_chat_admin(bot: &Bot, chat_id: ChatId, user_id: UserId) -> anyhow::Result<bool> {
+    let admins = bot.get_chat_administrators(chat_id).await?;
     Ok(admins.into_iter().any(|member| member.user.id == user_id))
 }
 
@@ -45,7 +47,7 @@ pub fn get_full_name(user: &User) -> Username {
 
 pub mod date {
     use std::borrow::Cow;
-    use chrono::{DateTime, Duration, Timelike, Utc};
+    use chrono::{DateTime, Duration, FixedOffset, Offset, Timelike, Utc};
     use rust_i18n::t;
     use crate::domain::primitives::LanguageCode;
 
@@ -62,7 +64,13 @@ pub mod date {
     /// The gap till the upcoming UTC midnight, or `None` on the (practically impossible) failure to
     /// build it — which callers must not conflate with a legitimately zero duration.
     pub fn duration_till_next_day() -> Option<Duration> {
-        let now = now_utc();
+        duration_till_next_day_in(Utc.fix())
+    }
+
+    /// The same as [`duration_till_next_day`], but till the midnight of a chat whose clock is at
+    /// `offset` from UTC.
+    pub fn duration_till_next_day_in(offset: FixedOffset) -> Option<Duration> {
+        let now = now_utc().with_timezone(&offset);
         Some(now + Duration::days(1))
             .and_then(|d| d.with_hour(0))
             .and_then(|d| d.with_minute(0))
@@ -70,8 +78,8 @@ pub mod date {
             .map(|tomorrow| tomorrow - now)
     }
 
-    pub fn get_time_till_next_day_string(lang_code: &LanguageCode) -> Cow<'_, str> {
-        duration_till_next_day()
+    pub fn get_time_till_next_day_string(lang_code: &LanguageCode, offset: FixedOffset) -> Cow<'_, str> {
+        duration_till_next_day_in(offset)
             .map(|time_left| {
                 let hrs = time_left.num_hours();
                 let mins = time_left.num_minutes() - hrs * 60;
@@ -84,6 +92,7 @@ pub mod date {
 
 #[cfg(test)]
 mod tests {
+    use chrono::{FixedOffset, Offset, Utc};
     use crate::domain::primitives::LanguageCode;
     use super::*;
 
@@ -91,8 +100,18 @@ mod tests {
     fn get_time_till_next_day_string() {
         let expected = "<b>1</b>h <b>49</b>m.";
         let lang_code = LanguageCode::of("en");
-        let actual = date::get_time_till_next_day_string(&lang_code);
+        let actual = date::get_time_till_next_day_string(&lang_code, Utc.fix());
         let actual = &actual[actual.len()-expected.len()..];
         assert_eq!(expected, actual)
     }
+
+    #[test]
+    fn duration_till_next_day_in_time_zones() {
+        let till_midnight = |hours| FixedOffset::east_opt(hours * 3600)
+            .and_then(date::duration_till_next_day_in)
+            .map(|d| (d.num_hours(), d.num_minutes() % 60));
+        assert_eq!(till_midnight(3), Some((22, 49)), "it's already 01:10 of the next day in Moscow");
+        assert_eq!(till_midnight(-5), Some((6, 49)), "it's still 17:10 in New York");
+        assert_eq!(date::duration_till_next_day().map(|d| d.num_minutes()), Some(109));
+    }
 }
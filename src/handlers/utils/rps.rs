@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use crate::domain::primitives::{Bet, UserId};
+
+/// A move of rock-paper-scissors. The snake_case spelling is shared by the callback data of the
+/// buttons and the i18n keys under `commands.pvp.rps.hands`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq,
+         strum_macros::Display, strum_macros::EnumString, strum_macros::EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum Hand {
+    Rock,
+    Paper,
+    Scissors,
+}
+
+impl Hand {
+    pub fn beats(self, other: Hand) -> bool {
+        matches!((self, other),
+            (Hand::Rock, Hand::Scissors) | (Hand::Scissors, Hand::Paper) | (Hand::Paper, Hand::Rock))
+    }
+}
+
+/// A battle whose players are picking their moves. The moment it was accepted tells it from another
+/// battle of the same players for the same stake.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, derive_more::Display)]
+#[display("{initiator}:{acceptor}:{bet}:{timestamp}")]
+#[cfg_attr(test, derive(Debug))]
+pub struct RpsGame {
+    pub initiator: UserId,
+    pub acceptor: UserId,
+    /// As it was offered, before an event multiplies it.
+    pub bet: Bet,
+    pub timestamp: i64,
+}
+
+impl RpsGame {
+    pub fn is_player(&self, uid: UserId) -> bool {
+        uid == self.initiator || uid == self.acceptor
+    }
+}
+
+pub enum PickResult {
+    /// The move is made, and the other player hasn't made theirs yet.
+    Waiting,
+    AlreadyPicked,
+    /// Both players have shown the same hand. Both moves are forgotten to be made once again.
+    Draw(Hand),
+    Revealed {
+        initiator: Hand,
+        acceptor: Hand,
+    },
+}
+
+struct Picks {
+    initiator: Option<Hand>,
+    acceptor: Option<Hand>,
+    since: Instant,
+}
+
+/// The moves made so far in every battle of rock-paper-scissors. The moves can't travel in the
+/// callback data as everything else does: the buttons are the same for both players, and the data
+/// of a button is there for any client to read.
+///
+/// Kept in memory: nothing is paid until both moves are made, so a restart costs the players their
+/// moves and no length.
+#[derive(Clone, Default)]
+pub struct RpsGames {
+    games: Arc<Mutex<HashMap<RpsGame, Picks>>>,
+}
+
+impl RpsGames {
+    /// Makes the move of one of the players. A battle nobody has finished within `expiry` is
+    /// forgotten along the way, so that abandoned battles don't pile up.
+    pub fn pick(&self, game: RpsGame, player: UserId, hand: Hand, expiry: Duration) -> PickResult {
+        let mut games = self.lock();
+        games.retain(|_, picks| picks.since.elapsed() <= expiry);
+
+        let picks = games.entry(game)
+            .or_insert_with(|| Picks { initiator: None, acceptor: None, since: Instant::now() });
+        let slot = if player == game.initiator { &mut picks.initiator } else { &mut picks.acceptor };
+        if slot.is_some() {
+            return PickResult::AlreadyPicked
+        }
+        slot.replace(hand);
+
+        match (picks.initiator, picks.acceptor) {
+            (Some(initiator), Some(acceptor)) if initiator == acceptor => {
+                picks.initiator = None;
+                picks.acceptor = None;
+                PickResult::Draw(initiator)
+            }
+            (Some(initiator), Some(acceptor)) => {
+                games.remove(&game);
+                PickResult::Revealed { initiator, acceptor }
+            }
+            _ => PickResult::Waiting,
+        }
+    }
+
+    /// Who of the players has made a move, the initiator first.
+    pub fn picked(&self, game: &RpsGame) -> (bool, bool) {
+        self.lock().get(game)
+            .map(|picks| (picks.initiator.is_some(), picks.acceptor.is_some()))
+            .unwrap_or_default()
+    }
+
+    pub fn forget(&self, game: &RpsGame) {
+        self.lock().remove(game);
+    }
+
+    /// Nothing is ever left half-written under the lock, so a poisoned one is still good to use.
+    fn lock(&self) -> MutexGuard<'_, HashMap<RpsGame, Picks>> {
+        self.games.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use strum::IntoEnumIterator;
+    use crate::domain::primitives::{Bet, UserId};
+    use super::{Hand, PickResult, RpsGame, RpsGames};
+
+    const EXPIRY: Duration = Duration::from_secs(60);
+
+    fn game() -> RpsGame {
+        RpsGame { initiator: UserId::new(1), acceptor: UserId::new(2), bet: Bet::new(5), timestamp: 1000 }
+    }
+
+    #[test]
+    fn test_every_hand_beats_exactly_one_other() {
+        for hand in Hand::iter() {
+            assert!(!hand.beats(hand), "{hand} beats itself");
+            assert_eq!(Hand::iter().filter(|other| hand.beats(*other)).count(), 1, "{hand}");
+        }
+        assert!(Hand::Rock.beats(Hand::Scissors));
+        assert!(Hand::Scissors.beats(Hand::Paper));
+        assert!(Hand::Paper.beats(Hand::Rock));
+    }
+
+    #[test]
+    fn test_the_moves_are_revealed_once_both_are_made() {
+        let games = RpsGames::default();
+        let game = game();
+        assert!(matches!(games.pick(game, game.acceptor, Hand::Paper, EXPIRY), PickResult::Waiting));
+        assert_eq!(games.picked(&game), (false, true));
+        assert!(matches!(games.pick(game, game.acceptor, Hand::Rock, EXPIRY), PickResult::AlreadyPicked));
+        assert!(matches!(games.pick(game, game.initiator, Hand::Scissors, EXPIRY),
+            PickResult::Revealed { initiator: Hand::Scissors, acceptor: Hand::Paper }));
+        assert_eq!(games.picked(&game), (false, false), "a revealed battle must be forgotten");
+    }
+
+    #[test]
+    fn test_a_draw_is_played_again() {
+        let games = RpsGames::default();
+        let game = game();
+        games.pick(game, game.initiator, Hand::Rock, EXPIRY);
+        assert!(matches!(games.pick(game, game.acceptor, Hand::Rock, EXPIRY), PickResult::Draw(Hand::Rock)));
+        assert_eq!(games.picked(&game), (false, false));
+        assert!(matches!(games.pick(game, game.initiator, Hand::Rock, EXPIRY), PickResult::Waiting));
+    }
+}
@@ -19,6 +19,7 @@ mod reload;
 mod bans;
 mod topics;
 mod cleanup;
+mod settings;
 mod cache;
 
 #[cfg(test)]
@@ -37,8 +38,8 @@ use cache::Cache;
 use config::AppConfig;
 use handlers::SupportService;
 use handlers::utils::SelfDestructionService;
-use crate::handlers::{checks, HandlerDeps, HelpCommands, LanguageCommands, LoanCommands, PrivacyCommands, PromoCommandState, StartCommands, SupportCommandState, SupportCommands};
-use crate::handlers::{CleanupCommands, DickCommands, DickOfDayCommands, ImportCommands, PromoCommands, TopicsCommands};
+use crate::handlers::{checks, HandlerDeps, HelpCommands, LanguageCommands, LoanCommands, GiftCommands, PrivacyCommands, PromoCommandState, RepayCommands, StartCommands, SupportCommandState, SupportCommands};
+use crate::handlers::{AchievementsCommands, CleanupCommands, DickCommands, DickOfDayCommands, GlobalTopCommands, HistoryCommands, ImportCommands, InviteCommands, LeagueCommands, PromoAdminCommands, PromoCommands, PvpTopCommands, RivalryCommands, RoyaleCommands, SeasonsCommands, SettingsCommands, ShopCommands, TimeZoneCommands, TopicsCommands, TournamentCommands};
 use crate::handlers::pvp::{BattleCommands, BattleCommandsNoArgs};
 use crate::handlers::stats::StatsCommands;
 use crate::handlers::utils::locks::LockCallbackServiceFacade;
@@ -67,6 +68,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let ban_list = bans::BanList::load(repos.users.clone()).await;
     let topic_policy = topics::TopicPolicy::new(app_config.caches.chat_topics, repos.chats.clone());
     let cleanup_policy = cleanup::CleanupPolicy::new(app_config.caches.chat_cleanup, repos.chats.clone());
+    let settings_policy = settings::GameSettingsPolicy::new(app_config.caches.chat_settings, repos.chats.clone());
 
     let handler = dptree::map_with_description(
         DpHandlerDescription::entry(),
@@ -98,10 +100,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .branch(checks::group_command::<DickOfDayCommands>().endpoint(handlers::dod_cmd_handler))
         .branch(checks::group_command::<BattleCommands>().endpoint(handlers::pvp::pvp_cmd_handler))
         .branch(checks::group_command::<BattleCommandsNoArgs>().endpoint(handlers::pvp::pvp_cmd_handler_no_args))
+        .branch(checks::group_command::<TournamentCommands>().endpoint(handlers::tournament::tournament_cmd_handler))
+        .branch(checks::group_command::<RoyaleCommands>().endpoint(handlers::royale::royale_cmd_handler))
         .branch(checks::group_command::<LoanCommands>().endpoint(handlers::loan::loan_cmd_handler))
+        .branch(checks::group_command::<RepayCommands>().endpoint(handlers::loan::repay_cmd_handler))
+        .branch(checks::group_command::<GiftCommands>().endpoint(handlers::gift::gift_cmd_handler))
+        .branch(checks::group_command::<ShopCommands>().endpoint(handlers::shop::shop_cmd_handler))
         .branch(checks::group_command::<ImportCommands>().endpoint(handlers::import_cmd_handler))
         .branch(checks::group_command::<CleanupCommands>().endpoint(handlers::cleanup::cleanup_cmd_handler))
+        .branch(checks::group_command::<SettingsCommands>().endpoint(handlers::settings::settings_cmd_handler))
+        .branch(checks::group_command::<TimeZoneCommands>().endpoint(handlers::timezone::timezone_cmd_handler))
         .branch(Update::filter_message().filter_command::<StatsCommands>().branch(checks::require_anchored_group()).endpoint(handlers::stats::stats_cmd_handler))
+        .branch(checks::group_command::<PvpTopCommands>().endpoint(handlers::pvptop::pvptop_cmd_handler))
+        .branch(checks::group_command::<LeagueCommands>().endpoint(handlers::league::league_cmd_handler))
+        .branch(checks::group_command::<SeasonsCommands>().endpoint(handlers::seasons::seasons_cmd_handler))
+        .branch(checks::group_command::<RivalryCommands>().endpoint(handlers::rivalry::rivalry_cmd_handler))
+        .branch(checks::group_command::<HistoryCommands>().endpoint(handlers::history::history_cmd_handler))
+        .branch(checks::group_command::<AchievementsCommands>().endpoint(handlers::achievements::achievements_cmd_handler))
+        .branch(Update::filter_message().filter_command::<PromoAdminCommands>().filter(handlers::promo_admin::is_owner).endpoint(handlers::promo_admin::promo_admin_cmd_handler))
+        .branch(Update::filter_message().filter_command::<GlobalTopCommands>().filter(checks::is_not_group_chat).endpoint(handlers::globaltop::globaltop_cmd_handler))
+        .branch(Update::filter_message().filter_command::<InviteCommands>().filter(checks::is_not_group_chat).endpoint(handlers::invite::invite_cmd_handler))
         .branch(Update::filter_message().filter_command::<PromoCommands>().filter(checks::is_not_group_chat).enter_dialogue::<Message, InMemStorage<PromoCommandState>, PromoCommandState>()
             .branch(dptree::case![PromoCommandState::Start].endpoint(handlers::promo_cmd_handler)))
         .branch(Update::filter_message().enter_dialogue::<Message, InMemStorage<PromoCommandState>, PromoCommandState>()
@@ -126,10 +144,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .branch(Update::filter_callback_query().filter(handlers::page_callback_filter).endpoint(handlers::page_callback_handler))
         .branch(Update::filter_callback_query().filter(handlers::shrink::callback_filter).endpoint(handlers::shrink::shrink_callback_handler))
         .branch(Update::filter_callback_query().filter(handlers::pvp::callback_filter).endpoint(handlers::pvp::pvp_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::pvp::decline_callback_filter).endpoint(handlers::pvp::decline_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::pvp::mercy_callback_filter).endpoint(handlers::pvp::mercy_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::pvp::rematch_callback_filter).endpoint(handlers::pvp::rematch_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::pvp::rps_callback_filter).endpoint(handlers::pvp::rps_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::pvptop::callback_filter).endpoint(handlers::pvptop::pvptop_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::globaltop::callback_filter).endpoint(handlers::globaltop::globaltop_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::tournament::callback_filter).endpoint(handlers::tournament::tournament_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::royale::callback_filter).endpoint(handlers::royale::royale_callback_handler))
         .branch(Update::filter_callback_query().filter(handlers::loan::callback_filter).endpoint(handlers::loan::loan_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::gift::callback_filter).endpoint(handlers::gift::gift_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::shop::callback_filter).endpoint(handlers::shop::shop_callback_handler))
         .branch(Update::filter_callback_query().filter(handlers::language::callback_filter).endpoint(handlers::language::language_callback_handler))
         .branch(Update::filter_callback_query().filter(handlers::topics::callback_filter).endpoint(handlers::topics::topics_callback_handler))
         .branch(Update::filter_callback_query().filter(handlers::cleanup::callback_filter).endpoint(handlers::cleanup::cleanup_callback_handler))
+        .branch(Update::filter_callback_query().filter(handlers::settings::callback_filter).endpoint(handlers::settings::settings_callback_handler))
         .branch(Update::filter_callback_query().endpoint(handlers::inline_callback_handler));
 
     let bot = config::BotConfig::build_bot()?;
@@ -142,6 +171,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         personal_language_enabled: language_service.user_service_enabled(),
         support_enabled: app_config.support_chat_id.is_some(),
         cleanup_enabled: app_config.self_destruction.configurable(),
+        invite_enabled: app_config.referral.enabled(),
+        seasons_enabled: app_config.seasons.enabled(),
     };
     let locales = _rust_i18n_available_locales();
     let set_my_commands_requests = locales
@@ -155,7 +186,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let me = bot.get_me().await?;
     let perks = handlers::perks::all(&db_conn, &app_config);
-    let incrementor = handlers::utils::Incrementor::new(app_config.incrementor.clone(), &repos.dicks, perks);
+    let incrementor = handlers::utils::Incrementor::new(app_config.incrementor.clone(), app_config.events.clone(),
+                                                        settings_policy.clone(), &repos.dicks, perks);
     let help_context = config::build_context_for_help_messages(&me, &incrementor, &handlers::ORIGINAL_BOT_USERNAMES)?;
     let help_container = help::render_help_messages(help_context)?;
     let battle_locker = LockCallbackServiceFacade::from_config(app_config.features);
@@ -169,19 +201,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (metrics_router, prometheus_layer) = metrics::init();
     metrics::register_db_pool_collector(db_conn.clone());
 
-    // Best-effort background job that shrinks inactive dicks at each UTC midnight. Spawned before
+    // Best-effort background job that shrinks inactive dicks at the midnight of each chat. Spawned before
     // `deps!` moves the shared services, and before the webhook/polling split so it runs in both.
     // One throttle for both schedulers: it counts the requests in a worker of its own, so a second
     // one would count a second budget and let twice as much through.
     // TODO: [#153] Use a common `Throttle` object shared between handlers and schedulers
     let throttled_bot = scheduler::throttled(bot.clone(), config::ThrottleConfig::from_env());
     scheduler::spawn_daily_shrink(repos.clone(), app_config.clone());
+    scheduler::spawn_podiums(repos.clone(), app_config.clone());
+    scheduler::spawn_seasons(repos.clone(), app_config.clone());
     scheduler::spawn_broadcast_worker(throttled_bot.clone(), repos.clone(), language_service.clone(),
                                       topic_policy.clone(), app_config.clone());
     scheduler::spawn_broadcast_cleaner(repos.clone(), app_config.clone());
     scheduler::spawn_deletion_worker(throttled_bot, repos.clone(), cache.clone(), app_config.clone());
     scheduler::spawn_deletion_cleaner(repos.clone(), app_config.clone());
-    reload::spawn_reload_on_sighup(repos.announcements.clone(), ban_list.clone());
+    reload::spawn_reload_on_sighup(repos.announcements.clone(), app_config.events.clone(), ban_list.clone());
     ban_list.spawn_refresh_task(app_config.caches.ban_list_refresh);
 
     let ignore_unknown_updates = |_| Box::pin(async {});
@@ -192,12 +226,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         app_config,
         help_container,
         battle_locker,
+        handlers::tournament::Tournaments::default(),
+        handlers::royale::Royales::default(),
+        handlers::utils::rps::RpsGames::default(),
         language_service,
         self_destruction,
         support_service,
         ban_list,
         topic_policy,
         cleanup_policy,
+        settings_policy,
         cache,
         InMemStorage::<PromoCommandState>::new(),
         InMemStorage::<SupportCommandState>::new()
@@ -7,6 +7,7 @@ use teloxide::types::UpdateKind;
 use tokio_metrics_collector::TaskMonitor;
 use domain_types::traits::SaturatingInto;
 use crate::config::MessageGroup;
+use crate::domain::enums::{Achievement, ShopItem};
 use crate::domain::primitives::{Count, SupportedLanguage};
 use crate::repo::{BroadcastState, ChatMigrationOutcome, DeletionState, MessageKind, ScheduledDeletion};
 
@@ -32,18 +33,48 @@ pub static CMD_TOP_COUNTER: Lazy<BothModesCounters> = Lazy::new(||
     BothModesCounters::new("command_top_usage_total", "count of /top invocations"));
 pub static CMD_LOAN_COUNTER: Lazy<BothModesComplexCommandCounters> = Lazy::new(||
     BothModesComplexCommandCounters::new("command_loan_usage_total", "count of /loan invocations"));
+pub static CMD_REPAY: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_repay_usage_total", "count of /repay invocations and presses of the repayment button"));
+pub static CMD_GIFT: Lazy<ComplexCommandCounters> = Lazy::new(||
+    ComplexCommandCounters::new("command_gift_usage_total", "count of /gift invocations and gifts or loans actually given", ["invoked", "finished"]));
 pub static CMD_DOD_COUNTER: Lazy<BothModesCounters> = Lazy::new(||
     BothModesCounters::new("command_dick_of_day_usage_total", "count of /dick_of_day invocations"));
 pub static CMD_PVP_COUNTER: Lazy<BothModesCounters> = Lazy::new(||
     BothModesCounters::new("command_pvp_usage_total", "count of /pvp invocations"));
+pub static PVP_MERCY_SHOWN: Lazy<Counter> = Lazy::new(||
+    Counter::new("pvp_mercy_shown_total", "count of battle awards given back by the winners"));
+pub static CMD_TOURNAMENT: Lazy<ComplexCommandCounters> = Lazy::new(||
+    ComplexCommandCounters::new("command_tournament_usage_total", "count of /tournament invocations and tournaments played till the champion", ["invoked", "finished"]));
+pub static CMD_ROYALE: Lazy<ComplexCommandCounters> = Lazy::new(||
+    ComplexCommandCounters::new("command_royale_usage_total", "count of /royale invocations and battle royales played till the winner", ["invoked", "finished"]));
 pub static CMD_STATS: Lazy<BothModesCounters> = Lazy::new(||
     BothModesCounters::new("command_stats_usage_total", "count of /stats invocations"));
+pub static CMD_PVPTOP: Lazy<BothModesCounters> = Lazy::new(||
+    BothModesCounters::new("command_pvptop_usage_total", "count of /pvptop invocations"));
+pub static CMD_GLOBALTOP: Lazy<BothModesCounters> = Lazy::new(||
+    BothModesCounters::new("command_globaltop_usage_total", "count of /globaltop invocations"));
+pub static CMD_LEAGUE: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_league_usage_total", "count of /league invocations"));
+pub static CMD_SEASONS: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_seasons_usage_total", "count of /seasons invocations"));
+pub static CMD_RIVALRY: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_rivalry_usage_total", "count of /rivalry invocations"));
+pub static CMD_HISTORY: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_history_usage_total", "count of /history invocations"));
+pub static CMD_ACHIEVEMENTS: Lazy<BothModesCounters> = Lazy::new(||
+    BothModesCounters::new("command_achievements_usage_total", "count of /achievements invocations"));
+pub static CMD_SHOP_COUNTER: Lazy<BothModesComplexCommandCounters> = Lazy::new(||
+    BothModesComplexCommandCounters::new("command_shop_usage_total", "count of /shop invocations"));
 pub static CMD_SHRINKS: Lazy<Counter> = Lazy::new(||
     Counter::new("command_shrinks_usage_total", "count of the inline shrinks command invocations"));
 pub static CMD_IMPORT: Lazy<ComplexCommandCounters> = Lazy::new(||
     ComplexCommandCounters::new("command_import_usage_total", "count of /import invocations and successes", ["invoked", "finished"]));
 pub static CMD_PROMO: Lazy<DeepLinkedCommandsCounters> = Lazy::new(||
     DeepLinkedCommandsCounters::new("command_promo_usage_total", "count of /promo invocations and successes"));
+pub static CMD_INVITE: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_invite_usage_total", "count of /invite invocations"));
+pub static CMD_PROMO_ADMIN: Lazy<Counter> = Lazy::new(||
+    Counter::new("command_promo_admin_usage_total", "count of the owner's /promo_* invocations"));
 pub static USER_SERVICE: Lazy<UserServiceCounters> = Lazy::new(||
     UserServiceCounters::new("user_service_get_total", "count of user-service get() resolutions, split by whether they were served from cache or sent over gRPC"));
 pub static CMD_LANGUAGE: Lazy<LanguageCommandCounters> = Lazy::new(||
@@ -56,8 +87,14 @@ pub static CHAT_TOPICS: Lazy<CacheSourceCounters> = Lazy::new(||
     CacheSourceCounters::new("chat_topics_get_total", "count of allowed-topics lookups, split by whether they were served from cache or read from the database"));
 pub static CMD_CLEANUP: Lazy<ComplexCommandCounters> = Lazy::new(||
     ComplexCommandCounters::new("command_cleanup_usage_total", "count of /cleanup invocations and changes of the setting", ["invoked", "finished"]));
+pub static CMD_SETTINGS: Lazy<ComplexCommandCounters> = Lazy::new(||
+    ComplexCommandCounters::new("command_settings_usage_total", "count of /settings invocations and changes of the rules", ["invoked", "finished"]));
+pub static CMD_TIMEZONE: Lazy<ComplexCommandCounters> = Lazy::new(||
+    ComplexCommandCounters::new("command_timezone_usage_total", "count of /timezone invocations and changes of the time zone of a chat", ["invoked", "finished"]));
 pub static CHAT_CLEANUP: Lazy<CacheSourceCounters> = Lazy::new(||
     CacheSourceCounters::new("chat_cleanup_get_total", "count of per-chat cleanup-setting lookups, split by whether they were served from cache or read from the database"));
+pub static CHAT_SETTINGS: Lazy<CacheSourceCounters> = Lazy::new(||
+    CacheSourceCounters::new("chat_settings_get_total", "count of per-chat game-setting lookups, split by whether they were served from cache or read from the database"));
 pub static BOT_ADMIN_LOOKUP: Lazy<CacheLookupCounters> = Lazy::new(||
     CacheLookupCounters::new("bot_admin_lookup_total", "count of lookups of the bot's right to delete messages in a chat, split by whether the cache knew the answer"));
 pub static BROADCAST_LANGUAGE: Lazy<BroadcastLanguageCounter> = Lazy::new(||
@@ -88,11 +125,17 @@ pub static SELF_DESTRUCTION_FINISHED: Lazy<SelfDestructionFinishedGauges> = Lazy
     SelfDestructionFinishedGauges::new("self_destruction_finished", "number of self-destructions that are done with and kept for inspection until the cleaning process runs, by state: removed (the bot took the message down), removed_before (someone else had already done it), expired (the message outlived Telegram's 48-hour limit while it waited) or failed (every attempt was refused)"));
 pub static ANNOUNCEMENT_SHOWN: Lazy<AnnouncementCounter> = Lazy::new(||
     AnnouncementCounter::new("announcement_shown_total", "count of announcements shown at the end of the Dick of the Day message, split by the recipient's language"));
+pub static ACHIEVEMENT_UNLOCKED: Lazy<AchievementCounter> = Lazy::new(||
+    AchievementCounter::new("achievement_unlocked_total", "count of achievements unlocked by the players, split by the achievement"));
+pub static SHOP_ITEM_BOUGHT: Lazy<ShopItemCounter> = Lazy::new(||
+    ShopItemCounter::new("shop_item_bought_total", "count of items bought in the shop, split by the item"));
+pub static SHOP_ITEM_USED: Lazy<ShopItemCounter> = Lazy::new(||
+    ShopItemCounter::new("shop_item_used_total", "count of bought items used up by the handlers they belong to, split by the item"));
 pub static CHAT_MIGRATION: Lazy<ChatMigrationCounter> = Lazy::new(||
     ChatMigrationCounter::new("chat_migration_total", "count of group to supergroup migrations the bot witnessed, by outcome: migrated when the chat came across whole, migrated_unanchored when it came across but left its inline half behind, untraceable when it wasn't known by its old id at all, conflict when both ids already had a row of their own"));
 pub static DAILY_SHRINK: Lazy<DailyShrinkCounters> = Lazy::new(DailyShrinkCounters::new);
 pub static DAILY_SHRINK_LAST_RUN_TIMESTAMP: Lazy<Gauge> = Lazy::new(||
-    Gauge::new("daily_shrink_last_run_timestamp_seconds", "the UTC midnight the last logged shrink belongs to, as a Unix timestamp. Read from the database rather than counted in this process, so it survives a restart: alert when time() minus this passes 26 hours"));
+    Gauge::new("daily_shrink_last_run_timestamp_seconds", "the moment the last logged shrink was made, as a Unix timestamp. Read from the database rather than counted in this process, so it survives a restart: alert when time() minus this passes 26 hours"));
 pub static DAILY_SHRINK_BROADCAST_PENDING: Lazy<Gauge> = Lazy::new(||
     Gauge::new("daily_shrink_broadcast_pending", "number of shrink summaries the chats are still owed; a number that only grows means the worker stopped draining the queue"));
 pub static DAILY_SHRINK_BROADCAST_BATCH_SIZE: Lazy<Histogram> = Lazy::new(||
@@ -101,6 +144,10 @@ pub static DAILY_SHRINK_BROADCAST_BATCH_SIZE: Lazy<Histogram> = Lazy::new(||
         &[0.0, 1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0]));
 pub static DAILY_SHRINK_BROADCAST_BATCH_LIMIT: Lazy<Gauge> = Lazy::new(||
     Gauge::new("daily_shrink_broadcast_batch_limit", "the value of DAILY_SHRINK_BROADCAST_BATCH_SIZE, so that a graph can tell a full batch from a small one without knowing the setting"));
+pub static PODIUMS_QUEUED: Lazy<Counter> = Lazy::new(||
+    Counter::new("podiums_queued_total", "count of the podiums of a week or a month queued for the chats at the end of the period. They are sent by the shrink broadcast worker and counted with the shrink summaries from then on"));
+pub static SEASONS_ENDED: Lazy<Counter> = Lazy::new(||
+    Counter::new("seasons_ended_total", "count of the seasons ended in the chats, each of which archived the lengths of its members and reset them to zero"));
 pub static TELEGRAM_REQUEST_ERRORS: Lazy<TelegramRequestErrorCounters> = Lazy::new(||
     TelegramRequestErrorCounters::new("telegram_request_errors_total", "count of failed requests to the Telegram Bot API, split by kind (connect/timeout/network/api/rate_limited/other). A spike of connect/timeout is the DPI-stalling signal; rate_limited means Telegram asked the bot to slow down, so the THROTTLE_* limits are set too high"));
 pub static TELEGRAM_REQUEST_DURATION: Lazy<TelegramRequestDuration> = Lazy::new(||
@@ -124,6 +171,8 @@ pub static TASK_WEBHOOK_SERVER: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("w
 pub static TASK_POLLING_DISPATCHER: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("polling_dispatcher"));
 pub static TASK_METRICS_SERVER: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("metrics_http_server"));
 pub static TASK_DAILY_SHRINK: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("daily_shrink"));
+pub static TASK_PODIUMS: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("podiums"));
+pub static TASK_SEASONS: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("seasons"));
 pub static TASK_DAILY_SHRINK_BROADCAST: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("daily_shrink_broadcast"));
 pub static TASK_DAILY_SHRINK_BROADCAST_CLEANING: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("daily_shrink_broadcast_cleaning"));
 pub static TASK_SELF_DESTRUCTION: Lazy<TaskMonitor> = Lazy::new(|| task_monitor("self_destruction"));
@@ -174,19 +223,37 @@ fn force_registration() {
     Lazy::force(&CMD_GROW_COUNTER);
     Lazy::force(&CMD_TOP_COUNTER);
     Lazy::force(&CMD_LOAN_COUNTER);
+    Lazy::force(&CMD_REPAY);
+    Lazy::force(&CMD_GIFT);
     Lazy::force(&CMD_DOD_COUNTER);
     Lazy::force(&CMD_PVP_COUNTER);
+    Lazy::force(&PVP_MERCY_SHOWN);
+    Lazy::force(&CMD_TOURNAMENT);
+    Lazy::force(&CMD_ROYALE);
     Lazy::force(&CMD_STATS);
+    Lazy::force(&CMD_PVPTOP);
+    Lazy::force(&CMD_GLOBALTOP);
+    Lazy::force(&CMD_LEAGUE);
+    Lazy::force(&CMD_SEASONS);
+    Lazy::force(&CMD_RIVALRY);
+    Lazy::force(&CMD_HISTORY);
+    Lazy::force(&CMD_ACHIEVEMENTS);
+    Lazy::force(&CMD_SHOP_COUNTER);
     Lazy::force(&CMD_SHRINKS);
     Lazy::force(&CMD_IMPORT);
     Lazy::force(&CMD_PROMO);
+    Lazy::force(&CMD_INVITE);
+    Lazy::force(&CMD_PROMO_ADMIN);
     Lazy::force(&USER_SERVICE);
     Lazy::force(&CMD_LANGUAGE);
     Lazy::force(&CHAT_LANGUAGE);
     Lazy::force(&CMD_TOPICS);
     Lazy::force(&CHAT_TOPICS);
     Lazy::force(&CMD_CLEANUP);
+    Lazy::force(&CMD_SETTINGS);
+    Lazy::force(&CMD_TIMEZONE);
     Lazy::force(&CHAT_CLEANUP);
+    Lazy::force(&CHAT_SETTINGS);
     Lazy::force(&BOT_ADMIN_LOOKUP);
     Lazy::force(&BROADCAST_LANGUAGE);
     Lazy::force(&USER_SERVICE_LANGUAGES_BATCH_SIZE);
@@ -196,8 +263,13 @@ fn force_registration() {
     Lazy::force(&SELF_DESTRUCTION);
     Lazy::force(&SELF_DESTRUCTION_RETRIES);
     Lazy::force(&ANNOUNCEMENT_SHOWN);
+    Lazy::force(&ACHIEVEMENT_UNLOCKED);
+    Lazy::force(&SHOP_ITEM_BOUGHT);
+    Lazy::force(&SHOP_ITEM_USED);
     Lazy::force(&CHAT_MIGRATION);
     Lazy::force(&DAILY_SHRINK);
+    Lazy::force(&PODIUMS_QUEUED);
+    Lazy::force(&SEASONS_ENDED);
     Lazy::force(&TELEGRAM_REQUEST_ERRORS);
     Lazy::force(&TELEGRAM_REQUEST_DURATION);
     Lazy::force(&TELEGRAM_THROTTLE_QUEUE_FULL);
@@ -763,6 +835,44 @@ impl AnnouncementCounter {
     }
 }
 
+/// Counts the achievements unlocked by the players, labeled by the [`Achievement`]. An achievement
+/// is unlocked once per player and chat, so this is how many such pairs reached it, not how often.
+pub struct AchievementCounter(CounterVec);
+
+impl AchievementCounter {
+    fn new(name: &str, help: &str) -> Self {
+        let vec = CounterVec::new(name, help, &["achievement"]);
+        for achievement in Achievement::iter() {
+            vec.counter(&[&achievement.to_string()]);
+        }
+        Self(vec)
+    }
+
+    /// Record one achievement unlocked by one player in one chat.
+    pub fn record(&self, achievement: Achievement) {
+        self.0.counter(&[&achievement.to_string()]).inc()
+    }
+}
+
+/// Counts the items of the shop, labeled by the [`ShopItem`]. The same type serves both ends of
+/// an item's life, so that the bought and the used ones can be put side by side.
+pub struct ShopItemCounter(CounterVec);
+
+impl ShopItemCounter {
+    fn new(name: &str, help: &str) -> Self {
+        let vec = CounterVec::new(name, help, &["item"]);
+        for item in ShopItem::iter() {
+            vec.counter(&[&item.to_string()]);
+        }
+        Self(vec)
+    }
+
+    /// Record one item bought or used.
+    pub fn record(&self, item: ShopItem) {
+        self.0.counter(&[&item.to_string()]).inc()
+    }
+}
+
 /// Counts the group to supergroup migrations the bot witnessed, labeled by what became of the
 /// chat. A migration is announced in both chats at once; only the announcement that lands in the
 /// new supergroup is counted, so one migration is one sample.
@@ -0,0 +1,105 @@
+use autometrics::autometrics;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use num_traits::ToPrimitive;
+use crate::domain::enums::Achievement;
+use crate::domain::objects::{AchievementProgress, UnlockedAchievement};
+use crate::domain::primitives::{ElectionsCount, Length, UserId, WinStreak};
+use crate::repo::ChatIdKind;
+use crate::repository;
+
+struct AchievementProgressEntity {
+    length: Option<i64>,
+    win_streak_max: Option<i16>,
+    loan_repaid: bool,
+    dod_elections: i64,
+}
+
+struct UnlockedAchievementEntity {
+    achievement: Achievement,
+    unlocked_at: DateTime<Utc>,
+}
+
+impl TryFrom<AchievementProgressEntity> for AchievementProgress {
+    type Error = anyhow::Error;
+
+    fn try_from(entity: AchievementProgressEntity) -> anyhow::Result<Self> {
+        Ok(Self {
+            length: Length::new(entity.length.unwrap_or_default()),
+            win_streak_max: entity.win_streak_max.unwrap_or_default().to_u16().map(WinStreak::new)
+                .context("win_streak_max, fetched from the database, must not be negative")?,
+            loan_repaid: entity.loan_repaid,
+            dod_elections: entity.dod_elections.to_u32().map(ElectionsCount::new)
+                .context("the count of elections, fetched from the database, must fit into u32")?,
+        })
+    }
+}
+
+impl From<UnlockedAchievementEntity> for UnlockedAchievement {
+    fn from(entity: UnlockedAchievementEntity) -> Self {
+        Self {
+            achievement: entity.achievement,
+            unlocked_at: entity.unlocked_at,
+        }
+    }
+}
+
+repository!(Achievements,
+    /// Each part is a lookup by the leading columns of a primary key or an index, so reading all
+    /// of them after every event costs about as much as the event itself.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, uid = uid.value()))]
+    pub async fn get_progress(&self, chat_id: &ChatIdKind, uid: UserId) -> anyhow::Result<AchievementProgress> {
+        sqlx::query_as!(AchievementProgressEntity,
+            r#"WITH c AS (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+               SELECT
+                   (SELECT length FROM Dicks WHERE chat_id = (SELECT id FROM c) AND uid = $2) AS length,
+                   (SELECT win_streak_max FROM Battle_Stats WHERE chat_id = (SELECT id FROM c) AND uid = $2) AS win_streak_max,
+                   EXISTS (SELECT 1 FROM Loans WHERE chat_id = (SELECT id FROM c) AND uid = $2
+                                                 AND repaid_at IS NOT NULL) AS "loan_repaid!",
+                   (SELECT count(*) FROM Dick_of_Day WHERE chat_id = (SELECT id FROM c) AND winner_uid = $2) AS "dod_elections!""#,
+                chat_id.value() as String, uid as UserId)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't get the achievement progress of {uid} in {chat_id}"))?
+            .try_into()
+    }
+,
+    /// Returns only the achievements that weren't unlocked before, so the caller announces each of
+    /// them once however many times it asks.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, uid = uid.value(), achievements = ?achievements))]
+    pub async fn unlock(&self, chat_id: &ChatIdKind, uid: UserId, achievements: &[Achievement]) -> anyhow::Result<Vec<Achievement>> {
+        if achievements.is_empty() {
+            return Ok(Vec::new())
+        }
+        let mut unlocked = sqlx::query_scalar!(
+            r#"INSERT INTO Achievements (chat_id, uid, achievement)
+                SELECT c.id, $2, a.achievement
+                    FROM Chats c, unnest($3::achievement[]) AS a(achievement)
+                    WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text
+                ON CONFLICT (chat_id, uid, achievement) DO NOTHING
+                RETURNING achievement AS "achievement: Achievement""#,
+                chat_id.value() as String, uid as UserId, achievements as &[Achievement])
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't unlock the achievements {achievements:?} for {uid} in {chat_id}"))?;
+        unlocked.sort();
+        Ok(unlocked)
+    }
+,
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, uid = uid.value()))]
+    pub async fn get_unlocked(&self, chat_id: &ChatIdKind, uid: UserId) -> anyhow::Result<Vec<UnlockedAchievement>> {
+        sqlx::query_as!(UnlockedAchievementEntity,
+            r#"SELECT achievement AS "achievement: Achievement", unlocked_at FROM Achievements
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                  AND uid = $2
+                ORDER BY achievement"#,
+                chat_id.value() as String, uid as UserId)
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| rows.into_iter().map(UnlockedAchievement::from).collect())
+            .context(format!("couldn't get the achievements of {uid} in {chat_id}"))
+    }
+);
@@ -1,8 +1,10 @@
 use autometrics::autometrics;
 use anyhow::Context;
 use chrono::{DateTime, NaiveDate, Utc};
+use crate::domain::enums::TopPeriod;
 use crate::domain::primitives::{AttemptsCount, Count, Limit, ScheduledBroadcastId};
-use crate::domain::primitives::chat::TelegramChatId;
+use crate::domain::primitives::chat::{InternalChatId, TelegramChatId};
+use crate::repo::Chat;
 use crate::repository;
 
 /// How far a summary got. `Created` is the only actionable one; the rest are terminal and stay in
@@ -29,6 +31,39 @@ impl BroadcastState {
     pub const TERMINAL: [Self; 4] = [Self::Sent, Self::Unreachable, Self::Expired, Self::Failed];
 }
 
+/// What a queued message is about. The queue was made for the shrink summaries and is shared by
+/// the podiums since: the delivery is the same, and so are the metrics of it.
+///
+/// The snake_case spelling is shared by the `broadcast_kind` enum of the database and the log field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+#[sqlx(type_name = "broadcast_kind", rename_all = "snake_case")]
+pub enum BroadcastKind {
+    Shrink,
+    WeekPodium,
+    MonthPodium,
+}
+
+impl BroadcastKind {
+    /// The period whose podium this is, if it's one at all.
+    pub fn podium_period(self) -> Option<TopPeriod> {
+        match self {
+            Self::Shrink => None,
+            Self::WeekPodium => Some(TopPeriod::Week),
+            Self::MonthPodium => Some(TopPeriod::Month),
+        }
+    }
+}
+
+impl From<TopPeriod> for BroadcastKind {
+    fn from(period: TopPeriod) -> Self {
+        match period {
+            TopPeriod::Week => Self::WeekPodium,
+            TopPeriod::Month => Self::MonthPodium,
+        }
+    }
+}
+
 /// A summary a chat is owed, as the worker claims it.
 #[derive(Clone, Debug)]
 pub struct ScheduledBroadcast {
@@ -36,6 +71,8 @@ pub struct ScheduledBroadcast {
     /// Read at claim time rather than stored, so a group that became a supergroup meanwhile is
     /// addressed by the id it answers to now.
     pub chat_id: TelegramChatId,
+    pub kind: BroadcastKind,
+    /// The day of the shrinks, or the first day of the period of a podium.
     pub shrink_date: NaiveDate,
     /// When the shrink that owes this summary was committed, which is the summary's age.
     pub created_at: DateTime<Utc>,
@@ -66,7 +103,7 @@ repository!(ScheduledBroadcasts,
                 )
                 RETURNING b.id AS "id: ScheduledBroadcastId",
                           (SELECT c.chat_id FROM Chats c WHERE c.id = b.chat_id) AS "chat_id: TelegramChatId",
-                          b.shrink_date, b.created_at, b.attempts AS "attempts!: AttemptsCount""#,
+                          b.kind AS "kind: BroadcastKind", b.shrink_date, b.created_at, b.attempts AS "attempts!: AttemptsCount""#,
             limit as Limit, lease_until
         )
             .fetch_all(&self.pool)
@@ -85,6 +122,7 @@ repository!(ScheduledBroadcasts,
             claimed.push(ScheduledBroadcast {
                 id: row.id,
                 chat_id,
+                kind: row.kind,
                 shrink_date: row.shrink_date,
                 created_at: row.created_at,
                 attempts: row.attempts,
@@ -93,6 +131,43 @@ repository!(ScheduledBroadcasts,
         Ok(claimed)
     },
 
+    /// Queues the podium of `period` for those of `chat_ids` where somebody has gained anything
+    /// over it, and says for how many. Like the shrink summaries, the chats that can't be messaged
+    /// or have been lost get nothing; unlike them, the podium isn't rendered until it's sent.
+    ///
+    /// Queueing the same podium again does nothing, so a run that is repeated costs no messages.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chats = chat_ids.len(), period = %period, since = %since, until = %until))]
+    pub async fn queue_podiums(
+        &self,
+        chat_ids: &[InternalChatId],
+        period: TopPeriod,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> anyhow::Result<Count<Chat>> {
+        sqlx::query_scalar!(
+            r#"WITH gainers AS (
+                    SELECT e.chat_id FROM Length_Events e
+                    WHERE e.chat_id = ANY($1) AND e.created_at >= $2::date AND e.created_at < $3::date
+                      AND e.reason NOT IN ('import', 'season')
+                    GROUP BY e.chat_id, e.uid
+                    HAVING SUM(e.change) > 0
+                ),
+                queued AS (
+                    INSERT INTO Scheduled_Shrink_Broadcasts (chat_id, shrink_date, kind)
+                    SELECT DISTINCT g.chat_id, $2::date, $4::broadcast_kind FROM gainers g
+                    JOIN Chats c ON c.id = g.chat_id
+                    WHERE c.chat_id IS NOT NULL AND NOT c.is_unreachable
+                    ON CONFLICT DO NOTHING
+                    RETURNING chat_id
+                )
+                SELECT count(*) AS "count!: Count<Chat>" FROM queued"#,
+                chat_ids as &[InternalChatId], since, until, BroadcastKind::from(period) as BroadcastKind)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't queue the podiums of the {period} since {since}"))
+    },
+
     /// How many summaries are still owed — reported as a gauge, so a queue that stops draining is
     /// visible before the chats are. The finished rows are left out: they are history, and counting
     /// them would make the gauge grow on its own until the cleaning process runs.
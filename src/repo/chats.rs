@@ -2,10 +2,12 @@ use autometrics::autometrics;
 use std::fmt::Formatter;
 use std::str::FromStr;
 use anyhow::{bail, Context};
+use chrono::{FixedOffset, NaiveDate};
 use sqlx::{Postgres, Transaction};
-use crate::domain::enums::MessageGroup;
-use crate::domain::objects::{AllowedTopics, ChatCleanupSettings};
-use crate::domain::primitives::{DelayMinutes, SupportedLanguage};
+use domain_types::traits::SaturatingInto;
+use crate::domain::enums::{GameSetting, LeagueRanking, MessageGroup};
+use crate::domain::objects::{AllowedTopics, ChatCleanupSettings, ChatGameSettings, ChatTimeZone, Dick, LeagueStanding};
+use crate::domain::primitives::{Count, DelayMinutes, Length, Limit, Position, SupportedLanguage};
 use crate::domain::primitives::chat::{ChatIdFull, ChatIdKind, ChatIdPartiality, ChatIdSource, InternalChatId, TelegramChatId, TelegramChatInstanceId, TopicId};
 use crate::repo::ensure_only_one_row_updated;
 use crate::repository;
@@ -24,6 +26,28 @@ pub struct Chat {
     pub is_unreachable: bool,
 }
 
+struct LeagueStandingEntity {
+    chat_id: InternalChatId,
+    title: String,
+    members: Count<Dick>,
+    length: i64,
+    position: i64,
+    is_current: bool,
+}
+
+impl From<LeagueStandingEntity> for LeagueStanding {
+    fn from(entity: LeagueStandingEntity) -> Self {
+        Self {
+            chat_id: entity.chat_id,
+            title: entity.title,
+            members: entity.members,
+            length: Length::new(entity.length),
+            position: Position::new(entity.position.saturating_into()),
+            is_current: entity.is_current,
+        }
+    }
+}
+
 #[derive(Debug, derive_more::Error, derive_more::Display)]
 pub struct NoChatIdError(#[error(not(source))] InternalChatId);
 
@@ -103,6 +127,32 @@ fn parse_cleanup_settings(value: sqlx::types::JsonValue) -> ChatCleanupSettings
     ChatCleanupSettings::new(delays, inline)
 }
 
+/// Reads the `game` object of `Chats.settings` — a setting-keyed map of the values the chat chose,
+/// `{"top_limit": "15"}`. The values are written as strings, but a number or a boolean put there by
+/// hand is read just as well; anything else is skipped with a warning.
+fn parse_game_settings(value: sqlx::types::JsonValue) -> ChatGameSettings {
+    let sqlx::types::JsonValue::Object(entries) = value else {
+        tracing::warn!(value = ?value, "the game settings of a chat are not an object");
+        return ChatGameSettings::default()
+    };
+    let mut settings = ChatGameSettings::default();
+    for (name, value) in entries {
+        let Ok(setting) = GameSetting::from_str(&name) else {
+            tracing::warn!(setting = %name, "an unknown game setting is stored for the chat");
+            continue
+        };
+        let text = match &value {
+            sqlx::types::JsonValue::String(text) => text.clone(),
+            sqlx::types::JsonValue::Number(_) | sqlx::types::JsonValue::Bool(_) => value.to_string(),
+            _ => String::default(),
+        };
+        if !settings.set(setting, &text) {
+            tracing::warn!(setting = %name, value = ?value, "an invalid value of a game setting is stored for the chat");
+        }
+    }
+    settings
+}
+
 impl ChatMigrationOutcome {
     /// Which of the two "the row moved" outcomes applies, given the instance that row held.
     fn of_migrated(instance: Option<&TelegramChatInstanceId>) -> Self {
@@ -408,6 +458,236 @@ repository!(Chats, with_feature_toggles,
             .context(format!("couldn't reset the cleanup settings of the chat {chat_id}"))?;
         Ok(())
     }
+,
+    /// What the chat changed of the rules of the game. A default [`ChatGameSettings`] means it
+    /// plays by the bot's own configuration.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn get_game_settings(&self, chat_id: &ChatIdKind) -> anyhow::Result<ChatGameSettings> {
+        let settings = sqlx::query_scalar!(
+                "SELECT settings->'game' FROM Chats
+                    WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+                chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get the game settings of the chat with id = {chat_id}"))?
+            .flatten();
+        Ok(settings.map(parse_game_settings).unwrap_or_default())
+    }
+,
+    /// Writes down one rule the chat changed, leaving the others as they were. The value is
+    /// expected to be accepted by [`ChatGameSettings::set`] already.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, setting = %setting, value = %value))]
+    pub async fn set_game_setting(
+        &self,
+        chat_id: &ChatIdPartiality,
+        setting: GameSetting,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!(
+                "UPDATE Chats SET settings = jsonb_set(settings, '{game}',
+                    COALESCE(settings->'game', '{}'::jsonb) || jsonb_build_object($2::text, $3::text))
+                    WHERE id = $1",
+                internal_id as InternalChatId, setting.to_string(), value)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't set the {setting} game setting of the chat {chat_id}"))?;
+        Ok(())
+    }
+,
+    /// Drops one rule the chat changed. As with the cleanup, the last one takes the key with it.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, setting = %setting))]
+    pub async fn forget_game_setting(
+        &self,
+        chat_id: &ChatIdPartiality,
+        setting: GameSetting,
+    ) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!(
+                "UPDATE Chats SET settings = CASE
+                    WHEN (settings->'game') - $2::text = '{}'::jsonb THEN settings - 'game'
+                    ELSE jsonb_set(settings, '{game}', (settings->'game') - $2::text)
+                    END
+                    WHERE id = $1 AND settings->'game' IS NOT NULL",
+                internal_id as InternalChatId, setting.to_string())
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't forget the {setting} game setting of the chat {chat_id}"))?;
+        Ok(())
+    }
+,
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn reset_game_settings(&self, chat_id: &ChatIdPartiality) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!("UPDATE Chats SET settings = settings - 'game' WHERE id = $1",
+                internal_id as InternalChatId)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't reset the game settings of the chat {chat_id}"))?;
+        Ok(())
+    }
+,
+    /// The `league` object of `Chats.settings` marks a chat that has joined the league and keeps
+    /// its title, which is nowhere else in the database. Joining again only refreshes the title.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn join_league(&self, chat_id: &ChatIdPartiality, title: &str) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!(
+                "UPDATE Chats SET settings = jsonb_set(settings, '{league}', jsonb_build_object('title', $2::text))
+                    WHERE id = $1",
+                internal_id as InternalChatId, title)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't make the chat {chat_id} join the league"))?;
+        Ok(())
+    }
+,
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn leave_league(&self, chat_id: &ChatIdPartiality) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        sqlx::query!("UPDATE Chats SET settings = settings - 'league' WHERE id = $1",
+                internal_id as InternalChatId)
+            .execute(&self.pool)
+            .await
+            .context(format!("couldn't make the chat {chat_id} leave the league"))?;
+        Ok(())
+    }
+,
+    /// The `season` object of `Chats.settings` keeps the last day of the current season when the
+    /// admins have set it, and `None` forgets it. The seasons scheduler drops it once that day is
+    /// over, together with the season.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, ends_on = ?ends_on))]
+    pub async fn set_season_end(&self, chat_id: &ChatIdPartiality, ends_on: Option<NaiveDate>) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        match ends_on {
+            Some(date) => sqlx::query!(
+                    "UPDATE Chats SET settings = jsonb_set(settings, '{season}', jsonb_build_object('ends_on', $2::date))
+                        WHERE id = $1",
+                    internal_id as InternalChatId, date)
+                .execute(&self.pool)
+                .await
+                .context(format!("couldn't set the end of the season of the chat {chat_id} to {date}"))?,
+            None => sqlx::query!("UPDATE Chats SET settings = settings - 'season' WHERE id = $1",
+                    internal_id as InternalChatId)
+                .execute(&self.pool)
+                .await
+                .context(format!("couldn't clear the end of the season of the chat {chat_id}"))?,
+        };
+        Ok(())
+    }
+,
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn get_season_end(&self, chat_id: &ChatIdKind) -> anyhow::Result<Option<NaiveDate>> {
+        sqlx::query_scalar!(
+                "SELECT (settings->'season'->>'ends_on')::date FROM Chats
+                    WHERE chat_id = $1::bigint OR chat_instance = $1::text",
+                chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::flatten)
+            .context(format!("couldn't get the end of the season of the chat with id = {chat_id}"))
+    }
+,
+    /// The canonical spelling of an IANA time zone Postgres knows, looked up case-insensitively, or
+    /// `None` if there's no such zone. The abbreviations like `MSK` aren't names and aren't found.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(name = name))]
+    pub async fn find_time_zone(&self, name: &str) -> anyhow::Result<Option<String>> {
+        sqlx::query_scalar!(
+                r#"SELECT name AS "name!" FROM pg_timezone_names
+                    WHERE lower(name) = lower($1) AND name NOT LIKE 'posix/%'
+                    ORDER BY name LIMIT 1"#,
+                name)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't look up the time zone {name}"))
+    }
+,
+    /// The `timezone` key of `Chats.settings` keeps the IANA name of the zone the days of the chat
+    /// start in, and `None` forgets it, which brings the chat back to UTC. The name must be one
+    /// [`Chats::find_time_zone`] has returned: the triggers of the dicks and the Dick of the Day
+    /// read it on every growth and election.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, time_zone = ?time_zone))]
+    pub async fn set_time_zone(&self, chat_id: &ChatIdPartiality, time_zone: Option<&str>) -> anyhow::Result<()> {
+        let internal_id = self.upsert_chat(chat_id).await?;
+        match time_zone {
+            Some(name) => sqlx::query!(
+                    "UPDATE Chats SET settings = jsonb_set(settings, '{timezone}', to_jsonb($2::text)) WHERE id = $1",
+                    internal_id as InternalChatId, name)
+                .execute(&self.pool)
+                .await
+                .context(format!("couldn't set the time zone of the chat {chat_id} to {name}"))?,
+            None => sqlx::query!("UPDATE Chats SET settings = settings - 'timezone' WHERE id = $1",
+                    internal_id as InternalChatId)
+                .execute(&self.pool)
+                .await
+                .context(format!("couldn't clear the time zone of the chat {chat_id}"))?,
+        };
+        Ok(())
+    }
+,
+    /// The time zone of the chat with its current offset from UTC, which Postgres knows the daylight
+    /// saving time for. A chat the bot hasn't seen yet lives in UTC.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn get_time_zone(&self, chat_id: &ChatIdKind) -> anyhow::Result<ChatTimeZone> {
+        let maybe_row = sqlx::query!(
+                r#"SELECT settings->>'timezone' AS name,
+                        EXTRACT(EPOCH FROM (current_timestamp AT TIME ZONE coalesce(settings->>'timezone', 'UTC'))
+                                         - (current_timestamp AT TIME ZONE 'UTC'))::int AS "utc_offset!"
+                    FROM Chats
+                    WHERE chat_id = $1::bigint OR chat_instance = $1::text"#,
+                chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get the time zone of the chat with id = {chat_id}"))?;
+        let Some(row) = maybe_row else {
+            return Ok(ChatTimeZone::default())
+        };
+        let utc_offset = FixedOffset::east_opt(row.utc_offset)
+            .context(format!("the offset of the time zone {:?} is out of range: {}", row.name, row.utc_offset))?;
+        Ok(ChatTimeZone { name: row.name, utc_offset })
+    }
+,
+    /// The first `limit` chats of the league and the one asking, wherever it stands, best first.
+    /// A chat that has joined but has nobody playing yet is ranked with a zero length, last.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, ranking = %ranking, limit = %limit))]
+    pub async fn get_league_standings(&self, chat_id: &ChatIdKind, ranking: LeagueRanking, limit: Limit) -> anyhow::Result<Vec<LeagueStanding>> {
+        sqlx::query_as!(LeagueStandingEntity,
+            r#"SELECT id AS "chat_id!: InternalChatId", title AS "title!", members AS "members!: Count<Dick>",
+                    length AS "length!", position AS "position!", is_current AS "is_current!"
+                FROM (
+                    SELECT id, title, members, length, is_current,
+                           ROW_NUMBER() OVER (ORDER BY length DESC, members DESC, id) AS position
+                    FROM (
+                        SELECT c.id, c.settings->'league'->>'title' AS title, count(d.uid) AS members,
+                               CASE WHEN $2 = 'sum' THEN coalesce(sum(d.length), 0)::bigint
+                                    ELSE coalesce(round(avg(d.length)), 0)::bigint END AS length,
+                               coalesce(c.chat_id = $1::bigint OR c.chat_instance = $1::text, false) AS is_current
+                        FROM Chats c
+                        LEFT JOIN Dicks d ON d.chat_id = c.id
+                        WHERE c.settings->'league' IS NOT NULL
+                        GROUP BY c.id
+                    ) lengths
+                ) ranked
+                WHERE position <= $3 OR is_current
+                ORDER BY position"#,
+                chat_id.value() as String, ranking.to_string(), limit as Limit)
+            .fetch_all(&self.pool)
+            .await
+            .map(|standings| standings.into_iter().map(LeagueStanding::from).collect())
+            .context(format!("couldn't get the {ranking} league standings for the chat {chat_id} with limit = {limit}"))
+    }
 ,
     #[autometrics]
     #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
@@ -627,14 +907,20 @@ repository!(Chats, with_feature_toggles,
         deleted_id: InternalChatId,
     ) -> anyhow::Result<()> {
         let loans = Self::move_loans(tx, main_id, deleted_id).await?;
+        let member_loans = Self::move_member_loans(tx, main_id, deleted_id).await?;
         let battle_stats = Self::move_battle_stats(tx, main_id, deleted_id).await?;
+        let battle_log = Self::move_battle_log(tx, main_id, deleted_id).await?;
         let announcements = Self::move_announcements(tx, main_id, deleted_id).await?;
         let imports = Self::move_imports(tx, main_id, deleted_id).await?;
         let dod = Self::move_dicks_of_the_day(tx, main_id, deleted_id).await?;
         let shrinks = Self::move_shrinks(tx, main_id, deleted_id).await?;
         let migrations = Self::move_chat_migrations(tx, main_id, deleted_id).await?;
+        let achievements = Self::move_achievements(tx, main_id, deleted_id).await?;
+        let inventory = Self::move_inventory(tx, main_id, deleted_id).await?;
+        let length_events = Self::move_length_events(tx, main_id, deleted_id).await?;
+        let seasons = Self::move_seasons(tx, main_id, deleted_id).await?;
 
-        tracing::info!(loans, battle_stats, announcements, imports, dod, shrinks, migrations,
+        tracing::info!(loans, member_loans, battle_stats, battle_log, announcements, imports, dod, shrinks, migrations, achievements, inventory, length_events, seasons,
             "moved the rows of the deleted chat to the main one");
         Ok(())
     }
@@ -654,6 +940,22 @@ repository!(Chats, with_feature_toggles,
             .map(|res| res.rows_affected())
             .context(format!("couldn't move loans from the chat with id = {deleted_id} to {main_id}"))
     }
+,
+    /// Repointed as well: a member may owe several others, and even the same one twice.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(main_id = %main_id, deleted_id = %deleted_id))]
+    async fn move_member_loans(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: InternalChatId,
+        deleted_id: InternalChatId,
+    ) -> anyhow::Result<u64> {
+        sqlx::query!("UPDATE Member_Loans SET chat_id = $1 WHERE chat_id = $2",
+                main_id as InternalChatId, deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .map(|res| res.rows_affected())
+            .context(format!("couldn't move the loans between members from the chat with id = {deleted_id} to {main_id}"))
+    }
 ,
     /// `(uid, chat_id)` is a primary key, so a user present in both chats needs their counters
     /// folded together instead of one row simply landing on top of the other.
@@ -665,15 +967,17 @@ repository!(Chats, with_feature_toggles,
         deleted_id: InternalChatId,
     ) -> anyhow::Result<u64> {
         let moved = sqlx::query!(
-            "INSERT INTO Battle_Stats (uid, chat_id, battles_total, battles_won, win_streak_current, win_streak_max, acquired_length, lost_length)
-                    SELECT uid, $1, battles_total, battles_won, win_streak_current, win_streak_max, acquired_length, lost_length
+            "INSERT INTO Battle_Stats (uid, chat_id, battles_total, battles_won, win_streak_current, win_streak_max, lose_streak_current, acquired_length, lost_length, mercies_shown, support_paid)
+                    SELECT uid, $1, battles_total, battles_won, win_streak_current, win_streak_max, lose_streak_current, acquired_length, lost_length, mercies_shown, support_paid
                         FROM Battle_Stats WHERE chat_id = $2
                     ON CONFLICT (uid, chat_id) DO UPDATE SET
                         battles_total = Battle_Stats.battles_total + EXCLUDED.battles_total,
                         battles_won = Battle_Stats.battles_won + EXCLUDED.battles_won,
                         win_streak_max = GREATEST(Battle_Stats.win_streak_max, EXCLUDED.win_streak_max),
                         acquired_length = Battle_Stats.acquired_length + EXCLUDED.acquired_length,
-                        lost_length = Battle_Stats.lost_length + EXCLUDED.lost_length",
+                        lost_length = Battle_Stats.lost_length + EXCLUDED.lost_length,
+                        mercies_shown = Battle_Stats.mercies_shown + EXCLUDED.mercies_shown,
+                        support_paid = Battle_Stats.support_paid + EXCLUDED.support_paid",
                 main_id as InternalChatId, deleted_id as InternalChatId)
             .execute(&mut **tx)
             .await
@@ -685,6 +989,41 @@ repository!(Chats, with_feature_toggles,
             .context(format!("couldn't delete battle stats of the chat with id = {deleted_id}"))?;
         Ok(moved)
     }
+,
+    /// Every battle is a row of its own, so the log of both chats is simply put together.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(main_id = %main_id, deleted_id = %deleted_id))]
+    async fn move_battle_log(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: InternalChatId,
+        deleted_id: InternalChatId,
+    ) -> anyhow::Result<u64> {
+        sqlx::query!("UPDATE Battle_Log SET chat_id = $1 WHERE chat_id = $2",
+                main_id as InternalChatId, deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .map(|res| res.rows_affected())
+            .context(format!("couldn't move the battle log from the chat with id = {deleted_id} to {main_id}"))
+    }
+,
+    /// Put together like the battle log. The lengths written into the events before the merge stay
+    /// the ones of their own chat, so the history of somebody who played in both chats jumps
+    /// between the two dicks until then: rewriting every event to the sum is not worth it for a
+    /// chart.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(main_id = %main_id, deleted_id = %deleted_id))]
+    async fn move_length_events(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: InternalChatId,
+        deleted_id: InternalChatId,
+    ) -> anyhow::Result<u64> {
+        sqlx::query!("UPDATE Length_Events SET chat_id = $1 WHERE chat_id = $2",
+                main_id as InternalChatId, deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .map(|res| res.rows_affected())
+            .context(format!("couldn't move the length events from the chat with id = {deleted_id} to {main_id}"))
+    }
 ,
     /// Keyed by `(chat_id, language)`; the shown counters add up when both chats saw the same
     /// announcement.
@@ -841,6 +1180,79 @@ repository!(Chats, with_feature_toggles,
             .context(format!("couldn't delete shrinks of the chat with id = {deleted_id}"))?;
         Ok(moved)
     }
+,
+    /// Keyed by `(chat_id, uid, achievement)`; an achievement unlocked in both chats keeps the
+    /// earlier date. `Achievements` cascades like `Battle_Stats` does, so the rows left behind would
+    /// go anyway — the delete only keeps the count in the log honest.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(main_id = %main_id, deleted_id = %deleted_id))]
+    async fn move_achievements(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: InternalChatId,
+        deleted_id: InternalChatId,
+    ) -> anyhow::Result<u64> {
+        let moved = sqlx::query!(
+            "INSERT INTO Achievements (chat_id, uid, achievement, unlocked_at)
+                    SELECT $1, uid, achievement, unlocked_at FROM Achievements WHERE chat_id = $2
+                    ON CONFLICT (chat_id, uid, achievement) DO UPDATE SET
+                        unlocked_at = LEAST(Achievements.unlocked_at, EXCLUDED.unlocked_at)",
+                main_id as InternalChatId, deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't move achievements from the chat with id = {deleted_id} to {main_id}"))?
+            .rows_affected();
+        sqlx::query!("DELETE FROM Achievements WHERE chat_id = $1", deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't delete achievements of the chat with id = {deleted_id}"))?;
+        Ok(moved)
+    }
+,
+    /// Both chats' items were paid for, so a user who bought some in each keeps them all.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(main_id = %main_id, deleted_id = %deleted_id))]
+    async fn move_inventory(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: InternalChatId,
+        deleted_id: InternalChatId,
+    ) -> anyhow::Result<u64> {
+        let moved = sqlx::query!(
+            "INSERT INTO Inventory (chat_id, uid, item, quantity)
+                    SELECT $1, uid, item, quantity FROM Inventory WHERE chat_id = $2
+                    ON CONFLICT (chat_id, uid, item) DO UPDATE SET
+                        quantity = Inventory.quantity + EXCLUDED.quantity",
+                main_id as InternalChatId, deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't move the inventory from the chat with id = {deleted_id} to {main_id}"))?
+            .rows_affected();
+        sqlx::query!("DELETE FROM Inventory WHERE chat_id = $1", deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't delete the inventory of the chat with id = {deleted_id}"))?;
+        Ok(moved)
+    }
+,
+    /// `Seasons` cascades, and its results with it. The seasons of the deleted chat are numbered on
+    /// after those of the main one instead, since both have their own first season; the results
+    /// follow their season by its id.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(main_id = %main_id, deleted_id = %deleted_id))]
+    async fn move_seasons(
+        tx: &mut Transaction<'_, Postgres>,
+        main_id: InternalChatId,
+        deleted_id: InternalChatId,
+    ) -> anyhow::Result<u64> {
+        sqlx::query!(
+            "UPDATE Seasons SET chat_id = $1,
+                    number = number + (SELECT coalesce(max(number), 0) FROM Seasons WHERE chat_id = $1)
+                WHERE chat_id = $2",
+                main_id as InternalChatId, deleted_id as InternalChatId)
+            .execute(&mut **tx)
+            .await
+            .map(|res| res.rows_affected())
+            .context(format!("couldn't move the seasons from the chat with id = {deleted_id} to {main_id}"))
+    }
 ,
     #[autometrics]
     #[tracing::instrument(skip_all, fields(chat_a = %chats[0].internal_id, chat_b = %chats[1].internal_id))]
@@ -854,13 +1266,16 @@ repository!(Chats, with_feature_toggles,
         // once per write; the increment merely cancels that out. It has to be spelled out again in
         // the conflict branch: the BEFORE INSERT trigger runs before the conflict is detected, so
         // by then `EXCLUDED` already carries the decremented value.
+        //
+        // The streak is the one of the dick grown the last: the other one may be broken already.
         let updated_dicks = sqlx::query!(
-            "INSERT INTO Dicks (uid, chat_id, length, bonus_attempts, updated_at)
-                    SELECT uid, $1, length, bonus_attempts + 1, updated_at FROM Dicks WHERE chat_id = $2
+            "INSERT INTO Dicks (uid, chat_id, length, bonus_attempts, updated_at, grow_streak)
+                    SELECT uid, $1, length, bonus_attempts + 1, updated_at, grow_streak FROM Dicks WHERE chat_id = $2
                     ON CONFLICT (chat_id, uid) DO UPDATE SET
                         length = Dicks.length + EXCLUDED.length,
                         bonus_attempts = Dicks.bonus_attempts + EXCLUDED.bonus_attempts + 1,
-                        updated_at = GREATEST(Dicks.updated_at, EXCLUDED.updated_at)",
+                        updated_at = GREATEST(Dicks.updated_at, EXCLUDED.updated_at),
+                        grow_streak = CASE WHEN EXCLUDED.updated_at > Dicks.updated_at THEN EXCLUDED.grow_streak ELSE Dicks.grow_streak END",
                 state.main.internal_id as InternalChatId, state.deleted.0 as InternalChatId)
             .execute(&mut **tx)
             .await
@@ -1,13 +1,17 @@
+use std::ops::Range;
 use autometrics::autometrics;
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use chrono::NaiveDate;
 use futures::TryFutureExt;
 use domain_types::traits::SaturatingInto;
+use num_traits::ToPrimitive;
 use sqlx::{Executor, Pool, Postgres, Transaction};
 use crate::config::FeatureToggles;
-use crate::domain::objects::{Dick, GrowthResult};
-use crate::domain::primitives::{Bet, DaysCount, LengthChange, Limit, Offset, UserId, Position, Length};
+use crate::domain::enums::LengthChangeReason;
+use crate::domain::objects::{DailyLength, Dick, GrowthResult, GrowthSettlement, LengthHistory, PeriodGrowth, ReasonedChange, Streak};
+use crate::domain::primitives::{Bet, DaysCount, GrowStreak, LengthChange, Limit, Offset, UserId, Position, Length};
 use crate::domain::primitives::chat::{ChatIdPartiality, ChatIdKind, InternalChatId};
-use super::Chats;
+use super::{BattleStatsRepo, Chats, Gifts, Inventory};
 
 /// The database projection of a [`Dick`]. `position` is a `ROW_NUMBER()` (a plain `int8`),
 /// so it's decoded as `i64` here and converted to the `Position` domain type at this boundary
@@ -49,23 +53,50 @@ impl Dicks {
         }
     }
 
+    #[cfg(test)]
+    pub async fn create_or_grow(&self, uid: UserId, chat_id: &ChatIdPartiality, increment: LengthChange) -> anyhow::Result<GrowthResult> {
+        self.create_or_grow_with_settlements(uid, chat_id, increment, &[]).await
+    }
+
+    /// Keeps the streak as well: a growth the day after the last one extends it, a later one starts
+    /// it anew, and another one on the same day (by a bonus attempt) leaves it as it is. The days are
+    /// those of the time zone of the chat, like in the trigger that allows one growth a day.
+    ///
+    /// The settlements are written in the same transaction: when the trigger rejects the growth,
+    /// nothing the perks have counted on is paid either.
     #[autometrics]
     #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id, increment = %increment))]
-    pub async fn create_or_grow(
+    pub async fn create_or_grow_with_settlements(
         &self,
         uid: UserId,
         chat_id: &ChatIdPartiality,
         increment: LengthChange,
+        settlements: &[GrowthSettlement],
     ) -> anyhow::Result<GrowthResult> {
         let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        let mut tx = self.pool.begin().await?;
         let new_length = sqlx::query_scalar!(
-            "INSERT INTO dicks(uid, chat_id, length, updated_at) VALUES ($1, $2, $3, current_timestamp)
-                ON CONFLICT (uid, chat_id) DO UPDATE SET length = (dicks.length + $3), updated_at = current_timestamp
-                RETURNING length",
+            r#"WITH grown AS (
+                    INSERT INTO dicks(uid, chat_id, length, updated_at, grow_streak) VALUES ($1, $2, $3, current_timestamp, 1)
+                    ON CONFLICT (uid, chat_id) DO UPDATE SET length = (dicks.length + $3), updated_at = current_timestamp,
+                        grow_streak = CASE chat_date($2, dicks.updated_at)
+                            WHEN chat_date($2, current_timestamp) THEN GREATEST(dicks.grow_streak, 1)
+                            WHEN chat_date($2, current_timestamp) - 1 THEN dicks.grow_streak + 1
+                            ELSE 1
+                        END
+                    RETURNING uid, chat_id, length
+                ),
+                logged AS (
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT chat_id, uid, $3, length, 'grow'::length_change_reason FROM grown
+                )
+                SELECT length AS "length!" FROM grown"#,
                 uid as UserId, internal_chat_id as InternalChatId, increment.value())
-            .fetch_one(&self.pool)
+            .fetch_one(&mut *tx)
             .await
             .context(format!("couldn't upsert the dick of {uid} in {chat_id} with increment of {increment}"))?;
+        Self::settle_internal(&mut tx, internal_chat_id, uid, settlements).await?;
+        tx.commit().await?;
         let pos_in_top = self.get_position_in_top(internal_chat_id, uid).await?;
         Ok(GrowthResult { new_length: Length::new(new_length), pos_in_top })
     }
@@ -84,6 +115,32 @@ impl Dicks {
             .context(format!("couldn't fetch length for {chat_id} and {uid}"))
     }
 
+    /// A streak whose last day is before yesterday is already broken, so it's zero here whatever
+    /// the column still keeps until the next growth.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id))]
+    pub async fn fetch_grow_streak(&self, uid: UserId, chat_id: &ChatIdKind) -> anyhow::Result<Streak> {
+        let maybe_row = sqlx::query!(
+            r#"SELECT CASE WHEN local_date(d.updated_at, c.settings->>'timezone') >= local_date(current_timestamp, c.settings->>'timezone') - 1
+                                THEN d.grow_streak ELSE 0 END AS "days!",
+                      local_date(d.updated_at, c.settings->>'timezone') = local_date(current_timestamp, c.settings->>'timezone') AS "grown_today!"
+                FROM Dicks d
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE uid = $1 AND (c.chat_id = $2::bigint OR c.chat_instance = $2::text)"#,
+                uid as UserId, chat_id.value() as String)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't fetch the grow streak for {chat_id} and {uid}"))?;
+        let Some(row) = maybe_row else {
+            return Ok(Streak::default())
+        };
+        Ok(Streak {
+            days: row.days.to_u32().map(GrowStreak::new)
+                .context("grow_streak, fetched from the database, must not be negative")?,
+            grown_today: row.grown_today,
+        })
+    }
+
     #[autometrics]
     #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id))]
     pub async fn fetch_dick(&self, uid: UserId, chat_id: &ChatIdKind) -> anyhow::Result<Option<Dick>> {
@@ -103,6 +160,52 @@ impl Dicks {
             .context(format!("couldn't fetch dick for {chat_id} and {uid}"))
     }
 
+    /// The length at the end of each of the last `days` days, today included, and the totals it has
+    /// changed by over them. `None` if the user has no dick in the chat.
+    ///
+    /// A day without events keeps the length of the day before. The days before the first event
+    /// (a dick grown before `Length_Events` existed has some) get the length that event started
+    /// from, or the current one if nothing has happened to the dick since.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id, days = %days))]
+    pub async fn get_history(&self, uid: UserId, chat_id: &ChatIdKind, days: DaysCount) -> anyhow::Result<Option<LengthHistory>> {
+        let daily_lengths = sqlx::query_as!(DailyLength,
+            r#"SELECT s.day::date AS "day!", COALESCE(
+                        (SELECT e.length FROM Length_Events e
+                            WHERE e.chat_id = d.chat_id AND e.uid = d.uid AND e.created_at < s.day + interval '1 day'
+                            ORDER BY e.created_at DESC, e.id DESC LIMIT 1),
+                        (SELECT e.length - e.change FROM Length_Events e
+                            WHERE e.chat_id = d.chat_id AND e.uid = d.uid
+                            ORDER BY e.created_at, e.id LIMIT 1),
+                        d.length) AS "length!: Length"
+                FROM Dicks d
+                JOIN Chats c ON c.id = d.chat_id
+                CROSS JOIN generate_series(current_date - ($3::bigint::int - 1), current_date, interval '1 day') AS s(day)
+                WHERE d.uid = $1 AND (c.chat_id = $2::bigint OR c.chat_instance = $2::text)
+                ORDER BY s.day"#,
+                uid as UserId, chat_id.value() as String, days as DaysCount)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't fetch the daily lengths of {uid} in {chat_id} for {days} days"))?;
+        if daily_lengths.is_empty() {
+            return Ok(None)
+        }
+
+        let changes = sqlx::query_as!(ReasonedChange,
+            r#"SELECT e.reason AS "reason!: LengthChangeReason", SUM(e.change)::bigint AS "change!: Length"
+                FROM Length_Events e
+                JOIN Chats c ON c.id = e.chat_id
+                WHERE e.uid = $1 AND (c.chat_id = $2::bigint OR c.chat_instance = $2::text)
+                  AND e.created_at >= current_date - ($3::bigint::int - 1)
+                GROUP BY e.reason
+                ORDER BY e.reason"#,
+                uid as UserId, chat_id.value() as String, days as DaysCount)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't fetch the changes of the length of {uid} in {chat_id} for {days} days"))?;
+        Ok(Some(LengthHistory { days: daily_lengths, changes }))
+    }
+
     /// Returns the uids of everyone who has a dick in the chat (i.e. its players).
     #[autometrics]
     #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
@@ -144,6 +247,40 @@ impl Dicks {
             .context(format!("couldn't get the top of {chat_id} with offset = {offset} and limit = {limit}"))
     }
 
+    /// Ranks the members by what they have gained over the days of `period`, read from
+    /// `Length_Events`. An import isn't a gain: the length was grown elsewhere, and counting it would
+    /// put whoever has just come from another bot at the head of the week. Nor is the reset at the
+    /// end of a season a loss, or the week it falls in would be won by whoever had the least. Neither
+    /// is the length passed from hand to hand by gifts, loans and their repayments: the top would be
+    /// won by whoever is given the most, and a pair could take turns at the head.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, since = %period.start, until = %period.end, limit = %limit))]
+    pub async fn get_period_top(
+        &self,
+        chat_id: &ChatIdKind,
+        period: Range<NaiveDate>,
+        limit: Limit,
+    ) -> anyhow::Result<Vec<PeriodGrowth>> {
+        sqlx::query_as!(PeriodGrowth,
+            r#"SELECT e.uid AS "owner_uid: UserId", u.name AS owner_name,
+                      SUM(e.change)::bigint AS "gained!: Length", d.length AS "length: Length"
+                FROM Length_Events e
+                JOIN Chats c ON c.id = e.chat_id
+                JOIN Users u ON u.uid = e.uid
+                JOIN Dicks d ON d.uid = e.uid AND d.chat_id = e.chat_id
+                WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
+                  AND e.created_at >= $2::date AND e.created_at < $3::date
+                  AND e.reason NOT IN ('import', 'season', 'gift', 'lend', 'lend_repayment')
+                GROUP BY e.uid, u.name, d.length
+                HAVING SUM(e.change) > 0
+                ORDER BY 3 DESC, d.length DESC, u.name
+                LIMIT $4"#,
+                chat_id.value() as String, period.start, period.end, limit as Limit)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the top of {chat_id} from {} to {}", period.start, period.end))
+    }
+
     #[autometrics]
     #[tracing::instrument(skip_all, fields(uid = user_id.value(), chat_id = %chat_id, bonus = %bonus))]
     pub async fn set_dod_winner(
@@ -151,14 +288,16 @@ impl Dicks {
         chat_id: &ChatIdPartiality,
         user_id: UserId,
         bonus: LengthChange,
+        settlements: &[GrowthSettlement],
     ) -> anyhow::Result<Option<GrowthResult>> {
         let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
 
         let mut tx = self.pool.begin().await?;
-        let new_length = match Self::grow_no_attempts_check_internal(&mut *tx, internal_chat_id, user_id, bonus).await? {
+        let new_length = match Self::grow_no_attempts_check_internal(&mut *tx, internal_chat_id, user_id, bonus, LengthChangeReason::Dod).await? {
             Some(length) => length,
             None => return Ok(None)
         };
+        Self::settle_internal(&mut tx, internal_chat_id, user_id, settlements).await?;
         Self::insert_to_dod_table(&mut tx, internal_chat_id, user_id).await?;
         tx.commit().await?;
 
@@ -181,21 +320,18 @@ impl Dicks {
     }
 
     #[autometrics]
-    #[tracing::instrument(skip_all, fields(from = from.value(), to = to.value(), chat_id = %chat_id, length = %length))]
+    #[tracing::instrument(skip_all, fields(from = from.value(), to = to.value(), chat_id = %chat_id, length = %length, reason = %reason))]
     pub async fn move_length(
         &self,
         chat_id: &ChatIdPartiality,
         from: UserId,
         to: UserId,
         length: Bet,
+        reason: LengthChangeReason,
     ) -> anyhow::Result<(GrowthResult, GrowthResult)> {
         let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
-        let winner_change = length.as_length_change_for_winner();
-        let loser_change = length.as_length_change_for_loser();
-
         let mut tx = self.pool.begin().await?;
-        let length_from = Self::move_length_for_one_user(&mut tx, internal_chat_id, from, loser_change).await?;
-        let length_to = Self::move_length_for_one_user(&mut tx, internal_chat_id, to, winner_change).await?;
+        let (length_from, length_to) = Self::move_length_internal(&mut tx, internal_chat_id, from, to, length, reason).await?;
         tx.commit().await?;
 
         let pos_from = self.get_position_in_top(internal_chat_id, from).await?;
@@ -211,16 +347,85 @@ impl Dicks {
         Ok((gr_from, gr_to))
     }
 
+    /// The part of [`Dicks::move_length`] for a transaction of a caller who has more to do in it.
     #[autometrics]
-    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value(), change = %change))]
+    #[tracing::instrument(skip_all, fields(from = from.value(), to = to.value(), internal_chat_id = %chat_id_internal, length = %length, reason = %reason))]
+    pub(super) async fn move_length_internal(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: InternalChatId,
+        from: UserId,
+        to: UserId,
+        length: Bet,
+        reason: LengthChangeReason,
+    ) -> anyhow::Result<(Length, Length)> {
+        let length_from = Self::move_length_for_one_user(tx, chat_id_internal, from, length.as_length_change_for_loser(), reason).await?;
+        let length_to = Self::move_length_for_one_user(tx, chat_id_internal, to, length.as_length_change_for_winner(), reason).await?;
+        Ok((length_from, length_to))
+    }
+
+    /// Takes the stake from each of the losers and gives all of it to the winner. One transaction:
+    /// a pot collected halfway would leave some of the losers poorer and the winner none the richer.
+    /// The lengths are checked once again under the lock, and a loser who has no stake anymore
+    /// drops out and pays nothing. Returns the ones who have paid, or `None` if nobody could, and
+    /// then nothing is moved at all.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(to = to.value(), losers = from.len(), chat_id = %chat_id, stake = %stake))]
+    pub async fn collect_pot(
+        &self,
+        chat_id: &ChatIdPartiality,
+        from: &[UserId],
+        to: UserId,
+        stake: Bet,
+    ) -> anyhow::Result<Option<(GrowthResult, Vec<UserId>)>> {
+        let internal_chat_id = self.chats.upsert_chat(chat_id).await?;
+        let mut tx = self.pool.begin().await?;
+
+        // in the order of the ids, so that two pots collected at once don't deadlock
+        let payers = sqlx::query_scalar!(
+            r#"SELECT uid AS "uid: UserId" FROM Dicks
+                WHERE chat_id = $1 AND uid = ANY($2) AND length >= $3
+                ORDER BY uid FOR UPDATE"#,
+                internal_chat_id as InternalChatId, from as &[UserId], stake as Bet)
+            .fetch_all(&mut *tx)
+            .await
+            .context(format!("couldn't fetch and lock the lengths of {} losers in {chat_id}", from.len()))?;
+        if payers.is_empty() {
+            return Ok(None)
+        }
+        let pot = Bet::new(stake.value().saturating_mul(payers.len().saturating_into()));
+
+        for loser in &payers {
+            Self::move_length_for_one_user(&mut tx, internal_chat_id, *loser, stake.as_length_change_for_loser(), LengthChangeReason::Royale).await?;
+        }
+        let length_to = Self::move_length_for_one_user(&mut tx, internal_chat_id, to, pot.as_length_change_for_winner(), LengthChangeReason::Royale).await?;
+        tx.commit().await?;
+
+        let winner = GrowthResult {
+            new_length: length_to,
+            pos_in_top: self.get_position_in_top(internal_chat_id, to).await?,
+        };
+        Ok(Some((winner, payers)))
+    }
+
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value(), change = %change, reason = %reason))]
     async fn move_length_for_one_user(
         tx: &mut Transaction<'_, Postgres>,
         chat_id_internal: InternalChatId,
         user_id: UserId,
         change: LengthChange,
+        reason: LengthChangeReason,
     ) -> anyhow::Result<Length> {
-        sqlx::query_scalar!("UPDATE Dicks SET length = (length + $3), bonus_attempts = (bonus_attempts + 1) WHERE chat_id = $1 AND uid = $2 RETURNING length",
-                    chat_id_internal as InternalChatId, user_id as UserId, change.value())
+        sqlx::query_scalar!(
+            r#"WITH moved AS (
+                    UPDATE Dicks SET length = (length + $3), bonus_attempts = (bonus_attempts + 1) WHERE chat_id = $1 AND uid = $2 RETURNING length
+                ),
+                logged AS (
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT $1, $2, $3, length, $4 FROM moved
+                )
+                SELECT length AS "length!" FROM moved"#,
+                    chat_id_internal as InternalChatId, user_id as UserId, change.value(), reason as LengthChangeReason)
             .fetch_one(&mut **tx)
             .await
             .map(Length::new)
@@ -253,16 +458,17 @@ impl Dicks {
     }
     
     #[autometrics]
-    #[tracing::instrument(skip_all, fields(uid = user_id.value(), chat_id = %chat_id, change = %change))]
+    #[tracing::instrument(skip_all, fields(uid = user_id.value(), chat_id = %chat_id, change = %change, reason = %reason))]
     pub async fn grow_no_attempts_check(
         &self,
         chat_id: &ChatIdKind,
         user_id: UserId,
         change: LengthChange,
+        reason: LengthChangeReason,
     ) -> anyhow::Result<GrowthResult> {
         let chat_internal_id = self.chats.get_internal_id(chat_id).await?;
 
-        let new_length = Self::grow_no_attempts_check_internal(&self.pool, chat_internal_id, user_id, change).await?
+        let new_length = Self::grow_no_attempts_check_internal(&self.pool, chat_internal_id, user_id, change, reason).await?
             .ok_or(anyhow!("couldn't find a dick of ({chat_id}, {user_id}) for some reason"))?;
         let pos_in_top = self.get_position_in_top(chat_internal_id, user_id).await?;
         
@@ -270,26 +476,61 @@ impl Dicks {
     }
 
     #[autometrics]
-    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value(), bonus = %bonus))]
+    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value(), bonus = %bonus, reason = %reason))]
     pub(super) async fn grow_no_attempts_check_internal<'c, E>(
         executor: E,
         chat_id_internal: InternalChatId,
         user_id: UserId,
         bonus: LengthChange,
+        reason: LengthChangeReason,
     ) -> anyhow::Result<Option<Length>>
     where E: Executor<'c, Database = Postgres>,
     {
         sqlx::query_scalar!(
-            "UPDATE Dicks SET bonus_attempts = (bonus_attempts + 1), length = (length + $3)
-                WHERE chat_id = $1 AND uid = $2
-                RETURNING length",
-                chat_id_internal as InternalChatId, user_id as UserId, bonus.value())
+            r#"WITH grown AS (
+                    UPDATE Dicks SET bonus_attempts = (bonus_attempts + 1), length = (length + $3)
+                    WHERE chat_id = $1 AND uid = $2
+                    RETURNING length
+                ),
+                logged AS (
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT $1, $2, $3, length, $4 FROM grown
+                )
+                SELECT length AS "length!" FROM grown"#,
+                chat_id_internal as InternalChatId, user_id as UserId, bonus.value(), reason as LengthChangeReason)
             .fetch_optional(executor)
             .await
             .map(|maybe_length| maybe_length.map(Length::new))
             .context(format!("couldn't grow the dick without attempts check for {chat_id_internal} and {user_id} by {bonus}"))
     }
 
+    /// Writes what a growth brings about besides the length of the grower, within its transaction.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value(), settlements = settlements.len()))]
+    async fn settle_internal(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_id_internal: InternalChatId,
+        user_id: UserId,
+        settlements: &[GrowthSettlement],
+    ) -> anyhow::Result<()> {
+        for settlement in settlements {
+            match *settlement {
+                // the refunds are bounded by the net loss, which a battle may have shrunk since the perk counted
+                GrowthSettlement::SupportPaid(payout) =>
+                    if !BattleStatsRepo::add_support_paid_internal(&mut **tx, chat_id_internal, user_id, payout).await? {
+                        bail!("the support of {payout} cm exceeds the net loss of {user_id} in {chat_id_internal}")
+                    },
+                GrowthSettlement::MemberLoanPayout { loan_id, lender, payout } =>
+                    Gifts::pay_member_loan_internal(tx, chat_id_internal, loan_id, lender, payout).await?,
+                GrowthSettlement::ItemUsed(item) =>
+                    if !Inventory::consume_internal(&mut **tx, chat_id_internal, user_id, item).await? {
+                        bail!("{user_id} has no {item} to use in {chat_id_internal}")
+                    },
+            }
+        }
+        Ok(())
+    }
+
     #[autometrics]
     #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value()))]
     async fn insert_to_dod_table(
@@ -0,0 +1,195 @@
+use autometrics::autometrics;
+use anyhow::{bail, Context};
+use domain_types::traits::{ApproxInto, SaturatingInto};
+use sqlx::{Postgres, Transaction};
+
+use crate::config;
+use crate::config::GiftConfig;
+use crate::domain::enums::{GiftKind, LengthChangeReason};
+use crate::domain::objects::GrowthSettlement;
+use crate::domain::primitives::{Bet, Coefficient, GiftAmount, Length, LengthChange, LengthIncrement, LoanPayout, PayoutRatio, UserId};
+use crate::domain::primitives::chat::InternalChatId;
+use crate::repo::{ChatIdKind, Chats, Dicks, SearchError};
+
+#[derive(Debug, PartialEq)]
+pub enum GiftResult {
+    Given {
+        giver_length: Length,
+        recipient_length: Length,
+    },
+    NotEnoughLength(Length),
+    /// The recipient hasn't grown in the chat yet.
+    NoRecipient,
+    /// What the giver may still give away today.
+    GivenCapReached(GiftAmount),
+    /// What the recipient may still get today.
+    ReceivedCapReached(GiftAmount),
+}
+
+struct MemberLoanEntity {
+    id: i64,
+    lender_uid: UserId,
+    debt: i32,
+    payout_ratio: PayoutRatio,
+}
+
+#[derive(Clone)]
+pub struct Gifts {
+    pool: sqlx::Pool<Postgres>,
+    chats: Chats,
+    caps: GiftConfig,
+}
+
+impl Gifts {
+    pub fn new(pool: sqlx::Pool<Postgres>, cfg: &config::AppConfig) -> Self {
+        let chats = Chats::new(pool.clone(), cfg.features);
+        Self { pool, chats, caps: cfg.gift }
+    }
+
+    /// The caps are checked, the length is moved and the loan is recorded in one transaction, with
+    /// both dicks locked: two quick clicks on the same button must not give more than allowed.
+    ///
+    /// What has been given today is read from `Length_Events`, which records every gift anyway.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, from = from.value(), to = to.value(), amount = %amount, kind = %kind))]
+    pub async fn give(
+        &self,
+        chat_id: &ChatIdKind,
+        from: UserId,
+        to: UserId,
+        amount: GiftAmount,
+        kind: GiftKind,
+        payout_ratio: PayoutRatio,
+    ) -> anyhow::Result<GiftResult> {
+        let chat_internal_id = match self.chats.get_internal_id(chat_id).await {
+            Ok(id) => id,
+            // nobody has grown anything in the chat yet, so there's nothing to give either
+            Err(SearchError::NotFound(_)) => return Ok(GiftResult::NotEnoughLength(Length::new(0))),
+            Err(SearchError::Internal(e)) => return Err(e),
+        };
+        let mut tx = self.pool.begin().await?;
+
+        // in the order of the ids, so that two members giving to each other at once don't deadlock
+        let lengths = sqlx::query!(
+            r#"SELECT uid AS "uid: UserId", length FROM Dicks
+                WHERE chat_id = $1 AND uid IN ($2, $3)
+                ORDER BY uid FOR UPDATE"#,
+                chat_internal_id as InternalChatId, from as UserId, to as UserId)
+            .fetch_all(&mut *tx)
+            .await
+            .context(format!("couldn't fetch and lock the lengths of {from} and {to} in {chat_id}"))?;
+        let length_of = |uid: UserId| lengths.iter().find(|row| row.uid == uid).map(|row| row.length);
+        let Some(giver_length) = length_of(from) else {
+            return Ok(GiftResult::NotEnoughLength(Length::new(0)))
+        };
+        if length_of(to).is_none() {
+            return Ok(GiftResult::NoRecipient)
+        }
+
+        let today = sqlx::query!(
+            r#"SELECT COALESCE(SUM(-change) FILTER (WHERE uid = $2 AND change < 0), 0)::bigint AS "given!",
+                      COALESCE(SUM(change) FILTER (WHERE uid = $3 AND change > 0), 0)::bigint AS "received!"
+                FROM Length_Events
+                WHERE chat_id = $1 AND uid IN ($2, $3) AND reason IN ('gift', 'lend') AND created_at >= current_date"#,
+                chat_internal_id as InternalChatId, from as UserId, to as UserId)
+            .fetch_one(&mut *tx)
+            .await
+            .context(format!("couldn't sum up today's gifts of {from} and {to} in {chat_id}"))?;
+        let given_left = cap_left(self.caps.daily_given_cap, today.given);
+        if amount > given_left {
+            return Ok(GiftResult::GivenCapReached(given_left))
+        }
+        let received_left = cap_left(self.caps.daily_received_cap, today.received);
+        if amount > received_left {
+            return Ok(GiftResult::ReceivedCapReached(received_left))
+        }
+        if giver_length < i64::from(amount.value()) {
+            return Ok(GiftResult::NotEnoughLength(Length::new(giver_length)))
+        }
+
+        let (giver_length, recipient_length) = Dicks::move_length_internal(&mut tx, chat_internal_id, from, to,
+            Bet::new(amount.value()), kind.into()).await?;
+        if kind == GiftKind::Lend {
+            sqlx::query!("INSERT INTO Member_Loans (chat_id, lender_uid, borrower_uid, debt, payout_ratio) VALUES ($1, $2, $3, $4, $5)",
+                    chat_internal_id as InternalChatId, from as UserId, to as UserId,
+                    i32::try_from(amount.value()).context("a gift must fit into a debt")?, payout_ratio as PayoutRatio)
+                .execute(&mut *tx)
+                .await
+                .context(format!("couldn't record the loan of {amount} cm from {from} to {to} in {chat_id}"))?;
+        }
+
+        tx.commit().await?;
+        Ok(GiftResult::Given { giver_length, recipient_length })
+    }
+
+    /// What the borrower owes to their lenders out of a growth, the oldest loan first, each one with
+    /// its own share of the growth. Nothing is paid here: the payouts are settled along with the
+    /// growth, so a growth that is rejected pays nothing either.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(borrower = borrower.value(), chat_id = %chat_id, growth = %growth))]
+    pub async fn plan_member_loan_payouts(&self, borrower: UserId, chat_id: &ChatIdKind, growth: LengthIncrement) -> anyhow::Result<Vec<GrowthSettlement>> {
+        let loans = sqlx::query_as!(MemberLoanEntity,
+            r#"SELECT id, lender_uid AS "lender_uid: UserId", debt, payout_ratio AS "payout_ratio: PayoutRatio"
+                FROM Member_Loans
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                  AND borrower_uid = $2 AND repaid_at IS NULL
+                ORDER BY created_at"#,
+                chat_id.value() as String, borrower as UserId)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the loans of {borrower} from other members of {chat_id}"))?;
+
+        let growth_value: f64 = growth.value().approx_into();
+        let mut left = growth.value();
+        let mut payouts = Vec::new();
+        for loan in loans {
+            let share: u64 = loan.payout_ratio.scale(growth_value).round().saturating_into();
+            let payout = share.min(loan.debt.saturating_into()).min(left);
+            if payout == 0 {
+                continue
+            }
+            payouts.push(GrowthSettlement::MemberLoanPayout {
+                loan_id: loan.id,
+                lender: loan.lender_uid,
+                payout: LoanPayout::new(payout.saturating_into()),
+            });
+            left = left.saturating_sub(payout);
+        }
+        Ok(payouts)
+    }
+
+    /// Pays a planned share of a growth for a loan and grows the dick of the lender by it. The debt
+    /// may have been paid by another growth since the payout was planned, so the whole growth is
+    /// given up rather than paying more than is owed.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_internal_id, loan_id = loan_id, lender = lender.value(), payout = %payout))]
+    pub(super) async fn pay_member_loan_internal(
+        tx: &mut Transaction<'_, Postgres>,
+        chat_internal_id: InternalChatId,
+        loan_id: i64,
+        lender: UserId,
+        payout: LoanPayout,
+    ) -> anyhow::Result<()> {
+        let payout_i32: i32 = payout.value().saturating_into();
+        let rows_affected = sqlx::query!(
+            "UPDATE Member_Loans SET debt = debt - $3, repaid_at = CASE WHEN debt = $3 THEN current_timestamp END
+                WHERE id = $1 AND chat_id = $2 AND repaid_at IS NULL AND debt >= $3",
+                loan_id, chat_internal_id as InternalChatId, payout_i32)
+            .execute(&mut **tx)
+            .await
+            .context(format!("couldn't pay {payout} cm for the loan with id = {loan_id}"))?
+            .rows_affected();
+        if rows_affected != 1 {
+            bail!("the loan with id = {loan_id} doesn't owe {payout} cm anymore")
+        }
+        // the lender may have left the game since; the debt is paid all the same
+        Dicks::grow_no_attempts_check_internal(&mut **tx, chat_internal_id, lender,
+            LengthChange::signed(payout.value().into()), LengthChangeReason::LendRepayment).await?;
+        Ok(())
+    }
+}
+
+/// A zero is what's left when more has been given than the cap, which a lowered cap may cause.
+fn cap_left(cap: GiftAmount, used: i64) -> GiftAmount {
+    GiftAmount::new(i64::from(cap.value()).saturating_sub(used).saturating_into())
+}
@@ -27,10 +27,15 @@ repository!(Import,
                         INSERT INTO Imports (chat_id, uid, original_length)
                         SELECT $1, * FROM UNNEST($2::bigint[], $3::bigint[])
                         RETURNING chat_id, uid, original_length
+                    ),
+                    grown AS (
+                        UPDATE Dicks d SET length = (d.length + i.original_length), bonus_attempts = (d.bonus_attempts + 1)
+                        FROM inserted i JOIN Chats c ON c.chat_id = i.chat_id
+                        WHERE d.chat_id = c.id AND d.uid = i.uid
+                        RETURNING d.chat_id, d.uid, i.original_length, d.length
                     )
-                    UPDATE Dicks d SET length = (d.length + i.original_length), bonus_attempts = (d.bonus_attempts + 1)
-                    FROM inserted i JOIN Chats c ON c.chat_id = i.chat_id
-                    WHERE d.chat_id = c.id AND d.uid = i.uid",
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT chat_id, uid, original_length, length, 'import'::length_change_reason FROM grown",
                 chat_id.0, &uids as &[UserId], &lengths as &[Length])
             .execute(&self.pool)
             .await
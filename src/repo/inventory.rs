@@ -0,0 +1,157 @@
+use autometrics::autometrics;
+use anyhow::Context;
+use num_traits::ToPrimitive;
+use sqlx::{Executor, Postgres};
+use crate::domain::enums::{LengthChangeReason, ShopItem};
+use crate::domain::objects::{InventoryItem, User};
+use crate::domain::primitives::{DaysCount, ItemsCount, Length, Price, UserId, Username};
+use crate::domain::primitives::chat::InternalChatId;
+use crate::repo::{ChatIdKind, Dicks, SearchError};
+use crate::repository;
+
+struct InventoryItemEntity {
+    item: ShopItem,
+    quantity: i32,
+}
+
+impl TryFrom<InventoryItemEntity> for InventoryItem {
+    type Error = anyhow::Error;
+
+    fn try_from(entity: InventoryItemEntity) -> anyhow::Result<Self> {
+        Ok(Self {
+            item: entity.item,
+            quantity: entity.quantity.to_u32().map(ItemsCount::new)
+                .context("quantity, fetched from the database, must not be negative")?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PurchaseResult {
+    Bought { length: Length, quantity: ItemsCount },
+    NotEnoughLength,
+}
+
+repository!(Inventory, with_(chats)_(Chats),
+    /// The price is taken from the length and the item is put into the inventory in one
+    /// transaction, with the dick locked while its length is checked: two quick clicks on the same
+    /// button must not buy two items with the length enough for one.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, uid = uid.value(), item = %item, price = %price))]
+    pub async fn buy(&self, chat_id: &ChatIdKind, uid: UserId, item: ShopItem, price: Price) -> anyhow::Result<PurchaseResult> {
+        let chat_internal_id = match self.chats.get_internal_id(chat_id).await {
+            Ok(id) => id,
+            // nobody has grown anything in the chat yet, so there's nothing to pay with either
+            Err(SearchError::NotFound(_)) => return Ok(PurchaseResult::NotEnoughLength),
+            Err(SearchError::Internal(e)) => return Err(e),
+        };
+        let mut tx = self.pool.begin().await?;
+
+        let length = sqlx::query_scalar!("SELECT length FROM Dicks WHERE chat_id = $1 AND uid = $2 FOR UPDATE",
+                chat_internal_id as InternalChatId, uid as UserId)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("couldn't fetch and lock the length for {chat_id} and {uid}"))?;
+        if length.is_none_or(|length| length < i64::from(price.value())) {
+            return Ok(PurchaseResult::NotEnoughLength)
+        }
+
+        let length = Dicks::grow_no_attempts_check_internal(&mut *tx, chat_internal_id, uid, price.as_length_change(), LengthChangeReason::Shop).await?
+            .context(format!("the dick of {uid} in {chat_id} has disappeared during the purchase"))?;
+        let quantity = sqlx::query_scalar!(
+            "INSERT INTO Inventory (chat_id, uid, item, quantity) VALUES ($1, $2, $3, 1)
+                ON CONFLICT (chat_id, uid, item) DO UPDATE SET quantity = Inventory.quantity + 1
+                RETURNING quantity",
+                chat_internal_id as InternalChatId, uid as UserId, item as ShopItem)
+            .fetch_one(&mut *tx)
+            .await
+            .context(format!("couldn't put {item} into the inventory of {uid} in {chat_id}"))?
+            .to_u32()
+            .map(ItemsCount::new)
+            .context("quantity, fetched from the database, must not be negative")?;
+
+        tx.commit().await?;
+        Ok(PurchaseResult::Bought { length, quantity })
+    }
+,
+    /// Takes one item away and tells whether there was one to take. The check and the decrement are
+    /// the same statement, so an item is never used twice by two events at once.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, uid = uid.value(), item = %item))]
+    pub async fn consume(&self, chat_id: &ChatIdKind, uid: UserId, item: ShopItem) -> anyhow::Result<bool> {
+        sqlx::query!(
+            "UPDATE Inventory SET quantity = quantity - 1
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                  AND uid = $2 AND item = $3 AND quantity > 0",
+                chat_id.value() as String, uid as UserId, item as ShopItem)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't consume {item} from the inventory of {uid} in {chat_id}"))
+    }
+,
+    /// The same as [`Self::consume`] within a transaction of another repository, for an item used
+    /// up by a change that must not happen without it.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = uid.value(), item = %item))]
+    pub(super) async fn consume_internal<'c, E>(
+        executor: E,
+        chat_id_internal: InternalChatId,
+        uid: UserId,
+        item: ShopItem,
+    ) -> anyhow::Result<bool>
+    where E: Executor<'c, Database = Postgres>,
+    {
+        sqlx::query!(
+            "UPDATE Inventory SET quantity = quantity - 1
+                WHERE chat_id = $1 AND uid = $2 AND item = $3 AND quantity > 0",
+                chat_id_internal as InternalChatId, uid as UserId, item as ShopItem)
+            .execute(executor)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't consume {item} from the inventory of {uid} in {chat_id_internal}"))
+    }
+,
+    /// Only the items there are: a used up kind is left out rather than listed with zero.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, uid = uid.value()))]
+    pub async fn get_items(&self, chat_id: &ChatIdKind, uid: UserId) -> anyhow::Result<Vec<InventoryItem>> {
+        sqlx::query_as!(InventoryItemEntity,
+            r#"SELECT item AS "item: ShopItem", quantity FROM Inventory
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                  AND uid = $2 AND quantity > 0
+                ORDER BY item"#,
+                chat_id.value() as String, uid as UserId)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the inventory of {uid} in {chat_id}"))?
+            .into_iter()
+            .map(InventoryItem::try_from)
+            .collect()
+    }
+,
+    /// A random one of the active members who hold the item. The activity is judged the way the
+    /// elections do it, so that a ticket of somebody who has left the game doesn't win for ever.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, item = %item))]
+    pub async fn get_random_active_holder(
+        &self,
+        chat_id: &ChatIdKind,
+        item: ShopItem,
+        inactivity_days: DaysCount,
+    ) -> anyhow::Result<Option<User>> {
+        sqlx::query_as!(User,
+            r#"SELECT u.uid AS "uid: UserId", name AS "name: Username", u.created_at FROM Users u
+                JOIN Dicks d USING (uid)
+                JOIN Inventory i ON i.chat_id = d.chat_id AND i.uid = d.uid
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
+                    AND i.item = $2 AND i.quantity > 0
+                    AND d.updated_at > current_timestamp - make_interval(days => $3::bigint::int)
+                ORDER BY random() LIMIT 1"#,
+                chat_id.value() as String, item as ShopItem, inactivity_days as DaysCount)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't get a random active holder of {item} in {chat_id}"))
+    }
+);
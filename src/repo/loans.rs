@@ -1,11 +1,14 @@
 use autometrics::autometrics;
 use anyhow::Context;
+use chrono::NaiveDate;
 use domain_types::traits::SaturatingInto;
 use sqlx::{Postgres, Transaction};
 
 use crate::config;
+use crate::config::LoanConfig;
+use crate::domain::enums::LengthChangeReason;
 use crate::domain::objects::Loan;
-use crate::domain::primitives::{Debt, LengthChange, LoanId, LoanPayout, PayoutRatio, UserId};
+use crate::domain::primitives::{DaysCount, Debt, Length, LengthChange, LoanId, LoanPayout, PayoutRatio, Ratio, UserId};
 use crate::domain::primitives::chat::InternalChatId;
 use crate::repo::{ensure_only_one_row_updated, ChatIdKind, Chats, Dicks};
 
@@ -13,6 +16,9 @@ struct LoanEntity {
     id: LoanId,
     debt: Debt,
     payout_ratio: PayoutRatio,
+    interest_rate: Ratio,
+    due_date: Option<NaiveDate>,
+    overdue: bool,
 }
 
 impl From<LoanEntity> for Loan {
@@ -20,6 +26,9 @@ impl From<LoanEntity> for Loan {
         Loan {
             debt: entity.debt,
             payout_ratio: entity.payout_ratio,
+            interest_rate: entity.interest_rate,
+            due_date: entity.due_date,
+            overdue: entity.overdue,
         }
     }
 }
@@ -30,25 +39,38 @@ pub enum BorrowResult {
     NotEligible,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum RepayResult {
+    Repaid {
+        paid: LoanPayout,
+        debt_left: Debt,
+        length: Length,
+    },
+    NoLoan,
+    NotEnoughLength(Length),
+}
+
 #[derive(Clone)]
 pub struct Loans {
     pool: sqlx::Pool<Postgres>,
     chats: Chats,
-    payout_ratio: PayoutRatio,
+    config: LoanConfig,
 }
 
 impl Loans {
     pub fn new(pool: sqlx::Pool<Postgres>, cfg: &config::AppConfig) -> Self {
         let chats = Chats::new(pool.clone(), cfg.features);
-        let payout_ratio = cfg.loan_payout_ratio;
-        Self { pool, chats, payout_ratio }
+        Self { pool, chats, config: cfg.loan }
     }
 
     #[autometrics]
     #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id))]
     pub async fn get_active_loan(&self, uid: UserId, chat_id: &ChatIdKind) -> anyhow::Result<Option<Loan>> {
         let maybe_loan = sqlx::query_as!(LoanEntity,
-            r#"SELECT id AS "id: LoanId", debt AS "debt: Debt", payout_ratio AS "payout_ratio: PayoutRatio" FROM loans
+            r#"SELECT id AS "id: LoanId", loan_balance(debt, interest_rate, accrued_at) AS "debt!: Debt",
+                        payout_ratio AS "payout_ratio: PayoutRatio", interest_rate AS "interest_rate: Ratio",
+                        due_date, COALESCE(due_date < current_date, false) AS "overdue!"
+                    FROM loans
                     WHERE uid = $1 AND
                     chat_id = (SELECT id FROM Chats WHERE chat_id = $2::bigint OR chat_instance = $2::text)
                     AND repaid_at IS NULL"#,
@@ -60,9 +82,18 @@ impl Loans {
         Ok(maybe_loan)
     }
 
+    /// The payout ratio is the one the user agreed to, which may be the chat's own rather than the
+    /// bot's (see `/settings`). The interest rate and the term are the current ones: refinancing
+    /// puts the whole balance under them.
     #[autometrics]
-    #[tracing::instrument(skip_all, fields(uid = user_id.value(), chat_id = %chat_id, value = %value))]
-    pub async fn borrow(&self, user_id: UserId, chat_id: &ChatIdKind, value: Debt) -> anyhow::Result<BorrowResult> {
+    #[tracing::instrument(skip_all, fields(uid = user_id.value(), chat_id = %chat_id, value = %value, payout_ratio = payout_ratio.value()))]
+    pub async fn borrow(
+        &self,
+        user_id: UserId,
+        chat_id: &ChatIdKind,
+        value: Debt,
+        payout_ratio: PayoutRatio,
+    ) -> anyhow::Result<BorrowResult> {
         let chat_internal_id = self.chats.get_internal_id(chat_id).await?;
         let mut tx = self.pool.begin().await?;
 
@@ -74,20 +105,22 @@ impl Loans {
         }
 
         match get_active_loan_in_tx(&mut tx, user_id, chat_internal_id).await? {
-            Some(LoanEntity { id, .. }) => refinance_loan(&mut tx, id, value, self.payout_ratio).await?,
-            None => create_loan(&mut tx, chat_internal_id, user_id, value, self.payout_ratio).await?
+            Some(LoanEntity { id, .. }) => refinance_loan(&mut tx, id, value, payout_ratio, self.config).await?,
+            None => create_loan(&mut tx, chat_internal_id, user_id, value, payout_ratio, self.config).await?
         };
         let borrowed_length = LengthChange::signed(value.saturating_into());
-        Dicks::grow_no_attempts_check_internal(&mut *tx, chat_internal_id, user_id, borrowed_length).await?;
+        Dicks::grow_no_attempts_check_internal(&mut *tx, chat_internal_id, user_id, borrowed_length, LengthChangeReason::Loan).await?;
 
         tx.commit().await?;
         Ok(BorrowResult::Granted)
     }
 
+    /// The interest accrued since the last payment is added to the debt before the payout is taken.
     #[autometrics]
     #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id, value = %value))]
     pub async fn pay(&self, uid: UserId, chat_id: &ChatIdKind, value: LoanPayout) -> anyhow::Result<()> {
-        sqlx::query!("UPDATE Loans SET debt = debt - $3
+        sqlx::query!("UPDATE Loans SET debt = GREATEST(loan_balance(debt, interest_rate, accrued_at) - $3, 0),
+                            accrued_at = current_date
                         WHERE uid = $1 AND
                         chat_id = (SELECT id FROM Chats WHERE chat_id = $2::bigint OR chat_instance = $2::text)
                         AND repaid_at IS NULL",
@@ -98,6 +131,34 @@ impl Loans {
             .and_then(ensure_only_one_row_updated)
             .context(format!("couldn't pay for a loan: {chat_id}, {uid}, {value}"))
     }
+
+    /// Pays the debt down with the length of its owner, both in one transaction. No more than the
+    /// balance is taken, and nothing at all if the length can't cover the payment.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(uid = uid.value(), chat_id = %chat_id, value = %value))]
+    pub async fn repay(&self, uid: UserId, chat_id: &ChatIdKind, value: LoanPayout) -> anyhow::Result<RepayResult> {
+        let chat_internal_id = self.chats.get_internal_id(chat_id).await?;
+        let mut tx = self.pool.begin().await?;
+
+        // the same order of the locks as in the borrowing: the dick first, the loan second
+        let length = fetch_length_locked(&mut tx, uid, chat_internal_id).await?.unwrap_or_default();
+        let Some(loan) = get_active_loan_in_tx(&mut tx, uid, chat_internal_id).await? else {
+            return Ok(RepayResult::NoLoan)
+        };
+        let paid = LoanPayout::new(value.value().min(loan.debt.value().saturating_into()));
+        if length < i64::from(paid.value()) {
+            return Ok(RepayResult::NotEnoughLength(Length::new(length)))
+        }
+
+        repay_loan(&mut tx, loan.id, paid).await?;
+        let repayment = LengthChange::signed(-i64::from(paid.value()));
+        let length = Dicks::grow_no_attempts_check_internal(&mut *tx, chat_internal_id, uid, repayment, LengthChangeReason::LoanPayout).await?
+            .context(format!("the dick of {uid} in {chat_internal_id} has disappeared during the repayment"))?;
+
+        tx.commit().await?;
+        let debt_left = Debt::new(loan.debt.value().saturating_sub(paid.value().into()));
+        Ok(RepayResult::Repaid { paid, debt_left, length })
+    }
 }
 
 #[autometrics]
@@ -122,9 +183,13 @@ async fn get_active_loan_in_tx(
     chat_internal_id: InternalChatId,
 ) -> anyhow::Result<Option<LoanEntity>> {
     let maybe_loan = sqlx::query_as!(LoanEntity,
-            r#"SELECT id AS "id: LoanId", debt AS "debt: Debt", payout_ratio AS "payout_ratio: PayoutRatio" FROM loans
+            r#"SELECT id AS "id: LoanId", loan_balance(debt, interest_rate, accrued_at) AS "debt!: Debt",
+                        payout_ratio AS "payout_ratio: PayoutRatio", interest_rate AS "interest_rate: Ratio",
+                        due_date, COALESCE(due_date < current_date, false) AS "overdue!"
+                    FROM loans
                     WHERE uid = $1 AND chat_id = $2
-                    AND repaid_at IS NULL"#,
+                    AND repaid_at IS NULL
+                    FOR UPDATE"#,
                 uid as UserId, chat_internal_id as InternalChatId)
         .fetch_optional(&mut **tx)
         .await
@@ -140,9 +205,12 @@ async fn create_loan(
     uid: UserId,
     value: Debt,
     payout_ratio: PayoutRatio,
+    terms: LoanConfig,
 ) -> anyhow::Result<()> {
-    sqlx::query!("INSERT INTO Loans (chat_id, uid, debt, payout_ratio) VALUES ($1, $2, $3, $4)",
-                chat_internal_id as InternalChatId, uid as UserId, value as Debt, payout_ratio as PayoutRatio)
+    sqlx::query!("INSERT INTO Loans (chat_id, uid, debt, payout_ratio, interest_rate, due_date)
+                    VALUES ($1, $2, $3, $4, $5, current_date + NULLIF($6::bigint::int, 0))",
+                chat_internal_id as InternalChatId, uid as UserId, value as Debt, payout_ratio as PayoutRatio,
+                terms.interest_rate as Ratio, terms.term as DaysCount)
         .execute(&mut **tx)
         .await
         .map(ensure_only_one_row_updated)
@@ -156,11 +224,30 @@ async fn refinance_loan(
     id: LoanId,
     value: Debt,
     payout_ratio: PayoutRatio,
+    terms: LoanConfig,
 ) -> anyhow::Result<()> {
-    sqlx::query!("UPDATE Loans l SET debt = l.debt + $2, payout_ratio = $3 WHERE id = $1",
-                id as LoanId, value as Debt, payout_ratio as PayoutRatio)
+    sqlx::query!("UPDATE Loans l SET debt = loan_balance(l.debt, l.interest_rate, l.accrued_at) + $2, accrued_at = current_date,
+                        payout_ratio = $3, interest_rate = $4, due_date = current_date + NULLIF($5::bigint::int, 0)
+                    WHERE id = $1",
+                id as LoanId, value as Debt, payout_ratio as PayoutRatio, terms.interest_rate as Ratio, terms.term as DaysCount)
         .execute(&mut **tx)
         .await
         .map(ensure_only_one_row_updated)
         .context(format!("couldn't update the loan with id = {id} (additional value is {value})"))?
 }
+
+#[autometrics]
+#[tracing::instrument(skip_all, fields(loan_id = id.value(), value = %value))]
+async fn repay_loan(
+    tx: &mut Transaction<'_, Postgres>,
+    id: LoanId,
+    value: LoanPayout,
+) -> anyhow::Result<()> {
+    sqlx::query!("UPDATE Loans SET debt = GREATEST(loan_balance(debt, interest_rate, accrued_at) - $2, 0), accrued_at = current_date
+                    WHERE id = $1",
+                id as LoanId, value as LoanPayout)
+        .execute(&mut **tx)
+        .await
+        .map(ensure_only_one_row_updated)
+        .context(format!("couldn't repay {value} of the loan with id = {id}"))?
+}
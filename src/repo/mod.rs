@@ -10,6 +10,10 @@ mod shrinks;
 mod announcements;
 mod deletions;
 mod broadcasts;
+mod achievements;
+mod inventory;
+mod gifts;
+mod seasons;
 
 #[cfg(test)]
 pub(crate) mod test;
@@ -29,6 +33,10 @@ pub use shrinks::*;
 pub use announcements::*;
 pub use deletions::*;
 pub use broadcasts::*;
+pub use achievements::*;
+pub use inventory::*;
+pub use gifts::*;
+pub use seasons::*;
 use crate::config;
 use crate::config::DatabaseConfig;
 use crate::domain::primitives::chat::ChatIdKind;
@@ -47,6 +55,10 @@ pub struct Repositories {
     pub shrinks: Shrinks,
     pub deletions: ScheduledDeletions,
     pub broadcasts: ScheduledBroadcasts,
+    pub achievements: Achievements,
+    pub inventory: Inventory,
+    pub gifts: Gifts,
+    pub seasons: Seasons,
 }
 
 impl Repositories {
@@ -64,6 +76,10 @@ impl Repositories {
             shrinks: Shrinks::new(db_conn.clone()),
             deletions: ScheduledDeletions::new(db_conn.clone()),
             broadcasts: ScheduledBroadcasts::new(db_conn.clone()),
+            achievements: Achievements::new(db_conn.clone()),
+            inventory: Inventory::new(db_conn.clone(), config.features),
+            gifts: Gifts::new(db_conn.clone(), config),
+            seasons: Seasons::new(db_conn.clone()),
         }
     }
 }
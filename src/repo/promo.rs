@@ -1,10 +1,10 @@
 use autometrics::autometrics;
 use std::fmt::Debug;
 use anyhow::{anyhow, Context};
+use chrono::NaiveDate;
 use sqlx::{FromRow, Postgres};
-use crate::domain::primitives::{AffectedRows, LengthChange, PromoBonus, UserId};
-#[cfg(test)]
-use crate::domain::primitives::{PromoCapacity, PromoCode};
+use domain_types::traits::SaturatingInto;
+use crate::domain::primitives::{AffectedRows, DaysCount, LengthChange, PromoBonus, PromoCapacity, PromoCode, UserId};
 use crate::repository;
 
 const PROMOCODE_ACTIVATIONS_PK: &str = "promo_code_activations_pkey";
@@ -20,6 +20,12 @@ pub enum ActivationError {
     NoActivationsLeft,
     NoDicks,
     AlreadyActivated,
+    /// A referral code is only for those who have registered a few days ago at most.
+    NotNewcomer,
+    /// Only the first referral code a user activates pays out, or one newcomer would be enough to
+    /// reward everybody who had asked them.
+    AlreadyInvited,
+    OwnCode,
     Other(anyhow::Error)
 }
 
@@ -29,27 +35,111 @@ impl <T: Into<anyhow::Error>> From<T> for ActivationError {
     }
 }
 
-#[cfg(test)]
 pub struct PromoCodeParams {
     pub code: PromoCode,
     pub bonus_length: PromoBonus,
     pub capacity: PromoCapacity,
+    /// Today if not set.
+    pub since: Option<NaiveDate>,
+    /// The last day the code works; no end if not set.
+    pub until: Option<NaiveDate>,
+}
+
+/// An ordinary promo code as the owner sees it in the list.
+pub struct PromoCodeStats {
+    pub code: String,
+    pub bonus_length: PromoBonus,
+    pub capacity: PromoCapacity,
+    pub since: NaiveDate,
+    pub until: Option<NaiveDate>,
+    pub activations: i64,
+}
+
+/// What a personal referral code pays, how many more newcomers it pays for, and for how long
+/// after the registration a user is still a newcomer.
+pub struct ReferralCodeParams {
+    pub invitee_bonus: PromoBonus,
+    pub inviter_bonus: PromoBonus,
+    pub max_invites: PromoCapacity,
+    pub newcomer_days: DaysCount,
+}
+
+pub struct ReferralCode {
+    pub code: String,
+    pub invites_left: PromoCapacity,
 }
 
 #[derive(FromRow)]
 struct PromoCodeInfo {
     found_code: String,
     bonus_length: PromoBonus,
+    inviter_uid: Option<UserId>,
+    inviter_bonus: Option<PromoBonus>,
+    newcomer_days: Option<i32>,
 }
 
 repository!(Promo,
-    #[cfg(test)]
-    pub async fn create_promo_code(&self, p: PromoCodeParams) -> anyhow::Result<()> {
-        sqlx::query!("INSERT INTO Promo_Codes (code, bonus_length, capacity) VALUES ($1, $2, $3)",
-                p.code as PromoCode, p.bonus_length as PromoBonus, p.capacity as PromoCapacity)
+    /// Returns `false` if there is such a code already, whatever its case.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(code = %p.code))]
+    pub async fn create_promo_code(&self, p: PromoCodeParams) -> anyhow::Result<bool> {
+        let code = p.code.to_string();
+        sqlx::query!("INSERT INTO Promo_Codes (code, bonus_length, capacity, since, until)
+                SELECT $1::varchar, $2::int, $3::int, coalesce($4::date, current_date), $5::date
+                WHERE NOT EXISTS (SELECT 1 FROM Promo_Codes WHERE lower(code) = lower($1))
+                ON CONFLICT (code) DO NOTHING",
+                p.code as PromoCode, p.bonus_length as PromoBonus, p.capacity as PromoCapacity, p.since, p.until)
             .execute(&self.pool)
-            .await?;
-        Ok(())
+            .await
+            .map(|res| res.rows_affected() == 1)
+            .context(format!("couldn't create the promo code {code}"))
+    }
+,
+    /// The ordinary codes, the newest first. The personal codes of `/invite` are left out: there
+    /// is one for every user who has asked for it, and they are managed by the bot itself.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(limit))]
+    pub async fn list_promo_codes(&self, limit: i64) -> anyhow::Result<Vec<PromoCodeStats>> {
+        sqlx::query_as!(PromoCodeStats,
+            r#"SELECT pc.code, pc.bonus_length AS "bonus_length: PromoBonus", pc.capacity AS "capacity: PromoCapacity",
+                      pc.since, pc.until,
+                      (SELECT count(*) FROM Promo_Code_Activations pca WHERE pca.code = pc.code) AS "activations!"
+                FROM Promo_Codes pc
+                WHERE pc.inviter_uid IS NULL
+                ORDER BY pc.since DESC, pc.code
+                LIMIT $1"#,
+                limit)
+            .fetch_all(&self.pool)
+            .await
+            .context("couldn't list the promo codes")
+    }
+,
+    /// Moves the last day of the code and adds activations to it. Returns `false` if there is no
+    /// such ordinary code.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(code = %code))]
+    pub async fn extend_promo_code(&self, code: &str, until: Option<NaiveDate>, extra_capacity: PromoCapacity) -> anyhow::Result<bool> {
+        sqlx::query!("UPDATE Promo_Codes SET until = $2, capacity = capacity + $3
+                WHERE lower(code) = lower($1) AND inviter_uid IS NULL",
+                code, until, extra_capacity as PromoCapacity)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't extend the promo code {code}"))
+    }
+,
+    /// Ends the code yesterday, so that the activations left and the dates it worked on stay in
+    /// the list. Returns `false` if there is no such ordinary code.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(code = %code))]
+    pub async fn disable_promo_code(&self, code: &str) -> anyhow::Result<bool> {
+        sqlx::query!("UPDATE Promo_Codes SET until = current_date - 1
+                WHERE lower(code) = lower($1) AND inviter_uid IS NULL",
+                code)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't disable the promo code {code}"))
     }
 ,
     #[autometrics]
@@ -57,9 +147,13 @@ repository!(Promo,
     pub async fn activate(&self, user_id: UserId, code: &str) -> Result<ActivationResult, ActivationError> {
         let mut tx = self.pool.begin().await?;
 
-        let PromoCodeInfo { found_code, bonus_length } = Self::find_code_length_and_decr_capacity(&mut tx, code)
-            .await?
-            .ok_or(ActivationError::NoActivationsLeft)?;
+        let PromoCodeInfo { found_code, bonus_length, inviter_uid, inviter_bonus, newcomer_days } =
+            Self::find_code_length_and_decr_capacity(&mut tx, code)
+                .await?
+                .ok_or(ActivationError::NoActivationsLeft)?;
+        if let Some(inviter_uid) = inviter_uid {
+            Self::check_invitee(&mut tx, user_id, inviter_uid, &found_code, newcomer_days.unwrap_or_default()).await?;
+        }
         // The bonus may be negative: such a promo code shrinks the dick instead of growing it.
         let bonus = LengthChange::signed(bonus_length.value().into());
         let chats_affected = Self::grow_dicks(&mut tx, user_id, bonus_length).await?;
@@ -80,6 +174,10 @@ repository!(Promo,
                     Err(e) => ActivationError::Other(e)
                 }
             })?;
+        // The inviter may have no dicks at all yet: the newcomer is paid anyway.
+        if let (Some(inviter_uid), Some(inviter_bonus)) = (inviter_uid, inviter_bonus) {
+            Self::grow_dicks(&mut tx, inviter_uid, inviter_bonus).await?;
+        }
 
         tx.commit().await?;
         Ok(ActivationResult{ chats_affected, bonus_length: bonus })
@@ -97,7 +195,8 @@ repository!(Promo,
                     (current_date BETWEEN since AND until
                     OR
                     current_date >= since AND until IS NULL)
-                RETURNING bonus_length as \"bonus_length: PromoBonus\", code as found_code",
+                RETURNING bonus_length as \"bonus_length: PromoBonus\", code as found_code,
+                    inviter_uid as \"inviter_uid: UserId\", inviter_bonus as \"inviter_bonus: PromoBonus\", newcomer_days",
                 code)
             .fetch_optional(&mut **tx)
             .await
@@ -111,7 +210,13 @@ repository!(Promo,
         user_id: UserId,
         bonus: PromoBonus,
     ) -> anyhow::Result<AffectedRows> {
-        let rows_affected = sqlx::query!("UPDATE Dicks SET bonus_attempts = (bonus_attempts + 1), length = (length + $2) WHERE uid = $1",
+        // one event per dick grown, so the count of the events is the count of the chats
+        let rows_affected = sqlx::query!("WITH grown AS (
+                        UPDATE Dicks SET bonus_attempts = (bonus_attempts + 1), length = (length + $2) WHERE uid = $1
+                        RETURNING chat_id, uid, length
+                    )
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT chat_id, uid, $2, length, 'promo'::length_change_reason FROM grown",
                 user_id as UserId, i64::from(bonus.value()))
             .execute(&mut **tx)
             .await
@@ -136,4 +241,63 @@ repository!(Promo,
             .context(format!("couldn't insert a promo code activation for {uid} and {code} with {affected_chats} affected chats"))?;
         Ok(())
     }
+,
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(uid = invitee.value(), inviter = inviter.value(), code = %code))]
+    async fn check_invitee(
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        invitee: UserId,
+        inviter: UserId,
+        code: &str,
+        newcomer_days: i32,
+    ) -> Result<(), ActivationError> {
+        if invitee == inviter {
+            return Err(ActivationError::OwnCode)
+        }
+        // Another activation of the same code is left for the primary key to refuse, so that the
+        // user is told they've used this very code already. A newcomer is counted from the
+        // registration rather than from the code: a code made long ago mustn't make everybody who
+        // has come since a newcomer for ever.
+        let invitee_info = sqlx::query!(
+                r#"SELECT u.created_at >= current_timestamp - make_interval(days => $2) AS "newcomer!",
+                          EXISTS (SELECT 1 FROM Promo_Code_Activations pca
+                                  JOIN Promo_Codes pc ON pc.code = pca.code
+                                  WHERE pca.uid = u.uid AND pc.inviter_uid IS NOT NULL AND pc.code <> $3) AS "invited!"
+                   FROM Users u WHERE u.uid = $1"#,
+                invitee as UserId, newcomer_days, code)
+            .fetch_optional(&mut **tx)
+            .await
+            .context(format!("couldn't check whether {invitee} is a newcomer"))?
+            .ok_or(ActivationError::NoDicks)?;
+        if invitee_info.invited {
+            Err(ActivationError::AlreadyInvited)
+        } else if !invitee_info.newcomer {
+            Err(ActivationError::NotNewcomer)
+        } else {
+            Ok(())
+        }
+    }
+,
+    /// The personal code of the inviter, created on the first call. The bonuses follow the current
+    /// configuration every time, while the invites left are what the cap leaves after the newcomers
+    /// who have already come.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(inviter = inviter.value()))]
+    pub async fn get_or_create_referral_code(&self, inviter: UserId, new_code: &str, p: ReferralCodeParams) -> anyhow::Result<ReferralCode> {
+        let newcomer_days: i32 = p.newcomer_days.value().saturating_into();
+        sqlx::query_as!(ReferralCode,
+            r#"INSERT INTO Promo_Codes AS pc (code, bonus_length, capacity, inviter_uid, inviter_bonus, newcomer_days)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (inviter_uid) WHERE inviter_uid IS NOT NULL DO UPDATE SET
+                    bonus_length = excluded.bonus_length,
+                    inviter_bonus = excluded.inviter_bonus,
+                    newcomer_days = excluded.newcomer_days,
+                    capacity = GREATEST(excluded.capacity - (SELECT count(*) FROM Promo_Code_Activations pca WHERE pca.code = pc.code), 0)
+                RETURNING code, capacity AS "invites_left: PromoCapacity""#,
+                new_code, p.invitee_bonus as PromoBonus, p.max_invites as PromoCapacity,
+                inviter as UserId, p.inviter_bonus as PromoBonus, newcomer_days)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't get or create the referral code of {inviter}"))
+    }
 );
@@ -1,9 +1,11 @@
 use autometrics::autometrics;
 use anyhow::Context;
 use num_traits::ToPrimitive;
-use sqlx::FromRow;
-use crate::domain::objects::{BattleStats, LoserStats, UserStats};
-use crate::domain::primitives::{BattlesCount, Bet, Length, UserId, WinStreak};
+use sqlx::{Executor, FromRow, Postgres};
+use domain_types::traits::SaturatingInto;
+use crate::domain::enums::BattleRanking;
+use crate::domain::objects::{BattleStats, Encounter, LoserStats, RankedFighter, Rivalry, UserStats};
+use crate::domain::primitives::{BattlesCount, Bet, Length, Limit, LoseStreak, Offset, Position, UserId, WinStreak};
 use crate::domain::primitives::chat::InternalChatId;
 use crate::repo::ChatIdKind;
 use crate::repository;
@@ -14,8 +16,51 @@ struct UserStatsEntity {
     battles_won: i32,
     win_streak_max: i16,
     win_streak_current: i16,
+    lose_streak_current: i16,
     acquired_length: i64,
     lost_length: i64,
+    mercies_shown: i32,
+}
+
+struct RivalryEntity {
+    battles: i64,
+    wins: i64,
+    net_length: i64,
+}
+
+struct EncounterEntity {
+    winner_uid: UserId,
+    bet: i64,
+    mercy_shown: bool,
+    fought_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TryFrom<EncounterEntity> for Encounter {
+    type Error = anyhow::Error;
+
+    fn try_from(entity: EncounterEntity) -> anyhow::Result<Self> {
+        Ok(Self {
+            winner: entity.winner_uid,
+            bet: entity.bet.to_u32().map(Bet::new)
+                .context("bet, fetched from the database, must fit into u32")?,
+            mercy_shown: entity.mercy_shown,
+            fought_at: entity.fought_at,
+        })
+    }
+}
+
+struct RankedFighterEntity {
+    uid: UserId,
+    name: String,
+    position: i64,
+    battles_total: i32,
+    battles_won: i32,
+    win_streak_max: i16,
+    win_streak_current: i16,
+    lose_streak_current: i16,
+    acquired_length: i64,
+    lost_length: i64,
+    mercies_shown: i32,
 }
 
 /// The winner's full stats, the loser's battle counts, and the loser's win streak before this
@@ -25,8 +70,10 @@ struct BattleResultEntity {
     winner_battles_won: i32,
     winner_win_streak_max: i16,
     winner_win_streak_current: i16,
+    winner_lose_streak_current: i16,
     winner_acquired_length: i64,
     winner_lost_length: i64,
+    winner_mercies_shown: i32,
     loser_battles_total: i32,
     loser_battles_won: i32,
     loser_prev_win_streak: Option<i16>,
@@ -45,8 +92,12 @@ impl TryFrom<UserStatsEntity> for UserStats {
                 .context("win_streak_max, fetched from the database, must not be negative")?,
             win_streak_current: entity.win_streak_current.to_u16().map(WinStreak::new)
                 .context("win_streak_current, fetched from the database, must not be negative")?,
+            lose_streak_current: entity.lose_streak_current.to_u16().map(LoseStreak::new)
+                .context("lose_streak_current, fetched from the database, must not be negative")?,
             acquired_length: Length::new(entity.acquired_length),
             lost_length: Length::new(entity.lost_length),
+            mercies_shown: entity.mercies_shown.to_u32().map(BattlesCount::new)
+                .context("mercies_shown, fetched from the database, must not be negative")?,
         })
     }
 }
@@ -60,8 +111,10 @@ impl TryFrom<&BattleResultEntity> for UserStats {
             battles_won: row.winner_battles_won,
             win_streak_max: row.winner_win_streak_max,
             win_streak_current: row.winner_win_streak_current,
+            lose_streak_current: row.winner_lose_streak_current,
             acquired_length: row.winner_acquired_length,
             lost_length: row.winner_lost_length,
+            mercies_shown: row.winner_mercies_shown,
         }.try_into()
     }
 }
@@ -80,6 +133,29 @@ impl TryFrom<&BattleResultEntity> for LoserStats {
     }
 }
 
+impl TryFrom<RankedFighterEntity> for RankedFighter {
+    type Error = anyhow::Error;
+
+    fn try_from(entity: RankedFighterEntity) -> anyhow::Result<Self> {
+        let stats = UserStatsEntity {
+            battles_total: entity.battles_total,
+            battles_won: entity.battles_won,
+            win_streak_max: entity.win_streak_max,
+            win_streak_current: entity.win_streak_current,
+            lose_streak_current: entity.lose_streak_current,
+            acquired_length: entity.acquired_length,
+            lost_length: entity.lost_length,
+            mercies_shown: entity.mercies_shown,
+        }.try_into()?;
+        Ok(Self {
+            uid: entity.uid,
+            name: entity.name,
+            position: Position::new(entity.position.saturating_into()),
+            stats,
+        })
+    }
+}
+
 repository!(BattleStatsRepo, with_(chats)_(Chats),
     #[autometrics]
     #[tracing::instrument(skip_all, fields(chat_id = %chat_id_kind, winner_id = winner_id.value(), loser_id = loser_id.value(), bet = %bet))]
@@ -95,6 +171,9 @@ repository!(BattleStatsRepo, with_(chats)_(Chats),
             r#"WITH loser_before AS (
                     SELECT win_streak_current FROM Battle_Stats WHERE chat_id = $1 AND uid = $3
                 ),
+                logged AS (
+                    INSERT INTO Battle_Log(chat_id, winner_uid, loser_uid, bet) VALUES ($1, $2, $3, $4)
+                ),
                 winner_upsert AS (
                     INSERT INTO Battle_Stats(uid, chat_id, battles_total, battles_won, win_streak_current, acquired_length)
                     VALUES ($2, $1, 1, 1, 1, $4)
@@ -102,22 +181,26 @@ repository!(BattleStatsRepo, with_(chats)_(Chats),
                         battles_total = Battle_Stats.battles_total + 1,
                         battles_won = Battle_Stats.battles_won + 1,
                         win_streak_current = Battle_Stats.win_streak_current + 1,
+                        lose_streak_current = 0,
                         acquired_length = Battle_Stats.acquired_length + $4
-                    RETURNING battles_total, battles_won, win_streak_max, win_streak_current, acquired_length, lost_length
+                    RETURNING battles_total, battles_won, win_streak_max, win_streak_current, lose_streak_current, acquired_length, lost_length, mercies_shown
                 ),
                 loser_upsert AS (
-                    INSERT INTO Battle_Stats(uid, chat_id, battles_total, battles_won, win_streak_current, lost_length)
-                    VALUES ($3, $1, 1, 0, 0, $4)
+                    INSERT INTO Battle_Stats(uid, chat_id, battles_total, battles_won, win_streak_current, lose_streak_current, lost_length)
+                    VALUES ($3, $1, 1, 0, 0, 1, $4)
                     ON CONFLICT (uid, chat_id) DO UPDATE SET
                         battles_total = Battle_Stats.battles_total + 1,
                         win_streak_current = 0,
+                        lose_streak_current = Battle_Stats.lose_streak_current + 1,
                         lost_length = Battle_Stats.lost_length + $4
                     RETURNING battles_total, battles_won
                 )
                 SELECT
                     w.battles_total AS "winner_battles_total!", w.battles_won AS "winner_battles_won!",
                     w.win_streak_max AS "winner_win_streak_max!", w.win_streak_current AS "winner_win_streak_current!",
+                    w.lose_streak_current AS "winner_lose_streak_current!",
                     w.acquired_length AS "winner_acquired_length!", w.lost_length AS "winner_lost_length!",
+                    w.mercies_shown AS "winner_mercies_shown!",
                     l.battles_total AS "loser_battles_total!", l.battles_won AS "loser_battles_won!",
                     lb.win_streak_current AS loser_prev_win_streak
                 FROM winner_upsert w, loser_upsert l
@@ -135,7 +218,7 @@ repository!(BattleStatsRepo, with_(chats)_(Chats),
     #[autometrics]
     #[tracing::instrument(skip_all, fields(chat_id = %chat_id_kind, uid = user_id.value()))]
     pub async fn get_stats(&self, chat_id_kind: &ChatIdKind, user_id: UserId) -> anyhow::Result<UserStats> {
-        sqlx::query_as!(UserStatsEntity, "SELECT battles_total, battles_won, win_streak_max, win_streak_current, acquired_length, lost_length FROM Battle_Stats \
+        sqlx::query_as!(UserStatsEntity, "SELECT battles_total, battles_won, win_streak_max, win_streak_current, lose_streak_current, acquired_length, lost_length, mercies_shown FROM Battle_Stats \
                 WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text) AND uid = $2",
             chat_id_kind.value() as String, user_id as UserId)
         .fetch_optional(&self.pool)
@@ -144,4 +227,173 @@ repository!(BattleStatsRepo, with_(chats)_(Chats),
         .unwrap_or_default()
         .try_into()
     }
+,
+    /// How much of the net loss in battles the loser-support perk has refunded to the user so far.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id_kind, uid = user_id.value()))]
+    pub async fn get_support_paid(&self, chat_id_kind: &ChatIdKind, user_id: UserId) -> anyhow::Result<Length> {
+        let paid = sqlx::query_scalar!("SELECT support_paid FROM Battle_Stats \
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text) AND uid = $2",
+            chat_id_kind.value() as String, user_id as UserId)
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("couldn't get the paid support for {chat_id_kind} and {user_id}"))?;
+        Ok(Length::new(paid.unwrap_or_default()))
+    }
+,
+    /// Adds `payout` to what the loser-support perk has refunded, along with the growth that pays it.
+    /// Returns `false` and records nothing if the refunds would exceed the net loss, which may have
+    /// shrunk since it was read.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(internal_chat_id = %chat_id_internal, uid = user_id.value(), payout = %payout))]
+    pub(super) async fn add_support_paid_internal<'c, E>(
+        executor: E,
+        chat_id_internal: InternalChatId,
+        user_id: UserId,
+        payout: Length,
+    ) -> anyhow::Result<bool>
+    where E: Executor<'c, Database = Postgres>,
+    {
+        let rows_affected = sqlx::query!("UPDATE Battle_Stats SET support_paid = support_paid + $3 \
+                WHERE chat_id = $1 AND uid = $2 AND support_paid + $3 <= lost_length - acquired_length",
+            chat_id_internal as InternalChatId, user_id as UserId, payout as Length)
+        .execute(executor)
+        .await
+        .context(format!("couldn't add {payout} to the paid support for {chat_id_internal} and {user_id}"))?
+        .rows_affected();
+        Ok(rows_affected == 1)
+    }
+,
+    /// The members of the chat who have fought at least once, best first. Ties are broken by the
+    /// number of battles won, then by the fewer battles fought, so that a win rate earned in many
+    /// battles is not outranked by the same rate of a single lucky one.
+    ///
+    /// The win rate is ranked by the lower bound of its Wilson score interval at 95% rather than as
+    /// is: a single won battle proves little, so 1 of 1 must not go above 8 of 10.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id_kind, ranking = %ranking, offset = %offset, limit = %limit))]
+    pub async fn get_top(
+        &self,
+        chat_id_kind: &ChatIdKind,
+        ranking: BattleRanking,
+        offset: Offset,
+        limit: Limit,
+    ) -> anyhow::Result<Vec<RankedFighter>> {
+        sqlx::query_as!(RankedFighterEntity,
+            r#"SELECT uid AS "uid!: UserId", name AS "name!", position AS "position!",
+                    battles_total AS "battles_total!", battles_won AS "battles_won!",
+                    win_streak_max AS "win_streak_max!", win_streak_current AS "win_streak_current!",
+                    lose_streak_current AS "lose_streak_current!",
+                    acquired_length AS "acquired_length!", lost_length AS "lost_length!",
+                    mercies_shown AS "mercies_shown!"
+                FROM (
+                    SELECT bs.uid, u.name, bs.battles_total, bs.battles_won, bs.win_streak_max, bs.win_streak_current,
+                           bs.lose_streak_current, bs.acquired_length, bs.lost_length, bs.mercies_shown,
+                           ROW_NUMBER() OVER (ORDER BY
+                               CASE WHEN $2 = 'win_rate' THEN (bs.battles_won + 1.9208
+                                   - 1.96 * sqrt(bs.battles_won::float8 * (bs.battles_total - bs.battles_won) / bs.battles_total + 0.9604))
+                                   / (bs.battles_total + 3.8416) END DESC,
+                               CASE WHEN $2 = 'wins' THEN bs.battles_won END DESC,
+                               CASE WHEN $2 = 'win_streak' THEN bs.win_streak_max END DESC,
+                               CASE WHEN $2 = 'net_length' THEN bs.acquired_length - bs.lost_length END DESC,
+                               bs.battles_won DESC, bs.battles_total, u.name
+                           ) AS position
+                    FROM Battle_Stats bs
+                    JOIN Users u USING (uid)
+                    WHERE bs.chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                      AND bs.battles_total > 0
+                ) ranked
+                ORDER BY position
+                OFFSET $3 LIMIT $4"#,
+                chat_id_kind.value() as String, ranking.to_string(), offset as Offset, limit as Limit)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the {ranking} top of {chat_id_kind} with offset = {offset} and limit = {limit}"))?
+            .into_iter()
+            .map(RankedFighter::try_from)
+            .collect()
+    }
+,
+    /// The head-to-head record of `user_id` against `rival_id` and their last `last_encounters`
+    /// battles. The pair is matched whichever of the two has won, the way the index is built.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id_kind, uid = user_id.value(), rival_id = rival_id.value()))]
+    pub async fn get_rivalry(
+        &self,
+        chat_id_kind: &ChatIdKind,
+        user_id: UserId,
+        rival_id: UserId,
+        last_encounters: Limit,
+    ) -> anyhow::Result<Rivalry> {
+        let record = sqlx::query_as!(RivalryEntity,
+            r#"SELECT count(*) AS "battles!",
+                    count(*) FILTER (WHERE winner_uid = $2) AS "wins!",
+                    coalesce(sum(CASE WHEN mercy_shown THEN 0 WHEN winner_uid = $2 THEN bet ELSE -bet END), 0)::bigint AS "net_length!"
+                FROM Battle_Log
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                  AND LEAST(winner_uid, loser_uid) = LEAST($2::bigint, $3::bigint)
+                  AND GREATEST(winner_uid, loser_uid) = GREATEST($2::bigint, $3::bigint)"#,
+                chat_id_kind.value() as String, user_id as UserId, rival_id as UserId)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't get the rivalry of {user_id} and {rival_id} in {chat_id_kind}"))?;
+        let last_encounters = sqlx::query_as!(EncounterEntity,
+            r#"SELECT winner_uid AS "winner_uid: UserId", bet, mercy_shown, fought_at
+                FROM Battle_Log
+                WHERE chat_id = (SELECT id FROM Chats WHERE chat_id = $1::bigint OR chat_instance = $1::text)
+                  AND LEAST(winner_uid, loser_uid) = LEAST($2::bigint, $3::bigint)
+                  AND GREATEST(winner_uid, loser_uid) = GREATEST($2::bigint, $3::bigint)
+                ORDER BY fought_at DESC, id DESC
+                LIMIT $4"#,
+                chat_id_kind.value() as String, user_id as UserId, rival_id as UserId, last_encounters as Limit)
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't get the last encounters of {user_id} and {rival_id} in {chat_id_kind}"))?
+            .into_iter()
+            .map(Encounter::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let battles = record.battles.to_u32().map(BattlesCount::new)
+            .context("the count of battles must fit into u32")?;
+        let wins = record.wins.to_u32().map(BattlesCount::new)
+            .context("the count of wins must fit into u32")?;
+        let losses = BattlesCount::new(battles.value().saturating_sub(wins.value()));
+        Ok(Rivalry { battles, wins, losses, net_length: Length::new(record.net_length), last_encounters })
+    }
+,
+    /// The winner has given the award back: the battle stays won, but neither the acquired nor the
+    /// lost length counts it any longer, and neither does the length the two have swapped. The floor at zero is for the rows summed up by a merge of
+    /// chats, which may have lost the battle the award is subtracted for.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id_kind, winner_id = winner_id.value(), loser_id = loser_id.value(), bet = %bet))]
+    pub async fn send_mercy(
+        &self,
+        chat_id_kind: &ChatIdKind,
+        winner_id: UserId,
+        loser_id: UserId,
+        bet: Bet,
+    ) -> anyhow::Result<()> {
+        let chat_id = self.chats.get_internal_id(chat_id_kind).await?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("UPDATE Battle_Stats SET mercies_shown = mercies_shown + 1, acquired_length = GREATEST(acquired_length - $3, 0) \
+                WHERE chat_id = $1 AND uid = $2",
+                chat_id as InternalChatId, winner_id as UserId, bet as Bet)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't record the mercy of {winner_id} in {chat_id}"))?;
+        sqlx::query!("UPDATE Battle_Stats SET lost_length = GREATEST(lost_length - $3, 0) WHERE chat_id = $1 AND uid = $2",
+                chat_id as InternalChatId, loser_id as UserId, bet as Bet)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't return the lost length to {loser_id} in {chat_id}"))?;
+        sqlx::query!("UPDATE Battle_Log SET mercy_shown = true WHERE id = ( \
+                    SELECT id FROM Battle_Log WHERE chat_id = $1 AND winner_uid = $2 AND loser_uid = $3 AND bet = $4 AND NOT mercy_shown \
+                    ORDER BY fought_at DESC, id DESC LIMIT 1)",
+                chat_id as InternalChatId, winner_id as UserId, loser_id as UserId, bet as Bet)
+            .execute(&mut *tx)
+            .await
+            .context(format!("couldn't log the mercy of {winner_id} to {loser_id} in {chat_id}"))?;
+        tx.commit().await?;
+        Ok(())
+    }
 );
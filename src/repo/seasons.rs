@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use autometrics::autometrics;
+use anyhow::Context;
+use chrono::NaiveDate;
+use domain_types::traits::SaturatingInto;
+use crate::domain::objects::SeasonChampion;
+use crate::domain::primitives::{Count, Length, Limit, UserId};
+use crate::domain::primitives::chat::{ChatIdKind, InternalChatId};
+use crate::repo::Chat;
+use crate::repository;
+
+struct SeasonChampionEntity {
+    number: i32,
+    ended_on: NaiveDate,
+    uid: UserId,
+    name: String,
+    length: Length,
+}
+
+impl From<SeasonChampionEntity> for SeasonChampion {
+    fn from(entity: SeasonChampionEntity) -> Self {
+        Self {
+            number: entity.number.saturating_into(),
+            ended_on: entity.ended_on,
+            uid: entity.uid,
+            name: entity.name,
+            length: entity.length,
+        }
+    }
+}
+
+repository!(Seasons,
+    /// Ends the seasons of those of `chat_ids` that are due at the midnight of `today`: archives
+    /// the lengths into `Season_Results`, resets them to zero and writes the resets down into
+    /// `Length_Events`. Like the daily shrink, it's one statement, so a chat is never left with its
+    /// results archived and its lengths kept, or the other way round.
+    ///
+    /// A chat whose admins have set the end of the season is due once that day is over and only
+    /// then, whatever `global_due` says; the end is forgotten with it, so the next season goes by
+    /// the global schedule again. A chat where no dick is above zero has nothing to archive and gets
+    /// no season: it would only have a champion with no length.
+    ///
+    /// A season takes back what has been grown, not what is owed. A length below zero is neither
+    /// archived nor reset, or a new season would be a way out of every loss. For the same reason
+    /// the loans of the bank and between members are carried into the next season as they are and
+    /// go on being paid out of its growths.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chats = chat_ids.len(), today = %today, global_due = global_due))]
+    pub async fn end_seasons(
+        &self,
+        chat_ids: &[InternalChatId],
+        today: NaiveDate,
+        global_due: bool,
+    ) -> anyhow::Result<Count<Chat>> {
+        sqlx::query_scalar!(
+            r#"WITH due AS (
+                    SELECT c.id FROM Chats c
+                    WHERE c.id = ANY($1)
+                      AND CASE WHEN c.settings->'season'->>'ends_on' IS NULL THEN $3::bool
+                               ELSE (c.settings->'season'->>'ends_on')::date < $2::date END
+                ),
+                forgotten AS (
+                    UPDATE Chats c SET settings = c.settings - 'season'
+                    FROM due WHERE c.id = due.id AND c.settings->'season' IS NOT NULL
+                ),
+                archived AS (
+                    SELECT d.chat_id, d.uid, d.length,
+                           ROW_NUMBER() OVER (PARTITION BY d.chat_id ORDER BY d.length DESC, d.updated_at DESC, d.uid) AS position
+                    FROM Dicks d JOIN due ON due.id = d.chat_id
+                    WHERE d.length > 0
+                ),
+                seasons AS (
+                    INSERT INTO Seasons (chat_id, number, ended_on)
+                    SELECT a.chat_id, coalesce((SELECT max(s.number) FROM Seasons s WHERE s.chat_id = a.chat_id), 0) + 1, $2::date - 1
+                    FROM (SELECT DISTINCT chat_id FROM archived) a
+                    RETURNING id, chat_id
+                ),
+                results AS (
+                    INSERT INTO Season_Results (season_id, uid, length, position)
+                    SELECT s.id, a.uid, a.length, a.position FROM archived a JOIN seasons s USING (chat_id)
+                ),
+                reset AS (
+                    UPDATE Dicks d SET length = 0, bonus_attempts = d.bonus_attempts + 1
+                    FROM archived a WHERE d.chat_id = a.chat_id AND d.uid = a.uid
+                    RETURNING d.chat_id, d.uid, a.length AS lost_length
+                ),
+                events AS (
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT chat_id, uid, -lost_length, 0, 'season'::length_change_reason FROM reset
+                )
+                SELECT count(*) AS "count!: Count<Chat>" FROM seasons"#,
+                chat_ids as &[InternalChatId], today, global_due)
+            .fetch_one(&self.pool)
+            .await
+            .context(format!("couldn't end the seasons of {} chats on {today}", chat_ids.len()))
+    },
+
+    /// The champions of the last `limit` seasons of the chat, the latest first.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, limit = %limit))]
+    pub async fn get_champions(&self, chat_id: &ChatIdKind, limit: Limit) -> anyhow::Result<Vec<SeasonChampion>> {
+        sqlx::query_as!(SeasonChampionEntity,
+            r#"SELECT s.number, s.ended_on, r.uid AS "uid: UserId", u.name, r.length AS "length: Length"
+                FROM Seasons s
+                JOIN Chats c ON c.id = s.chat_id
+                JOIN Season_Results r ON r.season_id = s.id AND r.position = 1
+                JOIN Users u ON u.uid = r.uid
+                WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text
+                ORDER BY s.number DESC
+                LIMIT $2"#,
+                chat_id.value() as String, limit as Limit)
+            .fetch_all(&self.pool)
+            .await
+            .map(|champions| champions.into_iter().map(SeasonChampion::from).collect())
+            .context(format!("couldn't get the season champions of {chat_id} with limit = {limit}"))
+    },
+
+    /// How many seasons of the chat each of its champions has won, for the badges of `/top`.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn get_season_wins(&self, chat_id: &ChatIdKind) -> anyhow::Result<HashMap<UserId, Count<SeasonChampion>>> {
+        sqlx::query!(
+            r#"SELECT r.uid AS "uid: UserId", count(*) AS "wins!: Count<SeasonChampion>"
+                FROM Seasons s
+                JOIN Chats c ON c.id = s.chat_id
+                JOIN Season_Results r ON r.season_id = s.id AND r.position = 1
+                WHERE c.chat_id = $1::bigint OR c.chat_instance = $1::text
+                GROUP BY r.uid"#,
+                chat_id.value() as String)
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows| rows.into_iter().map(|row| (row.uid, row.wins)).collect())
+            .context(format!("couldn't get the season wins in {chat_id}"))
+    }
+);
@@ -1,6 +1,6 @@
 use autometrics::autometrics;
 use anyhow::Context;
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use crate::domain::primitives::{Count, DaysCount, Length, Limit, Offset, Ratio, UserId, Username};
 use crate::domain::primitives::chat::{ChatIdKind, InternalChatId};
 use crate::repo::Chat;
@@ -82,6 +82,39 @@ repository!(Shrinks,
             .context("couldn't read a batch of chats to shrink")
     },
 
+    /// The same as [`Shrinks::select_chats_batch`], but only the chats whose day has started no
+    /// longer than `window` before `at`: those the shrink scheduler has to take on at its tick. A
+    /// window of a day takes on every chat.
+    ///
+    /// The time of the day of a chat depends on `at`, so it can't be indexed itself. Its time zone
+    /// can: the few hundred zones Postgres knows are sorted out first, and only the chats of those
+    /// at their midnight are read, off `idx_chats_time_zone`. So a tick reads only the chats it
+    /// takes on, and the table is walked once a day rather than at every one of the 96 ticks.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(after = ?after, limit = %limit, at = %at, window = %window))]
+    pub async fn select_midnight_chats_batch(
+        &self,
+        after: Option<InternalChatId>,
+        limit: Limit,
+        at: DateTime<Utc>,
+        window: Duration,
+    ) -> anyhow::Result<Vec<InternalChatId>> {
+        sqlx::query_scalar!(
+            r#"WITH zones AS (
+                    SELECT name FROM (
+                        SELECT name, $3::timestamptz AT TIME ZONE name AS local_at FROM pg_timezone_names
+                    ) z
+                    WHERE local_at - date_trunc('day', local_at) < make_interval(mins => $4::bigint::int)
+                )
+                SELECT id AS "id!: InternalChatId" FROM Chats
+                WHERE id > $1 AND coalesce(settings->>'timezone', 'UTC') IN (SELECT name FROM zones)
+                ORDER BY id LIMIT $2"#,
+                after.unwrap_or(InternalChatId::new(0)) as InternalChatId, limit as Limit, at, window.num_minutes())
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("couldn't read a batch of chats to shrink at {at}"))
+    },
+
     /// Shrinks the stale dicks of `chat_ids`, logs each shrink into `Stale_Dick_Shrinks` and queues
     /// one broadcast per chat that can be messaged. The whole thing is one statement: Postgres runs
     /// the unreferenced data-modifying CTEs to completion.
@@ -94,6 +127,9 @@ repository!(Shrinks,
     /// batches, so a `/grow` at midnight waits behind one batch instead of behind every victim in
     /// the database, and a batch that fails costs its own chats rather than the whole day.
     ///
+    /// The shrinks and the summaries are dated with the day of the time zone of each chat, which
+    /// is the day that has just started for the chats the scheduler brings here at their midnight.
+    ///
     /// The full `ratio` doesn't apply from day one of staleness: it ramps up linearly over
     /// `ramp_up_days`, starting at `ratio / ramp_up_days` on the first overdue day and reaching
     /// the full `ratio` once a dick has been overdue for `ramp_up_days` days (and staying there
@@ -114,8 +150,10 @@ repository!(Shrinks,
                            LEAST(d.length, GREATEST(1, CEIL(d.length * $1::double precision * LEAST(1.0,
                                (EXTRACT(DAY FROM (current_timestamp - d.updated_at))::int - $2::bigint::int + 1)::double precision
                                    / GREATEST($3::bigint::int, 1)
-                           ))::bigint)) AS loss
+                           ))::bigint)) AS loss,
+                           local_date(current_timestamp, c.settings->>'timezone') AS shrink_date
                     FROM Dicks d
+                    JOIN Chats c ON c.id = d.chat_id
                     WHERE d.chat_id = ANY($4)
                       AND d.length > 0
                       AND d.updated_at <= current_timestamp - make_interval(days => $2::bigint::int)
@@ -123,19 +161,23 @@ repository!(Shrinks,
                 updated AS (
                     UPDATE Dicks d SET length = d.length - v.loss, bonus_attempts = d.bonus_attempts + 1
                     FROM victims v WHERE d.uid = v.uid AND d.chat_id = v.chat_id
-                    RETURNING d.uid, d.chat_id, v.loss AS loss
+                    RETURNING d.uid, d.chat_id, v.loss AS loss, d.length, v.shrink_date
                 ),
                 logged AS (
-                    INSERT INTO Stale_Dick_Shrinks (chat_id, uid, lost_length)
-                    SELECT chat_id, uid, loss FROM updated
+                    INSERT INTO Stale_Dick_Shrinks (chat_id, uid, lost_length, created_at)
+                    SELECT chat_id, uid, loss, shrink_date FROM updated
+                ),
+                events AS (
+                    INSERT INTO Length_Events (chat_id, uid, change, length, reason)
+                    SELECT chat_id, uid, -loss, length, 'shrink'::length_change_reason FROM updated
                 ),
                 classified AS (
-                    SELECT u.uid, u.chat_id, c.chat_id IS NOT NULL AS messageable, c.is_unreachable
+                    SELECT u.uid, u.chat_id, u.shrink_date, c.chat_id IS NOT NULL AS messageable, c.is_unreachable
                     FROM updated u JOIN Chats c ON c.id = u.chat_id
                 ),
                 queued AS (
                     INSERT INTO Scheduled_Shrink_Broadcasts (chat_id, shrink_date)
-                    SELECT DISTINCT chat_id, current_date FROM classified
+                    SELECT DISTINCT chat_id, shrink_date FROM classified
                     WHERE messageable AND NOT is_unreachable
                     ON CONFLICT DO NOTHING
                     RETURNING chat_id
@@ -155,8 +197,9 @@ repository!(Shrinks,
         Ok(outcome)
     },
 
-    /// When the last shrink was logged, as the moment of the UTC midnight it belongs to. `None`
-    /// before the first run ever.
+    /// When the last shrink was made, as the moment the run wrote it down. `None` before the first
+    /// run that has shrunk anything. The date of a shrink is the day of its chat, which may be many
+    /// hours off the real moment, so the time is kept apart in `shrunk_at`.
     ///
     /// Published as a gauge, which is the only shape that answers "is the scheduler still alive?"
     /// across a restart: the table remembers, where a counter in this process does not.
@@ -164,7 +207,7 @@ repository!(Shrinks,
     #[tracing::instrument(skip_all)]
     pub async fn get_last_shrink_timestamp(&self) -> anyhow::Result<Option<DateTime<Utc>>> {
         sqlx::query_scalar!(
-            r#"SELECT (max(created_at)::timestamp AT TIME ZONE 'UTC') AS "at?" FROM Stale_Dick_Shrinks"#)
+            r#"SELECT max(shrunk_at) AS "at?" FROM Stale_Dick_Shrinks"#)
             .fetch_one(&self.pool)
             .await
             .context("couldn't read the time of the last shrink")
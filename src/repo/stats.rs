@@ -2,7 +2,10 @@ use autometrics::autometrics;
 use anyhow::Context;
 use num_traits::ToPrimitive;
 use sqlx::FromRow;
-use crate::domain::primitives::{Length, UserId};
+use domain_types::traits::SaturatingInto;
+use crate::domain::enums::GlobalRanking;
+use crate::domain::objects::RankedUser;
+use crate::domain::primitives::{Length, Limit, Offset, Position, UserId};
 use crate::repository;
 
 #[derive(FromRow)]
@@ -10,12 +13,22 @@ struct PersonalStatsEntity {
     chats: Option<i64>,
     max_length: Option<i64>,
     total_length: Option<i64>,
+    referrals: Option<i64>,
+}
+
+struct RankedUserEntity {
+    uid: UserId,
+    name: String,
+    length: i64,
+    position: i64,
 }
 
 pub struct PersonalStats {
     pub chats: u64,
     pub max_length: Length,
     pub total_length: Length,
+    /// Newcomers who have activated the user's `/invite` code.
+    pub referrals: u64,
 }
 
 impl From<PersonalStatsEntity> for PersonalStats {
@@ -24,6 +37,18 @@ impl From<PersonalStatsEntity> for PersonalStats {
             chats: value.chats.map(|x| x.to_u64().expect("chats count, fetched from the database, must fit into u64")).unwrap_or_default(),
             max_length: Length::new(value.max_length.unwrap_or_default()),
             total_length: Length::new(value.total_length.unwrap_or_default()),
+            referrals: value.referrals.map(|x| x.to_u64().expect("referrals count, fetched from the database, must fit into u64")).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<RankedUserEntity> for RankedUser {
+    fn from(entity: RankedUserEntity) -> Self {
+        Self {
+            uid: entity.uid,
+            name: entity.name,
+            length: Length::new(entity.length),
+            position: Position::new(entity.position.saturating_into()),
         }
     }
 }
@@ -35,7 +60,10 @@ repository!(PersonalStatsRepo,
         sqlx::query_as!(PersonalStatsEntity,
                 r#"SELECT count(chat_id) AS chats,
                           max(length) AS max_length,
-                          sum(length)::bigint AS total_length
+                          sum(length)::bigint AS total_length,
+                          (SELECT count(*) FROM Promo_Code_Activations pca
+                           JOIN Promo_Codes pc ON pc.code = pca.code
+                           WHERE pc.inviter_uid = $1) AS referrals
                    FROM Dicks WHERE uid = $1"#,
                 user_id as UserId)
             .fetch_one(&self.pool)
@@ -43,4 +71,35 @@ repository!(PersonalStatsRepo,
             .map(PersonalStats::from)
             .context(format!("couldn't get the personal stats of {user_id}"))
     }
+,
+    /// The users of every chat ranked by the longest of their dicks or by the sum of them. Those
+    /// who have opted out are left out before the positions are counted, so nobody can tell their
+    /// place by a gap, and so are the banned ones, whose names are no longer shown anywhere. The
+    /// namesakes of equal length are told apart by their ids, so that the pages neither repeat nor
+    /// miss anybody.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(ranking = %ranking, offset = %offset, limit = %limit))]
+    pub async fn get_global_top(&self, ranking: GlobalRanking, offset: Offset, limit: Limit) -> anyhow::Result<Vec<RankedUser>> {
+        sqlx::query_as!(RankedUserEntity,
+            r#"SELECT uid AS "uid!: UserId", name AS "name!", length AS "length!", position AS "position!"
+                FROM (
+                    SELECT uid, name, length, ROW_NUMBER() OVER (ORDER BY length DESC, name, uid) AS position
+                    FROM (
+                        SELECT u.uid, u.name,
+                               CASE WHEN $1 = 'total' THEN sum(d.length)::bigint ELSE max(d.length) END AS length
+                        FROM Dicks d
+                        JOIN Users u USING (uid)
+                        WHERE NOT u.hidden_from_global_top
+                          AND (u.banned_until IS NULL OR u.banned_until <= current_timestamp)
+                        GROUP BY u.uid, u.name
+                    ) lengths
+                ) ranked
+                ORDER BY position
+                OFFSET $2 LIMIT $3"#,
+                ranking.to_string(), offset as Offset, limit as Limit)
+            .fetch_all(&self.pool)
+            .await
+            .map(|users| users.into_iter().map(RankedUser::from).collect())
+            .context(format!("couldn't get the {ranking} global top with offset = {offset} and limit = {limit}"))
+    }
 );
@@ -0,0 +1,65 @@
+use crate::domain::enums::Achievement;
+use crate::domain::primitives::UserId;
+use crate::repo;
+use crate::repo::test::dicks::{create_dick, create_user};
+use crate::repo::test::{fresh_db, internal_chat_id, CHAT_ID_KIND, USER_ID};
+
+#[tokio::test]
+async fn test_all() {
+    let db = fresh_db().await;
+    let achievements = repo::Achievements::new(db.clone());
+    create_user(&db).await;
+    create_dick(&db).await;
+
+    let progress = achievements.get_progress(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the progress of a newcomer");
+    assert_eq!(progress.length, 0);
+    assert_eq!(progress.win_streak_max, 0);
+    assert!(!progress.loan_repaid);
+    assert_eq!(progress.dod_elections, 0);
+
+    let unlocked = achievements.get_unlocked(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the empty list");
+    assert!(unlocked.is_empty());
+
+    let newly = achievements.unlock(&CHAT_ID_KIND, USER_ID, &[Achievement::WinStreak, Achievement::HundredCm])
+        .await.expect("couldn't unlock the achievements");
+    assert_eq!(newly, vec![Achievement::HundredCm, Achievement::WinStreak]);
+
+    // Asking again is how every event ends, so it must not announce anything twice.
+    let newly = achievements.unlock(&CHAT_ID_KIND, USER_ID, &[Achievement::HundredCm, Achievement::LoanRepaid])
+        .await.expect("couldn't unlock the achievements again");
+    assert_eq!(newly, vec![Achievement::LoanRepaid]);
+
+    let unlocked: Vec<Achievement> = achievements.get_unlocked(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the unlocked achievements")
+        .into_iter()
+        .map(|a| a.achievement)
+        .collect();
+    assert_eq!(unlocked, vec![Achievement::HundredCm, Achievement::WinStreak, Achievement::LoanRepaid]);
+}
+
+#[tokio::test]
+async fn the_progress_is_read_from_every_table() {
+    let db = fresh_db().await;
+    let achievements = repo::Achievements::new(db.clone());
+    create_user(&db).await;
+    create_dick(&db).await;
+    let chat_id = internal_chat_id(&db).await;
+
+    sqlx::query!("UPDATE Dicks SET length = 150, bonus_attempts = 1 WHERE chat_id = $1 AND uid = $2", chat_id, USER_ID as UserId)
+        .execute(&db).await.expect("couldn't set the length");
+    sqlx::query!("INSERT INTO Battle_Stats (uid, chat_id, battles_total, battles_won, win_streak_current) VALUES ($1, $2, 12, 12, 12)",
+            USER_ID as UserId, chat_id)
+        .execute(&db).await.expect("couldn't create the battle stats");
+    sqlx::query!("INSERT INTO Loans (uid, chat_id, debt, payout_ratio, repaid_at) VALUES ($1, $2, 0, 0.1, current_date)",
+            USER_ID as UserId, chat_id)
+        .execute(&db).await.expect("couldn't create a repaid loan");
+
+    let progress = achievements.get_progress(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the progress");
+    assert_eq!(progress.length, 150);
+    assert_eq!(progress.win_streak_max, 12);
+    assert!(progress.loan_repaid);
+    assert_eq!(progress.dod_elections, 0);
+}
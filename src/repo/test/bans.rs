@@ -4,15 +4,26 @@ use crate::repo;
 use crate::domain::primitives::UserId;
 use crate::repo::test::{fresh_db, CHAT_ID, NAME, USER_ID};
 
+const RIVAL_UID: u64 = 54321;
+
 /// Every table `erase_user` must clear, as `(table, uid column)`. The guard test below fails when a
 /// new one appears in the schema, because then the function needs a new DELETE too.
-const TABLES_WITH_USER_ROWS: [(&str, &str); 7] = [
+const TABLES_WITH_USER_ROWS: [(&str, &str); 16] = [
+    ("achievements", "uid"),
+    ("battle_log", "loser_uid"),
+    ("battle_log", "winner_uid"),
     ("battle_stats", "uid"),
     ("dick_of_day", "winner_uid"),
     ("dicks", "uid"),
     ("imports", "uid"),
+    ("inventory", "uid"),
+    ("length_events", "uid"),
     ("loans", "uid"),
+    ("member_loans", "borrower_uid"),
+    ("member_loans", "lender_uid"),
     ("promo_code_activations", "uid"),
+    ("promo_codes", "inviter_uid"),
+    ("season_results", "uid"),
     ("stale_dick_shrinks", "uid"),
 ];
 
@@ -55,8 +66,8 @@ async fn erase_user_covers_every_table_with_a_uid() {
             FROM information_schema.columns
             WHERE table_schema = 'public'
               AND table_name <> 'users'
-              AND column_name IN ('uid', 'winner_uid')
-            ORDER BY table_name"#)
+              AND column_name IN ('uid', 'winner_uid', 'loser_uid', 'inviter_uid', 'lender_uid', 'borrower_uid')
+            ORDER BY table_name, column_name"#)
         .fetch_all(&db)
         .await.expect("couldn't read the schema");
     let found: Vec<(String, String)> = found.into_iter()
@@ -185,6 +196,12 @@ async fn fill_all_tables(db: &Pool<Postgres>) {
         .execute(db).await.expect("couldn't create the dick");
     sqlx::query!("INSERT INTO Battle_Stats (uid, chat_id) VALUES ($1, $2)", USER_ID as UserId, internal_chat_id)
         .execute(db).await.expect("couldn't create the battle stats");
+    // one battle won and one lost, against somebody who must keep nothing but their Users row
+    sqlx::query!("INSERT INTO Users (uid, name) VALUES ($1, 'Rival')", UserId::new(RIVAL_UID) as UserId)
+        .execute(db).await.expect("couldn't create the rival");
+    sqlx::query!("INSERT INTO Battle_Log (chat_id, winner_uid, loser_uid, bet) VALUES ($1, $2, $3, 5), ($1, $3, $2, 5)",
+            internal_chat_id, USER_ID as UserId, UserId::new(RIVAL_UID) as UserId)
+        .execute(db).await.expect("couldn't create the battle log");
     sqlx::query!("INSERT INTO Loans (uid, chat_id, debt, payout_ratio) VALUES ($1, $2, 100, 0.1)", USER_ID as UserId, internal_chat_id)
         .execute(db).await.expect("couldn't create the loan");
     sqlx::query!("INSERT INTO Dick_of_Day (chat_id, winner_uid) VALUES ($1, $2)", internal_chat_id, USER_ID as UserId)
@@ -193,10 +210,28 @@ async fn fill_all_tables(db: &Pool<Postgres>) {
         .execute(db).await.expect("couldn't create the promo code");
     sqlx::query!("INSERT INTO Promo_Code_Activations (uid, code, affected_chats) VALUES ($1, 'TEST', 1)", USER_ID as UserId)
         .execute(db).await.expect("couldn't create the promo code activation");
+    sqlx::query!("INSERT INTO Promo_Codes (code, bonus_length, capacity, inviter_uid, inviter_bonus) VALUES ('INVITE', 5, 10, $1, 5)", USER_ID as UserId)
+        .execute(db).await.expect("couldn't create the referral code");
     sqlx::query!("INSERT INTO Stale_Dick_Shrinks (chat_id, uid, lost_length) VALUES ($1, $2, 3)", internal_chat_id, USER_ID as UserId)
         .execute(db).await.expect("couldn't create the shrink");
     sqlx::query!("INSERT INTO Imports (chat_id, uid, original_length) VALUES ($1, $2, 7)", internal_chat_id, USER_ID as UserId)
         .execute(db).await.expect("couldn't create the import");
+    sqlx::query!("INSERT INTO Achievements (chat_id, uid, achievement) VALUES ($1, $2, 'hundred_cm')", internal_chat_id, USER_ID as UserId)
+        .execute(db).await.expect("couldn't create the achievement");
+    sqlx::query!("INSERT INTO Inventory (chat_id, uid, item, quantity) VALUES ($1, $2, 'shield', 1)", internal_chat_id, USER_ID as UserId)
+        .execute(db).await.expect("couldn't create the inventory");
+    sqlx::query!("INSERT INTO Length_Events (chat_id, uid, change, length, reason) VALUES ($1, $2, 5, 5, 'grow')", internal_chat_id, USER_ID as UserId)
+        .execute(db).await.expect("couldn't create the length event");
+    // one loan given and one taken, the other side being the rival again
+    sqlx::query!("INSERT INTO Member_Loans (chat_id, lender_uid, borrower_uid, debt, payout_ratio) VALUES ($1, $2, $3, 5, 0.1), ($1, $3, $2, 5, 0.1)",
+            internal_chat_id, USER_ID as UserId, UserId::new(RIVAL_UID) as UserId)
+        .execute(db).await.expect("couldn't create the loans between members");
+    let season_id = sqlx::query!("INSERT INTO Seasons (chat_id, number, ended_on) VALUES ($1, 1, current_date) RETURNING id", internal_chat_id)
+        .fetch_one(db)
+        .await.expect("couldn't create the season")
+        .id;
+    sqlx::query!("INSERT INTO Season_Results (season_id, uid, length, position) VALUES ($1, $2, 5, 1)", season_id, USER_ID as UserId)
+        .execute(db).await.expect("couldn't create the season result");
 }
 
 /// The one query in this file that can't be a `query_scalar!`: the macro needs a string literal,
@@ -1,10 +1,13 @@
 use std::time::Duration;
 use chrono::Utc;
 use sqlx::{Pool, Postgres};
-use crate::repo::{BroadcastState, ScheduledBroadcasts};
-use crate::domain::primitives::Limit;
-use crate::domain::primitives::chat::TelegramChatId;
-use crate::repo::test::{create_chat, far_future, fresh_db};
+use crate::repo;
+use crate::repo::{BroadcastKind, BroadcastState, ScheduledBroadcasts};
+use crate::domain::enums::TopPeriod;
+use crate::domain::primitives::{LengthChange, Limit};
+use crate::domain::primitives::chat::{InternalChatId, TelegramChatId};
+use crate::repo::test::{create_chat, far_future, fresh_db, internal_chat_id, CHAT_ID, CHAT_ID_KIND, USER_ID};
+use crate::repo::test::dicks::create_user;
 
 /// The enqueue is a CTE of the shrinking statement in production; here it is spelled out, so
 /// that these tests are about the queue rather than about the shrink.
@@ -219,3 +222,32 @@ async fn a_summary_for_a_chat_without_a_telegram_id_is_given_up_on() {
         .await.expect("couldn't claim the summaries");
     assert!(claimed_again.is_empty());
 }
+
+/// A podium is owed only where somebody has gained anything over the period, and only once: the
+/// chats where everybody has lost or nobody has played get nothing.
+#[tokio::test]
+async fn a_podium_is_queued_where_somebody_has_gained() {
+    let db = fresh_db().await;
+    let repo = ScheduledBroadcasts::new(db.clone());
+    create_user(&db).await;
+    repo::Dicks::new(db.clone(), Default::default())
+        .create_or_grow(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(5))
+        .await.expect("couldn't grow a dick");
+    let gainer = internal_chat_id(&db).await;
+    let idle = create_chat(&db, -1001234567890).await;
+    let chats = [InternalChatId::new(gainer), InternalChatId::new(idle)];
+    let week = TopPeriod::Week.range_of(Utc::now().date_naive());
+
+    let queued = repo.queue_podiums(&chats, TopPeriod::Week, week.start, week.end)
+        .await.expect("couldn't queue the podiums");
+    assert_eq!(queued, 1);
+    let queued = repo.queue_podiums(&chats, TopPeriod::Week, week.start, week.end)
+        .await.expect("couldn't queue the podiums once again");
+    assert_eq!(queued, 0, "the same podium must not be queued twice");
+
+    let claimed = repo.claim_due(Limit::new(10), far_future()).await.expect("couldn't claim the podiums");
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].chat_id, TelegramChatId::new(CHAT_ID));
+    assert_eq!(claimed[0].kind, BroadcastKind::WeekPodium);
+    assert_eq!(claimed[0].shrink_date, week.start);
+}
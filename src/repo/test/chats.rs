@@ -1,12 +1,13 @@
 use sqlx::{Pool, Postgres};
-use crate::domain::enums::MessageGroup;
+use crate::domain::enums::{GameSetting, LeagueRanking, MessageGroup};
+use crate::domain::objects::{ChatTimeZone, LeagueStanding};
 use crate::domain::primitives::{DaysCount, DelayMinutes, LengthChange, Limit, Offset, SupportedLanguage};
 use crate::domain::primitives::chat::{InternalChatId, TelegramChatId, TelegramChatInstanceId, TopicId};
 use crate::domain::primitives::chat::{ChatIdFull, ChatIdKind, ChatIdPartiality, ChatIdSource};
 use crate::repo;
 use crate::repo::ChatMigrationOutcome;
 use crate::repo::test::{fresh_db, repos, CHAT_ID, UID, USER_ID};
-use crate::repo::test::dicks::create_user;
+use crate::repo::test::dicks::{create_another_user_and_dick, create_user};
 
 #[tokio::test]
 async fn chat_language_roundtrip() {
@@ -182,6 +183,140 @@ async fn cleanup_settings_roundtrip() {
     assert_eq!(settings.compresses_inline(), None);
 }
 
+#[tokio::test]
+async fn game_settings_roundtrip() {
+    let db = fresh_db().await;
+    let chats = repo::Chats::new(db.clone(), Default::default());
+    let partiality = ChatIdPartiality::Specific(ChatIdKind::ID(TelegramChatId::new(CHAT_ID)));
+    let kind = partiality.kind();
+
+    let settings = chats.get_game_settings(&kind)
+        .await.expect("couldn't read the game settings");
+    assert!(settings.is_default());
+
+    chats.set_game_setting(&partiality, GameSetting::TopLimit, "15")
+        .await.expect("couldn't set the top limit");
+    chats.set_game_setting(&partiality, GameSetting::DodSelectionMode, "WEIGHTS")
+        .await.expect("couldn't set the selection mode");
+    let settings = chats.get_game_settings(&kind)
+        .await.expect("couldn't read the game settings");
+    assert_eq!(settings.get(GameSetting::TopLimit).as_deref(), Some("15"));
+    assert_eq!(settings.get(GameSetting::DodSelectionMode).as_deref(), Some("WEIGHTS"));
+    assert_eq!(settings.get(GameSetting::GrowthMax), None);
+
+    // The game settings share the column with the cleanup ones and must leave them alone.
+    chats.set_cleanup_group(&partiality, MessageGroup::Notice, DelayMinutes::new(5))
+        .await.expect("couldn't set the delay of the notices");
+
+    chats.forget_game_setting(&partiality, GameSetting::TopLimit)
+        .await.expect("couldn't forget the top limit");
+    let settings = chats.get_game_settings(&kind)
+        .await.expect("couldn't read the game settings");
+    assert_eq!(settings.get(GameSetting::TopLimit), None);
+    assert_eq!(settings.get(GameSetting::DodSelectionMode).as_deref(), Some("WEIGHTS"));
+
+    chats.reset_game_settings(&partiality)
+        .await.expect("couldn't reset the game settings");
+    let settings = chats.get_game_settings(&kind)
+        .await.expect("couldn't read the game settings");
+    assert!(settings.is_default());
+    let cleanup = chats.get_cleanup_settings(&kind)
+        .await.expect("couldn't read the cleanup settings");
+    assert_eq!(cleanup.get(MessageGroup::Notice), Some(DelayMinutes::new(5)));
+}
+
+#[tokio::test]
+async fn league_standings() {
+    let db = fresh_db().await;
+    let repo::Repositories { chats, dicks, .. } = repos(&db);
+    let chat = |n: i64| ChatIdPartiality::Specific(ChatIdKind::ID(TelegramChatId::new(CHAT_ID - n)));
+    let (alpha, beta, gamma, outsider) = (chat(0), chat(1), chat(2), chat(3));
+
+    create_user(&db).await;
+    dicks.create_or_grow(USER_ID, &alpha, LengthChange::signed(10))
+        .await.expect("couldn't grow the dick in the first chat");
+    create_another_user_and_dick(&db, &alpha, 2, "second", 30).await;
+    create_another_user_and_dick(&db, &beta, 3, "third", 25).await;
+    create_another_user_and_dick(&db, &outsider, 4, "fourth", 100).await;
+
+    let standings = chats.get_league_standings(&alpha.kind(), LeagueRanking::Average, Limit::new(10))
+        .await.expect("couldn't get the empty standings");
+    assert!(standings.is_empty());
+
+    chats.join_league(&alpha, "Alpha").await.expect("couldn't join the first chat");
+    chats.join_league(&beta, "Beta").await.expect("couldn't join the second chat");
+    chats.join_league(&gamma, "Gamma").await.expect("couldn't join the empty chat");
+
+    let lines_of = |standings: Vec<LeagueStanding>| standings.into_iter()
+        .map(|s| (s.title, s.length.value(), s.members.value(), s.position.value(), s.is_current))
+        .collect::<Vec<_>>();
+    let standings = chats.get_league_standings(&alpha.kind(), LeagueRanking::Average, Limit::new(10))
+        .await.expect("couldn't get the standings by the average");
+    assert_eq!(lines_of(standings), vec![
+        ("Beta".to_owned(), 25, 1, 1, false),
+        ("Alpha".to_owned(), 20, 2, 2, true),
+        ("Gamma".to_owned(), 0, 0, 3, false),
+    ]);
+    let standings = chats.get_league_standings(&alpha.kind(), LeagueRanking::Sum, Limit::new(10))
+        .await.expect("couldn't get the standings by the sum");
+    assert_eq!(lines_of(standings), vec![
+        ("Alpha".to_owned(), 40, 2, 1, true),
+        ("Beta".to_owned(), 25, 1, 2, false),
+        ("Gamma".to_owned(), 0, 0, 3, false),
+    ]);
+
+    // The asking chat comes after the first ones, wherever it stands.
+    let standings = chats.get_league_standings(&gamma.kind(), LeagueRanking::Average, Limit::new(1))
+        .await.expect("couldn't get the cut standings");
+    assert_eq!(lines_of(standings), vec![
+        ("Beta".to_owned(), 25, 1, 1, false),
+        ("Gamma".to_owned(), 0, 0, 3, true),
+    ]);
+
+    chats.leave_league(&beta).await.expect("couldn't leave the league");
+    chats.join_league(&alpha, "Alpha Renamed").await.expect("couldn't join the first chat again");
+    let standings = chats.get_league_standings(&outsider.kind(), LeagueRanking::Average, Limit::new(10))
+        .await.expect("couldn't get the standings after the leaving");
+    assert_eq!(lines_of(standings), vec![
+        ("Alpha Renamed".to_owned(), 20, 2, 1, false),
+        ("Gamma".to_owned(), 0, 0, 2, false),
+    ]);
+}
+
+#[tokio::test]
+async fn time_zone_roundtrip() {
+    let db = fresh_db().await;
+    let chats = repo::Chats::new(db.clone(), Default::default());
+    let partiality = ChatIdPartiality::Specific(ChatIdKind::ID(TelegramChatId::new(CHAT_ID)));
+    let kind = partiality.kind();
+
+    let zone = chats.find_time_zone("asia/KOLKATA")
+        .await.expect("couldn't look up the time zone");
+    assert_eq!(zone.as_deref(), Some("Asia/Kolkata"), "the name must be spelled the canonical way");
+    let zone = chats.find_time_zone("Mars/Olympus_Mons")
+        .await.expect("couldn't look up the unknown time zone");
+    assert_eq!(zone, None);
+
+    // Neither a chat the bot hasn't seen nor one without the setting has a zone but UTC.
+    let time_zone = chats.get_time_zone(&kind)
+        .await.expect("couldn't read the time zone of an unknown chat");
+    assert_eq!(time_zone, ChatTimeZone::default());
+
+    // India has no daylight saving time, so its offset is the same all year round.
+    chats.set_time_zone(&partiality, Some("Asia/Kolkata"))
+        .await.expect("couldn't set the time zone");
+    let time_zone = chats.get_time_zone(&kind)
+        .await.expect("couldn't read the time zone");
+    assert_eq!(time_zone.name.as_deref(), Some("Asia/Kolkata"));
+    assert_eq!(time_zone.utc_offset.local_minus_utc(), 5 * 3600 + 30 * 60);
+
+    chats.set_time_zone(&partiality, None)
+        .await.expect("couldn't clear the time zone");
+    let time_zone = chats.get_time_zone(&kind)
+        .await.expect("couldn't read the cleared time zone");
+    assert_eq!(time_zone, ChatTimeZone::default());
+}
+
 /// All three settings live in the same jsonb column, so each one's writes must leave the others
 /// alone.
 ///
@@ -474,7 +609,10 @@ async fn merge_moves_dependent_rows() {
     let chat = SplitChat::create(&db).await;
 
     chat.add_loan().await;
+    chat.add_member_loans().await;
     chat.add_battle_stats().await;
+    chat.add_battle_log().await;
+    chat.add_length_events().await;
     chat.add_dick_of_the_day().await;
     chat.add_shrinks().await;
 
@@ -482,9 +620,12 @@ async fn merge_moves_dependent_rows() {
 
     // everything that pointed at the merged-away row now points at the surviving one
     assert_eq!(chat.loan_chat_id().await, chat.id_row);
+    assert_eq!(chat.member_loans().await, (2, 0), "the loans between members of both chats must be kept");
 
     assert_eq!(chat.battle_stats().await, (7, 3), "the counters of both chats must be folded");
     assert_eq!(chat.battle_stats_left().await, 0);
+    assert_eq!(chat.battles_logged().await, (2, 0), "the battles of both chats must be kept");
+    assert_eq!(chat.length_events().await, (2, 0), "the history of both chats must be kept");
 
     // the win keeps the day it was won on instead of being restamped with today's date
     assert_eq!(chat.dick_of_the_day_days_ago().await, 1);
@@ -567,6 +708,17 @@ impl SplitChat {
             .await.expect("couldn't create a loan");
     }
 
+    /// The same pair in both chats, which the merge doesn't fold: the loans are repaid one by one.
+    async fn add_member_loans(&self) {
+        sqlx::query!("INSERT INTO Users (uid, name) VALUES ($1, 'Rival') ON CONFLICT DO NOTHING", UID + 1)
+            .execute(&self.db)
+            .await.expect("couldn't create the rival");
+        sqlx::query!("INSERT INTO Member_Loans (chat_id, lender_uid, borrower_uid, debt, payout_ratio) VALUES ($1, $3, $4, 5, 0.1), ($2, $3, $4, 3, 0.1)",
+                self.instance_row, self.id_row, UID, UID + 1)
+            .execute(&self.db)
+            .await.expect("couldn't create loans between members");
+    }
+
     /// The same user battled in both chats, so the two rows collide and have to be folded.
     async fn add_battle_stats(&self) {
         sqlx::query!("INSERT INTO Battle_Stats (uid, chat_id, battles_total, battles_won) VALUES ($1, $2, 3, 2), ($1, $3, 4, 1)",
@@ -575,6 +727,24 @@ impl SplitChat {
             .await.expect("couldn't create battle stats");
     }
 
+    /// One battle in either chat, fought by the same pair.
+    async fn add_battle_log(&self) {
+        sqlx::query!("INSERT INTO Users (uid, name) VALUES ($1, 'Rival') ON CONFLICT DO NOTHING", UID + 1)
+            .execute(&self.db)
+            .await.expect("couldn't create the rival");
+        sqlx::query!("INSERT INTO Battle_Log (chat_id, winner_uid, loser_uid, bet) VALUES ($1, $3, $4, 5), ($2, $4, $3, 5)",
+                self.instance_row, self.id_row, UID, UID + 1)
+            .execute(&self.db)
+            .await.expect("couldn't create the battle log");
+    }
+
+    async fn add_length_events(&self) {
+        sqlx::query!("INSERT INTO Length_Events (chat_id, uid, change, length, reason) VALUES ($1, $3, 1, 1, 'grow'), ($2, $3, 2, 2, 'grow')",
+                self.instance_row, self.id_row, UID)
+            .execute(&self.db)
+            .await.expect("couldn't create length events");
+    }
+
     /// The insertion trigger stamps `created_at` with today's date, so a dated row can only be
     /// planted past it — which is exactly what the merge has to do to keep the history intact.
     async fn add_dick_of_the_day(&self) {
@@ -611,6 +781,15 @@ impl SplitChat {
             .await.expect("the loan must survive the merge")
     }
 
+    /// The loans between members in the surviving chat and in the merged-away one.
+    async fn member_loans(&self) -> (i64, i64) {
+        let row = sqlx::query!(r#"SELECT count(*) FILTER (WHERE chat_id = $1) AS "kept!", count(*) FILTER (WHERE chat_id = $2) AS "left!" FROM Member_Loans"#,
+                self.id_row, self.instance_row)
+            .fetch_one(&self.db)
+            .await.expect("couldn't count the loans between members");
+        (row.kept, row.left)
+    }
+
     async fn battle_stats(&self) -> (i32, i32) {
         let row = sqlx::query!("SELECT battles_total, battles_won FROM Battle_Stats WHERE uid = $1 AND chat_id = $2",
                 UID, self.id_row)
@@ -625,6 +804,24 @@ impl SplitChat {
             .await.expect("couldn't count the leftover battle stats")
     }
 
+    /// The battles logged in the surviving chat and in the merged-away one.
+    async fn battles_logged(&self) -> (i64, i64) {
+        let row = sqlx::query!(r#"SELECT count(*) FILTER (WHERE chat_id = $1) AS "kept!", count(*) FILTER (WHERE chat_id = $2) AS "left!" FROM Battle_Log"#,
+                self.id_row, self.instance_row)
+            .fetch_one(&self.db)
+            .await.expect("couldn't count the logged battles");
+        (row.kept, row.left)
+    }
+
+    /// The length events in the surviving chat and in the merged-away one.
+    async fn length_events(&self) -> (i64, i64) {
+        let row = sqlx::query!(r#"SELECT count(*) FILTER (WHERE chat_id = $1) AS "kept!", count(*) FILTER (WHERE chat_id = $2) AS "left!" FROM Length_Events"#,
+                self.id_row, self.instance_row)
+            .fetch_one(&self.db)
+            .await.expect("couldn't count the length events");
+        (row.kept, row.left)
+    }
+
     async fn dick_of_the_day_days_ago(&self) -> i32 {
         sqlx::query_scalar!(r#"SELECT current_date - created_at AS "days_ago!" FROM Dick_of_Day WHERE chat_id = $1"#,
                 self.id_row)
@@ -1,7 +1,10 @@
+use chrono::{Days, Utc};
 use num_traits::ToPrimitive;
 use sqlx::{Pool, Postgres};
 use crate::config::FeatureToggles;
-use crate::domain::primitives::{Bet, DaysCount, Length, LengthChange, Limit, Offset, Position};
+use crate::domain::enums::{LengthChangeReason, TopPeriod};
+use crate::domain::objects::Streak;
+use crate::domain::primitives::{Bet, DaysCount, GrowStreak, Length, LengthChange, Limit, Offset, Position};
 use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
 use crate::repo;
 use crate::repo::test::{fresh_db, get_chat_id_and_dicks, internal_chat_id, repos, seed_aged_dick, user_id, CHAT_ID_KIND, NAME, UID, USER_ID};
@@ -32,7 +35,7 @@ async fn test_all() {
     assert_eq!(growth.new_length, increment);
     check_top(&dicks, &chat_id, increment).await;
 
-    let growth = dicks.set_dod_winner(&chat_id_partiality, user_id, increment_of(increment))
+    let growth = dicks.set_dod_winner(&chat_id_partiality, user_id, increment_of(increment), &[])
         .await
         .expect("couldn't elect a winner")
         .expect("the winner hasn't a dick");
@@ -42,6 +45,143 @@ async fn test_all() {
     check_top(&dicks, &chat_id, new_length).await;
 }
 
+#[tokio::test]
+async fn test_grow_streak() {
+    let db = fresh_db().await;
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    create_user(&db).await;
+    let chat_id = CHAT_ID_KIND;
+    let streak = |days: u32, grown_today: bool| Streak { days: GrowStreak::new(days), grown_today };
+
+    let no_dick = dicks.fetch_grow_streak(USER_ID, &chat_id)
+        .await.expect("couldn't fetch the streak of nobody");
+    assert_eq!(no_dick, Streak::default());
+
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect("couldn't grow a dick");
+    assert_eq!(dicks.fetch_grow_streak(USER_ID, &chat_id).await.expect("couldn't fetch the first streak"), streak(1, true));
+
+    // another growth the same day, by a bonus attempt, counts for nothing
+    move_last_growth(&db, 0).await;
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect("couldn't grow a dick once again");
+    assert_eq!(dicks.fetch_grow_streak(USER_ID, &chat_id).await.expect("couldn't fetch the same streak"), streak(1, true));
+
+    move_last_growth(&db, 1).await;
+    assert_eq!(dicks.fetch_grow_streak(USER_ID, &chat_id).await.expect("couldn't fetch yesterday's streak"), streak(1, false),
+        "the streak must last until the end of the day after the growth");
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect("couldn't grow a dick the next day");
+    assert_eq!(dicks.fetch_grow_streak(USER_ID, &chat_id).await.expect("couldn't fetch the extended streak"), streak(2, true));
+
+    move_last_growth(&db, 2).await;
+    assert_eq!(dicks.fetch_grow_streak(USER_ID, &chat_id).await.expect("couldn't fetch the broken streak"), streak(0, false));
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect("couldn't grow a dick after a break");
+    assert_eq!(dicks.fetch_grow_streak(USER_ID, &chat_id).await.expect("couldn't fetch the new streak"), streak(1, true));
+}
+
+/// The day of a growth is the day of the chat. At +14 hours its midnight is never a UTC one, so
+/// whatever time the test runs at, one of the two growths would go wrong with the UTC dates.
+#[tokio::test]
+async fn test_grow_in_the_time_zone_of_the_chat() {
+    let db = fresh_db().await;
+    let repo::Repositories { chats, dicks, .. } = repos(&db);
+    create_user(&db).await;
+    let chat_id = CHAT_ID_KIND;
+    let zone = "Pacific/Kiritimati";
+    chats.set_time_zone(&chat_id.clone().into(), Some(zone))
+        .await.expect("couldn't set the time zone");
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect("couldn't grow a dick");
+
+    move_last_growth_to_midnight(&db, zone, 0).await;
+    let error = dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect_err("the dick must not grow twice a day of the chat");
+    let code = error.downcast_ref::<sqlx::Error>()
+        .and_then(|e| e.as_database_error())
+        .and_then(|e| e.code())
+        .map(|code| code.into_owned());
+    assert_eq!(code.as_deref(), Some("GD0E1"));
+
+    move_last_growth_to_midnight(&db, zone, -1).await;
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(1))
+        .await.expect("couldn't grow a dick the next day of the chat");
+    let streak = dicks.fetch_grow_streak(USER_ID, &chat_id)
+        .await.expect("couldn't fetch the streak");
+    assert_eq!(streak, Streak { days: GrowStreak::new(2), grown_today: true });
+
+    dicks.set_dod_winner(&chat_id.clone().into(), USER_ID, increment_of(1), &[])
+        .await.expect("couldn't elect a winner")
+        .expect("the winner hasn't a dick");
+    let elected_today = sqlx::query_scalar!(
+            r#"SELECT created_at = local_date(current_timestamp, $1) AS "today!" FROM Dick_of_Day"#, zone)
+        .fetch_one(&db)
+        .await.expect("couldn't read the date of the election");
+    assert!(elected_today, "the Dick of the Day must be elected for the day of the chat");
+}
+
+#[tokio::test]
+async fn test_get_period_top() {
+    let db = fresh_db().await;
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    create_user(&db).await;
+    let chat_id = CHAT_ID_KIND;
+    let today = Utc::now().date_naive();
+    let this_week = TopPeriod::Week.range_of(today);
+    let top = |period| dicks.get_period_top(&chat_id, period, Limit::new(10));
+
+    dicks.create_or_grow(USER_ID, &chat_id.clone().into(), increment_of(10))
+        .await.expect("couldn't grow a dick");
+    create_another_user_and_dick(&db, &chat_id.clone().into(), 2, "Second", 3).await;
+    create_another_user_and_dick(&db, &chat_id.clone().into(), 3, "Loser", -3).await;
+    // imported length has been grown elsewhere
+    sqlx::query!("INSERT INTO Length_Events (chat_id, uid, change, length, reason) VALUES ($1, $2, 100, 103, 'import')",
+            internal_chat_id(&db).await, UID + 1)
+        .execute(&db).await.expect("couldn't import the length");
+    // the length passed from hand to hand hasn't been grown at all
+    for (change, reason) in [(50, "gift"), (20, "lend"), (5, "lend_repayment")] {
+        sqlx::query!("INSERT INTO Length_Events (chat_id, uid, change, length, reason) VALUES ($1, $2, $3, 100, $4::text::length_change_reason)",
+                internal_chat_id(&db).await, UID + 2, change, reason)
+            .execute(&db).await.expect("couldn't hand the length over");
+    }
+
+    let ranked = top(this_week.clone()).await.expect("couldn't fetch the top of the week");
+    let ranked = ranked.iter().map(|g| (g.owner_name.as_str(), g.gained.value())).collect::<Vec<_>>();
+    assert_eq!(ranked, vec![(NAME, 10), ("Second", 3)], "only those who have gained something are ranked");
+
+    sqlx::query!("UPDATE Length_Events SET created_at = $2::date - 1 WHERE uid = $1",
+            UID, this_week.start)
+        .execute(&db).await.expect("couldn't move the growth to the week before");
+    let ranked = top(this_week.clone()).await.expect("couldn't fetch the top of the week once again");
+    assert_eq!(ranked.len(), 1);
+    assert_eq!(ranked[0].owner_uid, user_id(UID + 1));
+    let last_week = TopPeriod::Week.range_of(this_week.start - Days::new(1));
+    let ranked = top(last_week).await.expect("couldn't fetch the top of the last week");
+    assert_eq!(ranked.len(), 1);
+    assert_eq!(ranked[0].owner_uid, USER_ID);
+    assert_eq!(ranked[0].length, 10);
+}
+
+/// Pretends the last growth was `shift_seconds` off the last midnight of the time zone, with a
+/// bonus attempt like [`move_last_growth`].
+async fn move_last_growth_to_midnight(db: &Pool<Postgres>, time_zone: &str, shift_seconds: i32) {
+    sqlx::query!("UPDATE Dicks SET updated_at = (date_trunc('day', current_timestamp AT TIME ZONE $2) AT TIME ZONE $2) + make_interval(secs => $3::int),
+                    bonus_attempts = bonus_attempts + 1 WHERE uid = $1",
+            UID, time_zone, shift_seconds)
+        .execute(db)
+        .await.expect("couldn't move the last growth to the midnight");
+}
+
+/// Pretends the last growth was `days_ago`, with a bonus attempt to get past the trigger refusing
+/// another growth today.
+async fn move_last_growth(db: &Pool<Postgres>, days_ago: i32) {
+    sqlx::query!("UPDATE Dicks SET updated_at = current_timestamp - make_interval(days => $2), bonus_attempts = bonus_attempts + 1 WHERE uid = $1",
+            UID, days_ago)
+        .execute(db)
+        .await.expect("couldn't move the last growth");
+}
+
 #[tokio::test]
 async fn test_all_with_top_pagination_disabled() {
     let db = fresh_db().await;
@@ -68,7 +208,7 @@ async fn test_all_with_top_pagination_disabled() {
     assert_eq!(growth.new_length, increment);
     check_top(&dicks, &chat_id, increment).await;
 
-    let growth = dicks.set_dod_winner(&chat_id_partiality, user_id, increment_of(increment))
+    let growth = dicks.set_dod_winner(&chat_id_partiality, user_id, increment_of(increment), &[])
         .await
         .expect("couldn't elect a winner")
         .expect("the winner hasn't a dick");
@@ -202,7 +342,7 @@ async fn test_pvp() {
     {
         create_user_and_dick_2(&db, chat_id_part, Default::default()).await;
         let uid2 = user_id(UID + 1);
-        let (gr1, gr2) = dicks.move_length(chat_id_part, uid, uid2, Bet::new(1))
+        let (gr1, gr2) = dicks.move_length(chat_id_part, uid, uid2, Bet::new(1), LengthChangeReason::Pvp)
             .await.expect("couldn't move the length");
 
         assert_eq!(gr1.new_length, 0);
@@ -212,6 +352,101 @@ async fn test_pvp() {
     }
 }
 
+#[tokio::test]
+async fn test_collect_pot() {
+    let db = fresh_db().await;
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let chat_id_part: &ChatIdPartiality = &CHAT_ID_KIND.into();
+    create_user(&db).await;
+    dicks.create_or_grow(USER_ID, chat_id_part, increment_of(5))
+        .await.expect("couldn't create a dick");
+    create_another_user_and_dick(&db, chat_id_part, 2, "second", 3).await;
+    create_another_user_and_dick(&db, chat_id_part, 3, "third", 4).await;
+    let (uid2, uid3) = (user_id(UID + 1), user_id(UID + 2));
+
+    let (winner, payers) = dicks.collect_pot(chat_id_part, &[USER_ID, uid3], uid2, Bet::new(2))
+        .await.expect("couldn't collect the pot")
+        .expect("both losers must pay");
+    assert_eq!(winner.new_length, 3 + 2 * 2);
+    assert_eq!(winner.pos_in_top, Some(Position::new(1)));
+    assert_eq!(payers, vec![USER_ID, uid3]);
+
+    let length1 = dicks.fetch_length(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length #1");
+    let length3 = dicks.fetch_length(uid3, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length #3");
+    assert_eq!(length1, 3);
+    assert_eq!(length3, 2);
+}
+
+#[tokio::test]
+async fn test_collect_pot_drops_the_losers_without_the_stake() {
+    let db = fresh_db().await;
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let chat_id_part: &ChatIdPartiality = &CHAT_ID_KIND.into();
+    create_user(&db).await;
+    dicks.create_or_grow(USER_ID, chat_id_part, increment_of(5))
+        .await.expect("couldn't create a dick");
+    create_another_user_and_dick(&db, chat_id_part, 2, "second", 3).await;
+    create_another_user_and_dick(&db, chat_id_part, 3, "third", 4).await;
+    let (uid2, uid3) = (user_id(UID + 1), user_id(UID + 2));
+
+    let (winner, payers) = dicks.collect_pot(chat_id_part, &[USER_ID, uid3], uid2, Bet::new(5))
+        .await.expect("couldn't collect the pot")
+        .expect("the first loser must pay");
+    assert_eq!(winner.new_length, 3 + 5);
+    assert_eq!(payers, vec![USER_ID]);
+    let length3 = dicks.fetch_length(uid3, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length #3");
+    assert_eq!(length3, 4);
+
+    let nobody = dicks.collect_pot(chat_id_part, &[USER_ID, uid3], uid2, Bet::new(5))
+        .await.expect("couldn't collect the pot");
+    assert!(nobody.is_none());
+    let length2 = dicks.fetch_length(uid2, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length #2");
+    assert_eq!(length2, 3 + 5);
+}
+
+#[tokio::test]
+async fn test_get_history() {
+    let db = fresh_db().await;
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let chat_id_part: &ChatIdPartiality = &CHAT_ID_KIND.into();
+    create_user(&db).await;
+    let history = dicks.get_history(USER_ID, &CHAT_ID_KIND, DaysCount::new(3))
+        .await.expect("couldn't fetch the history of nobody");
+    assert!(history.is_none());
+
+    dicks.create_or_grow(USER_ID, chat_id_part, increment_of(2))
+        .await.expect("couldn't create a dick");
+    sqlx::query!("UPDATE Length_Events SET created_at = current_timestamp - interval '1 day'")
+        .execute(&db)
+        .await.expect("couldn't move the growth to yesterday");
+    dicks.set_dod_winner(chat_id_part, USER_ID, increment_of(3), &[])
+        .await.expect("couldn't elect a winner")
+        .expect("the winner hasn't a dick");
+    dicks.grow_no_attempts_check(&CHAT_ID_KIND, USER_ID, increment_of(5), LengthChangeReason::Grow)
+        .await.expect("couldn't grow the dick again");
+
+    let history = dicks.get_history(USER_ID, &CHAT_ID_KIND, DaysCount::new(3))
+        .await.expect("couldn't fetch the history")
+        .expect("the dick has disappeared");
+    let lengths: Vec<i64> = history.days.iter().map(|day| day.length.value()).collect();
+    assert_eq!(lengths, vec![0, 2, 10], "the day before the first event must start from zero");
+    assert!(history.days.windows(2).all(|days| days[0].day < days[1].day), "the oldest day must go first");
+    let changes: Vec<(LengthChangeReason, i64)> = history.changes.iter().map(|c| (c.reason, c.change.value())).collect();
+    assert_eq!(changes, vec![(LengthChangeReason::Grow, 7), (LengthChangeReason::Dod, 3)]);
+
+    let today = dicks.get_history(USER_ID, &CHAT_ID_KIND, DaysCount::new(1))
+        .await.expect("couldn't fetch the history of today")
+        .expect("the dick has disappeared");
+    assert_eq!(today.days.len(), 1);
+    assert_eq!(today.days[0].length, 10);
+    let changes: Vec<(LengthChangeReason, i64)> = today.changes.iter().map(|c| (c.reason, c.change.value())).collect();
+    assert_eq!(changes, vec![(LengthChangeReason::Grow, 5), (LengthChangeReason::Dod, 3)]);
+}
+
 pub async fn create_user(db: &Pool<Postgres>) {
     let users = repo::Users::new(db.clone());
     users.create_or_update(USER_ID, NAME)
@@ -0,0 +1,133 @@
+use crate::{config, repo};
+use crate::config::GiftConfig;
+use crate::domain::enums::GiftKind;
+use crate::domain::objects::GrowthSettlement;
+use crate::domain::primitives::{GiftAmount, Length, LengthChange, LengthIncrement, LoanPayout, PayoutRatio, UserId};
+use crate::domain::primitives::chat::ChatIdPartiality;
+use crate::repo::GiftResult;
+use crate::repo::test::dicks::{create_another_user_and_dick, create_dick, create_user, create_user_and_dick_2};
+use crate::repo::test::{fresh_db, user_id, CHAT_ID, CHAT_ID_KIND, UID, USER_ID};
+use domain_types::literal;
+use sqlx::{Pool, Postgres};
+
+#[tokio::test]
+async fn test_all() {
+    let db = fresh_db().await;
+    let chat_id = CHAT_ID_KIND;
+    let gifts = repo::Gifts::new(db.clone(), &config::AppConfig {
+        gift: GiftConfig {
+            daily_given_cap: GiftAmount::new(10),
+            daily_received_cap: GiftAmount::new(8),
+        },
+        ..Default::default()
+    });
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    let no_lending = literal!(PayoutRatio = 0.0);
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    create_user_and_dick_2(&db, &ChatIdPartiality::Specific(chat_id.clone()), "Recipient").await;
+    create_another_user_and_dick(&db, &ChatIdPartiality::Specific(chat_id.clone()), 3, "Third", 1).await;
+    let (giver, recipient, third) = (USER_ID, user_id(UID + 1), user_id(UID + 2));
+    // bonus_attempts = 1 bypasses the "already grown today" trigger (it decrements to 0 after)
+    sqlx::query!("UPDATE Dicks SET length = 30, bonus_attempts = 1 WHERE uid = $1 AND chat_id = (SELECT id FROM Chats WHERE chat_id = $2)",
+            UID, CHAT_ID)
+        .execute(&db)
+        .await.expect("couldn't set the length of the giver");
+
+    let result = gifts.give(&chat_id, giver, recipient, GiftAmount::new(9), GiftKind::Gift, no_lending)
+        .await.expect("couldn't give more than the recipient may get");
+    assert_eq!(result, GiftResult::ReceivedCapReached(GiftAmount::new(8)));
+
+    let result = gifts.give(&chat_id, giver, recipient, GiftAmount::new(5), GiftKind::Gift, no_lending)
+        .await.expect("couldn't give a gift");
+    assert_eq!(result, GiftResult::Given { giver_length: Length::new(25), recipient_length: Length::new(6) });
+
+    // what the recipient got as a gift counts against the cap of a loan as well
+    let payout_ratio = literal!(PayoutRatio = 0.5);
+    let result = gifts.give(&chat_id, giver, recipient, GiftAmount::new(4), GiftKind::Lend, payout_ratio)
+        .await.expect("couldn't lend more than the recipient may get");
+    assert_eq!(result, GiftResult::ReceivedCapReached(GiftAmount::new(3)));
+    let result = gifts.give(&chat_id, giver, recipient, GiftAmount::new(3), GiftKind::Lend, payout_ratio)
+        .await.expect("couldn't lend");
+    assert_eq!(result, GiftResult::Given { giver_length: Length::new(22), recipient_length: Length::new(9) });
+
+    let result = gifts.give(&chat_id, giver, third, GiftAmount::new(3), GiftKind::Gift, no_lending)
+        .await.expect("couldn't give more than the giver may give away");
+    assert_eq!(result, GiftResult::GivenCapReached(GiftAmount::new(2)));
+
+    let result = gifts.give(&chat_id, third, giver, GiftAmount::new(5), GiftKind::Gift, no_lending)
+        .await.expect("couldn't give more than the length");
+    assert_eq!(result, GiftResult::NotEnoughLength(Length::new(1)));
+
+    let result = gifts.give(&chat_id, giver, user_id(UID + 10), GiftAmount::new(1), GiftKind::Gift, no_lending)
+        .await.expect("couldn't give to somebody without a dick");
+    assert_eq!(result, GiftResult::NoRecipient);
+
+    // half of a growth of 4 cm goes to the lender...
+    let payouts = gifts.plan_member_loan_payouts(recipient, &chat_id, LengthIncrement::new(4))
+        .await.expect("couldn't plan the payouts of the loan");
+    assert!(matches!(payouts.as_slice(), [GrowthSettlement::MemberLoanPayout { lender, payout, .. }]
+        if *lender == giver && *payout == LoanPayout::new(2)));
+
+    // ...but only along with a growth: the recipient has grown today already, so the growth is rejected
+    let recipient_chat_id = ChatIdPartiality::Specific(chat_id.clone());
+    let error = dicks.create_or_grow_with_settlements(recipient, &recipient_chat_id, LengthChange::signed(2), &payouts)
+        .await.expect_err("the dick must not grow twice a day");
+    let code = error.downcast_ref::<sqlx::Error>()
+        .and_then(|e| e.as_database_error())
+        .and_then(|e| e.code())
+        .map(|code| code.into_owned());
+    assert_eq!(code.as_deref(), Some("GD0E1"));
+    assert_eq!(fetch_length(&dicks, giver).await, 22, "the lender must not be paid for a rejected growth");
+    assert_eq!(fetch_length(&dicks, recipient).await, 9, "the borrower must not be charged for a rejected growth");
+    assert_eq!(fetch_debt(&db, recipient).await, 3, "the debt must stay as it was");
+
+    allow_another_growth(&db, recipient).await;
+    dicks.create_or_grow_with_settlements(recipient, &recipient_chat_id, LengthChange::signed(2), &payouts)
+        .await.expect("couldn't grow with the payouts");
+    assert_eq!(fetch_length(&dicks, giver).await, 24, "the lender must get the payout");
+    assert_eq!(fetch_debt(&db, recipient).await, 1);
+
+    // a plan made before the debt was paid doesn't pay it twice
+    allow_another_growth(&db, recipient).await;
+    dicks.create_or_grow_with_settlements(recipient, &recipient_chat_id, LengthChange::signed(2), &payouts)
+        .await.expect_err("the debt must not be paid more than is owed");
+    assert_eq!(fetch_length(&dicks, giver).await, 24);
+    assert_eq!(fetch_length(&dicks, recipient).await, 11);
+
+    // never more than what is left of the debt
+    let payouts = gifts.plan_member_loan_payouts(recipient, &chat_id, LengthIncrement::new(10))
+        .await.expect("couldn't plan the rest of the payouts");
+    assert!(matches!(payouts.as_slice(), [GrowthSettlement::MemberLoanPayout { payout, .. }] if *payout == LoanPayout::new(1)));
+    dicks.create_or_grow_with_settlements(recipient, &recipient_chat_id, LengthChange::signed(9), &payouts)
+        .await.expect("couldn't grow with the rest of the payouts");
+    let payouts = gifts.plan_member_loan_payouts(recipient, &chat_id, LengthIncrement::new(10))
+        .await.expect("couldn't check the repaid loan");
+    assert!(payouts.is_empty(), "the loan must be repaid already");
+
+    assert_eq!(fetch_length(&dicks, giver).await, 25, "the lender must get the payouts");
+    let repaid = sqlx::query_scalar!(r#"SELECT repaid_at IS NOT NULL AS "repaid!" FROM Member_Loans WHERE borrower_uid = $1"#, UID + 1)
+        .fetch_one(&db)
+        .await.expect("couldn't fetch the loan");
+    assert!(repaid);
+}
+
+async fn fetch_length(dicks: &repo::Dicks, uid: UserId) -> i64 {
+    dicks.fetch_length(uid, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length")
+        .value()
+}
+
+async fn fetch_debt(db: &Pool<Postgres>, borrower: UserId) -> i32 {
+    sqlx::query_scalar!("SELECT debt FROM Member_Loans WHERE borrower_uid = $1", borrower as UserId)
+        .fetch_one(db)
+        .await.expect("couldn't fetch the debt")
+}
+
+/// A bonus attempt lets the trigger allow one more growth today.
+async fn allow_another_growth(db: &Pool<Postgres>, uid: UserId) {
+    sqlx::query!("UPDATE Dicks SET bonus_attempts = bonus_attempts + 1 WHERE uid = $1", uid as UserId)
+        .execute(db)
+        .await.expect("couldn't give a bonus attempt");
+}
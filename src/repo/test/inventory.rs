@@ -0,0 +1,116 @@
+use crate::domain::enums::ShopItem;
+use crate::domain::objects::{GrowthSettlement, InventoryItem};
+use crate::domain::primitives::{DaysCount, ItemsCount, Length, LengthChange, Price, UserId};
+use crate::repo;
+use crate::repo::test::dicks::{create_dick, create_user};
+use crate::repo::test::{fresh_db, internal_chat_id, CHAT_ID_KIND, USER_ID};
+
+#[tokio::test]
+async fn test_all() {
+    let db = fresh_db().await;
+    let inventory = repo::Inventory::new(db.clone(), Default::default());
+    create_user(&db).await;
+    create_dick(&db).await;
+    let chat_id = internal_chat_id(&db).await;
+
+    sqlx::query!("UPDATE Dicks SET length = 20, bonus_attempts = 1 WHERE chat_id = $1 AND uid = $2", chat_id, USER_ID as UserId)
+        .execute(&db).await.expect("couldn't set the length");
+
+    let items = inventory.get_items(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the empty inventory");
+    assert!(items.is_empty());
+
+    let res = inventory.buy(&CHAT_ID_KIND, USER_ID, ShopItem::Shield, Price::new(10))
+        .await.expect("couldn't buy a shield");
+    assert_eq!(res, repo::PurchaseResult::Bought { length: Length::new(10), quantity: ItemsCount::new(1) });
+
+    let res = inventory.buy(&CHAT_ID_KIND, USER_ID, ShopItem::Shield, Price::new(15))
+        .await.expect("couldn't try to buy a shield for more than there is");
+    assert_eq!(res, repo::PurchaseResult::NotEnoughLength);
+
+    let items = inventory.get_items(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the inventory");
+    assert_eq!(items, vec![InventoryItem { item: ShopItem::Shield, quantity: ItemsCount::new(1) }]);
+
+    let consumed = inventory.consume(&CHAT_ID_KIND, USER_ID, ShopItem::Shield)
+        .await.expect("couldn't consume the shield");
+    assert!(consumed);
+    let consumed = inventory.consume(&CHAT_ID_KIND, USER_ID, ShopItem::Shield)
+        .await.expect("couldn't try to consume the shield once more");
+    assert!(!consumed, "the same shield must not be used twice");
+
+    let items = inventory.get_items(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the used up inventory");
+    assert!(items.is_empty());
+}
+
+#[tokio::test]
+async fn nothing_is_bought_in_an_unknown_chat() {
+    let db = fresh_db().await;
+    let inventory = repo::Inventory::new(db.clone(), Default::default());
+    create_user(&db).await;
+
+    let res = inventory.buy(&CHAT_ID_KIND, USER_ID, ShopItem::Reroll, Price::new(1))
+        .await.expect("couldn't try to buy in an unknown chat");
+    assert_eq!(res, repo::PurchaseResult::NotEnoughLength);
+}
+
+#[tokio::test]
+async fn random_active_holder() {
+    let db = fresh_db().await;
+    let inventory = repo::Inventory::new(db.clone(), Default::default());
+    create_user(&db).await;
+    create_dick(&db).await;
+    let chat_id = internal_chat_id(&db).await;
+    let inactivity_days = DaysCount::new(7);
+
+    let holder = inventory.get_random_active_holder(&CHAT_ID_KIND, ShopItem::DodTicket, inactivity_days)
+        .await.expect("couldn't look for a holder");
+    assert!(holder.is_none());
+
+    sqlx::query!("INSERT INTO Inventory (chat_id, uid, item, quantity) VALUES ($1, $2, 'dod_ticket', 1)", chat_id, USER_ID as UserId)
+        .execute(&db).await.expect("couldn't give a ticket");
+    let holder = inventory.get_random_active_holder(&CHAT_ID_KIND, ShopItem::DodTicket, inactivity_days)
+        .await.expect("couldn't look for a holder")
+        .expect("the holder of a ticket must be found");
+    assert_eq!(holder.uid, USER_ID);
+
+    let holder = inventory.get_random_active_holder(&CHAT_ID_KIND, ShopItem::Shield, inactivity_days)
+        .await.expect("couldn't look for a holder of another item");
+    assert!(holder.is_none());
+}
+
+#[tokio::test]
+async fn reroll_is_used_up_only_by_a_growth() {
+    let db = fresh_db().await;
+    let inventory = repo::Inventory::new(db.clone(), Default::default());
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+    create_user(&db).await;
+    create_dick(&db).await;
+    let chat_id = internal_chat_id(&db).await;
+    sqlx::query!("INSERT INTO Inventory (chat_id, uid, item, quantity) VALUES ($1, $2, 'reroll', 1)", chat_id, USER_ID as UserId)
+        .execute(&db).await.expect("couldn't give a reroll");
+    let reroll = [GrowthSettlement::ItemUsed(ShopItem::Reroll)];
+
+    // the dick has grown today already, so the growth is rejected and the reroll stays
+    dicks.create_or_grow_with_settlements(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(3), &reroll)
+        .await.expect_err("the dick must not grow twice a day");
+    let items = inventory.get_items(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the inventory");
+    assert_eq!(items, vec![InventoryItem { item: ShopItem::Reroll, quantity: ItemsCount::new(1) }]);
+
+    sqlx::query!("UPDATE Dicks SET bonus_attempts = 2 WHERE chat_id = $1 AND uid = $2", chat_id, USER_ID as UserId)
+        .execute(&db).await.expect("couldn't give bonus attempts");
+    dicks.create_or_grow_with_settlements(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(3), &reroll)
+        .await.expect("couldn't grow with a reroll");
+    let items = inventory.get_items(&CHAT_ID_KIND, USER_ID)
+        .await.expect("couldn't fetch the used up inventory");
+    assert!(items.is_empty());
+
+    // no reroll, no growth
+    dicks.create_or_grow_with_settlements(USER_ID, &CHAT_ID_KIND.into(), LengthChange::signed(3), &reroll)
+        .await.expect_err("the reroll must not be used twice");
+    let length = dicks.fetch_length(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length");
+    assert_eq!(length, Length::new(3));
+}
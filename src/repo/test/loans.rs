@@ -1,8 +1,8 @@
 use domain_types::traits::SaturatingInto;
 use sqlx::{Pool, Postgres};
 use crate::{config, repo};
-use crate::domain::primitives::{Debt, LoanPayout, PayoutRatio};
-use crate::repo::BorrowResult;
+use crate::domain::primitives::{DaysCount, Debt, Length, LoanPayout, PayoutRatio, Ratio};
+use crate::repo::{BorrowResult, RepayResult};
 use crate::repo::test::dicks::{create_dick, create_user};
 use crate::repo::test::{fresh_db, user_id, CHAT_ID, CHAT_ID_KIND, NAME, UID, USER_ID};
 use domain_types::literal;
@@ -29,7 +29,7 @@ async fn test_all() {
     assert!(no_loan.is_none());
 
     // the length is zero, so the user is not eligible for a loan
-    let borrow_result = loans.borrow(user_id, &chat_id, value)
+    let borrow_result = loans.borrow(user_id, &chat_id, value, payout_ratio)
         .await.expect("couldn't apply for a loan with a zero length");
     assert_eq!(borrow_result, BorrowResult::NotEligible);
     let no_loan = loans.get_active_loan(user_id, &chat_id)
@@ -39,7 +39,7 @@ async fn test_all() {
     set_length(&db, UID, CHAT_ID, -length_of(value)).await;
 
     let debt = Debt::new(value.value() * 2);
-    let borrow_result = loans.borrow(user_id, &chat_id, debt)
+    let borrow_result = loans.borrow(user_id, &chat_id, debt, payout_ratio)
         .await.expect("couldn't apply for a loan");
     assert_eq!(borrow_result, BorrowResult::Granted);
 
@@ -48,6 +48,9 @@ async fn test_all() {
         .expect("the loan must be present");
     assert_eq!(loan.debt, debt);
     assert_eq!(loan.payout_ratio, payout_ratio);
+    assert!(loan.interest_rate.is_zero());
+    assert_eq!(loan.due_date, None, "the loans have no term by default");
+    assert!(!loan.overdue);
 
     let dicks = repo::Dicks::new(db.clone(), Default::default());
     let length_after_borrowing = dicks.fetch_length(user_id, &chat_id)
@@ -67,7 +70,7 @@ async fn test_all() {
     // the length is positive, so refinancing must be rejected as well
     // (this is the fix for the over-loaning exploit: stale confirmation buttons
     // must not grant a loan when the length is not negative anymore)
-    let borrow_result = loans.borrow(user_id, &chat_id, value)
+    let borrow_result = loans.borrow(user_id, &chat_id, value, payout_ratio)
         .await.expect("couldn't apply for a loan with a positive length");
     assert_eq!(borrow_result, BorrowResult::NotEligible);
     let untouched_debt = loans.get_active_loan(user_id, &chat_id)
@@ -78,7 +81,7 @@ async fn test_all() {
 
     set_length(&db, UID, CHAT_ID, -length_of(value)).await;
 
-    let borrow_result = loans.borrow(user_id, &chat_id, value)
+    let borrow_result = loans.borrow(user_id, &chat_id, value, payout_ratio)
         .await.expect("couldn't increase the total sum of the loan");
     assert_eq!(borrow_result, BorrowResult::Granted);
 
@@ -88,6 +91,116 @@ async fn test_all() {
     assert_eq!(loan.debt, debt);
 }
 
+#[tokio::test]
+async fn test_interest_and_due_date() {
+    let db = fresh_db().await;
+    let payout_ratio = literal!(PayoutRatio = 0.1);
+
+    create_user(&db).await;
+    create_dick(&db).await; // to create a chat
+
+    let loans = repo::Loans::new(db.clone(), &config::AppConfig {
+        loan_payout_ratio: payout_ratio,
+        loan: config::LoanConfig { interest_rate: literal!(Ratio = 0.1), term: DaysCount::new(7) },
+        ..Default::default()
+    });
+    set_length(&db, UID, CHAT_ID, -100).await;
+    let borrow_result = loans.borrow(USER_ID, &CHAT_ID_KIND, Debt::new(100), payout_ratio)
+        .await.expect("couldn't apply for a loan");
+    assert_eq!(borrow_result, BorrowResult::Granted);
+
+    let loan = loans.get_active_loan(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the new loan")
+        .expect("the loan must be present");
+    assert_eq!(loan.debt, Debt::new(100), "no interest is accrued on the day of the borrowing");
+    assert_eq!(loan.interest_rate, literal!(Ratio = 0.1));
+    let today = chrono::Utc::now().date_naive();
+    assert_eq!(loan.due_date, today.checked_add_days(chrono::Days::new(7)));
+    assert!(!loan.overdue);
+
+    // two days later, 10% a day compounded
+    sqlx::query!("UPDATE Loans SET accrued_at = accrued_at - 2, due_date = current_date - 1 WHERE uid = $1", UID)
+        .execute(&db).await.expect("couldn't move the loan back in time");
+    let loan = loans.get_active_loan(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the loan with the interest")
+        .expect("the loan must be present");
+    assert_eq!(loan.debt, Debt::new(121));
+    assert!(loan.overdue);
+
+    // the payment fixes the accrued interest in the debt
+    loans.pay(USER_ID, &CHAT_ID_KIND, LoanPayout::new(21))
+        .await.expect("couldn't pay the loan");
+    let row = sqlx::query!(r#"SELECT debt, accrued_at = current_date AS "accrued_today!" FROM Loans WHERE uid = $1"#, UID)
+        .fetch_one(&db).await.expect("couldn't fetch the loan row");
+    assert_eq!((row.debt, row.accrued_today), (100, true));
+
+    // refinancing puts the balance under the current terms and moves the due date
+    sqlx::query!("UPDATE Loans SET accrued_at = accrued_at - 1 WHERE uid = $1", UID)
+        .execute(&db).await.expect("couldn't move the loan back in time");
+    set_length(&db, UID, CHAT_ID, -5).await;
+    loans.borrow(USER_ID, &CHAT_ID_KIND, Debt::new(5), payout_ratio)
+        .await.expect("couldn't refinance the loan");
+    let loan = loans.get_active_loan(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the refinanced loan")
+        .expect("the loan must be present");
+    assert_eq!(loan.debt, Debt::new(115));
+    assert!(!loan.overdue);
+}
+
+#[tokio::test]
+async fn test_repay() {
+    let db = fresh_db().await;
+    let payout_ratio = literal!(PayoutRatio = 0.1);
+
+    create_user(&db).await;
+    create_dick(&db).await; // to create a chat
+
+    let loans = repo::Loans::new(db.clone(), &config::AppConfig {
+        loan_payout_ratio: payout_ratio,
+        ..Default::default()
+    });
+    let dicks = repo::Dicks::new(db.clone(), Default::default());
+
+    let no_loan = loans.repay(USER_ID, &CHAT_ID_KIND, LoanPayout::new(1))
+        .await.expect("couldn't try to repay without a loan");
+    assert_eq!(no_loan, RepayResult::NoLoan);
+
+    set_length(&db, UID, CHAT_ID, -10).await;
+    loans.borrow(USER_ID, &CHAT_ID_KIND, Debt::new(10), payout_ratio)
+        .await.expect("couldn't apply for a loan");
+
+    // the length is zero after the borrowing
+    let not_enough = loans.repay(USER_ID, &CHAT_ID_KIND, LoanPayout::new(1))
+        .await.expect("couldn't try to repay with a zero length");
+    assert_eq!(not_enough, RepayResult::NotEnoughLength(Length::new(0)));
+
+    set_length(&db, UID, CHAT_ID, 8).await;
+    let repaid = loans.repay(USER_ID, &CHAT_ID_KIND, LoanPayout::new(6))
+        .await.expect("couldn't repay a part of the loan");
+    assert_eq!(repaid, RepayResult::Repaid { paid: LoanPayout::new(6), debt_left: Debt::new(4), length: Length::new(2) });
+    let length = dicks.fetch_length(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the length after the repayment");
+    assert_eq!(length, Length::new(2));
+
+    let not_enough = loans.repay(USER_ID, &CHAT_ID_KIND, LoanPayout::new(4))
+        .await.expect("couldn't try to repay more than the length");
+    assert_eq!(not_enough, RepayResult::NotEnoughLength(Length::new(2)));
+    let untouched_debt = loans.get_active_loan(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the loan after the rejected repayment")
+        .expect("the loan must be still present")
+        .debt;
+    assert_eq!(untouched_debt, Debt::new(4));
+
+    // no more than the balance is taken
+    set_length(&db, UID, CHAT_ID, 20).await;
+    let repaid = loans.repay(USER_ID, &CHAT_ID_KIND, LoanPayout::new(100))
+        .await.expect("couldn't repay the rest of the loan");
+    assert_eq!(repaid, RepayResult::Repaid { paid: LoanPayout::new(4), debt_left: Debt::new(0), length: Length::new(16) });
+    let no_loan = loans.get_active_loan(USER_ID, &CHAT_ID_KIND)
+        .await.expect("couldn't fetch the repaid loan");
+    assert!(no_loan.is_none(), "the repaid loan must not be active anymore");
+}
+
 #[tokio::test]
 async fn test_borrow_without_dick() {
     let db = fresh_db().await;
@@ -105,7 +218,7 @@ async fn test_borrow_without_dick() {
         loan_payout_ratio: literal!(PayoutRatio = 0.1),
         ..Default::default()
     });
-    let borrow_result = loans.borrow(user_id_without_dick, &chat_id, Debt::new(10))
+    let borrow_result = loans.borrow(user_id_without_dick, &chat_id, Debt::new(10), literal!(PayoutRatio = 0.1))
         .await.expect("couldn't apply for a loan without a dick");
     assert_eq!(borrow_result, BorrowResult::NotEligible);
 }
@@ -11,6 +11,10 @@ mod announcements;
 mod bans;
 mod broadcasts;
 mod deletions;
+mod achievements;
+mod inventory;
+mod gifts;
+mod seasons;
 
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU32, Ordering};
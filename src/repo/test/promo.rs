@@ -2,11 +2,11 @@ use chrono::Utc;
 use domain_types::traits::SaturatingInto;
 use domain_types::literal;
 use sqlx::{Pool, Postgres};
-use crate::domain::primitives::{Length, PromoBonus, PromoCapacity, PromoCode, UserId};
+use crate::domain::primitives::{DaysCount, Length, PromoBonus, PromoCapacity, PromoCode, UserId};
 use crate::repo;
-use crate::repo::PromoCodeParams;
-use crate::repo::test::{fresh_db, USER_ID};
-use crate::repo::test::dicks::{check_dick, create_dick, create_user};
+use crate::repo::{ActivationError, PromoCodeParams, ReferralCodeParams};
+use crate::repo::test::{fresh_db, get_chat_id_and_dicks, repos, user_id, UID, USER_ID};
+use crate::repo::test::dicks::{check_dick, create_another_user_and_dick, create_dick, create_user};
 
 const PROMO_CODE: &str = "test10";
 const PROMO_CODE_UPPERCASE: &str = "TEST10";
@@ -24,6 +24,8 @@ async fn activate() {
         code: literal!(PromoCode = PROMO_CODE),
         bonus_length: PromoBonus::new(PROMO_BONUS),
         capacity: PromoCapacity::new(1),
+        since: None,
+        until: None,
     }).await.expect("couldn't create a promo code");
 
     create_user(&db).await;
@@ -49,6 +51,8 @@ async fn activate_with_negative_bonus() {
         code: literal!(PromoCode = PENALTY_PROMO_CODE),
         bonus_length: PromoBonus::new(PENALTY_PROMO_BONUS),
         capacity: PromoCapacity::new(1),
+        since: None,
+        until: None,
     }).await.expect("couldn't create a promo code");
 
     create_user(&db).await;
@@ -61,6 +65,114 @@ async fn activate_with_negative_bonus() {
     check_dick(&db, Length::new(PENALTY_PROMO_BONUS.into())).await;
 }
 
+#[tokio::test]
+async fn referral() {
+    let db = fresh_db().await;
+    let repo::Repositories { promo, dicks, personal_stats, .. } = repos(&db);
+    let (chat_id, _) = get_chat_id_and_dicks(&db);
+    let params = || ReferralCodeParams {
+        invitee_bonus: PromoBonus::new(5),
+        inviter_bonus: PromoBonus::new(3),
+        max_invites: PromoCapacity::new(2),
+        newcomer_days: DaysCount::new(1),
+    };
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    let code = promo.get_or_create_referral_code(USER_ID, "refabcdefgh", params())
+        .await.expect("couldn't create a referral code");
+    assert_eq!(code.code, "refabcdefgh");
+    assert_eq!(code.invites_left, 2);
+
+    // the code is personal: asking again hands out the same one instead of a new one
+    let same_code = promo.get_or_create_referral_code(USER_ID, "refzzzzzzzz", params())
+        .await.expect("couldn't get the referral code again");
+    assert_eq!(same_code.code, code.code);
+
+    let res = promo.activate(USER_ID, &code.code).await;
+    assert!(matches!(res, Err(ActivationError::OwnCode)));
+
+    // however long ago the code has been made, whoever has just come is a newcomer
+    sqlx::query!("UPDATE Promo_Codes SET since = current_date - 30 WHERE code = $1", code.code)
+        .execute(&db).await.expect("couldn't age the code");
+    create_another_user_and_dick(&db, &chat_id.clone().into(), 2, "newcomer", 0).await;
+    let res = promo.activate(user_id(UID + 1), &code.code)
+        .await.expect("couldn't activate the referral code");
+    assert_eq!(res.bonus_length.value(), 5);
+    let inviter_length = dicks.fetch_length(USER_ID, &chat_id)
+        .await.expect("couldn't fetch the length of the inviter");
+    assert_eq!(inviter_length, 3);
+
+    create_another_user_and_dick(&db, &chat_id.clone().into(), 3, "veteran", 0).await;
+    sqlx::query!("UPDATE Users SET created_at = current_timestamp - interval '2 days' WHERE uid = $1", UID + 2)
+        .execute(&db).await.expect("couldn't age the user");
+    let res = promo.activate(user_id(UID + 2), &code.code).await;
+    assert!(matches!(res, Err(ActivationError::NotNewcomer)));
+
+    let stats = personal_stats.get_personal_stats(USER_ID)
+        .await.expect("couldn't fetch the personal stats");
+    assert_eq!(stats.referrals, 1);
+    let code = promo.get_or_create_referral_code(USER_ID, "refzzzzzzzz", params())
+        .await.expect("couldn't get the referral code after an activation");
+    assert_eq!(code.invites_left, 1);
+}
+
+#[tokio::test]
+async fn manage() {
+    let db = fresh_db().await;
+    let repo::Repositories { promo, .. } = repos(&db);
+    let params = |code: &str| PromoCodeParams {
+        code: PromoCode::new(code.to_owned()).expect("invalid promo code in the test"),
+        bonus_length: PromoBonus::new(PROMO_BONUS),
+        capacity: PromoCapacity::new(1),
+        since: None,
+        until: None,
+    };
+
+    let created = promo.create_promo_code(params(PROMO_CODE))
+        .await.expect("couldn't create a promo code");
+    assert!(created);
+    let created = promo.create_promo_code(params(PROMO_CODE_UPPERCASE))
+        .await.expect("couldn't try to create the same promo code");
+    assert!(!created, "the codes are matched case-insensitively, so they must be unique that way too");
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    promo.get_or_create_referral_code(USER_ID, "refabcdefgh", ReferralCodeParams {
+        invitee_bonus: PromoBonus::new(5),
+        inviter_bonus: PromoBonus::new(5),
+        max_invites: PromoCapacity::new(1),
+        newcomer_days: DaysCount::new(1),
+    }).await.expect("couldn't create a referral code");
+    promo.activate(USER_ID, PROMO_CODE).await.expect("couldn't activate the promo code");
+
+    let codes = promo.list_promo_codes(10).await.expect("couldn't list the promo codes");
+    assert_eq!(codes.len(), 1, "the referral codes must be left out");
+    assert_eq!(codes[0].code, PROMO_CODE);
+    assert_eq!(codes[0].activations, 1);
+    assert_eq!(codes[0].capacity, 0);
+    assert_eq!(codes[0].until, None);
+
+    let until = Utc::now().date_naive() + chrono::Days::new(7);
+    let extended = promo.extend_promo_code(PROMO_CODE_UPPERCASE, Some(until), PromoCapacity::new(5))
+        .await.expect("couldn't extend the promo code");
+    assert!(extended);
+    let extended = promo.extend_promo_code("refabcdefgh", None, PromoCapacity::new(5))
+        .await.expect("couldn't try to extend a referral code");
+    assert!(!extended, "the referral codes are managed by the bot itself");
+
+    let codes = promo.list_promo_codes(10).await.expect("couldn't list the promo codes again");
+    assert_eq!(codes[0].capacity, 5);
+    assert_eq!(codes[0].until, Some(until));
+
+    let disabled = promo.disable_promo_code(PROMO_CODE).await.expect("couldn't disable the promo code");
+    assert!(disabled);
+    let res = promo.activate(user_id(UID + 1), PROMO_CODE).await;
+    assert!(matches!(res, Err(ActivationError::NoActivationsLeft)));
+    let disabled = promo.disable_promo_code("nosuchcode").await.expect("couldn't try to disable a missing promo code");
+    assert!(!disabled);
+}
+
 async fn check_promo_code_activations(db: &Pool<Postgres>) {
     // activated_at is nullable in the schema; the insert always sets it, so force non-null.
     let row = sqlx::query!(
@@ -1,8 +1,9 @@
+use crate::domain::enums::BattleRanking;
 use crate::domain::objects::WinRateAware;
-use crate::domain::primitives::Bet;
+use crate::domain::primitives::{Bet, Limit, Offset, Position, UserId};
 use crate::domain::primitives::chat::ChatIdPartiality;
 use crate::repo;
-use crate::repo::test::dicks::{create_dick, create_user, create_user_and_dick_2};
+use crate::repo::test::dicks::{create_another_user_and_dick, create_dick, create_user, create_user_and_dick_2};
 use crate::repo::test::{fresh_db, user_id, CHAT_ID_KIND, UID, USER_ID};
 
 #[tokio::test]
@@ -68,3 +69,134 @@ async fn test_all() {
     assert_eq!(stats.acquired_length, bet_length);
     assert_eq!(stats.lost_length, bet_length * 2);
 }
+
+#[tokio::test]
+async fn mercy() {
+    let db = fresh_db().await;
+    let pvp_stats = repo::BattleStatsRepo::new(db.clone(), Default::default());
+    let chat_id = CHAT_ID_KIND;
+    let bet = Bet::new(42);
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    create_user_and_dick_2(&db, &ChatIdPartiality::Specific(chat_id.clone()), "User-2").await;
+    let (uid_1, uid_2) = (USER_ID, user_id(UID + 1));
+
+    pvp_stats.send_battle_result(&chat_id, uid_1, uid_2, bet).await
+        .expect("couldn't send the battle result");
+    pvp_stats.send_mercy(&chat_id, uid_1, uid_2, bet).await
+        .expect("couldn't send the mercy");
+
+    let winner = pvp_stats.get_stats(&chat_id, uid_1).await
+        .expect("couldn't fetch the stats of the winner");
+    assert_eq!(winner.battles_won, 1, "a battle given back is still won");
+    assert_eq!(winner.acquired_length, 0);
+    assert_eq!(winner.mercies_shown, 1);
+
+    let loser = pvp_stats.get_stats(&chat_id, uid_2).await
+        .expect("couldn't fetch the stats of the loser");
+    assert_eq!(loser.battles_total, 1);
+    assert_eq!(loser.lost_length, 0);
+    assert_eq!(loser.mercies_shown, 0);
+}
+
+#[tokio::test]
+async fn get_top() {
+    let db = fresh_db().await;
+    let pvp_stats = repo::BattleStatsRepo::new(db.clone(), Default::default());
+    let chat_id = CHAT_ID_KIND;
+    let chat_id_partiality = ChatIdPartiality::Specific(chat_id.clone());
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    create_user_and_dick_2(&db, &chat_id_partiality, "User-2").await;
+    create_another_user_and_dick(&db, &chat_id_partiality, 3, "User-3", 1).await;
+    create_another_user_and_dick(&db, &chat_id_partiality, 4, "Pacifist", 1).await;
+    let (uid_1, uid_2, uid_3) = (USER_ID, user_id(UID + 1), user_id(UID + 2));
+
+    assert!(top_uids(&pvp_stats, BattleRanking::WinRate).await.is_empty());
+
+    // the first user: 2 of 4 won, the longest streak, -35 cm;
+    // the second one: 1 of 3 won, +30 cm;
+    // the third one: 1 of 1 won, +5 cm.
+    for (winner, loser, bet) in [(uid_1, uid_2, 10), (uid_1, uid_2, 10), (uid_2, uid_1, 50), (uid_3, uid_1, 5)] {
+        pvp_stats.send_battle_result(&chat_id, winner, loser, Bet::new(bet)).await
+            .expect("couldn't send the battle result");
+    }
+
+    assert_eq!(top_uids(&pvp_stats, BattleRanking::WinRate).await, vec![uid_3, uid_1, uid_2],
+        "the pacifist must not be ranked at all");
+    assert_eq!(top_uids(&pvp_stats, BattleRanking::Wins).await, vec![uid_1, uid_3, uid_2],
+        "equal wins in fewer battles must go first");
+    assert_eq!(top_uids(&pvp_stats, BattleRanking::WinStreak).await, vec![uid_1, uid_3, uid_2]);
+    assert_eq!(top_uids(&pvp_stats, BattleRanking::NetLength).await, vec![uid_2, uid_3, uid_1]);
+
+    let page = pvp_stats.get_top(&chat_id, BattleRanking::NetLength, Offset::new(1), Limit::new(1)).await
+        .expect("couldn't fetch the second page");
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].uid, uid_3);
+    assert_eq!(page[0].position, Position::new(2), "the position must count the rows of the previous pages");
+    assert_eq!(page[0].stats.net_length(), 5);
+    assert_eq!(page[0].name, "User-3");
+
+    // the first user: 8 of 10 won
+    for _ in 0..6 {
+        pvp_stats.send_battle_result(&chat_id, uid_1, uid_2, Bet::new(1)).await
+            .expect("couldn't send the battle result");
+    }
+    assert_eq!(top_uids(&pvp_stats, BattleRanking::WinRate).await, vec![uid_1, uid_3, uid_2],
+        "a single lucky win must not outrank a high rate of many battles");
+}
+
+async fn top_uids(pvp_stats: &repo::BattleStatsRepo, ranking: BattleRanking) -> Vec<UserId> {
+    pvp_stats.get_top(&CHAT_ID_KIND, ranking, Offset::new(0), Limit::new(10)).await
+        .unwrap_or_else(|e| panic!("couldn't fetch the {ranking} top: {e:#}"))
+        .into_iter()
+        .map(|fighter| fighter.uid)
+        .collect()
+}
+
+#[tokio::test]
+async fn get_rivalry() {
+    let db = fresh_db().await;
+    let pvp_stats = repo::BattleStatsRepo::new(db.clone(), Default::default());
+    let chat_id = CHAT_ID_KIND;
+    let chat_id_partiality = ChatIdPartiality::Specific(chat_id.clone());
+
+    create_user(&db).await;
+    create_dick(&db).await;
+    create_user_and_dick_2(&db, &chat_id_partiality, "User-2").await;
+    create_another_user_and_dick(&db, &chat_id_partiality, 3, "User-3", 1).await;
+    let (uid_1, uid_2, uid_3) = (USER_ID, user_id(UID + 1), user_id(UID + 2));
+
+    let rivalry = pvp_stats.get_rivalry(&chat_id, uid_1, uid_2, Limit::new(2)).await
+        .expect("couldn't fetch an empty rivalry");
+    assert_eq!(rivalry.battles, 0);
+    assert_eq!(rivalry.net_length, 0);
+    assert!(rivalry.last_encounters.is_empty());
+
+    for (winner, loser, bet) in [(uid_1, uid_2, 10), (uid_2, uid_1, 3), (uid_1, uid_3, 100), (uid_1, uid_2, 7)] {
+        pvp_stats.send_battle_result(&chat_id, winner, loser, Bet::new(bet)).await
+            .expect("couldn't send the battle result");
+    }
+    // the last award is given back, so it must not count as swapped
+    pvp_stats.send_mercy(&chat_id, uid_1, uid_2, Bet::new(7)).await
+        .expect("couldn't send the mercy");
+
+    let rivalry = pvp_stats.get_rivalry(&chat_id, uid_1, uid_2, Limit::new(2)).await
+        .expect("couldn't fetch the rivalry");
+    assert_eq!(rivalry.battles, 3, "a battle against somebody else must not count");
+    assert_eq!(rivalry.wins, 2);
+    assert_eq!(rivalry.losses, 1);
+    assert_eq!(rivalry.net_length, 7);
+    assert_eq!(rivalry.last_encounters.len(), 2);
+    let last = &rivalry.last_encounters[0];
+    assert_eq!((last.winner, last.bet, last.mercy_shown), (uid_1, Bet::new(7), true));
+    let previous = &rivalry.last_encounters[1];
+    assert_eq!((previous.winner, previous.bet, previous.mercy_shown), (uid_2, Bet::new(3), false));
+
+    let mirrored = pvp_stats.get_rivalry(&chat_id, uid_2, uid_1, Limit::new(2)).await
+        .expect("couldn't fetch the mirrored rivalry");
+    assert_eq!((mirrored.wins, mirrored.losses), (rivalry.losses, rivalry.wins));
+    assert_eq!(mirrored.net_length, -7);
+}
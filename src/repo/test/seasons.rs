@@ -0,0 +1,167 @@
+use chrono::{Days, NaiveDate, Utc};
+use sqlx::{Pool, Postgres};
+use crate::domain::objects::SeasonChampion;
+use crate::domain::primitives::{LengthChange, Limit, UserId};
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality, InternalChatId, TelegramChatId};
+use crate::repo;
+use crate::repo::test::{fresh_db, internal_chat_id, repos, user_id, CHAT_ID, UID, USER_ID};
+use crate::repo::test::dicks::{create_another_user_and_dick, create_user};
+
+fn chat(n: i64) -> ChatIdPartiality {
+    ChatIdPartiality::Specific(ChatIdKind::ID(TelegramChatId::new(CHAT_ID - n)))
+}
+
+async fn all_chats(db: &Pool<Postgres>) -> Vec<InternalChatId> {
+    repos(db).shrinks.select_chats_batch(None, Limit::new(10))
+        .await.expect("couldn't read the chats")
+}
+
+fn lines_of(champions: Vec<SeasonChampion>) -> Vec<(u32, NaiveDate, UserId, String, i64)> {
+    champions.into_iter()
+        .map(|c| (c.number, c.ended_on, c.uid, c.name, c.length.value()))
+        .collect()
+}
+
+#[tokio::test]
+async fn end_seasons_archives_and_resets() {
+    let db = fresh_db().await;
+    let repo::Repositories { dicks, seasons, .. } = repos(&db);
+    let (alpha, beta) = (chat(0), chat(1));
+    let today = Utc::now().date_naive();
+    let yesterday = today.pred_opt().expect("no yesterday");
+
+    create_user(&db).await;
+    dicks.create_or_grow(USER_ID, &alpha, LengthChange::signed(10))
+        .await.expect("couldn't grow the dick");
+    create_another_user_and_dick(&db, &alpha, 2, "second", 30).await;
+    create_another_user_and_dick(&db, &beta, 3, "third", 0).await;
+    let chats = all_chats(&db).await;
+
+    let ended = seasons.end_seasons(&chats, today, false)
+        .await.expect("couldn't end no season");
+    assert_eq!(ended.value(), 0, "nothing is due without the global end or one of the chat");
+
+    // The chat with nothing but a zero has nothing to archive.
+    let ended = seasons.end_seasons(&chats, today, true)
+        .await.expect("couldn't end the seasons");
+    assert_eq!(ended.value(), 1);
+
+    let dick = dicks.fetch_dick(USER_ID, &alpha.kind())
+        .await.expect("couldn't fetch the dick")
+        .expect("the dick must survive the end of the season");
+    assert_eq!(dick.length.value(), 0);
+    let resets = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM Length_Events WHERE reason = 'season' AND length = 0"#)
+        .fetch_one(&db)
+        .await.expect("couldn't count the resets");
+    assert_eq!(resets, 2);
+
+    let champions = seasons.get_champions(&alpha.kind(), Limit::new(10))
+        .await.expect("couldn't get the champions");
+    assert_eq!(lines_of(champions), vec![(1, yesterday, user_id(UID + 1), "second".to_owned(), 30)]);
+    let champions = seasons.get_champions(&beta.kind(), Limit::new(10))
+        .await.expect("couldn't get the champions of the empty chat");
+    assert!(champions.is_empty());
+
+    // The next season is numbered on, and the zeros of the last one aren't in it.
+    create_another_user_and_dick(&db, &alpha, 4, "fourth", 5).await;
+    seasons.end_seasons(&chats, today, true)
+        .await.expect("couldn't end the second season");
+    let champions = seasons.get_champions(&alpha.kind(), Limit::new(10))
+        .await.expect("couldn't get the champions of two seasons");
+    assert_eq!(lines_of(champions), vec![
+        (2, yesterday, user_id(UID + 3), "fourth".to_owned(), 5),
+        (1, yesterday, user_id(UID + 1), "second".to_owned(), 30),
+    ]);
+    let results = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM Season_Results"#)
+        .fetch_one(&db)
+        .await.expect("couldn't count the results");
+    assert_eq!(results, 3);
+
+    let wins = seasons.get_season_wins(&alpha.kind())
+        .await.expect("couldn't get the wins");
+    assert_eq!(wins.len(), 2);
+    assert_eq!(wins.get(&user_id(UID + 1)).map(|w| w.value()), Some(1));
+    assert_eq!(wins.get(&user_id(UID + 3)).map(|w| w.value()), Some(1));
+}
+
+#[tokio::test]
+async fn end_seasons_carries_what_is_owed() {
+    let db = fresh_db().await;
+    let repo::Repositories { dicks, seasons, .. } = repos(&db);
+    let alpha = chat(0);
+    let today = Utc::now().date_naive();
+
+    create_user(&db).await;
+    dicks.create_or_grow(USER_ID, &alpha, LengthChange::signed(10))
+        .await.expect("couldn't grow the dick");
+    create_another_user_and_dick(&db, &alpha, 2, "debtor", -5).await;
+    let debtor = user_id(UID + 1);
+    let chat_internal_id = internal_chat_id(&db).await;
+    sqlx::query!("INSERT INTO Loans (uid, chat_id, debt) VALUES ($1, $2, 7)", UID + 1, chat_internal_id)
+        .execute(&db)
+        .await.expect("couldn't borrow from the bank");
+    sqlx::query!("INSERT INTO Member_Loans (chat_id, lender_uid, borrower_uid, debt, payout_ratio) VALUES ($1, $2, $3, 4, 0.5)",
+            chat_internal_id, UID, UID + 1)
+        .execute(&db)
+        .await.expect("couldn't borrow from the member");
+
+    let ended = seasons.end_seasons(&all_chats(&db).await, today, true)
+        .await.expect("couldn't end the season");
+    assert_eq!(ended.value(), 1);
+
+    let length = dicks.fetch_length(debtor, &alpha.kind())
+        .await.expect("couldn't fetch the length of the debtor");
+    assert_eq!(length.value(), -5, "a length below zero must not be reset");
+    let champions = seasons.get_champions(&alpha.kind(), Limit::new(10))
+        .await.expect("couldn't get the champions");
+    assert_eq!(lines_of(champions).len(), 1);
+    let results = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM Season_Results WHERE uid = $1"#, UID + 1)
+        .fetch_one(&db)
+        .await.expect("couldn't count the results of the debtor");
+    assert_eq!(results, 0, "a length below zero must not be archived");
+
+    let debts = sqlx::query_scalar!(
+            r#"SELECT (SELECT sum(debt) FROM Loans WHERE uid = $1 AND repaid_at IS NULL)
+                    + (SELECT sum(debt) FROM Member_Loans WHERE borrower_uid = $1 AND repaid_at IS NULL) AS "debt!""#,
+            UID + 1)
+        .fetch_one(&db)
+        .await.expect("couldn't sum the debts");
+    assert_eq!(debts, 11, "the debts must be carried into the next season");
+}
+
+#[tokio::test]
+async fn end_of_the_chat_takes_the_place_of_the_global_one() {
+    let db = fresh_db().await;
+    let repo::Repositories { chats, seasons, .. } = repos(&db);
+    let (alpha, beta) = (chat(0), chat(1));
+    let today = Utc::now().date_naive();
+    let tomorrow = today.checked_add_days(Days::new(1)).expect("no tomorrow");
+
+    create_another_user_and_dick(&db, &alpha, 2, "second", 30).await;
+    create_another_user_and_dick(&db, &beta, 3, "third", 20).await;
+    chats.set_season_end(&alpha, Some(today))
+        .await.expect("couldn't set the end of the season");
+    assert_eq!(chats.get_season_end(&alpha.kind()).await.expect("couldn't get the end"), Some(today));
+    let chats_batch = all_chats(&db).await;
+
+    // Today is the last day of the season of the first chat, so only the global one ends.
+    let ended = seasons.end_seasons(&chats_batch, today, true)
+        .await.expect("couldn't end the global season");
+    assert_eq!(ended.value(), 1);
+    assert!(seasons.get_champions(&alpha.kind(), Limit::new(10)).await.expect("couldn't get the champions").is_empty());
+
+    let ended = seasons.end_seasons(&chats_batch, tomorrow, false)
+        .await.expect("couldn't end the season of the chat");
+    assert_eq!(ended.value(), 1);
+    let champions = seasons.get_champions(&alpha.kind(), Limit::new(10))
+        .await.expect("couldn't get the champions");
+    assert_eq!(lines_of(champions), vec![(1, today, user_id(UID + 1), "second".to_owned(), 30)]);
+    assert_eq!(chats.get_season_end(&alpha.kind()).await.expect("couldn't get the end"), None,
+        "the end must be forgotten with the season");
+
+    chats.set_season_end(&beta, Some(tomorrow))
+        .await.expect("couldn't set the end of the season");
+    chats.set_season_end(&beta, None)
+        .await.expect("couldn't forget the end of the season");
+    assert_eq!(chats.get_season_end(&beta.kind()).await.expect("couldn't get the end"), None);
+}
@@ -1,8 +1,8 @@
 use sqlx::{Pool, Postgres};
 use crate::domain::primitives::{DaysCount, LengthChange, Limit, Offset, Ratio};
-use crate::domain::primitives::chat::{InternalChatId, TelegramChatId};
+use crate::domain::primitives::chat::{ChatIdKind, InternalChatId, TelegramChatId};
 use crate::repo;
-use crate::repo::test::{fresh_db, internal_chat_id, repos, seed_aged_dick, user_id, CHAT_ID, CHAT_ID_KIND, NAME, UID, USER_ID};
+use crate::repo::test::{create_chat, fresh_db, internal_chat_id, repos, seed_aged_dick, user_id, CHAT_ID, CHAT_ID_KIND, NAME, UID, USER_ID};
 use domain_types::literal;
 
 const GRACE_DAYS: DaysCount = DaysCount::new(7);
@@ -259,6 +259,55 @@ async fn test_perform_daily_shrink_queues_one_summary_per_chat_and_day() {
     assert_eq!(queued_broadcasts(&db).await, vec![(chat_id, "created".to_owned())]);
 }
 
+/// Every chat is shrunk at its own midnight, and its shrinks and its summary are dated with the
+/// day that has started for it, not with the UTC one.
+#[tokio::test]
+async fn test_chats_are_shrunk_at_their_midnight() {
+    let db = fresh_db().await;
+    let repo::Repositories { chats, shrinks, users, .. } = repos(&db);
+    let internal = |id: i64| InternalChatId::new(id.try_into().expect("the internal chat id must be positive"));
+    let utc_chat = create_chat(&db, CHAT_ID).await;
+    let kolkata_chat = create_chat(&db, CHAT_ID - 1).await;
+    chats.set_time_zone(&ChatIdKind::ID(TelegramChatId::new(CHAT_ID - 1)).into(), Some("Asia/Kolkata"))
+        .await.expect("couldn't set the time zone");
+
+    let tick = chrono::Duration::minutes(15);
+    let midnight_chats = |at: &str, window| {
+        let at = chrono::DateTime::parse_from_rfc3339(at).expect("invalid datetime string").with_timezone(&chrono::Utc);
+        shrinks.select_midnight_chats_batch(None, Limit::new(10), at, window)
+    };
+    let due = midnight_chats("2023-10-21T18:30:00Z", tick).await.expect("couldn't read the chats at the midnight of India");
+    assert_eq!(due, vec![internal(kolkata_chat)]);
+    let due = midnight_chats("2023-10-22T00:05:00Z", tick).await.expect("couldn't read the chats at the UTC midnight");
+    assert_eq!(due, vec![internal(utc_chat)]);
+    let due = midnight_chats("2023-10-22T00:30:00Z", tick * 3).await.expect("couldn't read the chats of the missed ticks");
+    assert_eq!(due, vec![internal(utc_chat)], "a window of the ticks missed must take on their midnights as well");
+    let due = midnight_chats("2023-10-22T12:00:00Z", tick).await.expect("couldn't read the chats at noon");
+    assert!(due.is_empty(), "it's nobody's midnight");
+    let due = midnight_chats("2023-10-22T12:00:00Z", chrono::Duration::days(1)).await.expect("couldn't read all the chats");
+    assert_eq!(due, vec![internal(utc_chat), internal(kolkata_chat)]);
+
+    let victim_uid = UID + 1;
+    users.create_or_update(user_id(victim_uid), "stale-victim")
+        .await.expect("couldn't create the victim user");
+    seed_aged_dick(&db, kolkata_chat, victim_uid, 100, 10).await;
+    shrinks.perform_daily_shrink(&[internal(kolkata_chat)], literal!(Ratio = 0.1), GRACE_DAYS, NO_RAMP)
+        .await.expect("couldn't perform the daily shrink");
+    let dated_locally = sqlx::query_scalar!(
+            r#"SELECT bool_and(s.created_at = local_date(current_timestamp, 'Asia/Kolkata')
+                           AND b.shrink_date = s.created_at) AS "dated_locally!"
+                FROM Stale_Dick_Shrinks s JOIN Scheduled_Shrink_Broadcasts b USING (chat_id)"#)
+        .fetch_one(&db)
+        .await.expect("couldn't read the dates of the shrink");
+    assert!(dated_locally);
+
+    let last_shrink = shrinks.get_last_shrink_timestamp()
+        .await.expect("couldn't read the time of the last shrink")
+        .expect("the shrink must have been logged");
+    let off = (chrono::Utc::now() - last_shrink).abs();
+    assert!(off < chrono::Duration::minutes(1), "the time of the last shrink must be the real one, not the day of the chat: {last_shrink}");
+}
+
 #[tokio::test]
 async fn test_perform_daily_shrink_ramps_up_the_ratio() {
     let db = fresh_db().await;
@@ -1,8 +1,9 @@
-use crate::domain::primitives::LengthChange;
+use crate::domain::enums::GlobalRanking;
+use crate::domain::primitives::{LengthChange, Limit, Offset, Position};
 use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality, TelegramChatId};
 use crate::repo;
-use crate::repo::test::{fresh_db, repos, CHAT_ID, USER_ID};
-use crate::repo::test::dicks::create_user;
+use crate::repo::test::{fresh_db, repos, user_id, CHAT_ID, UID, USER_ID};
+use crate::repo::test::dicks::{create_another_user_and_dick, create_user};
 
 fn increment_of(value: i64) -> LengthChange {
     LengthChange::signed(value)
@@ -48,3 +49,67 @@ async fn test_all() {
     assert_eq!(stats.max_length, -10);
     assert_eq!(stats.total_length, -30);
 }
+
+#[tokio::test]
+async fn test_global_top() {
+    let db = fresh_db().await;
+    let repo::Repositories { personal_stats, dicks, users, .. } = repos(&db);
+
+    let chat_id_1 = ChatIdPartiality::Specific(ChatIdKind::ID(TelegramChatId::new(CHAT_ID)));
+    let chat_id_2 = ChatIdPartiality::Specific(ChatIdKind::ID(TelegramChatId::new(CHAT_ID + 1)));
+    let top = personal_stats.get_global_top(GlobalRanking::Max, Offset::new(0), Limit::new(10)).await
+        .expect("couldn't fetch the empty top");
+    assert!(top.is_empty());
+
+    create_user(&db).await;
+    dicks.create_or_grow(USER_ID, &chat_id_1, increment_of(10)).await
+        .expect("couldn't grow the dick in the first chat");
+    dicks.create_or_grow(USER_ID, &chat_id_2, increment_of(20)).await
+        .expect("couldn't grow the dick in the second chat");
+    create_another_user_and_dick(&db, &chat_id_1, 2, "second", 25).await;
+    let uid_2 = user_id(UID + 1);
+
+    let top = personal_stats.get_global_top(GlobalRanking::Max, Offset::new(0), Limit::new(10)).await
+        .expect("couldn't fetch the top by the longest dick");
+    let lines: Vec<_> = top.iter().map(|u| (u.uid, u.length.value(), u.position)).collect();
+    assert_eq!(lines, vec![(uid_2, 25, Position::new(1)), (USER_ID, 20, Position::new(2))]);
+
+    let top = personal_stats.get_global_top(GlobalRanking::Total, Offset::new(0), Limit::new(10)).await
+        .expect("couldn't fetch the top by all dicks together");
+    let lines: Vec<_> = top.iter().map(|u| (u.uid, u.length.value(), u.position)).collect();
+    assert_eq!(lines, vec![(USER_ID, 30, Position::new(1)), (uid_2, 25, Position::new(2))]);
+
+    let page = personal_stats.get_global_top(GlobalRanking::Total, Offset::new(1), Limit::new(1)).await
+        .expect("couldn't fetch the second page");
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].uid, uid_2);
+    assert_eq!(page[0].position, Position::new(2));
+
+    let known = users.set_hidden_from_global_top(uid_2, true).await
+        .expect("couldn't hide the second user");
+    assert!(known);
+    let top = personal_stats.get_global_top(GlobalRanking::Max, Offset::new(0), Limit::new(10)).await
+        .expect("couldn't fetch the top without the hidden user");
+    let lines: Vec<_> = top.iter().map(|u| (u.uid, u.position)).collect();
+    assert_eq!(lines, vec![(USER_ID, Position::new(1))], "the hidden user must leave no gap either");
+
+    users.set_hidden_from_global_top(uid_2, false).await
+        .expect("couldn't show the second user again");
+    let top = personal_stats.get_global_top(GlobalRanking::Max, Offset::new(0), Limit::new(10)).await
+        .expect("couldn't fetch the top with the user shown again");
+    assert_eq!(top.len(), 2);
+
+    let known = users.set_hidden_from_global_top(user_id(UID + 100), true).await
+        .expect("couldn't try to hide an unknown user");
+    assert!(!known, "nobody must be found for a user who has never played");
+
+    create_another_user_and_dick(&db, &chat_id_2, 4, "twin", 5).await;
+    create_another_user_and_dick(&db, &chat_id_1, 3, "twin", 5).await;
+    let mut twins = Vec::new();
+    for offset in [2, 3] {
+        let page = personal_stats.get_global_top(GlobalRanking::Max, Offset::new(offset), Limit::new(1)).await
+            .expect("couldn't fetch a page of the twins");
+        twins.extend(page.into_iter().map(|u| u.uid));
+    }
+    assert_eq!(twins, vec![user_id(UID + 2), user_id(UID + 3)], "the namesakes must be ordered by their ids");
+}
@@ -53,6 +53,36 @@ async fn get_chat_members() {
     check_member_with_name(&members, NAME);
 }
 
+#[tokio::test]
+async fn get_chat_member_by_username() {
+    let db = fresh_db().await;
+    let users = repo::Users::new(db.clone());
+    let chat_id = ChatIdKind::ID(TelegramChatId::new(CHAT_ID));
+    let another_chat_id = ChatIdKind::ID(TelegramChatId::new(CHAT_ID + 1));
+
+    create_member(&db).await;
+    let user = users.get_chat_member_by_username(&chat_id, "TestUser")
+        .await.expect("couldn't look for a member without a username");
+    assert!(user.is_none());
+
+    users.create_or_update_with_username(USER_ID, NAME, Some("TestUser"))
+        .await.expect("couldn't set the username");
+    let user = users.get_chat_member_by_username(&chat_id, "testuser")
+        .await.expect("couldn't find a member by the username")
+        .expect("the username must be found regardless of the case");
+    assert_eq!(user.uid, USER_ID);
+
+    let user = users.get_chat_member_by_username(&another_chat_id, "TestUser")
+        .await.expect("couldn't look for a member of another chat");
+    assert!(user.is_none(), "a user must be found among the members of the chat only");
+
+    users.create_or_update_with_username(USER_ID, NAME, None)
+        .await.expect("couldn't reset the username");
+    let user = users.get_chat_member_by_username(&chat_id, "TestUser")
+        .await.expect("couldn't look for a member by a given up username");
+    assert!(user.is_none(), "a given up username must be forgotten");
+}
+
 macro_rules! base_checks {
     ($db:ident, $method:ident) => {
         base_checks!($db, $method,)
@@ -7,14 +7,20 @@ use crate::repository;
 use domain_types::literal;
 
 repository!(Users,
-    #[autometrics]
-    #[tracing::instrument(skip_all, fields(uid = user_id.value(), name = %name))]
+    #[cfg(test)]
     pub async fn create_or_update(&self, user_id: UserId, name: &str) -> anyhow::Result<User> {
+        self.create_or_update_with_username(user_id, name, None).await
+    }
+,
+    /// The username is overwritten along with the name, so that the one given up is forgotten at once.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(uid = user_id.value(), name = %name, username = ?username))]
+    pub async fn create_or_update_with_username(&self, user_id: UserId, name: &str, username: Option<&str>) -> anyhow::Result<User> {
         sqlx::query_as!(User,
-            r#"INSERT INTO Users(uid, name) VALUES ($1, $2)
-                ON CONFLICT (uid) DO UPDATE SET name = $2
+            r#"INSERT INTO Users(uid, name, username) VALUES ($1, $2, $3)
+                ON CONFLICT (uid) DO UPDATE SET name = $2, username = $3
                 RETURNING uid AS "uid: UserId", name AS "name: Username", created_at"#,
-                user_id as UserId, name)
+                user_id as UserId, name, username)
             .fetch_one(&self.pool)
             .await
             .context(format!("couldn't upsert a user with id = {user_id}"))
@@ -33,6 +39,24 @@ repository!(Users,
             .await
             .context(format!("couldn't get users of the chat with id = {chat_id}"))
     }
+,
+    /// Only the members of the chat are looked among: a username is the one a user had at their last
+    /// growth, and it may have been given up and taken by somebody else since.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id, username = %username))]
+    pub async fn get_chat_member_by_username(&self, chat_id: &ChatIdKind, username: &str) -> anyhow::Result<Option<User>> {
+        sqlx::query_as!(User,
+            r#"SELECT u.uid AS "uid: UserId", name AS "name: Username", u.created_at FROM Users u
+                JOIN Dicks d USING (uid)
+                JOIN Chats c ON d.chat_id = c.id
+                WHERE (c.chat_id = $1::bigint OR c.chat_instance = $1::text)
+                    AND lower(u.username) = lower($2)
+                ORDER BY d.updated_at DESC LIMIT 1"#,
+                chat_id.value() as String, username)
+            .fetch_optional(&self.pool)
+            .await
+            .context(format!("couldn't find a member of the chat with id = {chat_id} by the username"))
+    }
 ,
     #[autometrics]
     #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
@@ -147,6 +171,18 @@ repository!(Users,
             .await
             .context("couldn't get the list of banned users")
     }
+,
+    /// Returns whether the user is known at all: one who has never played has nothing to hide yet.
+    #[autometrics]
+    #[tracing::instrument(skip_all, fields(uid = user_id.value(), hidden = hidden))]
+    pub async fn set_hidden_from_global_top(&self, user_id: UserId, hidden: bool) -> anyhow::Result<bool> {
+        sqlx::query!("UPDATE Users SET hidden_from_global_top = $2 WHERE uid = $1",
+                user_id as UserId, hidden)
+            .execute(&self.pool)
+            .await
+            .map(|res| res.rows_affected() > 0)
+            .context(format!("couldn't set hidden_from_global_top = {hidden} for the user with id = {user_id}"))
+    }
 ,
     #[cfg(test)]
     pub async fn get_all_users(&self) -> anyhow::Result<Vec<User>> {
@@ -7,12 +7,13 @@ use teloxide::adaptors::Throttle;
 use teloxide::payloads::SendMessageSetters;
 use teloxide::requests::Requester;
 use teloxide::sugar::request::RequestLinkPreviewExt;
-use teloxide::types::{ChatId, ReplyMarkup, UserId as TeloxideUserId};
+use teloxide::types::{ChatId, InlineKeyboardMarkup, ReplyMarkup, UserId as TeloxideUserId};
 use teloxide::types::ParseMode::Html;
 use domain_types::traits::ApproxInto;
 use crate::config::AppConfig;
 use crate::domain::primitives::{LanguageCode, Page, ScheduledBroadcastId, SupportedLanguage};
 use crate::domain::primitives::chat::ChatIdKind;
+use crate::handlers::podium_impl;
 use crate::handlers::shrink::{build_shrink_keyboard, shrinks_page_impl, ShrinkView};
 use crate::metrics;
 use crate::repo::{BroadcastState, Repositories, ScheduledBroadcast};
@@ -91,7 +92,7 @@ pub async fn clean_finished_broadcasts(repos: &Repositories, retention: Duration
 }
 
 /// Sends one summary and writes down what became of it.
-#[tracing::instrument(skip_all, fields(id = %broadcast.id, chat_id = %broadcast.chat_id, date = %broadcast.shrink_date))]
+#[tracing::instrument(skip_all, fields(id = %broadcast.id, chat_id = %broadcast.chat_id, kind = %broadcast.kind, date = %broadcast.shrink_date))]
 async fn send_and_record(deps: BroadcastDeps<'_>, broadcast: ScheduledBroadcast) {
     let config = &deps.config.daily_shrink.broadcast;
     let id = broadcast.id;
@@ -134,7 +135,8 @@ async fn finish(
     repos.broadcasts.finish(id, state).await
 }
 
-/// Sends page 0 of the chat's shrink list for the day the row names, and says what became of it.
+/// Sends page 0 of the chat's shrink list for the day the row names, or the podium of the period it
+/// names, and says what became of it.
 ///
 /// The page comes from the same query the "next page" button uses, so what a chat reads first and
 /// what it reads after tapping are one list rather than two orderings of it.
@@ -154,21 +156,22 @@ async fn send(deps: BroadcastDeps<'_>, broadcast: &ScheduledBroadcast) -> Outcom
     let lang = resolve_broadcast_language(deps, &chat).await;
     let lang_code = LanguageCode::new(lang.to_string());
 
-    let page = match shrinks_page_impl(repos, config, &chat, &lang_code,
-                                       ShrinkView::Broadcast, broadcast.shrink_date, Page::first()).await {
-        Ok(page) => page,
+    let rendered = match broadcast.kind.podium_period() {
+        None => render_shrinks(deps, &chat, &lang_code, broadcast).await,
+        Some(period) => podium_impl(repos, &chat, &lang_code, period, broadcast.shrink_date).await
+            .map(|text| (text, None)),
+    };
+    let (text, keyboard) = match rendered {
+        Ok(rendered) => rendered,
         Err(e) => {
-            tracing::warn!(error = format!("{e:#}"), "couldn't render the shrink summary");
+            tracing::warn!(error = format!("{e:#}"), "couldn't render the summary");
             return Outcome::Retry
         },
     };
-    // A single day by definition, so day-navigation (`adjacent`) is always `None`.
-    let keyboard = build_shrink_keyboard(ShrinkView::Broadcast, broadcast.shrink_date,
-                                         Page::first(), page.has_more_pages, None);
 
     // The throttled request wraps the payload, so the keyboard goes through the setter rather than
     // the field the plain `Bot` exposes.
-    let mut request = bot.send_message(ChatId(broadcast.chat_id.value()), page.lines)
+    let mut request = bot.send_message(ChatId(broadcast.chat_id.value()), text)
         .parse_mode(Html)
         .disable_link_preview(true);
     if let Some(keyboard) = keyboard {
@@ -184,6 +187,21 @@ async fn send(deps: BroadcastDeps<'_>, broadcast: &ScheduledBroadcast) -> Outcom
     outcome_of(request.await.map(|_| ()), repos, broadcast).await
 }
 
+/// Page 0 of the chat's shrink list for the day the row names, with the buttons leading further.
+async fn render_shrinks(
+    deps: BroadcastDeps<'_>,
+    chat: &ChatIdKind,
+    lang_code: &LanguageCode,
+    broadcast: &ScheduledBroadcast,
+) -> anyhow::Result<(String, Option<InlineKeyboardMarkup>)> {
+    let page = shrinks_page_impl(deps.repos, deps.config, chat, lang_code,
+                                 ShrinkView::Broadcast, broadcast.shrink_date, Page::first()).await?;
+    // A single day by definition, so day-navigation (`adjacent`) is always `None`.
+    let keyboard = build_shrink_keyboard(ShrinkView::Broadcast, broadcast.shrink_date,
+                                         Page::first(), page.has_more_pages, None);
+    Ok((page.lines, keyboard))
+}
+
 /// Turns the answer of the Bot API into an outcome, remembering what it says about the chat.
 async fn outcome_of(
     result: Result<(), RequestError>,
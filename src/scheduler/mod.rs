@@ -1,8 +1,11 @@
 mod shrink;
+mod podiums;
+mod seasons;
 mod deletions;
 mod broadcasts;
 
 use std::time::Duration;
+use chrono::{DateTime, DurationRound, Utc};
 use teloxide::Bot;
 use teloxide::adaptors::throttle::{Settings, Throttle};
 use domain_types::traits::SaturatingInto;
@@ -15,9 +18,14 @@ use crate::repo::Repositories;
 use crate::topics::TopicPolicy;
 use crate::users::LanguageService;
 use shrink::run_daily_shrink;
+use podiums::run_podiums;
+use seasons::run_seasons;
 use deletions::{clean_finished_deletions, run_pending_deletions};
 use broadcasts::{clean_finished_broadcasts, run_pending_broadcasts, BroadcastDeps};
 
+/// How often the shrink scheduler looks for the chats whose midnight has come.
+const SHRINK_TICK_MINUTES: i64 = 15;
+
 /// A bot that keeps the schedulers inside Telegram's rate limits.
 ///
 /// Both schedulers send to many chats at once, so they need this. They must also share one, because
@@ -35,9 +43,15 @@ pub fn throttled(bot: Bot, config: ThrottleConfig) -> Throttle<Bot> {
     Throttle::spawn_with_settings(bot, settings)
 }
 
-/// Spawns a detached, best-effort task that runs the daily shrink at every UTC midnight. No-op when
-/// the feature is disabled. The run itself isn't persisted — a restart just resumes from the next
-/// midnight; failures are logged and never abort the loop.
+/// Spawns a detached, best-effort task that runs the daily shrink at the midnight of every chat.
+/// No-op when the feature is disabled. The run itself isn't persisted — a restart just resumes from
+/// the next tick; failures are logged and never abort the loop.
+///
+/// The chats start their days in the time zones their admins have set, and the offsets of the real
+/// ones are whole quarters of an hour, so the loop wakes up every [`SHRINK_TICK_MINUTES`] minutes
+/// and each run takes on the chats whose midnight has come since the tick it last ran for. A run
+/// that outlasts a tick, or a wake-up that comes late, makes the next one cover the ticks it has
+/// missed, instead of leaving the chats of their midnights unshrunk for the day.
 ///
 /// What the run produces *is* persisted: the summaries it owes are rows, written by the same
 /// statement that shrank the dicks, so nothing here has to survive for a chat to be notified.
@@ -49,35 +63,119 @@ pub fn spawn_daily_shrink(repos: Repositories, config: AppConfig) {
     tracing::info!(batch_size = %config.daily_shrink.batch_size, ratio = %config.daily_shrink.ratio,
         inactivity_days = %config.daily_shrink.inactivity_days, "the daily shrink scheduler has started");
     tokio::spawn(metrics::TASK_DAILY_SHRINK.instrument(async move {
+        let tick = chrono::Duration::minutes(SHRINK_TICK_MINUTES);
         // A failed run is logged and forgotten: the next midnight tries again, and one bad day
         // must not stop the scheduler for good.
-        let run = || async {
-            run_daily_shrink(repos.clone(), config.clone())
-                .await
-                .unwrap_or_else(|e| tracing::error!(error = format!("{e:#}"), "the daily shrink run failed"))
+        let run = |at: DateTime<Utc>, window: chrono::Duration| {
+            let (repos, config) = (repos.clone(), config.clone());
+            async move {
+                run_daily_shrink(repos, config, at, window)
+                    .await
+                    .unwrap_or_else(|e| tracing::error!(error = format!("{e:#}"), "the daily shrink run failed"))
+            }
         };
-        // Puts the feature into effect at once instead of hours later. Re-running the same day is
-        // harmless: nothing here touches `updated_at`, so the repeat picks the same victims and
-        // aborts on Stale_Dick_Shrinks' primary key, rolling the length change back with it.
+        // Puts the feature into effect at once instead of hours later, in every chat whatever time
+        // of the day it has. Re-running the same day is harmless: nothing here touches `updated_at`,
+        // so the repeat picks the same victims and aborts on Stale_Dick_Shrinks' primary key, rolling
+        // the length change back with it.
         if get_env_value_or_default("DAILY_SHRINK_RUN_ON_STARTUP", false) {
             tracing::warn!(variable = "DAILY_SHRINK_RUN_ON_STARTUP", "the variable is set, running the daily shrink right now");
-            run().await;
+            run(Utc::now(), chrono::Duration::days(1)).await;
         }
+        let Ok(mut last_tick) = Utc::now().duration_trunc(tick) else {
+            tracing::error!("couldn't compute the last tick, stopping the daily shrink scheduler");
+            return;
+        };
+        loop {
+            let Some(till_next_tick) = duration_till_next_tick(Utc::now(), tick).and_then(|d| d.to_std().ok()) else {
+                tracing::error!("couldn't compute a valid duration till the next tick, stopping the daily shrink scheduler");
+                return;
+            };
+            tracing::debug!(sleeping_for = ?till_next_tick, "waiting for the next tick");
+            tokio::time::sleep(till_next_tick).await;
+
+            let Some((at, window)) = ticks_since(last_tick, Utc::now(), tick) else {
+                continue;
+            };
+            if window > tick {
+                tracing::warn!(since = %last_tick, at = %at, "the daily shrink is catching up with the ticks it has missed");
+            }
+            run(at, window).await;
+            last_tick = at;
+        }
+    }));
+}
+
+/// The gap till the next moment that is a whole number of `tick`s since the Unix epoch, or `None`
+/// if `tick` can't be counted in.
+fn duration_till_next_tick(now: DateTime<Utc>, tick: chrono::Duration) -> Option<chrono::Duration> {
+    now.duration_trunc(tick).ok()
+        .map(|tick_start| tick_start + tick - now)
+}
+
+/// The last tick that has come by `now`, and the window from `last_tick` to it: every midnight in
+/// it is yet to be shrunk. `None` if no tick has come since `last_tick`. The window is a day at
+/// most, which takes on every chat once, as a chat is shrunk only once a day however late it is.
+fn ticks_since(last_tick: DateTime<Utc>, now: DateTime<Utc>, tick: chrono::Duration) -> Option<(DateTime<Utc>, chrono::Duration)> {
+    let at = now.duration_trunc(tick).ok()?;
+    (at > last_tick).then(|| (at, (at - last_tick).min(chrono::Duration::days(1))))
+}
+
+/// Spawns a detached, best-effort task that queues the podiums of the weeks and the months at the
+/// UTC midnight they end at. No-op when no podium is enabled. Like the shrink, a midnight the bot
+/// is down at is lost: the podiums of that period are never announced, and `/top` still has them.
+pub fn spawn_podiums(repos: Repositories, config: AppConfig) {
+    if !config.podium.enabled() {
+        tracing::info!("the podiums are disabled (set PODIUM_WEEK_ENABLED or PODIUM_MONTH_ENABLED to enable them)");
+        return;
+    }
+    tracing::info!(week = config.podium.week, month = config.podium.month, "the podium scheduler has started");
+    tokio::spawn(metrics::TASK_PODIUMS.instrument(async move {
         loop {
             let Some(till_next_day) = duration_till_next_day().and_then(|d| d.to_std().ok()) else {
-                tracing::error!("couldn't compute a valid duration till the next UTC midnight, stopping the daily shrink scheduler");
+                tracing::error!("couldn't compute a valid duration till the next UTC midnight, stopping the podium scheduler");
                 return;
             };
             tracing::debug!(sleeping_for = ?till_next_day, "waiting for the next UTC midnight");
             tokio::time::sleep(till_next_day).await;
 
-            run().await;
+            // A failed run is logged and forgotten, as with the shrink: the next period tries again.
+            run_podiums(repos.clone(), config.clone(), Utc::now().date_naive())
+                .await
+                .unwrap_or_else(|e| tracing::error!(error = format!("{e:#}"), "the podium run failed"));
         }
     }));
 }
 
-/// Spawns the task that sends the shrink summaries the chats are owed. No-op when the daily shrink
-/// is disabled, since nothing would ever write a row.
+/// Spawns a detached, best-effort task that ends the seasons at the UTC midnights they are over
+/// at. No-op when there are neither global seasons nor ones the admins may schedule. A midnight the
+/// bot is down at postpones the seasons the chats have scheduled to the next one, and skips the
+/// global season, which then lasts until the end of the following one.
+pub fn spawn_seasons(repos: Repositories, config: AppConfig) {
+    if !config.seasons.enabled() {
+        tracing::info!("the seasons are disabled (set SEASONS_LENGTH_MONTHS or SEASONS_CHAT_SCHEDULING_ENABLED to enable them)");
+        return;
+    }
+    tracing::info!(length_months = config.seasons.length_months, chat_scheduling = config.seasons.chat_scheduling,
+        "the season scheduler has started");
+    tokio::spawn(metrics::TASK_SEASONS.instrument(async move {
+        loop {
+            let Some(till_next_day) = duration_till_next_day().and_then(|d| d.to_std().ok()) else {
+                tracing::error!("couldn't compute a valid duration till the next UTC midnight, stopping the season scheduler");
+                return;
+            };
+            tracing::debug!(sleeping_for = ?till_next_day, "waiting for the next UTC midnight");
+            tokio::time::sleep(till_next_day).await;
+
+            run_seasons(repos.clone(), config.clone(), Utc::now().date_naive())
+                .await
+                .unwrap_or_else(|e| tracing::error!(error = format!("{e:#}"), "the season run failed"));
+        }
+    }));
+}
+
+/// Spawns the task that sends the shrink summaries and the podiums the chats are owed. No-op when
+/// neither the daily shrink nor the podiums are enabled, since nothing would ever write a row.
 ///
 /// Unlike the shrink above, this one survives a restart: what it acts on are rows, and the tick
 /// after the restart claims every summary that fell due meanwhile — which is what makes a broadcast
@@ -89,7 +187,7 @@ pub fn spawn_broadcast_worker(
     topics: TopicPolicy,
     config: AppConfig,
 ) {
-    if !config.daily_shrink.enabled() {
+    if !config.daily_shrink.enabled() && !config.podium.enabled() {
         return;
     }
     // Published so that a graph of the batch size can be read against the limit it may reach,
@@ -124,7 +222,7 @@ pub fn spawn_broadcast_worker(
 /// retention says — zero keeps it for ever, which is what to set while debugging the worker itself.
 pub fn spawn_broadcast_cleaner(repos: Repositories, config: AppConfig) {
     let retention = config.daily_shrink.broadcast.retention;
-    if !config.daily_shrink.enabled() {
+    if !config.daily_shrink.enabled() && !config.podium.enabled() {
         return;
     }
     if retention.is_zero() {
@@ -221,7 +319,7 @@ fn backoff(base: Duration, failures: AttemptsCount, max: Duration) -> Duration {
 /// states over time — is a panel over `Scheduled_Shrink_Broadcasts` instead, which costs nothing
 /// when nobody is looking at it.
 ///
-/// The day of the last shrink is a gauge rather than a counter because it has to survive a restart:
+/// The time of the last shrink is a gauge rather than a counter because it has to survive a restart:
 /// a counter incremented once a day reads zero both when nothing happened and when nobody scraped
 /// it in time, and there is no telling those apart afterwards.
 ///
@@ -275,4 +373,25 @@ mod tests {
         // The shift that would overflow must give the cap, not a wrapped-around delay of nothing.
         assert_eq!(backoff(base, AttemptsCount::new(u32::MAX), MAX), MAX);
     }
+
+    #[test]
+    fn the_shrink_ticks_at_the_quarters_of_an_hour() {
+        let tick = chrono::Duration::minutes(SHRINK_TICK_MINUTES);
+        let at = |time: &str| DateTime::parse_from_rfc3339(time).expect("invalid datetime string").with_timezone(&Utc);
+        assert_eq!(duration_till_next_tick(at("2023-10-21T22:10:57Z"), tick), Some(chrono::Duration::seconds(4 * 60 + 3)));
+        assert_eq!(duration_till_next_tick(at("2023-10-21T23:59:00Z"), tick), Some(chrono::Duration::minutes(1)));
+        assert_eq!(duration_till_next_tick(at("2023-10-21T18:15:00Z"), tick), Some(tick), "a tick that has just come is already run");
+    }
+
+    #[test]
+    fn the_shrink_catches_up_with_the_ticks_it_has_missed() {
+        let tick = chrono::Duration::minutes(SHRINK_TICK_MINUTES);
+        let at = |time: &str| DateTime::parse_from_rfc3339(time).expect("invalid datetime string").with_timezone(&Utc);
+        let last_tick = at("2023-10-21T23:45:00Z");
+        assert_eq!(ticks_since(last_tick, at("2023-10-22T00:00:03Z"), tick), Some((at("2023-10-22T00:00:00Z"), tick)));
+        assert_eq!(ticks_since(last_tick, at("2023-10-22T00:31:00Z"), tick), Some((at("2023-10-22T00:30:00Z"), tick * 3)),
+            "a run that took too long must not lose the midnights of the ticks after it");
+        assert_eq!(ticks_since(last_tick, at("2023-10-21T23:59:59Z"), tick), None, "an early wake-up has no tick to run");
+        assert_eq!(ticks_since(last_tick, at("2023-10-25T10:00:00Z"), tick), Some((at("2023-10-25T10:00:00Z"), chrono::Duration::days(1))));
+    }
 }
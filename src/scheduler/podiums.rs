@@ -0,0 +1,93 @@
+use std::ops::Range;
+use autometrics::autometrics;
+use chrono::NaiveDate;
+use strum::IntoEnumIterator;
+use crate::config::{AppConfig, PodiumConfig};
+use crate::domain::enums::TopPeriod;
+use crate::metrics;
+use crate::repo::Repositories;
+
+/// Queues the podiums of the periods that ended yesterday for every chat somebody has gained
+/// anything in. Like the shrink, it sends nothing itself: the broadcast worker does that.
+///
+/// The chats are walked in the same batches as by the shrink, so that a run over all of them
+/// neither holds a month of `Length_Events` in one statement nor loses every chat to one failure.
+#[autometrics]
+#[tracing::instrument(skip_all, fields(today = %today))]
+pub async fn run_podiums(repos: Repositories, config: AppConfig, today: NaiveDate) -> anyhow::Result<()> {
+    for (period, days) in ended_periods(config.podium, today) {
+        let mut queued = 0u64;
+        let mut failed_batches = 0u32;
+        let mut after = None;
+        loop {
+            let batch = repos.shrinks.select_chats_batch(after, config.daily_shrink.batch_size).await?;
+            let Some(last) = batch.last().copied() else { break };
+            after = Some(last);
+
+            match repos.broadcasts.queue_podiums(&batch, period, days.start, days.end).await {
+                Ok(count) => queued += count.value(),
+                // Nothing retries it, so those chats won't see the podium: an error.
+                Err(e) => {
+                    failed_batches += 1;
+                    tracing::error!(%period, chats = batch.len(), error = format!("{e:#}"), "a batch of the podiums failed");
+                }
+            }
+        }
+        metrics::PODIUMS_QUEUED.inc_by(queued);
+        tracing::info!(%period, since = %days.start, queued, failed_batches, "the podiums are queued");
+    }
+    Ok(())
+}
+
+/// The periods whose podiums are due today, each with its days: a period ends the day before
+/// the next one starts.
+fn ended_periods(config: PodiumConfig, today: NaiveDate) -> Vec<(TopPeriod, Range<NaiveDate>)> {
+    let Some(yesterday) = today.pred_opt() else {
+        return Vec::new()
+    };
+    TopPeriod::iter()
+        .filter(|period| config.is_enabled_for(*period))
+        .filter(|period| period.range_of(today).start == today)
+        .map(|period| (period, period.range_of(yesterday)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::config::PodiumConfig;
+    use crate::domain::enums::TopPeriod;
+    use super::ended_periods;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).expect("invalid date in the test")
+    }
+
+    const BOTH: PodiumConfig = PodiumConfig { week: true, month: true };
+
+    #[test]
+    fn a_monday_ends_the_week_before() {
+        assert_eq!(ended_periods(BOTH, date(2026, 10, 19)), vec![(TopPeriod::Week, date(2026, 10, 12)..date(2026, 10, 19))]);
+    }
+
+    #[test]
+    fn the_first_day_of_a_month_ends_the_month_before() {
+        assert_eq!(ended_periods(BOTH, date(2026, 3, 1)), vec![(TopPeriod::Month, date(2026, 2, 1)..date(2026, 3, 1))]);
+        // a Monday as well
+        assert_eq!(ended_periods(BOTH, date(2026, 6, 1)), vec![
+            (TopPeriod::Week, date(2026, 5, 25)..date(2026, 6, 1)),
+            (TopPeriod::Month, date(2026, 5, 1)..date(2026, 6, 1)),
+        ]);
+    }
+
+    #[test]
+    fn nothing_ends_in_the_middle_of_a_period() {
+        assert!(ended_periods(BOTH, date(2026, 10, 18)).is_empty());
+    }
+
+    #[test]
+    fn a_disabled_podium_is_never_due() {
+        let weekly_only = PodiumConfig { week: true, month: false };
+        assert_eq!(ended_periods(weekly_only, date(2026, 6, 1)), vec![(TopPeriod::Week, date(2026, 5, 25)..date(2026, 6, 1))]);
+    }
+}
@@ -0,0 +1,36 @@
+use autometrics::autometrics;
+use chrono::NaiveDate;
+use crate::config::AppConfig;
+use crate::metrics;
+use crate::repo::Repositories;
+
+/// Ends the seasons that are over at the midnight of `today`: the global one, if it ended
+/// yesterday, and those the admins of a chat have set to end earlier than that.
+///
+/// The chats are walked in the batches of the shrink, like by the podiums. A batch that fails keeps
+/// its lengths: the chats whose own end has passed are tried again the next midnight, but those
+/// on the global schedule have to wait for the next season to end.
+#[autometrics]
+#[tracing::instrument(skip_all, fields(today = %today))]
+pub async fn run_seasons(repos: Repositories, config: AppConfig, today: NaiveDate) -> anyhow::Result<()> {
+    let global_due = config.seasons.global_season_ends_before(today);
+    let mut ended = 0u64;
+    let mut failed_batches = 0u32;
+    let mut after = None;
+    loop {
+        let batch = repos.shrinks.select_chats_batch(after, config.daily_shrink.batch_size).await?;
+        let Some(last) = batch.last().copied() else { break };
+        after = Some(last);
+
+        match repos.seasons.end_seasons(&batch, today, global_due).await {
+            Ok(count) => ended += count.value(),
+            Err(e) => {
+                failed_batches += 1;
+                tracing::error!(chats = batch.len(), error = format!("{e:#}"), "a batch of the seasons failed");
+            }
+        }
+    }
+    metrics::SEASONS_ENDED.inc_by(ended);
+    tracing::info!(global_due, ended, failed_batches, "the seasons are done");
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use autometrics::autometrics;
+use chrono::{DateTime, Duration, Utc};
 use crate::config::AppConfig;
 use crate::metrics;
 use crate::repo::{Repositories, ShrinkBatchOutcome};
@@ -13,9 +14,13 @@ use crate::repo::{Repositories, ShrinkBatchOutcome};
 /// Inline-only chats (no messageable `chat_id`) get no summary — their members see the events via
 /// the `shrinks` inline command — and neither do the ones the bot is known to have lost access to.
 /// Both are counted, under the `inline_only` and `unreachable` labels of [`metrics::DAILY_SHRINK`].
+///
+/// Only the chats whose midnight has come no longer than `window` before `at` are taken on, since
+/// each chat starts its days in its own time zone. A tick at which no chat has its midnight does
+/// nothing and counts no run.
 #[autometrics]
-#[tracing::instrument(skip_all)]
-pub async fn run_daily_shrink(repos: Repositories, config: AppConfig) -> anyhow::Result<()> {
+#[tracing::instrument(skip_all, fields(at = %at, window = %window))]
+pub async fn run_daily_shrink(repos: Repositories, config: AppConfig, at: DateTime<Utc>, window: Duration) -> anyhow::Result<()> {
     let shrink_config = &config.daily_shrink;
     let mut total = ShrinkBatchOutcome::default();
     let mut chats = 0usize;
@@ -25,7 +30,7 @@ pub async fn run_daily_shrink(repos: Repositories, config: AppConfig) -> anyhow:
     // A batch of chats is read, shrunk and forgotten before the next one is asked for, so the run
     // holds one batch at a time however many chats there are.
     loop {
-        let batch = repos.shrinks.select_chats_batch(after, shrink_config.batch_size)
+        let batch = repos.shrinks.select_midnight_chats_batch(after, shrink_config.batch_size, at, window)
             .await
             .inspect_err(|_| metrics::DAILY_SHRINK.run_failed())?;
         let Some(last) = batch.last().copied() else { break };
@@ -46,6 +51,10 @@ pub async fn run_daily_shrink(repos: Repositories, config: AppConfig) -> anyhow:
         }
     }
 
+    if chats == 0 {
+        tracing::debug!("no chat has its midnight now");
+        return Ok(())
+    }
     metrics::DAILY_SHRINK.victims_to_broadcast(total.to_broadcast.value());
     metrics::DAILY_SHRINK.victims_inline_only(total.inline_only.value());
     metrics::DAILY_SHRINK.victims_unreachable(total.unreachable.value());
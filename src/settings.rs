@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::time::Instant;
+use crate::config::AppConfig;
+use crate::domain::enums::GameSetting;
+use crate::domain::objects::ChatGameSettings;
+use crate::domain::primitives::chat::{ChatIdKind, ChatIdPartiality};
+use crate::metrics;
+use crate::repo::Chats;
+
+/// The rules of the game each chat changed for itself, cached in front of the database.
+///
+/// The growth, the Dick of the Day, the top and the battles all look up the settings of the chat
+/// they are played in, so the lookup has to be cheap. As with [`crate::cleanup::CleanupPolicy`],
+/// the writes go through here and refresh the entry, so an admin's change takes effect at once.
+#[derive(Clone)]
+pub struct GameSettingsPolicy {
+    chats: Chats,
+    cache: Arc<Mutex<HashMap<ChatIdKind, CachedSettings>>>,
+    ttl: Duration,
+}
+
+#[derive(Clone)]
+struct CachedSettings {
+    settings: ChatGameSettings,
+    at: Instant,
+}
+
+impl GameSettingsPolicy {
+    pub fn new(ttl: Duration, chats: Chats) -> Self {
+        Self {
+            chats,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Read-through TTL cache over [`Chats::get_game_settings`].
+    ///
+    /// A failed read reports the chat as having changed nothing, so a database the bot can't reach
+    /// for a moment doesn't stop the game — it's just played by the bot's rules meanwhile.
+    #[tracing::instrument(skip_all, fields(chat_id = %chat_id))]
+    pub async fn settings(&self, chat_id: &ChatIdKind) -> ChatGameSettings {
+        let now = Instant::now();
+        if let Some(cached) = self.cache().get(chat_id)
+            .filter(|cached| now.duration_since(cached.at) <= self.ttl)
+        {
+            metrics::CHAT_SETTINGS.cache_hit();
+            return cached.settings.clone();
+        }
+
+        metrics::CHAT_SETTINGS.db_query();
+        let settings = self.chats.get_game_settings(chat_id).await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = format!("{e:#}"), "couldn't fetch the game settings of the chat");
+                ChatGameSettings::default()
+            });
+        self.cache().insert(chat_id.clone(), CachedSettings { settings: settings.clone(), at: now });
+        settings
+    }
+
+    /// The configuration the game is played by in the chat.
+    pub async fn config_for(&self, chat_id: &ChatIdKind, config: &AppConfig) -> AppConfig {
+        config.for_chat(&self.settings(chat_id).await)
+    }
+
+    pub async fn set(
+        &self,
+        chat_id: &ChatIdPartiality,
+        setting: GameSetting,
+        value: &str,
+    ) -> anyhow::Result<ChatGameSettings> {
+        self.chats.set_game_setting(chat_id, setting, value).await?;
+        self.refresh(chat_id).await
+    }
+
+    /// Takes back the chat's choice about one rule, leaving the rest of them as they are.
+    pub async fn follow_the_bot(
+        &self,
+        chat_id: &ChatIdPartiality,
+        setting: GameSetting,
+    ) -> anyhow::Result<ChatGameSettings> {
+        self.chats.forget_game_setting(chat_id, setting).await?;
+        self.refresh(chat_id).await
+    }
+
+    pub async fn reset(&self, chat_id: &ChatIdPartiality) -> anyhow::Result<ChatGameSettings> {
+        self.chats.reset_game_settings(chat_id).await?;
+        self.refresh(chat_id).await
+    }
+
+    async fn refresh(&self, chat_id: &ChatIdPartiality) -> anyhow::Result<ChatGameSettings> {
+        let kind = chat_id.kind();
+        self.cache().remove(&kind);
+        self.chats.get_game_settings(&kind).await
+    }
+
+    fn cache(&self) -> MutexGuard<'_, HashMap<ChatIdKind, CachedSettings>> {
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}